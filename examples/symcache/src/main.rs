@@ -0,0 +1,267 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use anyhow::{anyhow, Context, Result};
+use clap::{Arg, ArgMatches, Command};
+
+use symbolic::common::{Arch, ByteView, DSymPathExt};
+use symbolic::debuginfo::Archive;
+use symbolic::symcache::{OwnedSymCache, SymCacheWriter};
+
+fn create(matches: &ArgMatches) -> Result<()> {
+    let path = matches.value_of("object").unwrap();
+    let arch = match matches.value_of("arch") {
+        Some(arch) => arch.parse()?,
+        None => Arch::Unknown,
+    };
+
+    let dsym_path = Path::new(path).resolve_dsym();
+    let view = ByteView::open(dsym_path.as_deref().unwrap_or_else(|| path.as_ref()))
+        .context("failed to open file")?;
+    let archive = Archive::parse(&view).context("failed to parse file")?;
+    let objects = archive
+        .objects()
+        .collect::<Result<Vec<_>, _>>()
+        .context("failed to parse objects")?;
+
+    let object = match objects.iter().find(|o| o.arch() == arch) {
+        Some(object) => object,
+        None if arch == Arch::Unknown && objects.len() == 1 => &objects[0],
+        None if arch == Arch::Unknown => {
+            let archs: Vec<_> = objects.iter().map(|o| o.arch().name()).collect();
+            return Err(anyhow!(
+                "file contains multiple architectures, select one with --arch: {}",
+                archs.join(", ")
+            ));
+        }
+        None => return Err(anyhow!("did not find architecture {}", arch)),
+    };
+
+    let output = match matches.value_of("output") {
+        Some(output) => output.to_owned(),
+        None => format!("{}.symc", path),
+    };
+    let file = File::create(&output).context("failed to create output file")?;
+    SymCacheWriter::write_object(object, BufWriter::new(file))
+        .context("failed to convert debug file")?
+        .flush()
+        .context("failed to write symcache")?;
+
+    println!(
+        "Wrote symcache for {} [{}] to {}",
+        object.debug_id(),
+        object.arch(),
+        output
+    );
+
+    Ok(())
+}
+
+fn parse_addr(addr: &str) -> Result<u64> {
+    let addr = match addr.strip_prefix("0x") {
+        Some(addr) => u64::from_str_radix(addr, 16),
+        None => addr.parse(),
+    };
+    addr.context("unable to parse address")
+}
+
+fn open(path: &str) -> Result<OwnedSymCache> {
+    let view = ByteView::open(path).context("failed to open symcache")?;
+    OwnedSymCache::parse(view).context("failed to parse symcache")
+}
+
+fn lookup(matches: &ArgMatches) -> Result<()> {
+    let symcache = open(matches.value_of("symcache").unwrap())?;
+    let symcache = symcache.get();
+
+    for addr in matches.values_of("addrs").unwrap() {
+        let addr = parse_addr(addr)?;
+        println!("{:#x}", addr);
+
+        let mut found = false;
+        for source_location in symcache.lookup(addr) {
+            found = true;
+            let function = source_location.function();
+            let name = function.as_ref().and_then(|f| f.name()).unwrap_or("??");
+            let path = match source_location.file() {
+                Some(file) => file.full_path(),
+                None => "??".to_owned(),
+            };
+            let inlined = if source_location.is_inlined() {
+                " (inlined)"
+            } else {
+                ""
+            };
+            println!("  {}{}", name, inlined);
+            println!("    at {}:{}", path, source_location.line());
+        }
+
+        if !found {
+            println!("  ??");
+            println!("    at ??:0");
+        }
+    }
+
+    Ok(())
+}
+
+fn stats(matches: &ArgMatches) -> Result<()> {
+    let owned = open(matches.value_of("symcache").unwrap())?;
+    let symcache = owned.get();
+    let top: usize = matches.value_of("top").unwrap_or("10").parse()?;
+
+    println!("Debug ID: {}", symcache.debug_id());
+    println!("Arch:     {}", symcache.arch());
+    println!("Version:  {}", symcache.version());
+    println!("Size:     {} bytes", owned.as_slice().len());
+    println!();
+
+    let sections = symcache.section_sizes();
+    println!("Sections:");
+    for section in &sections {
+        println!(
+            "  {:<18} {:>10} entries {:>12} bytes",
+            section.name, section.count, section.bytes
+        );
+    }
+    println!();
+
+    // every string reference would store its own length-prefixed copy without deduplication
+    let string_len = |s: Option<&str>| s.map_or(0, |s| s.len() + 4);
+    let function_strings: usize = symcache
+        .functions()
+        .map(|f| string_len(f.name()) + string_len(f.comp_dir()) + string_len(f.linkage_name()))
+        .sum();
+    let file_strings: usize = symcache
+        .files()
+        .map(|f| string_len(f.comp_dir) + string_len(f.directory) + string_len(Some(f.path_name)))
+        .sum();
+    let referenced = function_strings + file_strings;
+    let stored = sections
+        .iter()
+        .find(|section| section.name == "strings")
+        .map_or(0, |section| section.bytes);
+    println!("Strings:");
+    println!(
+        "  {} bytes stored for {} bytes referenced",
+        stored, referenced
+    );
+    if referenced > 0 {
+        let saved = 100.0 * (1.0 - stored as f64 / referenced as f64);
+        println!("  {:.1}% saved by deduplication", saved);
+    }
+    println!();
+
+    println!("Largest functions:");
+    for function_size in symcache.function_sizes().iter().take(top) {
+        let name = function_size.function.name().unwrap_or("??");
+        println!("  {:>10} {}", function_size.size, name);
+    }
+    println!();
+
+    println!("Largest files:");
+    for file_size in symcache.file_sizes().iter().take(top) {
+        println!("  {:>10} {}", file_size.size, file_size.file.full_path());
+    }
+
+    Ok(())
+}
+
+fn dump(matches: &ArgMatches) -> Result<()> {
+    let symcache = open(matches.value_of("symcache").unwrap())?;
+
+    let mut listing = String::new();
+    symcache.get().dump(&mut listing)?;
+    print!("{}", listing);
+
+    Ok(())
+}
+
+fn main() {
+    let matches = Command::new("symcache")
+        .about("Creates and inspects symcaches")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("create")
+                .about("Creates a symcache from a debug file")
+                .arg(
+                    Arg::new("object")
+                        .value_name("OBJECT")
+                        .required(true)
+                        .help("Path to the debug file, such as an ELF, Mach-O or dSYM"),
+                )
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .value_name("PATH")
+                        .help("Path of the symcache to write, defaults to OBJECT.symc"),
+                )
+                .arg(
+                    Arg::new("arch")
+                        .short('a')
+                        .long("arch")
+                        .value_name("ARCH")
+                        .help("The architecture to convert in fat files"),
+                ),
+        )
+        .subcommand(
+            Command::new("lookup")
+                .about("Resolves addresses to their functions, files and lines, including inlinees")
+                .arg(
+                    Arg::new("symcache")
+                        .value_name("CACHE")
+                        .required(true)
+                        .help("Path to the symcache file"),
+                )
+                .arg(
+                    Arg::new("addrs")
+                        .value_name("ADDR")
+                        .required(true)
+                        .multiple_values(true)
+                        .help("The addresses to look up, in hex with a 0x prefix or decimal"),
+                ),
+        )
+        .subcommand(
+            Command::new("stats")
+                .about("Shows the size of every section and the largest functions and files")
+                .arg(
+                    Arg::new("symcache")
+                        .value_name("CACHE")
+                        .required(true)
+                        .help("Path to the symcache file"),
+                )
+                .arg(
+                    Arg::new("top")
+                        .short('n')
+                        .long("top")
+                        .value_name("N")
+                        .help("The number of largest functions and files to show, defaults to 10"),
+                ),
+        )
+        .subcommand(
+            Command::new("dump")
+                .about("Lists all address ranges with their functions, files and lines")
+                .arg(
+                    Arg::new("symcache")
+                        .value_name("CACHE")
+                        .required(true)
+                        .help("Path to the symcache file"),
+                ),
+        )
+        .get_matches();
+
+    let result = match matches.subcommand() {
+        Some(("create", sub_matches)) => create(sub_matches),
+        Some(("lookup", sub_matches)) => lookup(sub_matches),
+        Some(("stats", sub_matches)) => stats(sub_matches),
+        Some(("dump", sub_matches)) => dump(sub_matches),
+        _ => unreachable!(),
+    };
+
+    if let Err(error) = result {
+        eprintln!("Error: {:?}", error);
+        std::process::exit(1);
+    }
+}
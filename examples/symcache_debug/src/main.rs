@@ -12,7 +12,7 @@ use symbolic::debuginfo::Archive;
 use symbolic::demangle::{Demangle, DemangleOptions};
 use symbolic::il2cpp::LineMapping;
 use symbolic::symcache::transform::{self, Transformer};
-use symbolic::symcache::{SymCache, SymCacheWriter};
+use symbolic::symcache::{FunctionIdentity, NewSymCache, SymCache, SymCacheWriter};
 
 // FIXME: This is a huge pain, can't this be simpler somehow?
 struct OwnedBcSymbolMap(SelfCell<ByteView<'static>, BcSymbolMap<'static>>);
@@ -92,7 +92,30 @@ fn execute(matches: &ArgMatches) -> Result<()> {
             }
         }
 
-        writer.process_object(obj)?;
+        if let Some(alignment) = matches.value_of("section_alignment") {
+            writer.set_section_alignment(alignment.parse()?);
+        }
+
+        if matches.is_present("separate_files") {
+            writer.set_function_identity(FunctionIdentity::NameAndFile);
+        }
+
+        if matches.is_present("gopclntab") {
+            writer.process_gopclntab(obj)?;
+        } else {
+            writer.process_object(obj)?;
+        }
+
+        if let Some(max_size) = matches.value_of("max_size") {
+            if !writer.limit_size(max_size.parse()?) {
+                println!("Cache file exceeds the maximum size of {} bytes", max_size);
+            }
+        }
+
+        if matches.is_present("report") {
+            println!("Conversion stats:");
+            println!("{:#?}", writer.stats());
+        }
 
         buffer = ByteView::from_vec(writer.finish()?.into_inner());
         symcache = SymCache::parse(&buffer)?;
@@ -121,11 +144,7 @@ fn execute(matches: &ArgMatches) -> Result<()> {
 
     // lookup mode
     if let Some(addr) = matches.value_of("lookup_addr") {
-        let addr = if addr.len() > 2 && &addr[..2] == "0x" {
-            u64::from_str_radix(&addr[2..], 16)?
-        } else {
-            addr.parse()?
-        };
+        let addr = parse_addr(addr)?;
 
         let m = symcache.lookup(addr)?.collect::<Vec<_>>()?;
         if m.is_empty() {
@@ -173,6 +192,67 @@ fn execute(matches: &ArgMatches) -> Result<()> {
     Ok(())
 }
 
+fn parse_addr(addr: &str) -> Result<u64> {
+    Ok(if addr.len() > 2 && &addr[..2] == "0x" {
+        u64::from_str_radix(&addr[2..], 16)?
+    } else {
+        addr.parse()?
+    })
+}
+
+fn explain(matches: &ArgMatches) -> Result<()> {
+    let buffer = ByteView::open(matches.value_of("symcache_file_path").unwrap())?;
+    let symcache = NewSymCache::parse(&buffer)?;
+    let addr = parse_addr(matches.value_of("addr").unwrap())?;
+
+    let explanation = symcache.explain(addr);
+    println!("Address {:#x}", explanation.addr);
+
+    match explanation.range_idx {
+        Some(range_idx) => println!("Matched range #{}", range_idx),
+        None => println!("No range covers this address"),
+    }
+
+    if !explanation.inline_chain.is_empty() {
+        println!("Inline chain:");
+    }
+    for (source_location_idx, sl) in explanation.inline_chain.iter().zip(symcache.lookup(addr)) {
+        let function = sl.function();
+        let name = function.as_ref().and_then(|f| f.name()).unwrap_or("?");
+        let path = sl.file().map(|f| f.full_path()).unwrap_or_default();
+        println!(
+            "  source location #{}: {} at {}:{}",
+            source_location_idx,
+            name,
+            path,
+            sl.line()
+        );
+    }
+
+    println!("Neighboring ranges:");
+    for range in &explanation.ranges {
+        let marker = if Some(range.range_idx) == explanation.range_idx {
+            "=>"
+        } else {
+            "  "
+        };
+        let end = match range.end {
+            Some(end) => format!("{:#x}", end),
+            None => "end".to_owned(),
+        };
+        let target = match range.source_location_idx {
+            Some(idx) => format!("source location #{}", idx),
+            None => "no source location".to_owned(),
+        };
+        println!(
+            "{} #{} {:#x}..{} -> {}",
+            marker, range.range_idx, range.start, end, target
+        );
+    }
+
+    Ok(())
+}
+
 fn main() {
     let matches = Command::new("symcache-debug")
         .about("Works with symbol files with the symcache interface")
@@ -201,6 +281,28 @@ fn main() {
                     "Path to a il2cpp `LineNumberMappings.json` file that should be applied to transform the debug file",
                 ),
         )
+        .arg(
+            Arg::new("gopclntab")
+                .long("gopclntab")
+                .help("Convert the pc/line table of a Go binary instead of its debug info"),
+        )
+        .arg(
+            Arg::new("section_alignment")
+                .long("section-alignment")
+                .value_name("BYTES")
+                .help("Align the sections of the written cache file, for example to pages"),
+        )
+        .arg(
+            Arg::new("separate_files")
+                .long("separate-files")
+                .help("Keep apart equally named functions from different source files"),
+        )
+        .arg(
+            Arg::new("max_size")
+                .long("max-size")
+                .value_name("BYTES")
+                .help("Drop inline frames and line information to fit the cache into BYTES"),
+        )
         .arg(
             Arg::new("write_cache_file")
                 .short('w')
@@ -241,7 +343,26 @@ fn main() {
                 .long("symbols")
                 .help("Print all symbols"),
         )
+        .subcommand(
+            Command::new("explain")
+                .about("Explains how an address is resolved in a symcache")
+                .arg(
+                    Arg::new("symcache_file_path")
+                        .value_name("CACHE")
+                        .required(true)
+                        .help("Path to the symcache file"),
+                )
+                .arg(
+                    Arg::new("addr")
+                        .value_name("ADDR")
+                        .required(true)
+                        .help("The address to explain"),
+                ),
+        )
         .get_matches();
 
-    execute(&matches).unwrap()
+    match matches.subcommand() {
+        Some(("explain", sub_matches)) => explain(sub_matches).unwrap(),
+        _ => execute(&matches).unwrap(),
+    }
 }
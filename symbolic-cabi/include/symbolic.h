@@ -69,6 +69,16 @@ enum SymbolicErrorCode {
   SYMBOLIC_ERROR_CODE_SYM_CACHE_ERROR_VALUE_TOO_LARGE = 6010,
   SYMBOLIC_ERROR_CODE_SYM_CACHE_ERROR_WRITE_FAILED = 6011,
   SYMBOLIC_ERROR_CODE_SYM_CACHE_ERROR_TOO_MANY_VALUES = 6012,
+  SYMBOLIC_ERROR_CODE_SYM_CACHE2_ERROR_UNKNOWN = 6100,
+  SYMBOLIC_ERROR_CODE_SYM_CACHE2_ERROR_BUFFER_NOT_ALIGNED = 6101,
+  SYMBOLIC_ERROR_CODE_SYM_CACHE2_ERROR_HEADER_TOO_SMALL = 6102,
+  SYMBOLIC_ERROR_CODE_SYM_CACHE2_ERROR_WRONG_ENDIANNESS = 6103,
+  SYMBOLIC_ERROR_CODE_SYM_CACHE2_ERROR_WRONG_FORMAT = 6104,
+  SYMBOLIC_ERROR_CODE_SYM_CACHE2_ERROR_WRONG_VERSION = 6105,
+  SYMBOLIC_ERROR_CODE_SYM_CACHE2_ERROR_BAD_FORMAT_LENGTH = 6106,
+  SYMBOLIC_ERROR_CODE_SYM_CACHE2_ERROR_BAD_SECTION_ALIGNMENT = 6107,
+  SYMBOLIC_ERROR_CODE_SYM_CACHE2_ERROR_BAD_STRING_REFERENCE = 6108,
+  SYMBOLIC_ERROR_CODE_SYM_CACHE2_ERROR_BAD_CHECKSUM = 6109,
   SYMBOLIC_ERROR_CODE_UNREAL4_ERROR_UNKNOWN = 7001,
   SYMBOLIC_ERROR_CODE_UNREAL4_ERROR_EMPTY = 7002,
   SYMBOLIC_ERROR_CODE_UNREAL4_ERROR_BAD_COMPRESSION = 7004,
@@ -143,6 +153,11 @@ typedef struct SymbolicSourceView SymbolicSourceView;
  */
 typedef struct SymbolicSymCache SymbolicSymCache;
 
+/**
+ * Represents a symcache in the new binary format.
+ */
+typedef struct SymbolicSymCache2 SymbolicSymCache2;
+
 /**
  * An Unreal Engine 4 crash report.
  */
@@ -353,6 +368,40 @@ typedef struct SymbolicInstructionInfo {
   uint64_t ip_reg;
 } SymbolicInstructionInfo;
 
+/**
+ * Represents a single frame of a symcache lookup.
+ */
+typedef struct SymbolicSourceLocation {
+  /**
+   * The entry address of the function, or `u32::MAX` for inlined functions.
+   */
+  uint64_t entry_pc;
+  /**
+   * The source line, or `0` if unknown.
+   */
+  uint32_t line;
+  /**
+   * The source column, or `0` if unknown.
+   */
+  uint32_t column;
+  struct SymbolicStr lang;
+  struct SymbolicStr function;
+  struct SymbolicStr full_path;
+  struct SymbolicStr comp_dir;
+  /**
+   * This is true if the frame was inlined into the next frame.
+   */
+  bool is_inlined;
+} SymbolicSourceLocation;
+
+/**
+ * Represents the frames returned by a symcache lookup, innermost frame first.
+ */
+typedef struct SymbolicSourceLocations {
+  struct SymbolicSourceLocation *items;
+  uintptr_t len;
+} SymbolicSourceLocations;
+
 /**
  * Checks if an architecture is known.
  */
@@ -818,6 +867,72 @@ uint64_t symbolic_find_best_instruction(const struct SymbolicInstructionInfo *ii
  */
 uint32_t symbolic_symcache_latest_version(void);
 
+/**
+ * Opens a symcache in the new format from a given path.
+ */
+struct SymbolicSymCache2 *symbolic_symcache2_open(const char *path);
+
+/**
+ * Parses a symcache in the new format from a byte buffer.
+ *
+ * The buffer is copied, so the pointer does not need to outlive the symcache.
+ */
+struct SymbolicSymCache2 *symbolic_symcache2_from_bytes(const uint8_t *bytes,
+                                                        uintptr_t len);
+
+/**
+ * Frees a symcache in the new format.
+ */
+void symbolic_symcache2_free(struct SymbolicSymCache2 *symcache);
+
+/**
+ * Returns the internal buffer of the symcache.
+ *
+ * The internal buffer is exactly `symbolic_symcache2_get_size` bytes long.
+ */
+const uint8_t *symbolic_symcache2_get_bytes(const struct SymbolicSymCache2 *symcache);
+
+/**
+ * Returns the size in bytes of the symcache.
+ */
+uintptr_t symbolic_symcache2_get_size(const struct SymbolicSymCache2 *symcache);
+
+/**
+ * Returns the architecture of the symcache.
+ */
+struct SymbolicStr symbolic_symcache2_get_arch(const struct SymbolicSymCache2 *symcache);
+
+/**
+ * Returns the debug identifier of the symcache.
+ */
+struct SymbolicStr symbolic_symcache2_get_debug_id(const struct SymbolicSymCache2 *symcache);
+
+/**
+ * Returns the version of the cache file.
+ */
+uint32_t symbolic_symcache2_get_version(const struct SymbolicSymCache2 *symcache);
+
+/**
+ * Returns the image base that was subtracted from all addresses in the symcache.
+ */
+uint64_t symbolic_symcache2_get_image_base(const struct SymbolicSymCache2 *symcache);
+
+/**
+ * Verifies the checksum of the symcache to detect truncated or corrupted files.
+ */
+bool symbolic_symcache2_verify(const struct SymbolicSymCache2 *symcache);
+
+/**
+ * Looks up an address and returns all frames covering it, innermost frame first.
+ */
+struct SymbolicSourceLocations symbolic_symcache2_lookup(const struct SymbolicSymCache2 *symcache,
+                                                         uint64_t addr);
+
+/**
+ * Frees the frames returned by `symbolic_symcache2_lookup`.
+ */
+void symbolic_source_locations_free(struct SymbolicSourceLocations *source_locations);
+
 /**
  * Parses an Unreal Engine 4 crash from the given buffer.
  */
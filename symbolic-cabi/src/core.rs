@@ -203,6 +203,16 @@ pub enum SymbolicErrorCode {
     SymCacheErrorValueTooLarge = 6010,
     SymCacheErrorWriteFailed = 6011,
     SymCacheErrorTooManyValues = 6012,
+    SymCache2ErrorUnknown = 6100,
+    SymCache2ErrorBufferNotAligned = 6101,
+    SymCache2ErrorHeaderTooSmall = 6102,
+    SymCache2ErrorWrongEndianness = 6103,
+    SymCache2ErrorWrongFormat = 6104,
+    SymCache2ErrorWrongVersion = 6105,
+    SymCache2ErrorBadFormatLength = 6106,
+    SymCache2ErrorBadSectionAlignment = 6107,
+    SymCache2ErrorBadStringReference = 6108,
+    SymCache2ErrorBadChecksum = 6109,
 
     // symbolic::unreal
     Unreal4ErrorUnknown = 7001,
@@ -339,6 +349,34 @@ impl SymbolicErrorCode {
                 };
             }
 
+            use symbolic::symcache::NewSymCacheError as SymCache2Error;
+            if let Some(error) = error.downcast_ref::<SymCache2Error>() {
+                return match error {
+                    SymCache2Error::BufferNotAligned => {
+                        SymbolicErrorCode::SymCache2ErrorBufferNotAligned
+                    }
+                    SymCache2Error::HeaderTooSmall => {
+                        SymbolicErrorCode::SymCache2ErrorHeaderTooSmall
+                    }
+                    SymCache2Error::WrongEndianness => {
+                        SymbolicErrorCode::SymCache2ErrorWrongEndianness
+                    }
+                    SymCache2Error::WrongFormat => SymbolicErrorCode::SymCache2ErrorWrongFormat,
+                    SymCache2Error::WrongVersion => SymbolicErrorCode::SymCache2ErrorWrongVersion,
+                    SymCache2Error::BadFormatLength => {
+                        SymbolicErrorCode::SymCache2ErrorBadFormatLength
+                    }
+                    SymCache2Error::BadSectionAlignment => {
+                        SymbolicErrorCode::SymCache2ErrorBadSectionAlignment
+                    }
+                    SymCache2Error::BadStringReference => {
+                        SymbolicErrorCode::SymCache2ErrorBadStringReference
+                    }
+                    SymCache2Error::BadChecksum => SymbolicErrorCode::SymCache2ErrorBadChecksum,
+                    _ => SymbolicErrorCode::SymCache2ErrorUnknown,
+                };
+            }
+
             use symbolic::unreal::{Unreal4Error, Unreal4ErrorKind};
             if let Some(error) = error.downcast_ref::<Unreal4Error>() {
                 return match error.kind() {
@@ -5,6 +5,7 @@ use std::os::raw::c_char;
 use std::slice;
 
 use symbolic::common::{ByteView, InstructionInfo, SelfCell};
+use symbolic::symcache::OwnedSymCache;
 use symbolic::symcache::{SymCache, SymCacheWriter, SYMCACHE_VERSION};
 
 use crate::core::SymbolicStr;
@@ -218,3 +219,182 @@ ffi_fn! {
         Ok(SYMCACHE_VERSION)
     }
 }
+
+/// Represents a symcache in the new binary format.
+pub struct SymbolicSymCache2;
+
+impl ForeignObject for SymbolicSymCache2 {
+    type RustObject = OwnedSymCache;
+}
+
+/// Represents a single frame of a symcache lookup.
+#[repr(C)]
+pub struct SymbolicSourceLocation {
+    /// The entry address of the function, or `u32::MAX` for inlined functions.
+    pub entry_pc: u64,
+    /// The source line, or `0` if unknown.
+    pub line: u32,
+    /// The source column, or `0` if unknown.
+    pub column: u32,
+    pub lang: SymbolicStr,
+    pub function: SymbolicStr,
+    pub full_path: SymbolicStr,
+    pub comp_dir: SymbolicStr,
+    /// This is true if the frame was inlined into the next frame.
+    pub is_inlined: bool,
+}
+
+/// Represents the frames returned by a symcache lookup, innermost frame first.
+#[repr(C)]
+pub struct SymbolicSourceLocations {
+    pub items: *mut SymbolicSourceLocation,
+    pub len: usize,
+}
+
+ffi_fn! {
+    /// Opens a symcache in the new format from a given path.
+    unsafe fn symbolic_symcache2_open(path: *const c_char) -> Result<*mut SymbolicSymCache2> {
+        let byteview = ByteView::open(CStr::from_ptr(path).to_str()?)?;
+        let cache = OwnedSymCache::parse(byteview)?;
+        Ok(SymbolicSymCache2::from_rust(cache))
+    }
+}
+
+ffi_fn! {
+    /// Parses a symcache in the new format from a byte buffer.
+    ///
+    /// The buffer is copied, so the pointer does not need to outlive the symcache.
+    unsafe fn symbolic_symcache2_from_bytes(
+        bytes: *const u8,
+        len: usize,
+    ) -> Result<*mut SymbolicSymCache2> {
+        let byteview = ByteView::from_vec(slice::from_raw_parts(bytes, len).to_vec());
+        let cache = OwnedSymCache::parse(byteview)?;
+        Ok(SymbolicSymCache2::from_rust(cache))
+    }
+}
+
+ffi_fn! {
+    /// Frees a symcache in the new format.
+    unsafe fn symbolic_symcache2_free(symcache: *mut SymbolicSymCache2) {
+        SymbolicSymCache2::drop(symcache);
+    }
+}
+
+ffi_fn! {
+    /// Returns the internal buffer of the symcache.
+    ///
+    /// The internal buffer is exactly `symbolic_symcache2_get_size` bytes long.
+    unsafe fn symbolic_symcache2_get_bytes(
+        symcache: *const SymbolicSymCache2,
+    ) -> Result<*const u8> {
+        Ok(SymbolicSymCache2::as_rust(symcache).as_slice().as_ptr())
+    }
+}
+
+ffi_fn! {
+    /// Returns the size in bytes of the symcache.
+    unsafe fn symbolic_symcache2_get_size(symcache: *const SymbolicSymCache2) -> Result<usize> {
+        Ok(SymbolicSymCache2::as_rust(symcache).as_slice().len())
+    }
+}
+
+ffi_fn! {
+    /// Returns the architecture of the symcache.
+    unsafe fn symbolic_symcache2_get_arch(
+        symcache: *const SymbolicSymCache2,
+    ) -> Result<SymbolicStr> {
+        Ok(SymbolicSymCache2::as_rust(symcache).get().arch().name().into())
+    }
+}
+
+ffi_fn! {
+    /// Returns the debug identifier of the symcache.
+    unsafe fn symbolic_symcache2_get_debug_id(
+        symcache: *const SymbolicSymCache2,
+    ) -> Result<SymbolicStr> {
+        Ok(SymbolicSymCache2::as_rust(symcache).get().debug_id().to_string().into())
+    }
+}
+
+ffi_fn! {
+    /// Returns the version of the cache file.
+    unsafe fn symbolic_symcache2_get_version(symcache: *const SymbolicSymCache2) -> Result<u32> {
+        Ok(SymbolicSymCache2::as_rust(symcache).get().version())
+    }
+}
+
+ffi_fn! {
+    /// Returns the image base that was subtracted from all addresses in the symcache.
+    unsafe fn symbolic_symcache2_get_image_base(
+        symcache: *const SymbolicSymCache2,
+    ) -> Result<u64> {
+        Ok(SymbolicSymCache2::as_rust(symcache).get().image_base())
+    }
+}
+
+ffi_fn! {
+    /// Verifies the checksum of the symcache to detect truncated or corrupted files.
+    unsafe fn symbolic_symcache2_verify(symcache: *const SymbolicSymCache2) -> Result<bool> {
+        SymbolicSymCache2::as_rust(symcache).get().verify()?;
+        Ok(true)
+    }
+}
+
+ffi_fn! {
+    /// Looks up an address and returns all frames covering it, innermost frame first.
+    unsafe fn symbolic_symcache2_lookup(
+        symcache: *const SymbolicSymCache2,
+        addr: u64,
+    ) -> Result<SymbolicSourceLocations> {
+        let cache = SymbolicSymCache2::as_rust(symcache).get();
+
+        let mut items = vec![];
+        for source_location in cache.lookup(addr) {
+            let function = source_location.function();
+            let file = source_location.file();
+            items.push(SymbolicSourceLocation {
+                entry_pc: function.as_ref().map_or(u32::MAX.into(), |f| f.entry_pc()),
+                line: source_location.line(),
+                column: source_location.column(),
+                lang: function
+                    .as_ref()
+                    .map(|f| SymbolicStr::new(f.language().name()))
+                    .unwrap_or_default(),
+                function: function
+                    .as_ref()
+                    .and_then(|f| f.name())
+                    .map(SymbolicStr::new)
+                    .unwrap_or_default(),
+                full_path: file
+                    .as_ref()
+                    .map(|f| SymbolicStr::from_string(f.full_path()))
+                    .unwrap_or_default(),
+                comp_dir: file
+                    .as_ref()
+                    .and_then(|f| f.comp_dir())
+                    .map(SymbolicStr::new)
+                    .unwrap_or_default(),
+                is_inlined: source_location.is_inlined(),
+            });
+        }
+
+        items.shrink_to_fit();
+        let rv = SymbolicSourceLocations {
+            items: items.as_mut_ptr(),
+            len: items.len(),
+        };
+        mem::forget(items);
+        Ok(rv)
+    }
+}
+
+ffi_fn! {
+    /// Frees the frames returned by `symbolic_symcache2_lookup`.
+    unsafe fn symbolic_source_locations_free(source_locations: *mut SymbolicSourceLocations) {
+        if !source_locations.is_null() {
+            let result = &*source_locations;
+            Vec::from_raw_parts(result.items, result.len, result.len);
+        }
+    }
+}
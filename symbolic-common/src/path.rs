@@ -154,6 +154,48 @@ fn pop_path(path: &mut String) -> bool {
     }
 }
 
+/// Joins the compilation directory, directory and name of a source file into its full path.
+///
+/// This follows the rules of debug information such as DWARF: the directory is relative to the
+/// compilation directory, and the name is relative to the directory. An absolute directory or
+/// name replaces everything before it, and empty fragments are skipped. The fragments are joined
+/// with [`join_path`], which uses the separator of the detected platform, and the result is
+/// simplified with [`clean_path`].
+///
+/// # Examples
+///
+/// Join a file relative to the compilation directory:
+///
+/// ```
+/// assert_eq!(
+///     symbolic_common::join_file_path("/build", "src", "main.c"),
+///     "/build/src/main.c"
+/// );
+/// ```
+///
+/// An absolute directory overrides the compilation directory:
+///
+/// ```
+/// assert_eq!(
+///     symbolic_common::join_file_path("/build", "/usr/include", "stdio.h"),
+///     "/usr/include/stdio.h"
+/// );
+/// ```
+///
+/// Windows paths are joined with backslashes:
+///
+/// ```
+/// assert_eq!(
+///     symbolic_common::join_file_path("C:\\build", "src", "main.c"),
+///     "C:\\build\\src\\main.c"
+/// );
+/// ```
+pub fn join_file_path(comp_dir: &str, directory: &str, name: &str) -> String {
+    let prefix = join_path(comp_dir, directory);
+    let joined = join_path(&prefix, name);
+    clean_path(&joined).into_owned()
+}
+
 /// Simplifies paths by stripping redundant components.
 ///
 /// This removes redundant `../` or `./` path components. However, this function does not operate on
@@ -576,6 +618,39 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_join_file_path() {
+        assert_eq!(
+            join_file_path("/build", "src", "main.c"),
+            "/build/src/main.c"
+        );
+        assert_eq!(
+            join_file_path("/build", "", "src/main.c"),
+            "/build/src/main.c"
+        );
+        assert_eq!(join_file_path("", "src", "main.c"), "src/main.c");
+        assert_eq!(join_file_path("", "", "main.c"), "main.c");
+        assert_eq!(
+            join_file_path("/build", "/usr/include", "a.h"),
+            "/usr/include/a.h"
+        );
+        assert_eq!(join_file_path("/build", "src", "/abs/a.h"), "/abs/a.h");
+        assert_eq!(
+            join_file_path("/build", "src/../include", "a.h"),
+            "/build/include/a.h"
+        );
+        assert_eq!(join_file_path("/build", "src", "<stdin>"), "<stdin>");
+        assert_eq!(
+            join_file_path("C:\\build", "src", "a.c"),
+            "C:\\build\\src\\a.c"
+        );
+        assert_eq!(
+            join_file_path("C:\\build", "D:\\src", "a.c"),
+            "D:\\src\\a.c"
+        );
+        assert_eq!(join_file_path("C:\\build", "\\src", "a.c"), "C:\\src\\a.c");
+    }
+
     #[test]
     fn test_clean_path() {
         assert_eq!(clean_path("/foo/bar/baz/./blah"), "/foo/bar/baz/blah");
@@ -4,7 +4,7 @@ use std::iter::FromIterator;
 use std::ops::{Bound, Deref, RangeBounds};
 use std::str::FromStr;
 
-use symbolic_common::{clean_path, join_path, Arch, CodeId, DebugId, Name};
+use symbolic_common::{join_file_path, Arch, CodeId, DebugId, Name};
 
 /// An error returned for unknown or invalid `ObjectKinds`.
 #[derive(Debug)]
@@ -140,6 +140,7 @@ impl fmt::Display for UnknownFileFormatError {
 impl std::error::Error for UnknownFileFormatError {}
 
 /// Represents the physical object file format.
+#[non_exhaustive]
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Copy, Clone)]
 pub enum FileFormat {
     /// An unknown file format.
@@ -158,9 +159,24 @@ pub enum FileFormat {
     SourceBundle,
     /// WASM container.
     Wasm,
+    /// Unity il2cpp line mappings, parsed by `symbolic-il2cpp`.
+    Usym,
+    /// A SymCache, parsed by `symbolic-symcache`.
+    SymCache,
 }
 
 impl FileFormat {
+    /// Returns whether files of this format can be parsed as [`Object`](crate::Object).
+    ///
+    /// Other formats are recognized by [`peek`](crate::peek), but have to be parsed by other
+    /// crates.
+    pub fn is_object(self) -> bool {
+        !matches!(
+            self,
+            FileFormat::Unknown | FileFormat::Usym | FileFormat::SymCache
+        )
+    }
+
     /// Returns the name of the file format.
     pub fn name(self) -> &'static str {
         match self {
@@ -172,6 +188,8 @@ impl FileFormat {
             FileFormat::Pe => "pe",
             FileFormat::SourceBundle => "sourcebundle",
             FileFormat::Wasm => "wasm",
+            FileFormat::Usym => "usym",
+            FileFormat::SymCache => "symcache",
         }
     }
 }
@@ -194,6 +212,8 @@ impl FromStr for FileFormat {
             "pe" => FileFormat::Pe,
             "sourcebundle" => FileFormat::SourceBundle,
             "wasm" => FileFormat::Wasm,
+            "usym" => FileFormat::Usym,
+            "symcache" => FileFormat::SymCache,
             _ => return Err(UnknownFileFormatError),
         })
     }
@@ -461,8 +481,7 @@ impl<'data> FileInfo<'data> {
 
     /// The full path to the file, relative to the compilation directory.
     pub fn path_str(&self) -> String {
-        let joined = join_path(&self.dir_str(), &self.name_str());
-        clean_path(&joined).into_owned()
+        join_file_path("", &self.dir_str(), &self.name_str())
     }
 }
 
@@ -491,9 +510,11 @@ impl<'data> FileEntry<'data> {
 
     /// Absolute path to the file, including the compilation directory.
     pub fn abs_path_str(&self) -> String {
-        let joined_path = join_path(&self.dir_str(), &self.name_str());
-        let joined = join_path(&self.compilation_dir_str(), &joined_path);
-        clean_path(&joined).into_owned()
+        join_file_path(
+            &self.compilation_dir_str(),
+            &self.dir_str(),
+            &self.name_str(),
+        )
     }
 }
 
@@ -526,6 +547,20 @@ pub struct LineInfo<'data> {
     pub file: FileInfo<'data>,
     /// Absolute line number starting at 1. Zero means no line number.
     pub line: u64,
+    /// Column number starting at 1. Zero means no column number.
+    pub column: u64,
+    /// Whether this record starts at the end of the function prologue.
+    ///
+    /// This is where debuggers place breakpoints for a function, and usually where the first
+    /// statement of the function body begins. Only DWARF records this.
+    pub prologue_end: bool,
+    /// Whether this record starts a statement, which is a recommended breakpoint location.
+    ///
+    /// Records that are not statements usually belong to the middle of an expression. This is
+    /// `true` for all records if the debug format does not distinguish statements.
+    pub is_stmt: bool,
+    /// Whether this record starts a basic block. Only DWARF records this.
+    pub basic_block: bool,
 }
 
 impl fmt::Debug for LineInfo<'_> {
@@ -540,6 +575,10 @@ impl fmt::Debug for LineInfo<'_> {
 
         s.field("file", &self.file)
             .field("line", &self.line)
+            .field("column", &self.column)
+            .field("prologue_end", &self.prologue_end)
+            .field("is_stmt", &self.is_stmt)
+            .field("basic_block", &self.basic_block)
             .finish()
     }
 }
@@ -561,6 +600,10 @@ pub struct Function<'data> {
     pub inlinees: Vec<Function<'data>>,
     /// Specifies whether this function is inlined.
     pub inline: bool,
+    /// The file in which this function is declared, if known.
+    pub decl_file: Option<FileInfo<'data>>,
+    /// The line at which this function is declared, or `0` if unknown.
+    pub decl_line: u64,
 }
 
 impl Function<'_> {
@@ -585,6 +628,8 @@ impl fmt::Debug for Function<'_> {
             .field("lines", &self.lines)
             .field("inlinees", &self.inlinees)
             .field("inline", &self.inline)
+            .field("decl_file", &self.decl_file)
+            .field("decl_line", &self.decl_line)
             .finish()
     }
 }
@@ -362,6 +362,65 @@ impl<'d> Iterator for BreakpadFileRecords<'d> {
 /// A map of file paths by their file ID.
 pub type BreakpadFileMap<'d> = BTreeMap<u64, &'d str>;
 
+/// An [inline origin record], specifying the function name of inlined code.
+///
+/// The ID of this record is referenced by [`BreakpadInlineRecord`].
+///
+/// Example: `INLINE_ORIGIN 1305 SharedLibraryInfo::Initialize()`
+///
+/// [inline origin record]: https://github.com/google/breakpad/blob/master/docs/symbol_files.md#inline_origin-records
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct BreakpadInlineOriginRecord<'d> {
+    /// Breakpad-internal identifier of the inline origin.
+    pub id: u64,
+    /// The function name of the inlined code.
+    pub name: &'d str,
+}
+
+impl<'d> BreakpadInlineOriginRecord<'d> {
+    /// Parses an inline origin record from a single line.
+    pub fn parse(data: &'d [u8]) -> Result<Self, BreakpadError> {
+        let string = str::from_utf8(data)?;
+        Ok(parsing::inline_origin_record_final(string.trim())?)
+    }
+}
+
+/// An iterator over inline origin records in a Breakpad object.
+#[derive(Clone, Debug)]
+pub struct BreakpadInlineOriginRecords<'d> {
+    lines: Lines<'d>,
+    finished: bool,
+}
+
+impl<'d> Iterator for BreakpadInlineOriginRecords<'d> {
+    type Item = Result<BreakpadInlineOriginRecord<'d>, BreakpadError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        for line in &mut self.lines {
+            // Fast path: INLINE_ORIGIN records come before any FUNC records.
+            if line.starts_with(b"FUNC ") || line.starts_with(b"STACK ") {
+                break;
+            }
+
+            if !line.starts_with(b"INLINE_ORIGIN ") {
+                continue;
+            }
+
+            return Some(BreakpadInlineOriginRecord::parse(line));
+        }
+
+        self.finished = true;
+        None
+    }
+}
+
+/// A map of inlined function names by their inline origin ID.
+pub type BreakpadInlineOriginMap<'d> = BTreeMap<u64, &'d str>;
+
 /// A [public function symbol record].
 ///
 /// Example: `PUBLIC m 2160 0 Public2_1`
@@ -463,6 +522,14 @@ impl<'d> BreakpadFuncRecord<'d> {
         }
     }
 
+    /// Returns an iterator over inline records associated to this function.
+    pub fn inlinees(&self) -> BreakpadInlineRecords<'d> {
+        BreakpadInlineRecords {
+            lines: self.lines.clone(),
+            finished: false,
+        }
+    }
+
     /// Returns the range of addresses covered by this record.
     pub fn range(&self) -> Range<u64> {
         self.address..self.address + self.size
@@ -593,8 +660,8 @@ impl<'d> Iterator for BreakpadLineRecords<'d> {
 
             // There might be empty lines throughout the file (or at the end). This is the only
             // iterator that cannot rely on a record identifier, so we have to explicitly skip empty
-            // lines.
-            if line.is_empty() {
+            // lines, as well as inline records that are interleaved with line records.
+            if line.is_empty() || line.starts_with(b"INLINE ") {
                 continue;
             }
 
@@ -614,6 +681,90 @@ impl<'d> Iterator for BreakpadLineRecords<'d> {
     }
 }
 
+/// An address range covered by a [`BreakpadInlineRecord`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct BreakpadInlineAddressRange {
+    /// The start address of this range relative to the image base (load address).
+    pub address: u64,
+    /// The size of the code covered by this range.
+    pub size: u64,
+}
+
+impl BreakpadInlineAddressRange {
+    /// Returns the range of addresses covered by this record.
+    pub fn range(&self) -> Range<u64> {
+        self.address..self.address + self.size
+    }
+}
+
+/// An [inline record] associated to a [`BreakpadFuncRecord`].
+///
+/// Inline records are interleaved with the line records of their function. The line records
+/// describe the innermost inlined code, while inline records specify the call site of each
+/// inlined function in its caller.
+///
+/// Example: `INLINE 0 3082 52 1410 49200 10`
+///
+/// [inline record]: https://github.com/google/breakpad/blob/master/docs/symbol_files.md#inline-records
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct BreakpadInlineRecord {
+    /// The nesting depth of the inlined function, where `0` is inlined directly into the
+    /// function record.
+    pub inline_depth: u64,
+    /// The line number of the call site in the caller.
+    pub call_site_line: u64,
+    /// Identifier of the [`BreakpadFileRecord`] of the call site.
+    pub call_site_file_id: u64,
+    /// Identifier of the [`BreakpadInlineOriginRecord`] specifying the inlined function.
+    pub origin_id: u64,
+    /// The address ranges covered by the inlined function.
+    pub address_ranges: Vec<BreakpadInlineAddressRange>,
+}
+
+impl BreakpadInlineRecord {
+    /// Parses an inline record from a single line.
+    pub fn parse(data: &[u8]) -> Result<Self, BreakpadError> {
+        let string = str::from_utf8(data)?;
+        Ok(parsing::inline_record_final(string.trim())?)
+    }
+}
+
+/// An iterator over inline records in a `BreakpadFunctionRecord`.
+#[derive(Clone, Debug)]
+pub struct BreakpadInlineRecords<'d> {
+    lines: Lines<'d>,
+    finished: bool,
+}
+
+impl<'d> Iterator for BreakpadInlineRecords<'d> {
+    type Item = Result<BreakpadInlineRecord, BreakpadError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        for line in &mut self.lines {
+            // Stop parsing INLINE records once other expected records are encountered.
+            if line.starts_with(b"FUNC ")
+                || line.starts_with(b"PUBLIC ")
+                || line.starts_with(b"STACK ")
+            {
+                break;
+            }
+
+            if !line.starts_with(b"INLINE ") {
+                continue;
+            }
+
+            return Some(BreakpadInlineRecord::parse(line));
+        }
+
+        self.finished = true;
+        None
+    }
+}
+
 /// A `STACK CFI` record. Usually associated with a [BreakpadStackCfiRecord].
 #[derive(Clone, Debug, Eq, PartialEq, Default)]
 pub struct BreakpadStackCfiDeltaRecord<'d> {
@@ -987,6 +1138,7 @@ impl<'data> BreakpadObject<'data> {
     pub fn debug_session(&self) -> Result<BreakpadDebugSession<'data>, BreakpadError> {
         Ok(BreakpadDebugSession {
             file_map: self.file_map(),
+            inline_origin_map: self.inline_origin_map(),
             func_records: self.func_records(),
         })
     }
@@ -1030,6 +1182,22 @@ impl<'data> BreakpadObject<'data> {
             .collect()
     }
 
+    /// Returns an iterator over inline origin records.
+    pub fn inline_origin_records(&self) -> BreakpadInlineOriginRecords<'data> {
+        BreakpadInlineOriginRecords {
+            lines: Lines::new(self.data),
+            finished: false,
+        }
+    }
+
+    /// Returns a map for inlined function name lookups by inline origin id.
+    pub fn inline_origin_map(&self) -> BreakpadInlineOriginMap<'data> {
+        self.inline_origin_records()
+            .filter_map(Result::ok)
+            .map(|origin| (origin.id, origin.name))
+            .collect()
+    }
+
     /// Returns an iterator over public symbol records.
     pub fn public_records(&self) -> BreakpadPublicRecords<'data> {
         BreakpadPublicRecords {
@@ -1179,6 +1347,7 @@ impl<'data> Iterator for BreakpadSymbolIterator<'data> {
 /// Debug session for Breakpad objects.
 pub struct BreakpadDebugSession<'data> {
     file_map: BreakpadFileMap<'data>,
+    inline_origin_map: BreakpadInlineOriginMap<'data>,
     func_records: BreakpadFuncRecords<'data>,
 }
 
@@ -1187,6 +1356,7 @@ impl<'data> BreakpadDebugSession<'data> {
     pub fn functions(&self) -> BreakpadFunctionIterator<'_> {
         BreakpadFunctionIterator {
             file_map: &self.file_map,
+            inline_origin_map: &self.inline_origin_map,
             func_records: self.func_records.clone(),
         }
     }
@@ -1241,28 +1411,130 @@ impl<'s> Iterator for BreakpadFileIterator<'s> {
     }
 }
 
+/// An inlined function collected from the [`BreakpadInlineRecord`]s of a function.
+struct BreakpadInlinee<'s> {
+    parent: Option<usize>,
+    range: Range<u64>,
+    name: &'s str,
+    call_site_file: &'s str,
+    call_site_line: u64,
+    lines: Vec<LineInfo<'s>>,
+}
+
 /// An iterator over functions in a Breakpad object.
 pub struct BreakpadFunctionIterator<'s> {
     file_map: &'s BreakpadFileMap<'s>,
+    inline_origin_map: &'s BreakpadInlineOriginMap<'s>,
     func_records: BreakpadFuncRecords<'s>,
 }
 
 impl<'s> BreakpadFunctionIterator<'s> {
     fn convert(&self, record: BreakpadFuncRecord<'s>) -> Result<Function<'s>, BreakpadError> {
+        // Every address range of an inline record becomes a separate inlinee. Its parent is the
+        // most recent inlinee one level up that contains it, or the function itself.
+        let mut inlinees: Vec<BreakpadInlinee<'s>> = Vec::new();
+        // indexes of the inlinees at every depth, and of the direct children of every inlinee
+        let mut by_depth: Vec<Vec<usize>> = Vec::new();
+        let mut roots = Vec::new();
+        let mut nested: Vec<Vec<usize>> = Vec::new();
+        for inline in record.inlinees() {
+            let inline = inline?;
+            let name = self
+                .inline_origin_map
+                .get(&inline.origin_id)
+                .cloned()
+                .unwrap_or(UNKNOWN_NAME);
+            let call_site_file = self
+                .file_map
+                .get(&inline.call_site_file_id)
+                .cloned()
+                .unwrap_or_default();
+
+            for address_range in &inline.address_ranges {
+                let parent = match inline.inline_depth.checked_sub(1) {
+                    Some(parent_depth) => {
+                        let parent = by_depth.get(parent_depth as usize).and_then(|siblings| {
+                            siblings
+                                .iter()
+                                .rev()
+                                .copied()
+                                .find(|&i| inlinees[i].range.contains(&address_range.address))
+                        });
+                        // skip inlinees whose caller is missing
+                        if parent.is_none() {
+                            continue;
+                        }
+                        parent
+                    }
+                    None => None,
+                };
+
+                // the parent exists one level up, so this never skips a depth
+                let idx = inlinees.len();
+                let depth = inline.inline_depth as usize;
+                if by_depth.len() == depth {
+                    by_depth.push(Vec::new());
+                }
+                by_depth[depth].push(idx);
+                match parent {
+                    Some(parent) => nested[parent].push(idx),
+                    None => roots.push(idx),
+                }
+                nested.push(Vec::new());
+
+                inlinees.push(BreakpadInlinee {
+                    parent,
+                    range: address_range.range(),
+                    name,
+                    call_site_file,
+                    call_site_line: inline.call_site_line,
+                    lines: Vec::new(),
+                });
+            }
+        }
+
+        // Line records describe the innermost inlinee at their address. Every caller up the
+        // chain receives a line record at the same address pointing to the call site instead.
         let mut lines = Vec::new();
         for line in record.lines() {
             let line = line?;
             let filename = line.filename(self.file_map).unwrap_or_default();
-
-            lines.push(LineInfo {
+            let mut line_info = LineInfo {
                 address: line.address,
                 size: Some(line.size),
                 file: FileInfo::from_path(filename.as_bytes()),
                 line: line.line,
-            });
+                column: 0,
+                prologue_end: false,
+                is_stmt: true,
+                basic_block: false,
+            };
+
+            let mut chain = Vec::new();
+            let mut candidates = &roots;
+            while let Some(idx) = candidates
+                .iter()
+                .copied()
+                .find(|&i| inlinees[i].range.contains(&line.address))
+            {
+                chain.push(idx);
+                candidates = &nested[idx];
+            }
+
+            for &idx in chain.iter().rev() {
+                let inlinee = &mut inlinees[idx];
+                let call_site = LineInfo {
+                    file: FileInfo::from_path(inlinee.call_site_file.as_bytes()),
+                    line: inlinee.call_site_line,
+                    ..line_info.clone()
+                };
+                inlinee.lines.push(line_info);
+                line_info = call_site;
+            }
+            lines.push(line_info);
         }
 
-        Ok(Function {
+        let mut function = Function {
             address: record.address,
             size: record.size,
             name: Name::new(record.name, NameMangling::Unmangled, Language::Unknown),
@@ -1270,7 +1542,34 @@ impl<'s> BreakpadFunctionIterator<'s> {
             lines,
             inlinees: Vec::new(),
             inline: false,
-        })
+            decl_file: None,
+            decl_line: 0,
+        };
+
+        // Inlinees are always preceded by their parents, so they can be attached in reverse.
+        let mut children: Vec<Vec<Function<'s>>> = inlinees.iter().map(|_| Vec::new()).collect();
+        for (idx, inlinee) in inlinees.into_iter().enumerate().rev() {
+            let mut grandchildren = std::mem::take(&mut children[idx]);
+            grandchildren.reverse();
+            let child = Function {
+                address: inlinee.range.start,
+                size: inlinee.range.end - inlinee.range.start,
+                name: Name::new(inlinee.name, NameMangling::Unmangled, Language::Unknown),
+                compilation_dir: &[],
+                lines: inlinee.lines,
+                inlinees: grandchildren,
+                inline: true,
+                decl_file: None,
+                decl_line: 0,
+            };
+            match inlinee.parent {
+                Some(parent) => children[parent].push(child),
+                None => function.inlinees.push(child),
+            }
+        }
+        function.inlinees.reverse();
+
+        Ok(function)
     }
 }
 
@@ -1546,6 +1845,38 @@ mod parsing {
         nom_supreme::final_parser::final_parser(file_record)(input)
     }
 
+    /// Parse a [`BreakpadInlineOriginRecord`].
+    ///
+    /// An INLINE_ORIGIN record has the form `INLINE_ORIGIN <id> <name>`.
+    fn inline_origin_record(input: &str) -> ParseResult<'_, BreakpadInlineOriginRecord<'_>> {
+        let (input, _) = tag("INLINE_ORIGIN")
+            .terminated(multispace1)
+            .context("inline origin record prefix")
+            .parse(input)?;
+
+        let (input, (id, name)) = pair(
+            num_dec!(u64)
+                .terminated(multispace1.or(eof))
+                .context("inline origin id"),
+            name.context("inline origin name"),
+        )
+        .cut()
+        .context("inline origin record body")
+        .parse(input)?;
+
+        Ok((input, BreakpadInlineOriginRecord { id, name }))
+    }
+
+    /// Parse a [`BreakpadInlineOriginRecord`].
+    ///
+    /// An INLINE_ORIGIN record has the form `INLINE_ORIGIN <id> <name>`.
+    /// This will fail if there is any input left over after the record.
+    pub fn inline_origin_record_final(
+        input: &str,
+    ) -> Result<BreakpadInlineOriginRecord<'_>, ErrorTree<ErrorLine>> {
+        nom_supreme::final_parser::final_parser(inline_origin_record)(input)
+    }
+
     /// Parse a [`BreakpadPublicRecord`].
     ///
     /// A PUBLIC record has the form `PUBLIC (m )? <address> <parameter_size> ( <name>)?`.
@@ -1661,6 +1992,67 @@ mod parsing {
         nom_supreme::final_parser::final_parser(line_record)(input)
     }
 
+    /// Parse a [`BreakpadInlineAddressRange`].
+    ///
+    /// An address range has the form `<address> <size>`.
+    fn inline_address_range(input: &str) -> ParseResult<'_, BreakpadInlineAddressRange> {
+        let (input, (address, size)) = pair(
+            num_hex!(u64).terminated(multispace1).context("address"),
+            num_hex!(u64).context("size"),
+        )
+        .parse(input)?;
+
+        Ok((input, BreakpadInlineAddressRange { address, size }))
+    }
+
+    /// Parse a [`BreakpadInlineRecord`].
+    ///
+    /// An INLINE record has the form
+    /// `INLINE <inline_depth> <call_site_line> <call_site_file_id> <origin_id> (<address> <size>)+`.
+    fn inline_record(input: &str) -> ParseResult<'_, BreakpadInlineRecord> {
+        let (input, _) = tag("INLINE")
+            .terminated(multispace1)
+            .context("inline record prefix")
+            .parse(input)?;
+
+        let (input, (inline_depth, call_site_line, call_site_file_id, origin_id, address_ranges)) =
+            tuple((
+                num_dec!(u64)
+                    .terminated(multispace1)
+                    .context("inline depth"),
+                line_num.terminated(multispace1).context("call site line"),
+                num_dec!(u64)
+                    .terminated(multispace1)
+                    .context("call site file id"),
+                num_dec!(u64).terminated(multispace1).context("origin id"),
+                nom::multi::separated_list1(multispace1, inline_address_range)
+                    .context("address ranges"),
+            ))
+            .cut()
+            .context("inline record body")
+            .parse(input)?;
+
+        Ok((
+            input,
+            BreakpadInlineRecord {
+                inline_depth,
+                call_site_line,
+                call_site_file_id,
+                origin_id,
+                address_ranges,
+            },
+        ))
+    }
+
+    /// Parse a [`BreakpadInlineRecord`].
+    ///
+    /// An INLINE record has the form
+    /// `INLINE <inline_depth> <call_site_line> <call_site_file_id> <origin_id> (<address> <size>)+`.
+    /// This will fail if there is any input left over after the record.
+    pub fn inline_record_final(input: &str) -> Result<BreakpadInlineRecord, ErrorTree<ErrorLine>> {
+        nom_supreme::final_parser::final_parser(inline_record)(input)
+    }
+
     /// Parse a [`BreakpadStackCfiDeltaRecord`].
     ///
     /// A STACK CFI Delta record has the form `STACK CFI <address> <rules>`.
@@ -2003,6 +2395,108 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_parse_inline_origin_record() -> Result<(), BreakpadError> {
+        let string = b"INLINE_ORIGIN 1305 SharedLibraryInfo::Initialize()";
+        let record = BreakpadInlineOriginRecord::parse(string)?;
+
+        insta::assert_debug_snapshot!(record, @r###"
+        BreakpadInlineOriginRecord {
+            id: 1305,
+            name: "SharedLibraryInfo::Initialize()",
+        }
+        "###);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_inline_record() -> Result<(), BreakpadError> {
+        let string = b"INLINE 1 3082 52 1410 49200 10 49220 8";
+        let record = BreakpadInlineRecord::parse(string)?;
+
+        insta::assert_debug_snapshot!(record, @r###"
+        BreakpadInlineRecord {
+            inline_depth: 1,
+            call_site_line: 3082,
+            call_site_file_id: 52,
+            origin_id: 1410,
+            address_ranges: [
+                BreakpadInlineAddressRange {
+                    address: 299520,
+                    size: 16,
+                },
+                BreakpadInlineAddressRange {
+                    address: 299552,
+                    size: 8,
+                },
+            ],
+        }
+        "###);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_inlinees() -> Result<(), BreakpadError> {
+        let data = b"MODULE Linux x86_64 492E2DD23CC306CA9C494EEF1533A3810 crash
+FILE 0 main.c
+FILE 1 helper.h
+INLINE_ORIGIN 0 helper
+INLINE_ORIGIN 1 inner
+FUNC 1000 20 0 main
+INLINE 0 7 0 0 1008 10
+INLINE 1 9 1 1 100c 4
+1000 8 3 0
+1008 4 20 1
+100c 4 30 1
+1010 8 8 1
+1018 8 4 0
+";
+        let object = BreakpadObject::parse(data)?;
+        let session = object.debug_session()?;
+        let function = session.functions().next().unwrap()?;
+
+        let lines = |function: &Function<'_>| {
+            function
+                .lines
+                .iter()
+                .map(|line| (line.address, line.file.name_str().into_owned(), line.line))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(function.name.as_str(), "main");
+        assert_eq!(
+            lines(&function),
+            [
+                (0x1000, "main.c".into(), 3),
+                (0x1008, "main.c".into(), 7),
+                (0x100c, "main.c".into(), 7),
+                (0x1010, "main.c".into(), 7),
+                (0x1018, "main.c".into(), 4),
+            ]
+        );
+
+        let helper = &function.inlinees[0];
+        assert_eq!(helper.name.as_str(), "helper");
+        assert_eq!((helper.address, helper.size), (0x1008, 0x10));
+        assert_eq!(
+            lines(helper),
+            [
+                (0x1008, "helper.h".into(), 20),
+                (0x100c, "helper.h".into(), 9),
+                (0x1010, "helper.h".into(), 8),
+            ]
+        );
+
+        let inner = &helper.inlinees[0];
+        assert_eq!(inner.name.as_str(), "inner");
+        assert!(inner.inline);
+        assert_eq!(lines(inner), [(0x100c, "helper.h".into(), 30)]);
+
+        Ok(())
+    }
+
     #[test]
     fn test_parse_public_record() -> Result<(), BreakpadError> {
         let string = b"PUBLIC 5180 0 __clang_call_terminate";
@@ -8,7 +8,8 @@
 //! [`MachObject`]: ../macho/struct.MachObject.html
 
 use std::borrow::Cow;
-use std::collections::BTreeSet;
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap};
 use std::error::Error;
 use std::fmt;
 use std::marker::PhantomData;
@@ -16,9 +17,9 @@ use std::ops::Deref;
 use std::sync::Arc;
 
 use fallible_iterator::FallibleIterator;
-use gimli::read::{AttributeValue, Error as GimliError, Range};
+use gimli::read::{AttributeValue, ColumnType, Error as GimliError, Range};
 use gimli::{constants, DwarfFileType, UnitSectionOffset};
-use lazycell::LazyCell;
+use once_cell::sync::OnceCell;
 use thiserror::Error;
 
 use symbolic_common::{AsSelf, Language, Name, NameMangling, SelfCell};
@@ -51,6 +52,7 @@ type Slice<'a> = gimli::read::EndianSlice<'a, Endian>;
 type RangeLists<'a> = gimli::read::RangeLists<Slice<'a>>;
 type Unit<'a> = gimli::read::Unit<Slice<'a>>;
 type DwarfInner<'a> = gimli::read::Dwarf<Slice<'a>>;
+type DwarfPackage<'a> = gimli::read::DwarfPackage<Slice<'a>>;
 
 type Die<'d, 'u> = gimli::read::DebuggingInformationEntry<'u, 'u, Slice<'d>, usize>;
 type Attribute<'a> = gimli::read::Attribute<Slice<'a>>;
@@ -62,6 +64,21 @@ type IncompleteLineNumberProgram<'a> = gimli::read::IncompleteLineProgram<Slice<
 type LineNumberProgramHeader<'a> = gimli::read::LineProgramHeader<Slice<'a>>;
 type LineProgramFileEntry<'a> = gimli::read::FileEntry<Slice<'a>>;
 
+/// Function names resolved from referenced entries, keyed by the address of their unit and
+/// their offset within it.
+type NameCache<'d> = HashMap<(usize, UnitOffset), Option<Name<'d>>>;
+
+/// The qualified scopes of functions declared within namespaces or types, keyed by the offset of
+/// the function's entry, such as `foo::Bar` for a method `foo::Bar::run`.
+type Scopes = HashMap<UnitOffset, Arc<str>>;
+
+/// The maximum number of `DW_AT_abstract_origin` and `DW_AT_specification` references followed
+/// to resolve a function name.
+///
+/// Compilers emit chains of two or three references. The limit guards against cyclic references
+/// in corrupted debug information.
+const MAX_NAME_REFERENCE_DEPTH: usize = 16;
+
 /// This applies the offset to the address.
 ///
 /// This function does not panic but would wrap around if too large or small
@@ -70,6 +87,53 @@ fn offset(addr: u64, offset: i64) -> u64 {
     (addr as i64).wrapping_sub(offset as i64) as u64
 }
 
+/// Returns the lowest tombstone address for the given address size.
+///
+/// Linkers mark addresses of eliminated code with the maximum address, or with the maximum
+/// address minus one in `.debug_ranges` and `.debug_loc`, where the maximum address already
+/// denotes a base address selection entry.
+fn tombstone_address(address_size: u8) -> u64 {
+    (!0u64 >> (64 - u32::from(address_size).clamp(1, 8) * 8)) - 1
+}
+
+/// A callback that returns `true` once processing should stop, see
+/// [`DwarfFunctionIterator::with_interrupt`].
+type Interrupt = Box<dyn Fn() -> bool>;
+
+/// Calls an [`Interrupt`] once every few steps of a long-running loop.
+struct InterruptCheck<'a> {
+    interrupt: Option<&'a dyn Fn() -> bool>,
+    steps: u32,
+}
+
+impl<'a> InterruptCheck<'a> {
+    /// The number of steps between two calls of the interrupt callback.
+    const INTERVAL: u32 = 1024;
+
+    fn new(interrupt: Option<&'a dyn Fn() -> bool>) -> Self {
+        Self {
+            interrupt,
+            steps: 0,
+        }
+    }
+
+    /// Returns [`DwarfErrorKind::Interrupted`] if processing should stop.
+    ///
+    /// The first step always calls the callback.
+    fn step(&mut self) -> Result<(), DwarfError> {
+        let interrupt = match self.interrupt {
+            Some(interrupt) => interrupt,
+            None => return Ok(()),
+        };
+        let check = self.steps % Self::INTERVAL == 0;
+        self.steps = self.steps.wrapping_add(1);
+        if check && interrupt() {
+            return Err(DwarfErrorKind::Interrupted.into());
+        }
+        Ok(())
+    }
+}
+
 /// The error type for [`DwarfError`].
 #[non_exhaustive]
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -86,8 +150,14 @@ pub enum DwarfErrorKind {
     /// The debug_ranges of a function are invalid.
     InvertedFunctionRange,
 
+    /// A sequence in the line program is invalid and was truncated.
+    InvalidLineSequence,
+
     /// The DWARF file is corrupted. See the cause for more information.
     CorruptedData,
+
+    /// Processing was interrupted, see [`DwarfFunctionIterator::with_interrupt`].
+    Interrupted,
 }
 
 impl fmt::Display for DwarfErrorKind {
@@ -99,7 +169,9 @@ impl fmt::Display for DwarfErrorKind {
             Self::InvalidFileRef(id) => write!(f, "referenced file {} does not exist", id),
             Self::UnexpectedInline => write!(f, "unexpected inline function without parent"),
             Self::InvertedFunctionRange => write!(f, "function with inverted address range"),
+            Self::InvalidLineSequence => write!(f, "invalid sequence in line program"),
             Self::CorruptedData => write!(f, "corrupted dwarf debug data"),
+            Self::Interrupted => write!(f, "processing was interrupted"),
         }
     }
 }
@@ -109,6 +181,8 @@ impl fmt::Display for DwarfErrorKind {
 #[error("{kind}")]
 pub struct DwarfError {
     kind: DwarfErrorKind,
+    unit_name: Option<String>,
+    die_offset: Option<u64>,
     #[source]
     source: Option<Box<dyn Error + Send + Sync + 'static>>,
 }
@@ -121,18 +195,55 @@ impl DwarfError {
         E: Into<Box<dyn Error + Send + Sync>>,
     {
         let source = Some(source.into());
-        Self { kind, source }
+        Self {
+            kind,
+            unit_name: None,
+            die_offset: None,
+            source,
+        }
     }
 
     /// Returns the corresponding [`DwarfErrorKind`] for this error.
     pub fn kind(&self) -> DwarfErrorKind {
         self.kind
     }
+
+    /// Returns the name of the compilation unit in which this error occurred, if known.
+    ///
+    /// This is usually the path of the primary source file of the unit.
+    pub fn unit_name(&self) -> Option<&str> {
+        self.unit_name.as_deref()
+    }
+
+    /// Returns the offset of the debugging information entry that caused this error, if known.
+    ///
+    /// The offset is relative to the start of the `.debug_info` section, or the `.debug_info.dwo`
+    /// section for split units.
+    pub fn die_offset(&self) -> Option<u64> {
+        self.die_offset
+    }
+
+    /// Attaches the name of the compilation unit, unless a name is already set.
+    fn with_unit_name(mut self, unit_name: Option<String>) -> Self {
+        self.unit_name = self.unit_name.or(unit_name);
+        self
+    }
+
+    /// Attaches the offset of the debugging information entry, unless an offset is already set.
+    fn with_die_offset(mut self, die_offset: Option<u64>) -> Self {
+        self.die_offset = self.die_offset.or(die_offset);
+        self
+    }
 }
 
 impl From<DwarfErrorKind> for DwarfError {
     fn from(kind: DwarfErrorKind) -> Self {
-        Self { kind, source: None }
+        Self {
+            kind,
+            unit_name: None,
+            die_offset: None,
+            source: None,
+        }
     }
 }
 
@@ -223,7 +334,11 @@ struct DwarfRow {
     address: u64,
     file_index: u64,
     line: Option<u64>,
+    column: u64,
     size: Option<u64>,
+    prologue_end: bool,
+    is_stmt: bool,
+    basic_block: bool,
 }
 
 /// A sequence in the DWARF line program.
@@ -239,16 +354,34 @@ struct DwarfSequence {
 struct DwarfLineProgram<'d> {
     header: LineNumberProgramHeader<'d>,
     sequences: Vec<DwarfSequence>,
+    /// The error that truncated the line program, if any.
+    error: Option<GimliError>,
 }
 
 impl<'d, 'a> DwarfLineProgram<'d> {
-    fn prepare(program: IncompleteLineNumberProgram<'d>) -> Self {
+    fn prepare(
+        program: IncompleteLineNumberProgram<'d>,
+        interrupt: Option<&dyn Fn() -> bool>,
+    ) -> Result<Self, DwarfError> {
         let mut sequences = Vec::new();
         let mut sequence_rows = Vec::<DwarfRow>::new();
         let mut prev_address = 0;
         let mut state_machine = program.rows();
+        let mut error = None;
+        let mut interrupt = InterruptCheck::new(interrupt);
 
-        while let Ok(Some((_, &program_row))) = state_machine.next_row() {
+        loop {
+            interrupt.step()?;
+            let program_row = match state_machine.next_row() {
+                Ok(Some((_, &program_row))) => program_row,
+                Ok(None) => break,
+                // gimli stops decoding the line program after an error. The sequences read so far
+                // are kept, and the broken sequence is truncated below.
+                Err(e) => {
+                    error = Some(e);
+                    break;
+                }
+            };
             let address = program_row.address();
 
             // we have seen rustc emit for WASM targets a bad sequence that spans from 0 to
@@ -291,11 +424,23 @@ impl<'d, 'a> DwarfLineProgram<'d> {
             } else {
                 let file_index = program_row.file_index();
                 let line = program_row.line().map(|v| v.get());
+                let column = match program_row.column() {
+                    ColumnType::LeftEdge => 0,
+                    ColumnType::Column(column) => column.get(),
+                };
+                let prologue_end = program_row.prologue_end();
+                let is_stmt = program_row.is_stmt();
+                let basic_block = program_row.basic_block();
                 let mut duplicate = false;
                 if let Some(last_row) = sequence_rows.last_mut() {
                     if last_row.address == address {
                         last_row.file_index = file_index;
                         last_row.line = line;
+                        last_row.column = column;
+                        // the prologue ends at this address if any of its rows says so
+                        last_row.prologue_end |= prologue_end;
+                        last_row.is_stmt = is_stmt;
+                        last_row.basic_block |= basic_block;
                         duplicate = true;
                     }
                 }
@@ -304,7 +449,11 @@ impl<'d, 'a> DwarfLineProgram<'d> {
                         address,
                         file_index,
                         line,
+                        column,
                         size: None,
+                        prologue_end,
+                        is_stmt,
+                        basic_block,
                     });
                 }
                 prev_address = address;
@@ -312,7 +461,7 @@ impl<'d, 'a> DwarfLineProgram<'d> {
         }
 
         if !sequence_rows.is_empty() {
-            // A sequence without an end_sequence row.
+            // A sequence without an end_sequence row, or one truncated by an error.
             // Let's assume the last row covered 1 byte.
             let start = sequence_rows[0].address;
             let end = prev_address + 1;
@@ -326,10 +475,11 @@ impl<'d, 'a> DwarfLineProgram<'d> {
         // Sequences are not guaranteed to be in order.
         dmsort::sort_by_key(&mut sequences, |x| x.start);
 
-        DwarfLineProgram {
+        Ok(DwarfLineProgram {
             header: state_machine.header().clone(),
             sequences,
-        }
+            error,
+        })
     }
 
     pub fn get_rows(&self, range: &Range) -> &[DwarfRow] {
@@ -357,6 +507,7 @@ impl<'d, 'a> DwarfLineProgram<'d> {
 #[derive(Clone, Copy, Debug)]
 struct UnitRef<'d, 'a> {
     info: &'a DwarfInfo<'d>,
+    dwarf: &'a DwarfInner<'d>,
     unit: &'a Unit<'d>,
 }
 
@@ -364,7 +515,7 @@ impl<'d, 'a> UnitRef<'d, 'a> {
     /// Resolve the binary value of an attribute.
     #[inline(always)]
     fn slice_value(&self, value: AttributeValue<Slice<'d>>) -> Option<&'d [u8]> {
-        self.info
+        self.dwarf
             .attr_string(self.unit, value)
             .map(|reader| reader.slice())
             .ok()
@@ -387,6 +538,12 @@ impl<'d, 'a> UnitRef<'d, 'a> {
     {
         let (unit, offset) = match attr.value() {
             AttributeValue::UnitRef(offset) => (*self, offset),
+            // Split units cannot reference into the units of the main file.
+            AttributeValue::DebugInfoRef(_) if self.unit.dwo_id.is_some() => return Ok(None),
+            AttributeValue::DebugInfoRefSup(offset) => match self.info.sup {
+                Some(ref sup) => sup.find_unit_offset(offset)?,
+                None => return Ok(None),
+            },
             AttributeValue::DebugInfoRef(offset) => self.info.find_unit_offset(offset)?,
             // TODO: There is probably more that can come back here.
             _ => return Ok(None),
@@ -402,17 +559,85 @@ impl<'d, 'a> UnitRef<'d, 'a> {
         }
     }
 
-    /// Returns the offset of this unit within its section.
-    fn offset(&self) -> UnitSectionOffset {
-        self.unit.header.offset()
+    /// Returns the source language declared by the root entry of this unit.
+    fn language(&self) -> Result<Language, DwarfError> {
+        let mut entries = self.unit.entries();
+        let entry = match entries.next_dfs()? {
+            Some((_, entry)) => entry,
+            None => return Err(gimli::read::Error::MissingUnitDie.into()),
+        };
+
+        Ok(match entry.attr_value(constants::DW_AT_language)? {
+            Some(AttributeValue::Language(lang)) => language_from_dwarf(lang),
+            _ => Language::Unknown,
+        })
+    }
+
+    /// Collects the qualified scopes of all functions declared within namespaces, classes,
+    /// structures and unions of this unit.
+    fn collect_scopes(&self) -> Result<Scopes, DwarfError> {
+        let mut scopes = Scopes::new();
+        let mut stack: Vec<(isize, Arc<str>)> = Vec::new();
+        let mut depth = 0;
+
+        let mut entries = self.unit.entries();
+        while let Some((movement, entry)) = entries.next_dfs()? {
+            depth += movement;
+            while matches!(stack.last(), Some(&(scope_depth, _)) if scope_depth >= depth) {
+                stack.pop();
+            }
+
+            let name = match entry.tag() {
+                constants::DW_TAG_subprogram => {
+                    if let Some((_, scope)) = stack.last() {
+                        scopes.insert(entry.offset(), scope.clone());
+                    }
+                    continue;
+                }
+                constants::DW_TAG_namespace => match entry.attr_value(constants::DW_AT_name)? {
+                    Some(value) => self.string_value(value),
+                    None => Some(Cow::Borrowed("(anonymous namespace)")),
+                },
+                constants::DW_TAG_class_type
+                | constants::DW_TAG_structure_type
+                | constants::DW_TAG_union_type
+                | constants::DW_TAG_interface_type => entry
+                    .attr_value(constants::DW_AT_name)?
+                    .and_then(|value| self.string_value(value)),
+                _ => continue,
+            };
+
+            // unnamed types do not contribute to the name, but still nest their members
+            let scope = match (stack.last(), name) {
+                (Some((_, parent)), Some(name)) => format!("{}::{}", parent, name).into(),
+                (Some((_, parent)), None) => parent.clone(),
+                (None, Some(name)) => name.into(),
+                (None, None) => continue,
+            };
+            stack.push((depth, scope));
+        }
+
+        Ok(scopes)
     }
 
     /// Resolves the function name of a debug entry.
+    ///
+    /// If the entry has no name itself, this follows its `DW_AT_abstract_origin` or
+    /// `DW_AT_specification` reference, up to [`MAX_NAME_REFERENCE_DEPTH`] times. Names of
+    /// referenced entries are stored in `cache`, since many inlined functions refer to the same
+    /// abstract origin.
+    ///
+    /// Names without a linkage name are qualified with the namespaces and types enclosing their
+    /// entry, which are collected into `scopes` on first use. Entries in other units are not
+    /// qualified.
     fn resolve_function_name(
         &self,
         entry: &Die<'d, '_>,
         language: Language,
         bcsymbolmap: Option<&'d BcSymbolMap<'d>>,
+        cache: &mut NameCache<'d>,
+        scopes: Option<&OnceCell<Scopes>>,
+        depth: usize,
     ) -> Result<Option<Name<'d>>, DwarfError> {
         let mut attrs = entry.attrs();
         let mut fallback_name = None;
@@ -438,42 +663,151 @@ impl<'d, 'a> UnitRef<'d, 'a> {
         }
 
         if let Some(attr) = fallback_name {
-            return Ok(self
-                .string_value(attr.value())
-                .map(|n| resolve_cow_name(bcsymbolmap, n))
-                .map(|n| Name::new(n, NameMangling::Unmangled, language)));
+            let name = match self.string_value(attr.value()) {
+                Some(name) => resolve_cow_name(bcsymbolmap, name),
+                None => return Ok(None),
+            };
+            let scope = match scopes {
+                Some(scopes) => scopes
+                    .get_or_try_init(|| self.collect_scopes())?
+                    .get(&entry.offset()),
+                None => None,
+            };
+            let name = match scope {
+                Some(scope) => Cow::Owned(format!("{}::{}", scope, name)),
+                None => name,
+            };
+            return Ok(Some(Name::new(name, NameMangling::Unmangled, language)));
         }
 
-        if let Some(attr) = reference_target {
+        if let Some(attr) = reference_target.filter(|_| depth < MAX_NAME_REFERENCE_DEPTH) {
             return self.resolve_reference(attr, |ref_unit, ref_entry| {
-                if self.offset() != ref_unit.offset() || entry.offset() != ref_entry.offset() {
-                    ref_unit.resolve_function_name(ref_entry, language, bcsymbolmap)
-                } else {
-                    Ok(None)
+                let key = (
+                    ref_unit.unit as *const Unit<'d> as usize,
+                    ref_entry.offset(),
+                );
+                if let Some(name) = cache.get(&key) {
+                    return Ok(name.clone());
                 }
+
+                let name = if std::ptr::eq(self.unit, ref_unit.unit) {
+                    if entry.offset() != ref_entry.offset() {
+                        ref_unit.resolve_function_name(
+                            ref_entry,
+                            language,
+                            bcsymbolmap,
+                            cache,
+                            scopes,
+                            depth + 1,
+                        )?
+                    } else {
+                        None
+                    }
+                } else {
+                    // With LTO, the referenced function may be defined in a unit of another
+                    // language, which determines how its name is demangled.
+                    let language = match ref_unit.language()? {
+                        Language::Unknown => language,
+                        ref_language => ref_language,
+                    };
+                    ref_unit.resolve_function_name(
+                        ref_entry,
+                        language,
+                        bcsymbolmap,
+                        cache,
+                        None,
+                        depth + 1,
+                    )?
+                };
+
+                cache.insert(key, name.clone());
+                Ok(name)
             });
         }
 
         Ok(None)
     }
+
+    /// Resolves the file index and line at which a function entry is declared.
+    ///
+    /// Like names, declarations are usually recorded on the entry referenced by
+    /// `DW_AT_abstract_origin` or `DW_AT_specification`. Since file indices refer to the line
+    /// program of their unit, references are only followed within the same unit, up to
+    /// [`MAX_NAME_REFERENCE_DEPTH`] times.
+    fn resolve_declaration(
+        &self,
+        entry: &Die<'d, '_>,
+        depth: usize,
+    ) -> Result<Option<(u64, u64)>, DwarfError> {
+        let mut attrs = entry.attrs();
+        let mut file = None;
+        let mut line = 0;
+        let mut reference_target = None;
+
+        while let Some(attr) = attrs.next()? {
+            match attr.name() {
+                constants::DW_AT_decl_file => {
+                    if let AttributeValue::FileIndex(file_id) = attr.value() {
+                        file = Some(file_id);
+                    }
+                }
+                constants::DW_AT_decl_line => line = attr.udata_value().unwrap_or(0),
+                constants::DW_AT_abstract_origin | constants::DW_AT_specification => {
+                    reference_target = Some(attr);
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(file) = file {
+            return Ok(Some((file, line)));
+        }
+
+        match reference_target {
+            Some(attr)
+                if depth < MAX_NAME_REFERENCE_DEPTH
+                    && matches!(attr.value(), AttributeValue::UnitRef(_)) =>
+            {
+                self.resolve_reference(attr, |ref_unit, ref_entry| {
+                    if entry.offset() == ref_entry.offset() {
+                        return Ok(None);
+                    }
+                    ref_unit.resolve_declaration(ref_entry, depth + 1)
+                })
+            }
+            _ => Ok(None),
+        }
+    }
 }
 
 /// Wrapper around a DWARF Unit.
 #[derive(Debug)]
 struct DwarfUnit<'d, 'a> {
     inner: UnitRef<'d, 'a>,
+    /// The unit holding the line program, which is the skeleton unit for split DWARF.
+    skeleton: UnitRef<'d, 'a>,
     bcsymbolmap: Option<&'d BcSymbolMap<'d>>,
     language: Language,
     line_program: Option<DwarfLineProgram<'d>>,
     prefer_dwarf_names: bool,
+    /// Names of entries referenced by functions of this unit, see
+    /// [`UnitRef::resolve_function_name`].
+    name_cache: RefCell<NameCache<'d>>,
+    /// The scopes of functions in this unit, collected on first use.
+    scopes: OnceCell<Scopes>,
 }
 
 impl<'d, 'a> DwarfUnit<'d, 'a> {
     /// Creates a DWARF unit from the gimli `Unit` type.
+    ///
+    /// If `split` is given, `unit` is a skeleton unit and the debugging information entries are
+    /// read from the split unit instead.
     fn from_unit(
         unit: &'a Unit<'d>,
+        split: Option<&'a SplitUnit<'d>>,
         info: &'a DwarfInfo<'d>,
         bcsymbolmap: Option<&'d BcSymbolMap<'d>>,
+        interrupt: Option<&dyn Fn() -> bool>,
     ) -> Result<Option<Self>, DwarfError> {
         let mut entries = unit.entries();
         let entry = match entries.next_dfs()? {
@@ -492,15 +826,38 @@ impl<'d, 'a> DwarfUnit<'d, 'a> {
             return Ok(None);
         }
 
-        let language = match entry.attr_value(constants::DW_AT_language)? {
-            Some(AttributeValue::Language(lang)) => language_from_dwarf(lang),
-            _ => Language::Unknown,
+        let skeleton = UnitRef {
+            info,
+            dwarf: &info.inner,
+            unit,
+        };
+        let inner = match split {
+            Some(split) => UnitRef {
+                info,
+                dwarf: &split.dwarf,
+                unit: &split.unit,
+            },
+            None => skeleton,
         };
 
+        // The skeleton unit only carries the line program, all other attributes of the unit are
+        // stored in the split unit.
+        let mut split_entries = inner.unit.entries();
+        let entry = match split {
+            Some(_) => match split_entries.next_dfs()? {
+                Some((_, entry)) => entry,
+                None => return Err(gimli::read::Error::MissingUnitDie.into()),
+            },
+            None => entry,
+        };
+
+        let language = inner.language()?;
+
         let line_program = unit
             .line_program
             .as_ref()
-            .map(|program| DwarfLineProgram::prepare(program.clone()));
+            .map(|program| DwarfLineProgram::prepare(program.clone(), interrupt))
+            .transpose()?;
 
         let producer = match entry.attr_value(constants::DW_AT_producer)? {
             Some(AttributeValue::String(string)) => Some(string),
@@ -512,17 +869,32 @@ impl<'d, 'a> DwarfUnit<'d, 'a> {
         let prefer_dwarf_names = producer.as_deref() == Some(b"Dart VM");
 
         Ok(Some(DwarfUnit {
-            inner: UnitRef { info, unit },
+            inner,
+            skeleton,
             bcsymbolmap,
             language,
             line_program,
             prefer_dwarf_names,
+            name_cache: RefCell::default(),
+            scopes: OnceCell::new(),
         }))
     }
 
+    /// The name of this unit, which is usually the path of its primary source file.
+    fn name(&self) -> Option<String> {
+        unit_name(self.skeleton.unit, self.bcsymbolmap)
+    }
+
+    /// Returns the error that truncated the line program of this unit, if any.
+    fn line_program_error(&self) -> Option<DwarfError> {
+        let error = self.line_program.as_ref()?.error?;
+        let error = DwarfError::new(DwarfErrorKind::InvalidLineSequence, error);
+        Some(error.with_unit_name(self.name()))
+    }
+
     /// The path of the compilation directory. File names are usually relative to this path.
     fn compilation_dir(&self) -> &'d [u8] {
-        match self.inner.unit.comp_dir {
+        match self.skeleton.unit.comp_dir {
             Some(ref dir) => resolve_byte_name(self.bcsymbolmap, dir.slice()),
             None => &[],
         }
@@ -533,13 +905,14 @@ impl<'d, 'a> DwarfUnit<'d, 'a> {
         &self,
         entry: &Die<'d, '_>,
         range_buf: &mut Vec<Range>,
-    ) -> Result<(Option<u64>, Option<u64>), DwarfError> {
-        let mut tuple = (None, None);
+    ) -> Result<(Option<u64>, Option<u64>, u64), DwarfError> {
+        let mut tuple = (None, None, 0);
         let mut low_pc = None;
         let mut high_pc = None;
         let mut high_pc_rel = None;
 
         let kind = self.inner.info.kind;
+        let tombstone = tombstone_address(self.inner.unit.encoding().address_size);
 
         let mut attrs = entry.attrs();
         while let Some(attr) = attrs.next()? {
@@ -547,14 +920,14 @@ impl<'d, 'a> DwarfUnit<'d, 'a> {
                 constants::DW_AT_low_pc => match attr.value() {
                     AttributeValue::Addr(addr) => low_pc = Some(addr),
                     AttributeValue::DebugAddrIndex(index) => {
-                        low_pc = Some(self.inner.info.address(self.inner.unit, index)?)
+                        low_pc = Some(self.inner.dwarf.address(self.inner.unit, index)?)
                     }
                     _ => return Err(GimliError::UnsupportedAttributeForm.into()),
                 },
                 constants::DW_AT_high_pc => match attr.value() {
                     AttributeValue::Addr(addr) => high_pc = Some(addr),
                     AttributeValue::DebugAddrIndex(index) => {
-                        low_pc = Some(self.inner.info.address(self.inner.unit, index)?)
+                        high_pc = Some(self.inner.dwarf.address(self.inner.unit, index)?)
                     }
                     AttributeValue::Udata(size) => high_pc_rel = Some(size),
                     _ => return Err(GimliError::UnsupportedAttributeForm.into()),
@@ -567,10 +940,18 @@ impl<'d, 'a> DwarfUnit<'d, 'a> {
                     AttributeValue::FileIndex(file) => tuple.1 = Some(file),
                     _ => return Err(GimliError::UnsupportedAttributeForm.into()),
                 },
+                constants::DW_AT_call_column => match attr.value() {
+                    AttributeValue::Udata(column) => tuple.2 = column,
+                    _ => return Err(GimliError::UnsupportedAttributeForm.into()),
+                },
                 constants::DW_AT_ranges
                 | constants::DW_AT_rnglists_base
                 | constants::DW_AT_start_scope => {
-                    match self.inner.info.attr_ranges(self.inner.unit, attr.value())? {
+                    match self
+                        .inner
+                        .dwarf
+                        .attr_ranges(self.inner.unit, attr.value())?
+                    {
                         Some(mut ranges) => {
                             while let Some(range) = match ranges.next() {
                                 Ok(range) => range,
@@ -584,9 +965,10 @@ impl<'d, 'a> DwarfUnit<'d, 'a> {
                                     return Err(err.into());
                                 }
                             } {
-                                // A range that begins at 0 indicates code that was eliminated by
-                                // the linker, see below.
-                                if range.begin > 0 || kind == ObjectKind::Relocatable {
+                                // A range that begins at 0 or at a tombstone address indicates
+                                // code that was eliminated by the linker, see below.
+                                let eliminated = range.begin == 0 || range.begin >= tombstone;
+                                if !eliminated || kind == ObjectKind::Relocatable {
                                     range_buf.push(range);
                                 }
                             }
@@ -607,8 +989,13 @@ impl<'d, 'a> DwarfUnit<'d, 'a> {
         // eliminated duplicate when the GNU linker is used. In relocatable
         // objects, all functions are at `0` since they have not been placed
         // yet, so we want to retain them.
+        //
+        // Newer linkers, in particular `wasm-ld`, instead replace the addresses of eliminated
+        // code with a tombstone value. Since WASM addresses are made relative to the code
+        // section, such functions would otherwise end up right behind the valid address space.
         let low_pc = match low_pc {
-            Some(low_pc) if low_pc != 0 || kind == ObjectKind::Relocatable => low_pc,
+            Some(low_pc) if low_pc != 0 && low_pc < tombstone => low_pc,
+            Some(low_pc) if kind == ObjectKind::Relocatable => low_pc,
             _ => return Ok(tuple),
         };
 
@@ -671,13 +1058,24 @@ impl<'d, 'a> DwarfUnit<'d, 'a> {
                     size: first.size.map(|s| s + first.address - range.begin),
                     file: self.resolve_file(first.file_index).unwrap_or_default(),
                     line: first.line.unwrap_or(0),
+                    column: first.column,
+                    // the first row may start before the range, in which case the range starts
+                    // after the end of the prologue
+                    prologue_end: first.prologue_end && first.address >= range.begin,
+                    is_stmt: first.is_stmt,
+                    basic_block: first.basic_block && first.address >= range.begin,
                 };
 
                 for row in rows {
                     let line = row.line.unwrap_or(0);
 
-                    // We're in a range so we can collapse the lines without any side effects
-                    if (last_file, last_info.line) == (row.file_index, line) {
+                    // We're in a range so we can collapse the lines without any side effects, unless
+                    // the prologue ends at this row. The collapsed line keeps the statement and
+                    // basic block flags of its first row.
+                    if (last_file, last_info.line, last_info.column)
+                        == (row.file_index, line, row.column)
+                        && !row.prologue_end
+                    {
                         // We collapse the lines but need to fix the last line size
                         if let Some(size) = last_info.size.as_mut() {
                             *size += row.size.unwrap_or(0);
@@ -695,6 +1093,10 @@ impl<'d, 'a> DwarfUnit<'d, 'a> {
                         size: row.size,
                         file: self.resolve_file(row.file_index).unwrap_or_default(),
                         line,
+                        column: row.column,
+                        prologue_end: row.prologue_end,
+                        is_stmt: row.is_stmt,
+                        basic_block: row.basic_block,
                     };
                 }
 
@@ -720,12 +1122,14 @@ impl<'d, 'a> DwarfUnit<'d, 'a> {
             dir: resolve_byte_name(
                 self.bcsymbolmap,
                 file.directory(line_program)
-                    .and_then(|attr| self.inner.slice_value(attr))
+                    .and_then(|attr| self.skeleton.slice_value(attr))
                     .unwrap_or_default(),
             ),
             name: resolve_byte_name(
                 self.bcsymbolmap,
-                self.inner.slice_value(file.path_name()).unwrap_or_default(),
+                self.skeleton
+                    .slice_value(file.path_name())
+                    .unwrap_or_default(),
             ),
         }
     }
@@ -751,8 +1155,16 @@ impl<'d, 'a> DwarfUnit<'d, 'a> {
 
     /// Resolves the name of a function from DWARF debug information.
     fn resolve_dwarf_name(&self, entry: &Die<'d, '_>) -> Option<Name<'d>> {
+        let mut cache = self.name_cache.borrow_mut();
         self.inner
-            .resolve_function_name(entry, self.language, self.bcsymbolmap)
+            .resolve_function_name(
+                entry,
+                self.language,
+                self.bcsymbolmap,
+                &mut cache,
+                Some(&self.scopes),
+                0,
+            )
             .ok()
             .flatten()
     }
@@ -762,14 +1174,17 @@ impl<'d, 'a> DwarfUnit<'d, 'a> {
         &self,
         range_buf: &mut Vec<Range>,
         seen_ranges: &mut BTreeSet<(u64, u64)>,
+        interrupt: Option<&dyn Fn() -> bool>,
     ) -> Result<Vec<Function<'d>>, DwarfError> {
         let mut depth = 0;
         let mut skipped_depth = None;
         let mut functions = Vec::new();
+        let mut interrupt = InterruptCheck::new(interrupt);
 
         let mut stack = FunctionStack::new();
         let mut entries = self.inner.unit.entries();
         while let Some((movement, entry)) = entries.next_dfs()? {
+            interrupt.step()?;
             depth += movement;
 
             // If we're navigating within a skipped function (see below), we can ignore this
@@ -792,8 +1207,15 @@ impl<'d, 'a> DwarfUnit<'d, 'a> {
                 _ => continue,
             };
 
+            let die_offset = match entry.offset().to_unit_section_offset(self.inner.unit) {
+                UnitSectionOffset::DebugInfoOffset(offset) => offset.0 as u64,
+                UnitSectionOffset::DebugTypesOffset(offset) => offset.0 as u64,
+            };
+
             range_buf.clear();
-            let (call_line, call_file) = self.parse_ranges(entry, range_buf)?;
+            let (call_line, call_file, column) = self
+                .parse_ranges(entry, range_buf)
+                .map_err(|e| e.with_die_offset(Some(die_offset)))?;
 
             // Ranges can be empty for two reasons: (1) the function is a no-op and does not
             // contain any code, or (2) the function did contain eliminated dead code. In the
@@ -845,6 +1267,12 @@ impl<'d, 'a> DwarfUnit<'d, 'a> {
                 .or_else(|| self.resolve_dwarf_name(entry))
                 .unwrap_or_else(|| Name::new("", NameMangling::Unmangled, self.language));
 
+            // Declarations are optional, so errors in them do not invalidate the function.
+            let (decl_file, decl_line) = match self.inner.resolve_declaration(entry, 0) {
+                Ok(Some((file_id, line))) => (self.resolve_file(file_id), line),
+                _ => (None, 0),
+            };
+
             // Avoid constant allocations by collecting repeatedly into the same buffer and
             // draining the results out of it. This keeps the original buffer allocated and
             // allows for a single allocation per call to `resolve_lines`.
@@ -855,7 +1283,10 @@ impl<'d, 'a> DwarfUnit<'d, 'a> {
                 // indicates invalid debug information.
                 let parent = match stack.peek_mut() {
                     Some(parent) => parent,
-                    None => return Err(DwarfErrorKind::UnexpectedInline.into()),
+                    None => {
+                        let error = DwarfError::from(DwarfErrorKind::UnexpectedInline);
+                        return Err(error.with_die_offset(Some(die_offset)));
+                    }
                 };
 
                 // Make sure there is correct line information for the call site of this inlined
@@ -883,6 +1314,10 @@ impl<'d, 'a> DwarfUnit<'d, 'a> {
                                     size: Some(range_end.min(next.address) - range_begin),
                                     file: file.clone(),
                                     line,
+                                    column,
+                                    prologue_end: false,
+                                    is_stmt: true,
+                                    basic_block: false,
                                 };
 
                                 lines.insert(index, line_info);
@@ -918,6 +1353,10 @@ impl<'d, 'a> DwarfUnit<'d, 'a> {
                                     size: Some(record_end - range_end),
                                     file: record.file.clone(),
                                     line: record.line,
+                                    column: record.column,
+                                    prologue_end: false,
+                                    is_stmt: record.is_stmt,
+                                    basic_block: false,
                                 })
                             } else {
                                 None
@@ -943,6 +1382,10 @@ impl<'d, 'a> DwarfUnit<'d, 'a> {
                                     size: Some(size),
                                     file: file.clone(),
                                     line,
+                                    column,
+                                    prologue_end: false,
+                                    is_stmt: true,
+                                    basic_block: false,
                                 };
 
                                 lines.insert(index, line_info);
@@ -950,6 +1393,7 @@ impl<'d, 'a> DwarfUnit<'d, 'a> {
                             } else {
                                 record.file = file.clone();
                                 record.line = line;
+                                record.column = column;
                             };
 
                             // Insert the split record after mutating the previous one to avoid
@@ -970,6 +1414,10 @@ impl<'d, 'a> DwarfUnit<'d, 'a> {
                                     size: Some(range_end - record_end),
                                     file: file.clone(),
                                     line,
+                                    column,
+                                    prologue_end: false,
+                                    is_stmt: true,
+                                    basic_block: false,
                                 };
 
                                 lines.insert(index, line_info);
@@ -988,6 +1436,8 @@ impl<'d, 'a> DwarfUnit<'d, 'a> {
                 lines,
                 inlinees: Vec::new(),
                 inline,
+                decl_file,
+                decl_line,
             };
 
             stack.push(depth, function)
@@ -1007,10 +1457,13 @@ fn language_from_dwarf(language: gimli::DwLang) -> Language {
         constants::DW_LANG_C11 => Language::C,
         constants::DW_LANG_C89 => Language::C,
         constants::DW_LANG_C99 => Language::C,
+        constants::DW_LANG_C17 => Language::C,
         constants::DW_LANG_C_plus_plus => Language::Cpp,
         constants::DW_LANG_C_plus_plus_03 => Language::Cpp,
         constants::DW_LANG_C_plus_plus_11 => Language::Cpp,
         constants::DW_LANG_C_plus_plus_14 => Language::Cpp,
+        constants::DW_LANG_C_plus_plus_17 => Language::Cpp,
+        constants::DW_LANG_C_plus_plus_20 => Language::Cpp,
         constants::DW_LANG_D => Language::D,
         constants::DW_LANG_Go => Language::Go,
         constants::DW_LANG_ObjC => Language::ObjC,
@@ -1034,12 +1487,27 @@ where
 {
     /// Loads data for this section from the object file.
     fn load<D>(dwarf: &D) -> Self
+    where
+        D: Dwarf<'data>,
+    {
+        Self::load_named(dwarf, Some(S::section_name()))
+    }
+
+    /// Loads data for the `.dwo` variant of this section from a split DWARF file.
+    fn load_dwo<D>(dwarf: &D) -> Self
+    where
+        D: Dwarf<'data>,
+    {
+        Self::load_named(dwarf, S::id().dwo_name())
+    }
+
+    fn load_named<D>(dwarf: &D, name: Option<&str>) -> Self
     where
         D: Dwarf<'data>,
     {
         DwarfSectionData {
-            data: dwarf
-                .section(&S::section_name()[1..])
+            data: name
+                .and_then(|name| dwarf.section(&name[1..]))
                 .map(|section| section.data)
                 .unwrap_or_default(),
             endianity: dwarf.endianity(),
@@ -1075,6 +1543,7 @@ where
 /// All DWARF sections that are needed by `DwarfDebugSession`.
 struct DwarfSections<'data> {
     debug_abbrev: DwarfSectionData<'data, gimli::read::DebugAbbrev<Slice<'data>>>,
+    debug_addr: DwarfSectionData<'data, gimli::read::DebugAddr<Slice<'data>>>,
     debug_info: DwarfSectionData<'data, gimli::read::DebugInfo<Slice<'data>>>,
     debug_line: DwarfSectionData<'data, gimli::read::DebugLine<Slice<'data>>>,
     debug_line_str: DwarfSectionData<'data, gimli::read::DebugLineStr<Slice<'data>>>,
@@ -1082,6 +1551,8 @@ struct DwarfSections<'data> {
     debug_str_offsets: DwarfSectionData<'data, gimli::read::DebugStrOffsets<Slice<'data>>>,
     debug_ranges: DwarfSectionData<'data, gimli::read::DebugRanges<Slice<'data>>>,
     debug_rnglists: DwarfSectionData<'data, gimli::read::DebugRngLists<Slice<'data>>>,
+    split: Vec<DwarfSplitSections<'data>>,
+    sup: Option<Box<DwarfSections<'data>>>,
 }
 
 impl<'data> DwarfSections<'data> {
@@ -1092,6 +1563,7 @@ impl<'data> DwarfSections<'data> {
     {
         DwarfSections {
             debug_abbrev: DwarfSectionData::load(dwarf),
+            debug_addr: DwarfSectionData::load(dwarf),
             debug_info: DwarfSectionData::load(dwarf),
             debug_line: DwarfSectionData::load(dwarf),
             debug_line_str: DwarfSectionData::load(dwarf),
@@ -1099,14 +1571,136 @@ impl<'data> DwarfSections<'data> {
             debug_str_offsets: DwarfSectionData::load(dwarf),
             debug_ranges: DwarfSectionData::load(dwarf),
             debug_rnglists: DwarfSectionData::load(dwarf),
+            split: Vec::new(),
+            sup: None,
+        }
+    }
+
+    /// Creates the gimli DWARF object of the main file.
+    fn to_gimli(&'data self) -> DwarfInner<'data> {
+        gimli::read::Dwarf {
+            debug_abbrev: self.debug_abbrev.to_gimli(),
+            debug_addr: self.debug_addr.to_gimli(),
+            debug_aranges: Default::default(),
+            debug_info: self.debug_info.to_gimli(),
+            debug_line: self.debug_line.to_gimli(),
+            debug_line_str: self.debug_line_str.to_gimli(),
+            debug_str: self.debug_str.to_gimli(),
+            debug_str_offsets: self.debug_str_offsets.to_gimli(),
+            debug_types: Default::default(),
+            locations: Default::default(),
+            ranges: RangeLists::new(self.debug_ranges.to_gimli(), self.debug_rnglists.to_gimli()),
+            file_type: DwarfFileType::Main,
+            sup: Default::default(),
         }
     }
 }
 
+/// All DWARF sections that are needed from a split DWARF file, either a `.dwo` or a `.dwp`.
+struct DwarfSplitSections<'data> {
+    debug_abbrev: DwarfSectionData<'data, gimli::read::DebugAbbrev<Slice<'data>>>,
+    debug_info: DwarfSectionData<'data, gimli::read::DebugInfo<Slice<'data>>>,
+    debug_line: DwarfSectionData<'data, gimli::read::DebugLine<Slice<'data>>>,
+    debug_str: DwarfSectionData<'data, gimli::read::DebugStr<Slice<'data>>>,
+    debug_str_offsets: DwarfSectionData<'data, gimli::read::DebugStrOffsets<Slice<'data>>>,
+    // Location lists are not read, but `.dwp` indexes reference their contributions.
+    debug_loc: DwarfSectionData<'data, gimli::read::DebugLoc<Slice<'data>>>,
+    debug_loclists: DwarfSectionData<'data, gimli::read::DebugLocLists<Slice<'data>>>,
+    debug_rnglists: DwarfSectionData<'data, gimli::read::DebugRngLists<Slice<'data>>>,
+    debug_cu_index: DwarfSectionData<'data, gimli::read::DebugCuIndex<Slice<'data>>>,
+}
+
+impl<'data> DwarfSplitSections<'data> {
+    /// Loads all `.dwo` sections from a split DWARF object.
+    fn from_dwarf<D>(dwarf: &D) -> Self
+    where
+        D: Dwarf<'data>,
+    {
+        DwarfSplitSections {
+            debug_abbrev: DwarfSectionData::load_dwo(dwarf),
+            debug_info: DwarfSectionData::load_dwo(dwarf),
+            debug_line: DwarfSectionData::load_dwo(dwarf),
+            debug_str: DwarfSectionData::load_dwo(dwarf),
+            debug_str_offsets: DwarfSectionData::load_dwo(dwarf),
+            debug_loc: DwarfSectionData::load_dwo(dwarf),
+            debug_loclists: DwarfSectionData::load_dwo(dwarf),
+            debug_rnglists: DwarfSectionData::load_dwo(dwarf),
+            debug_cu_index: DwarfSectionData::load_dwo(dwarf),
+        }
+    }
+
+    /// Prepares access to the split units in these sections.
+    fn to_split_dwarf(&'data self) -> Result<SplitDwarf<'data>, DwarfError> {
+        if self.debug_cu_index.data.is_empty() {
+            return Ok(SplitDwarf::Object(self));
+        }
+
+        Ok(SplitDwarf::Package(Box::new(DwarfPackage {
+            cu_index: self.debug_cu_index.to_gimli().index()?,
+            tu_index: gimli::read::DebugTuIndex::default().index()?,
+            debug_abbrev: self.debug_abbrev.to_gimli(),
+            debug_info: self.debug_info.to_gimli(),
+            debug_line: self.debug_line.to_gimli(),
+            debug_str: self.debug_str.to_gimli(),
+            debug_str_offsets: self.debug_str_offsets.to_gimli(),
+            debug_loc: self.debug_loc.to_gimli(),
+            debug_loclists: self.debug_loclists.to_gimli(),
+            debug_rnglists: self.debug_rnglists.to_gimli(),
+            debug_types: Default::default(),
+            empty: Slice::new(&[], self.debug_info.endianity),
+        })))
+    }
+
+    /// Creates the DWARF of a `.dwo` object, borrowing addresses and ranges from its parent.
+    fn to_dwo(&'data self, parent: &DwarfInner<'data>) -> DwarfInner<'data> {
+        gimli::read::Dwarf {
+            debug_abbrev: self.debug_abbrev.to_gimli(),
+            debug_addr: parent.debug_addr,
+            debug_aranges: Default::default(),
+            debug_info: self.debug_info.to_gimli(),
+            debug_line: self.debug_line.to_gimli(),
+            debug_line_str: Default::default(),
+            debug_str: self.debug_str.to_gimli(),
+            debug_str_offsets: self.debug_str_offsets.to_gimli(),
+            debug_types: Default::default(),
+            locations: Default::default(),
+            ranges: RangeLists::new(
+                *parent.ranges.debug_ranges(),
+                self.debug_rnglists.to_gimli(),
+            ),
+            file_type: DwarfFileType::Dwo,
+            sup: Default::default(),
+        }
+    }
+}
+
+/// A split DWARF file, see [`DwarfSplitSections`].
+enum SplitDwarf<'data> {
+    /// A `.dwo` object containing the split units of one or more compilation units.
+    Object(&'data DwarfSplitSections<'data>),
+    /// A `.dwp` package with an index of all its split units.
+    Package(Box<DwarfPackage<'data>>),
+}
+
+/// The split units in `.dwo` objects by their DWO id, along with the index of their object.
+///
+/// Packages are not included, since they carry their own index.
+type SplitIndex<'data> = HashMap<gimli::DwoId, (usize, UnitHeader<'data>)>;
+
+/// A split unit resolved for a skeleton unit.
+struct SplitUnit<'data> {
+    dwarf: DwarfInner<'data>,
+    unit: Unit<'data>,
+}
+
 struct DwarfInfo<'data> {
     inner: DwarfInner<'data>,
     headers: Vec<UnitHeader<'data>>,
-    units: Vec<LazyCell<Option<Unit<'data>>>>,
+    units: Vec<OnceCell<Option<Unit<'data>>>>,
+    split: Vec<SplitDwarf<'data>>,
+    split_index: OnceCell<SplitIndex<'data>>,
+    split_units: Vec<OnceCell<Option<SplitUnit<'data>>>>,
+    sup: Option<Box<DwarfInfo<'data>>>,
     symbol_map: SymbolMap<'data>,
     address_offset: i64,
     kind: ObjectKind,
@@ -1128,33 +1722,42 @@ impl<'d> DwarfInfo<'d> {
         address_offset: i64,
         kind: ObjectKind,
     ) -> Result<Self, DwarfError> {
-        let inner = gimli::read::Dwarf {
-            debug_abbrev: sections.debug_abbrev.to_gimli(),
-            debug_addr: Default::default(),
-            debug_aranges: Default::default(),
-            debug_info: sections.debug_info.to_gimli(),
-            debug_line: sections.debug_line.to_gimli(),
-            debug_line_str: sections.debug_line_str.to_gimli(),
-            debug_str: sections.debug_str.to_gimli(),
-            debug_str_offsets: sections.debug_str_offsets.to_gimli(),
-            debug_types: Default::default(),
-            locations: Default::default(),
-            ranges: RangeLists::new(
-                sections.debug_ranges.to_gimli(),
-                sections.debug_rnglists.to_gimli(),
-            ),
-            file_type: DwarfFileType::Main,
-            sup: Default::default(),
+        let mut inner = sections.to_gimli();
+
+        // Strings in the supplementary file are resolved by gimli, while references to its units
+        // are resolved through a separate `DwarfInfo`.
+        let sup = match sections.sup {
+            Some(ref sup_sections) => {
+                inner.sup = Some(Arc::new(sup_sections.to_gimli()));
+                let sup = DwarfInfo::parse(sup_sections, SymbolMap::new(), address_offset, kind)?;
+                Some(Box::new(sup))
+            }
+            None => None,
         };
 
         // Prepare random access to unit headers.
         let headers = inner.units().collect::<Vec<_>>()?;
-        let units = headers.iter().map(|_| LazyCell::new()).collect();
+        let units = headers.iter().map(|_| OnceCell::new()).collect();
+
+        let split = sections
+            .split
+            .iter()
+            .map(DwarfSplitSections::to_split_dwarf)
+            .collect::<Result<Vec<_>, _>>()?;
+        let split_units = if split.is_empty() {
+            Vec::new()
+        } else {
+            headers.iter().map(|_| OnceCell::new()).collect()
+        };
 
         Ok(DwarfInfo {
             inner,
             headers,
             units,
+            split,
+            split_index: OnceCell::new(),
+            split_units,
+            sup,
             symbol_map,
             address_offset,
             kind,
@@ -1169,7 +1772,7 @@ impl<'d> DwarfInfo<'d> {
             None => return Ok(None),
         };
 
-        let unit_opt = cell.try_borrow_with(|| {
+        let unit_opt = cell.get_or_try_init(|| {
             // Parse the compilation unit from the header. This requires a top-level DIE that
             // describes the unit itself. For some older DWARF files, this DIE might be missing
             // which causes gimli to error out. We prefer to skip them silently as this simply marks
@@ -1185,6 +1788,93 @@ impl<'d> DwarfInfo<'d> {
         Ok(unit_opt.as_ref())
     }
 
+    /// Loads the split unit of the skeleton unit at the given index.
+    ///
+    /// Returns `None` if the unit is not a skeleton unit or its split unit cannot be found.
+    fn get_split_unit(
+        &self,
+        index: usize,
+        skeleton: &Unit<'d>,
+    ) -> Result<Option<&SplitUnit<'d>>, DwarfError> {
+        let cell = match self.split_units.get(index) {
+            Some(cell) => cell,
+            None => return Ok(None),
+        };
+
+        let split_opt = cell.get_or_try_init(|| self.find_split_unit(skeleton))?;
+        Ok(split_opt.as_ref())
+    }
+
+    /// Searches all split DWARF files for the split unit of a skeleton unit.
+    fn find_split_unit(&self, skeleton: &Unit<'d>) -> Result<Option<SplitUnit<'d>>, DwarfError> {
+        let dwo_id = match skeleton.dwo_id {
+            Some(dwo_id) => dwo_id,
+            None => return Ok(None),
+        };
+
+        let split_index = self
+            .split_index
+            .get_or_try_init(|| self.build_split_index())?;
+
+        for (index, split) in self.split.iter().enumerate() {
+            let (dwarf, mut unit) = match split {
+                SplitDwarf::Object(sections) => match split_index.get(&dwo_id) {
+                    Some(&(object, header)) if object == index => {
+                        let dwarf = sections.to_dwo(&self.inner);
+                        let unit = dwarf.unit(header)?;
+                        (dwarf, unit)
+                    }
+                    _ => continue,
+                },
+                SplitDwarf::Package(package) => {
+                    let dwarf = match package.find_cu(dwo_id, &self.inner)? {
+                        Some(dwarf) => dwarf,
+                        None => continue,
+                    };
+                    // the package index selects the sections of a single compilation unit
+                    let header = match dwarf.units().next()? {
+                        Some(header) => header,
+                        None => continue,
+                    };
+                    let unit = dwarf.unit(header)?;
+                    if unit.dwo_id != Some(dwo_id) {
+                        continue;
+                    }
+                    (dwarf, unit)
+                }
+            };
+
+            unit.copy_relocated_attributes(skeleton);
+            return Ok(Some(SplitUnit { dwarf, unit }));
+        }
+
+        Ok(None)
+    }
+
+    /// Indexes the units of all `.dwo` objects by their DWO id.
+    ///
+    /// If several objects contain the same unit, the first one wins.
+    fn build_split_index(&self) -> Result<SplitIndex<'d>, DwarfError> {
+        let mut split_index = SplitIndex::new();
+
+        for (index, split) in self.split.iter().enumerate() {
+            let sections = match split {
+                SplitDwarf::Object(sections) => sections,
+                SplitDwarf::Package(_) => continue,
+            };
+
+            let dwarf = sections.to_dwo(&self.inner);
+            let mut headers = dwarf.units();
+            while let Some(header) = headers.next()? {
+                if let Some(dwo_id) = dwarf.unit(header)?.dwo_id {
+                    split_index.entry(dwo_id).or_insert((index, header));
+                }
+            }
+        }
+
+        Ok(split_index)
+    }
+
     /// Resolves an offset into a different compilation unit.
     fn find_unit_offset(
         &self,
@@ -1203,7 +1893,12 @@ impl<'d> DwarfInfo<'d> {
 
         if let Some(unit) = self.get_unit(index)? {
             if let Some(unit_offset) = section_offset.to_unit_offset(unit) {
-                return Ok((UnitRef { unit, info: self }, unit_offset));
+                let unit_ref = UnitRef {
+                    info: self,
+                    dwarf: &self.inner,
+                    unit,
+                };
+                return Ok((unit_ref, unit_offset));
             }
         }
 
@@ -1212,10 +1907,21 @@ impl<'d> DwarfInfo<'d> {
 
     /// Returns an iterator over all compilation units.
     fn units(&'d self, bcsymbolmap: Option<&'d BcSymbolMap<'d>>) -> DwarfUnitIterator<'_> {
+        self.units_in(0..self.headers.len(), bcsymbolmap)
+    }
+
+    /// Returns an iterator over the compilation units with the given indices.
+    fn units_in(
+        &'d self,
+        indices: std::ops::Range<usize>,
+        bcsymbolmap: Option<&'d BcSymbolMap<'d>>,
+    ) -> DwarfUnitIterator<'d> {
         DwarfUnitIterator {
             info: self,
             bcsymbolmap,
-            index: 0,
+            index: indices.start,
+            end: indices.end.min(self.headers.len()),
+            interrupt: None,
         }
     }
 }
@@ -1243,13 +1949,15 @@ struct DwarfUnitIterator<'s> {
     info: &'s DwarfInfo<'s>,
     bcsymbolmap: Option<&'s BcSymbolMap<'s>>,
     index: usize,
+    end: usize,
+    interrupt: Option<Interrupt>,
 }
 
 impl<'s> Iterator for DwarfUnitIterator<'s> {
     type Item = Result<DwarfUnit<'s, 's>, DwarfError>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        while self.index < self.info.headers.len() {
+        while self.index < self.end {
             let result = self.info.get_unit(self.index);
             self.index += 1;
 
@@ -1259,10 +1967,22 @@ impl<'s> Iterator for DwarfUnitIterator<'s> {
                 Err(error) => return Some(Err(error)),
             };
 
-            match DwarfUnit::from_unit(unit, self.info, self.bcsymbolmap) {
+            let split = match self.info.get_split_unit(self.index - 1, unit) {
+                Ok(split) => split,
+                Err(error) => {
+                    let unit_name = unit_name(unit, self.bcsymbolmap);
+                    return Some(Err(error.with_unit_name(unit_name)));
+                }
+            };
+
+            let interrupt = self.interrupt.as_deref();
+            match DwarfUnit::from_unit(unit, split, self.info, self.bcsymbolmap, interrupt) {
                 Ok(Some(unit)) => return Some(Ok(unit)),
                 Ok(None) => continue,
-                Err(error) => return Some(Err(error)),
+                Err(error) => {
+                    let unit_name = unit_name(unit, self.bcsymbolmap);
+                    return Some(Err(error.with_unit_name(unit_name)));
+                }
             }
         }
 
@@ -1289,7 +2009,59 @@ impl<'data> DwarfDebugSession<'data> {
     where
         D: Dwarf<'data>,
     {
-        let sections = DwarfSections::from_dwarf(dwarf);
+        Self::parse_split::<D, D>(dwarf, &[], symbol_map, address_offset, kind)
+    }
+
+    /// Parses dwarf debugging information from a DWARF file and its split DWARF files.
+    ///
+    /// Projects compiled with `-gsplit-dwarf` only leave skeleton units in the main file, which
+    /// reference their full compilation units in `.dwo` files or a `.dwp` package. The skeleton
+    /// units are resolved to their split units in `split`, which may contain any number of `.dwo`
+    /// and `.dwp` files. Skeleton units without a matching split unit do not yield any functions.
+    pub fn parse_split<D, S>(
+        dwarf: &D,
+        split: &[S],
+        symbol_map: SymbolMap<'data>,
+        address_offset: i64,
+        kind: ObjectKind,
+    ) -> Result<Self, DwarfError>
+    where
+        D: Dwarf<'data>,
+        S: Dwarf<'data>,
+    {
+        let mut sections = DwarfSections::from_dwarf(dwarf);
+        sections.split = split.iter().map(DwarfSplitSections::from_dwarf).collect();
+        Self::from_sections(sections, symbol_map, address_offset, kind)
+    }
+
+    /// Parses dwarf debugging information from a DWARF file and its supplementary file.
+    ///
+    /// Tools like `dwz` move debugging information entries and strings shared by multiple files
+    /// into a supplementary object file, which is referenced via `.gnu_debugaltlink` or
+    /// `.debug_sup`. Without this file, function names referenced from the supplementary file
+    /// cannot be resolved.
+    pub fn parse_with_sup<D, S>(
+        dwarf: &D,
+        sup: &S,
+        symbol_map: SymbolMap<'data>,
+        address_offset: i64,
+        kind: ObjectKind,
+    ) -> Result<Self, DwarfError>
+    where
+        D: Dwarf<'data>,
+        S: Dwarf<'data>,
+    {
+        let mut sections = DwarfSections::from_dwarf(dwarf);
+        sections.sup = Some(Box::new(DwarfSections::from_dwarf(sup)));
+        Self::from_sections(sections, symbol_map, address_offset, kind)
+    }
+
+    fn from_sections(
+        sections: DwarfSections<'data>,
+        symbol_map: SymbolMap<'data>,
+        address_offset: i64,
+        kind: ObjectKind,
+    ) -> Result<Self, DwarfError> {
         let cell = SelfCell::try_new(Box::new(sections), |sections| {
             DwarfInfo::parse(unsafe { &*sections }, symbol_map, address_offset, kind)
         })?;
@@ -1320,11 +2092,29 @@ impl<'data> DwarfDebugSession<'data> {
 
     /// Returns an iterator over all functions in this debug file.
     pub fn functions(&self) -> DwarfFunctionIterator<'_> {
+        self.unit_functions(0..self.unit_count())
+    }
+
+    /// Returns the number of compilation units in this debug file.
+    ///
+    /// This includes units that do not contain any functions.
+    pub fn unit_count(&self) -> usize {
+        self.cell.get().headers.len()
+    }
+
+    /// Returns an iterator over the functions in the compilation units with the given indices.
+    ///
+    /// Units are parsed lazily and can be shared across threads, so disjoint ranges of units can
+    /// be processed in parallel. Functions that are defined at the same address in multiple units
+    /// are only skipped within the given range, see [`functions`](Self::functions).
+    pub fn unit_functions(&self, units: std::ops::Range<usize>) -> DwarfFunctionIterator<'_> {
         DwarfFunctionIterator {
-            units: self.cell.get().units(self.bcsymbolmap.as_deref()),
+            unit_index: units.start,
+            units: self.cell.get().units_in(units, self.bcsymbolmap.as_deref()),
             functions: Vec::new().into_iter(),
             range_buf: Vec::new(),
             seen_ranges: BTreeSet::new(),
+            warnings: false,
             finished: false,
         }
     }
@@ -1378,6 +2168,13 @@ impl<'s> Iterator for DwarfUnitFileIterator<'s> {
     }
 }
 
+/// Returns the name of a compilation unit, which is usually the path of its primary source file.
+fn unit_name(unit: &Unit<'_>, bcsymbolmap: Option<&BcSymbolMap<'_>>) -> Option<String> {
+    let name = unit.name?;
+    let name = resolve_byte_name(bcsymbolmap, name.slice());
+    Some(String::from_utf8_lossy(name).into_owned())
+}
+
 fn resolve_byte_name<'s>(bcsymbolmap: Option<&'s BcSymbolMap<'s>>, s: &'s [u8]) -> &'s [u8] {
     bcsymbolmap
         .and_then(|b| b.resolve_opt(s))
@@ -1435,9 +2232,44 @@ pub struct DwarfFunctionIterator<'s> {
     functions: std::vec::IntoIter<Function<'s>>,
     range_buf: Vec<Range>,
     seen_ranges: BTreeSet<(u64, u64)>,
+    unit_index: usize,
+    warnings: bool,
     finished: bool,
 }
 
+impl DwarfFunctionIterator<'_> {
+    /// Also yields errors that were recovered from, such as broken sequences in line programs.
+    ///
+    /// By default, such errors are ignored. When enabled, the error is yielded before the
+    /// functions of the affected compilation unit, and iteration continues normally afterwards.
+    /// Line programs are truncated at the broken sequence, since later sequences cannot be
+    /// decoded reliably, but line information from all other units is unaffected.
+    pub fn with_warnings(mut self) -> Self {
+        self.warnings = true;
+        self
+    }
+
+    /// Stops iteration once `interrupt` returns `true`.
+    ///
+    /// The callback is polled regularly while compilation units are parsed, including their line
+    /// programs and debugging information entries, so that even large units can be interrupted.
+    /// Iteration then yields an error of kind [`DwarfErrorKind::Interrupted`].
+    pub fn with_interrupt<F>(mut self, interrupt: F) -> Self
+    where
+        F: Fn() -> bool + 'static,
+    {
+        self.units.interrupt = Some(Box::new(interrupt));
+        self
+    }
+
+    /// Returns the index of the compilation unit that contains the last returned function.
+    ///
+    /// See [`DwarfDebugSession::unit_functions`].
+    pub fn unit_index(&self) -> usize {
+        self.unit_index
+    }
+}
+
 impl<'s> Iterator for DwarfFunctionIterator<'s> {
     type Item = Result<Function<'s>, DwarfError>;
 
@@ -1457,10 +2289,19 @@ impl<'s> Iterator for DwarfFunctionIterator<'s> {
                 None => break,
             };
 
-            self.functions = match unit.functions(&mut self.range_buf, &mut self.seen_ranges) {
+            self.unit_index = self.units.index - 1;
+            let interrupt = self.units.interrupt.as_deref();
+            let functions = unit.functions(&mut self.range_buf, &mut self.seen_ranges, interrupt);
+            self.functions = match functions {
                 Ok(functions) => functions.into_iter(),
-                Err(error) => return Some(Err(error)),
+                Err(error) => return Some(Err(error.with_unit_name(unit.name()))),
             };
+
+            if self.warnings {
+                if let Some(error) = unit.line_program_error() {
+                    return Some(Err(error));
+                }
+            }
         }
 
         self.finished = true;
@@ -532,6 +532,46 @@ impl<'data> ElfObject<'data> {
         DwarfDebugSession::parse(self, symbols, self.load_address() as i64, self.kind())
     }
 
+    /// Constructs a debugging session that resolves split DWARF from `.dwo` or `.dwp` files.
+    ///
+    /// When compiled with `-gsplit-dwarf`, this object only contains skeleton units and line
+    /// programs, while function and inlining information is stored in separate `.dwo` files, one
+    /// per compilation unit, or combined into a single `.dwp` package. Pass all of these files in
+    /// `split` to obtain the full debug information. See [`debug_session`](Self::debug_session).
+    pub fn debug_session_with_split_dwarf(
+        &self,
+        split: &[ElfObject<'data>],
+    ) -> Result<DwarfDebugSession<'data>, DwarfError> {
+        let symbols = self.symbol_map();
+        DwarfDebugSession::parse_split(
+            self,
+            split,
+            symbols,
+            self.load_address() as i64,
+            self.kind(),
+        )
+    }
+
+    /// Constructs a debugging session that resolves references into a supplementary file.
+    ///
+    /// Debug files processed with `dwz`, as shipped by many Linux distributions, move shared
+    /// debugging information into a supplementary file named in the `.gnu_debugaltlink` section.
+    /// Pass that file in `sup` to resolve function names defined there. See
+    /// [`debug_session`](Self::debug_session).
+    pub fn debug_session_with_sup(
+        &self,
+        sup: &ElfObject<'data>,
+    ) -> Result<DwarfDebugSession<'data>, DwarfError> {
+        let symbols = self.symbol_map();
+        DwarfDebugSession::parse_with_sup(
+            self,
+            sup,
+            symbols,
+            self.load_address() as i64,
+            self.kind(),
+        )
+    }
+
     /// Determines whether this object contains stack unwinding information.
     pub fn has_unwind_info(&self) -> bool {
         self.has_section("eh_frame") || self.has_section("debug_frame")
@@ -154,17 +154,10 @@ impl<'d> BcSymbolMap<'d> {
     /// assert_eq!(map.resolve("__hidden#43_"), "Sources/Sentry/Public/SentryMessage.h");
     /// assert_eq!(map.resolve("_addJSONData"), "_addJSONData");  // #64
     /// ```
-    pub fn resolve(&self, mut name: &'d str) -> &'d str {
-        if let Some(tail) = name.strip_prefix(SWIFT_HIDDEN_PREFIX) {
-            if let Some(index_as_string) = tail.strip_suffix('_') {
-                name = index_as_string
-                    .parse::<usize>()
-                    .ok()
-                    .and_then(|index| self.get(index))
-                    .unwrap_or(name);
-            }
-        }
-        name
+    pub fn resolve(&self, name: &'d str) -> &'d str {
+        Self::hidden_index(name)
+            .and_then(|index| self.get(index))
+            .unwrap_or(name)
     }
 
     /// Resolves a name given in raw bytes using this mapping.
@@ -173,10 +166,26 @@ impl<'d> BcSymbolMap<'d> {
     /// [`Option`].
     pub(crate) fn resolve_opt(&self, name: impl AsRef<[u8]>) -> Option<&str> {
         let name = std::str::from_utf8(name.as_ref()).ok()?;
+        self.get(Self::hidden_index(name)?)
+    }
+
+    /// Returns the index of the entry that an obfuscated name refers to.
+    ///
+    /// Returns `None` if the name does not match the `__hidden#NNN_` pattern. This allows to
+    /// resolve names against entries that were copied out of the mapping.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use symbolic_debuginfo::macho::BcSymbolMap;
+    ///
+    /// assert_eq!(BcSymbolMap::hidden_index("__hidden#43_"), Some(43));
+    /// assert_eq!(BcSymbolMap::hidden_index("_addJSONData"), None);
+    /// ```
+    pub fn hidden_index(name: &str) -> Option<usize> {
         let tail = name.strip_prefix(SWIFT_HIDDEN_PREFIX)?;
         let index_as_string = tail.strip_suffix('_')?;
-        let index = index_as_string.parse::<usize>().ok()?;
-        self.get(index)
+        index_as_string.parse().ok()
     }
 
     /// Returns an iterator over all the names in this bitcode symbol map.
@@ -24,6 +24,12 @@ pub use compact::*;
 /// Prefix for hidden symbols from Apple BCSymbolMap builds.
 const SWIFT_HIDDEN_PREFIX: &str = "__hidden#";
 
+/// Names of segments that contain executable code.
+///
+/// Most images only have a single `__TEXT` segment. Kernels, kernel extensions and some hardened
+/// binaries move their code into a separate `__TEXT_EXEC` segment, which is mapped after `__TEXT`.
+const CODE_SEGMENTS: &[&str] = &["__TEXT", "__TEXT_EXEC"];
+
 /// An error when dealing with [`MachObject`](struct.MachObject.html).
 #[derive(Debug, Error)]
 #[error("invalid MachO file")]
@@ -238,16 +244,63 @@ impl<'d> MachObject<'d> {
     /// Addresses used in `symbols` or `debug_session` have already been rebased relative to that
     /// load address, so that the caller only has to deal with addresses relative to the actual
     /// start of the image.
+    ///
+    /// This is the address of the `__TEXT` segment. Images without such a segment fall back to the
+    /// address of their `__TEXT_EXEC` segment.
     pub fn load_address(&self) -> u64 {
-        for seg in &self.macho.segments {
-            if seg.name().map(|name| name == "__TEXT").unwrap_or(false) {
-                return seg.vmaddr;
+        for name in CODE_SEGMENTS {
+            for seg in &self.macho.segments {
+                if seg.name().map(|n| n == *name).unwrap_or(false) {
+                    return seg.vmaddr;
+                }
             }
         }
 
         0
     }
 
+    /// Returns the segments that contain executable code, in the order of their load commands.
+    ///
+    /// Most images only have a `__TEXT` segment, while kernels and some hardened binaries also
+    /// have a `__TEXT_EXEC` segment. Each of these segments may be slid separately at runtime.
+    /// Their addresses are relative to the [`load_address`](Self::load_address), like those of
+    /// symbols and the debug session.
+    pub fn code_segments(&self) -> Vec<MachSegment<'_>> {
+        self.macho
+            .segments
+            .iter()
+            .filter_map(|segment| {
+                let name = segment.name().ok()?;
+                if !CODE_SEGMENTS.contains(&name) {
+                    return None;
+                }
+                Some(MachSegment {
+                    name,
+                    address: segment.vmaddr.saturating_sub(self.load_address()),
+                    size: segment.vmsize,
+                })
+            })
+            .collect()
+    }
+
+    /// Converts an offset into a code segment to an address relative to the load address.
+    ///
+    /// Images with multiple code segments, such as kernel collections, may slide every segment by
+    /// a different amount, so that their runtime addresses are only meaningful as offsets from the
+    /// start of their segment. This converts such an offset to the addresses used by symbols and
+    /// the debug session. Returns `None` if there is no code segment with the given name, or if
+    /// the offset lies outside of it.
+    pub fn normalize_address(&self, segment: &str, offset: u64) -> Option<u64> {
+        let segment = self
+            .code_segments()
+            .into_iter()
+            .find(|s| s.name == segment)?;
+        if offset >= segment.size {
+            return None;
+        }
+        segment.address.checked_add(offset)
+    }
+
     /// Determines whether this object exposes a public symbol table.
     pub fn has_symbols(&self) -> bool {
         self.macho.symbols.is_some()
@@ -255,14 +308,18 @@ impl<'d> MachObject<'d> {
 
     /// Returns an iterator over symbols in the public symbol table.
     pub fn symbols(&self) -> MachOSymbolIterator<'d> {
-        // Cache indices of code sections. These are either "__text" or "__stubs", always located in
-        // the "__TEXT" segment. It looks like each of those sections only occurs once, but to be
-        // safe they are collected into a vector.
+        // Cache indices of code sections. These are either "__text" or "__stubs", located in the
+        // "__TEXT" segment or one of the other code segments, such as "__TEXT_EXEC". It looks like
+        // each of those sections only occurs once per segment, but to be safe they are collected
+        // into a vector.
         let mut sections = SmallVec::new();
         let mut section_index = 0;
 
         'outer: for segment in &self.macho.segments {
-            if segment.name().ok() != Some("__TEXT") {
+            let is_code_segment = segment
+                .name()
+                .map_or(false, |name| CODE_SEGMENTS.contains(&name));
+            if !is_code_segment {
                 section_index += segment.nsects as usize;
                 continue;
             }
@@ -491,6 +548,19 @@ impl<'data> Dwarf<'data> for MachObject<'data> {
     }
 }
 
+/// A segment of a MachO image that contains executable code.
+///
+/// Returned by [`MachObject::code_segments`](struct.MachObject.html#method.code_segments).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct MachSegment<'a> {
+    /// The name of the segment, such as `__TEXT` or `__TEXT_EXEC`.
+    pub name: &'a str,
+    /// The start address of the segment, relative to the load address of the image.
+    pub address: u64,
+    /// The size of the segment in memory.
+    pub size: u64,
+}
+
 /// An iterator over symbols in the MachO file.
 ///
 /// Returned by [`MachObject::symbols`](struct.MachObject.html#method.symbols).
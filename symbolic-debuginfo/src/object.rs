@@ -123,12 +123,21 @@ impl Error for ObjectError {
 ///
 /// If `archive` is set to `true`, multi architecture objects will be allowed. Otherwise, only
 /// single-arch objects are checked.
+///
+/// Besides objects, this also recognizes usym files and SymCaches by their magic bytes, so that
+/// files can be routed to the right parser without relying on file extensions. These cannot be
+/// parsed as [`Object`], see [`FileFormat::is_object`].
 pub fn peek(data: &[u8], archive: bool) -> FileFormat {
     if data.len() < 16 {
         return FileFormat::Unknown;
     }
 
-    if ElfObject::test(data) {
+    if data.starts_with(b"usym") {
+        FileFormat::Usym
+    } else if data.starts_with(b"SYMC") || data.starts_with(b"CMYS") {
+        // SymCaches written on big-endian systems have their magic flipped
+        FileFormat::SymCache
+    } else if ElfObject::test(data) {
         FileFormat::Elf
     } else if PeObject::test(data) {
         FileFormat::Pe
@@ -189,7 +198,7 @@ pub enum Object<'data> {
 impl<'data> Object<'data> {
     /// Tests whether the buffer could contain an object.
     pub fn test(data: &[u8]) -> bool {
-        Self::peek(data) != FileFormat::Unknown
+        Self::peek(data).is_object()
     }
 
     /// Tries to infer the object type from the start of the given buffer.
@@ -213,7 +222,7 @@ impl<'data> Object<'data> {
             FileFormat::Pe => parse_object!(Pe, PeObject, data),
             FileFormat::SourceBundle => parse_object!(SourceBundle, SourceBundle, data),
             FileFormat::Wasm => parse_object!(Wasm, WasmObject, data),
-            FileFormat::Unknown => {
+            FileFormat::Usym | FileFormat::SymCache | FileFormat::Unknown => {
                 return Err(ObjectError::new(ObjectErrorRepr::UnsupportedObject))
             }
         };
@@ -614,7 +623,7 @@ pub struct Archive<'d>(ArchiveInner<'d>);
 impl<'d> Archive<'d> {
     /// Tests whether this buffer contains a valid object archive.
     pub fn test(data: &[u8]) -> bool {
-        Self::peek(data) != FileFormat::Unknown
+        Self::peek(data).is_object()
     }
 
     /// Tries to infer the object archive type from the start of the given buffer.
@@ -637,7 +646,7 @@ impl<'d> Archive<'d> {
             FileFormat::Pe => Archive(ArchiveInner::Pe(MonoArchive::new(data))),
             FileFormat::SourceBundle => Archive(ArchiveInner::SourceBundle(MonoArchive::new(data))),
             FileFormat::Wasm => Archive(ArchiveInner::Wasm(MonoArchive::new(data))),
-            FileFormat::Unknown => {
+            FileFormat::Usym | FileFormat::SymCache | FileFormat::Unknown => {
                 return Err(ObjectError::new(ObjectErrorRepr::UnsupportedObject))
             }
         };
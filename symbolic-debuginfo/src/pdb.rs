@@ -979,6 +979,10 @@ impl<'s> Unit<'s> {
                 size: line_info.length.map(u64::from),
                 file: self.debug_info.file_info(file_info)?,
                 line: line_info.line_start.into(),
+                column: line_info.column_start.map_or(0, u64::from),
+                prologue_end: false,
+                is_stmt: true,
+                basic_block: false,
             });
         }
 
@@ -1019,6 +1023,8 @@ impl<'s> Unit<'s> {
             lines,
             inlinees: Vec::new(),
             inline: false,
+            decl_file: None,
+            decl_line: 0,
         }))
     }
 
@@ -1064,6 +1070,8 @@ impl<'s> Unit<'s> {
             lines,
             inlinees: Vec::new(),
             inline: true,
+            decl_file: None,
+            decl_line: 0,
         }))
     }
 
@@ -1,7 +1,10 @@
 use std::{ffi::CString, fmt};
 
-use symbolic_common::ByteView;
-use symbolic_debuginfo::{elf::ElfObject, FileEntry, Function, Object, SymbolMap};
+use symbolic_common::{ByteView, Language};
+use symbolic_debuginfo::{
+    dwarf::DwarfErrorKind, elf::ElfObject, macho::MachObject, peek, Archive, FileEntry, FileFormat,
+    Function, Object, SymbolMap,
+};
 use symbolic_testutils::fixture;
 
 use similar_asserts::assert_eq;
@@ -75,6 +78,31 @@ impl fmt::Debug for FunctionsDebug<'_> {
     }
 }
 
+#[test]
+fn test_peek() -> Result<(), Error> {
+    let formats = [
+        ("linux/crash", FileFormat::Elf),
+        ("macos/crash", FileFormat::MachO),
+        ("windows/crash.pdb", FileFormat::Pdb),
+        ("linux/crash.sym", FileFormat::Breakpad),
+        ("il2cpp/managed.usym", FileFormat::Usym),
+        ("symcache/current/linux.symc", FileFormat::SymCache),
+        ("il2cpp/empty.usymlite", FileFormat::Unknown),
+    ];
+
+    for (path, format) in formats {
+        let view = ByteView::open(fixture(path))?;
+        assert_eq!(peek(&view, true), format, "{}", path);
+        assert_eq!(Archive::test(&view), format.is_object(), "{}", path);
+    }
+
+    let view = ByteView::open(fixture("il2cpp/managed.usym"))?;
+    assert!(!Object::test(&view));
+    assert!(Object::parse(&view).is_err());
+
+    Ok(())
+}
+
 #[test]
 fn test_breakpad() -> Result<(), Error> {
     // Using the windows version here since it contains all record kinds
@@ -234,6 +262,23 @@ fn test_elf_functions() -> Result<(), Error> {
     Ok(())
 }
 
+#[test]
+fn test_elf_functions_interrupted() -> Result<(), Error> {
+    let view = ByteView::open(fixture("linux/crash.debug"))?;
+    let object = ElfObject::parse(&view)?;
+    let session = object.debug_session()?;
+
+    let mut functions = session.functions().with_interrupt(|| true);
+    let error = functions.next().unwrap().unwrap_err();
+    assert_eq!(error.kind(), DwarfErrorKind::Interrupted);
+
+    let count = session.functions().count();
+    assert!(count > 0);
+    assert_eq!(session.functions().with_interrupt(|| false).count(), count);
+
+    Ok(())
+}
+
 fn elf_debug_crc() -> Result<u32, Error> {
     Ok(u32::from_str_radix(
         std::fs::read_to_string(fixture("linux/elf_debuglink/gen/debug_info.txt.crc"))?.trim(),
@@ -289,6 +334,62 @@ fn test_elf_debug_link_compressed() -> Result<(), Error> {
     check_debug_info("elf_with_compressed_debuglink", "debug_info.txt")
 }
 
+#[test]
+fn test_elf_split_dwarf() -> Result<(), Error> {
+    let view = ByteView::open(fixture("linux/split_dwarf/gen/split"))?;
+    let object = ElfObject::parse(&view)?;
+
+    // Skeleton units alone do not contain any functions.
+    let session = object.debug_session()?;
+    assert_eq!(session.functions().count(), 0);
+
+    let dwo_view = ByteView::open(fixture("linux/split_dwarf/gen/split-main.dwo"))?;
+    let dwo = ElfObject::parse(&dwo_view)?;
+    let session = object.debug_session_with_split_dwarf(&[dwo])?;
+    let functions = session.functions().collect::<Result<Vec<_>, _>>()?;
+    insta::assert_debug_snapshot!("elf_split_dwarf_functions", FunctionsDebug(&functions, 0));
+
+    let dwp_view = ByteView::open(fixture("linux/split_dwarf/gen/split.dwp"))?;
+    let dwp = ElfObject::parse(&dwp_view)?;
+    let session = object.debug_session_with_split_dwarf(&[dwp])?;
+    let dwp_functions = session.functions().collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(
+        format!("{:?}", FunctionsDebug(&dwp_functions, 0)),
+        format!("{:?}", FunctionsDebug(&functions, 0))
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_elf_sup() -> Result<(), Error> {
+    let view = ByteView::open(fixture("linux/dwz/gen/main"))?;
+    let object = ElfObject::parse(&view)?;
+    let sup_view = ByteView::open(fixture("linux/dwz/gen/main.sup"))?;
+    let sup = ElfObject::parse(&sup_view)?;
+
+    // Without the supplementary file, names defined there cannot be resolved.
+    let session = object.debug_session()?;
+    let functions = session.functions().collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(functions.len(), 2);
+    assert_eq!(functions[0].inlinees[0].name, "");
+    assert_eq!(functions[1].name, "");
+
+    let session = object.debug_session_with_sup(&sup)?;
+    let functions = session.functions().collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(functions.len(), 2);
+    assert_eq!(functions[0].name, "outer");
+    assert_eq!(functions[0].inlinees[0].name, "sup_inlined");
+    assert_eq!(functions[1].name, "sup_function");
+
+    // Names defined in the supplementary file use the language of the unit defining them.
+    assert_eq!(functions[0].name.language(), Language::C);
+    assert_eq!(functions[0].inlinees[0].name.language(), Language::Cpp);
+    assert_eq!(functions[1].name.language(), Language::C);
+
+    Ok(())
+}
+
 #[test]
 fn test_mach_executable() -> Result<(), Error> {
     let view = ByteView::open(fixture("macos/crash"))?;
@@ -358,6 +459,24 @@ fn test_mach_symbols() -> Result<(), Error> {
     Ok(())
 }
 
+#[test]
+fn test_mach_code_segments() -> Result<(), Error> {
+    let view = ByteView::open(fixture("macos/crash"))?;
+    let object = MachObject::parse(&view)?;
+
+    let segments = object.code_segments();
+    assert_eq!(segments.len(), 1);
+    assert_eq!(segments[0].name, "__TEXT");
+    assert_eq!(segments[0].address, 0);
+    assert!(segments[0].size > 0);
+
+    assert_eq!(object.normalize_address("__TEXT", 0x1234), Some(0x1234));
+    assert_eq!(object.normalize_address("__TEXT", segments[0].size), None);
+    assert_eq!(object.normalize_address("__TEXT_EXEC", 0x1234), None);
+
+    Ok(())
+}
+
 #[test]
 fn test_mach_files() -> Result<(), Error> {
     let view = ByteView::open(fixture("macos/crash.dSYM/Contents/Resources/DWARF/crash"))?;
@@ -573,3 +692,17 @@ fn test_wasm_line_program() -> Result<(), Error> {
 
     Ok(())
 }
+
+#[test]
+fn test_wasm_tombstone() -> Result<(), Error> {
+    let mut data = std::fs::read(fixture("wasm/simple.wasm"))?;
+    // replace the low_pc of `internal_func` with the tombstone of wasm-ld
+    data[0x10b..0x10f].copy_from_slice(&[0xff; 4]);
+    let object = Object::parse(&data)?;
+
+    let session = object.debug_session()?;
+    let functions: Vec<_> = session.functions().collect::<Result<_, _>>()?;
+    assert!(functions.is_empty());
+
+    Ok(())
+}
@@ -0,0 +1,28 @@
+#![no_main]
+
+use symbolic_il2cpp::usym::UsymSymbols;
+use symbolic_il2cpp::usymlite::UsymLiteSymbols;
+use symbolic_symcache::SymCacheWriter;
+
+// cargo +nightly fuzz run fuzz_usym -j 12
+libfuzzer_sys::fuzz_target!(|data: &[u8]| {
+    if let Ok(usyms) = UsymSymbols::parse(data) {
+        let _ = usyms.id();
+        let _ = usyms.arch();
+
+        for record in usyms.records() {
+            let _ = usyms.lookup_source_record(record.address);
+            let _ = usyms.lookup_source_record(record.address.wrapping_sub(1));
+        }
+
+        let mut writer = SymCacheWriter::new(Vec::new()).unwrap();
+        usyms.add_to_symcache(&mut writer);
+        let _ = writer.finish();
+    }
+
+    if let Ok(usyms) = UsymLiteSymbols::parse(data) {
+        let _ = usyms.id();
+        let _ = usyms.os();
+        let _ = usyms.arch();
+    }
+});
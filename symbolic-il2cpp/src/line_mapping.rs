@@ -1,5 +1,6 @@
 use indexmap::IndexSet;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use symbolic_common::split_path;
 use symbolic_symcache::transform::{self, Transformer};
 
 #[derive(Debug)]
@@ -9,13 +10,22 @@ struct LineEntry {
     cs_file_idx: usize,
 }
 
+/// A mapping from the C++ code generated by il2cpp back to the original C# source.
+///
+/// This is parsed from the `LineNumberMappings.json` file that il2cpp writes next to the generated
+/// code. As a [`Transformer`], it rewrites all source locations in generated `.cpp` files to the
+/// corresponding `.cs` file and line while a SymCache is written, see
+/// [`SymCacheWriter::add_transformer`](symbolic_symcache::SymCacheWriter::add_transformer).
 #[derive(Debug, Default)]
 pub struct LineMapping {
     cs_files: IndexSet<String>,
     cpp_file_map: HashMap<String, Vec<LineEntry>>,
+    /// The file names of all generated files, to skip all other files quickly.
+    cpp_file_names: HashSet<String>,
 }
 
 impl LineMapping {
+    /// Parses the line mapping JSON, returning `None` if it is malformed.
     pub fn parse(data: &[u8]) -> Option<Self> {
         let json: serde_json::Value = serde_json::from_slice(data).ok()?;
         let mut result = Self::default();
@@ -40,6 +50,8 @@ impl LineMapping {
                     }
                 }
                 lines.sort_by_key(|entry| entry.cpp_line);
+                let file_name = split_path(&cpp_file).1.to_owned();
+                result.cpp_file_names.insert(file_name);
                 result.cpp_file_map.insert(cpp_file, lines);
             }
         }
@@ -47,6 +59,9 @@ impl LineMapping {
         Some(result)
     }
 
+    /// Returns the C# file and line for a line in a generated C++ file.
+    ///
+    /// The C++ file must be given with its full path, as it appears in the mapping.
     pub fn lookup(&self, file: &str, line: u32) -> Option<(&str, u32)> {
         let lines = self.cpp_file_map.get(file)?;
 
@@ -66,28 +81,18 @@ impl LineMapping {
     }
 }
 
-fn full_path(file: &transform::File<'_>) -> String {
-    let comp_dir = file.comp_dir.as_deref().unwrap_or_default();
-    let directory = file.directory.as_deref().unwrap_or_default();
-    let path_name = &file.name;
-
-    let prefix = symbolic_common::join_path(comp_dir, directory);
-    let full_path = symbolic_common::join_path(&prefix, path_name);
-    symbolic_common::clean_path(&full_path).into_owned()
-}
-
 impl Transformer for LineMapping {
-    fn transform_function<'f>(&'f self, f: transform::Function<'f>) -> transform::Function<'f> {
-        f
-    }
-
     fn transform_source_location<'f>(
         &'f self,
         mut sl: transform::SourceLocation<'f>,
     ) -> transform::SourceLocation<'f> {
-        // TODO: this allocates, which is especially expensive since we run this transformer for
-        // every single source location (without dedupe-ing files). It might be worth caching this
-        let full_path = full_path(&sl.file);
+        // Building the full path allocates, and this runs for every single source location, so
+        // only do it for files that can possibly be part of the mapping.
+        if !self.cpp_file_names.contains(split_path(&sl.file.name).1) {
+            return sl;
+        }
+
+        let full_path = sl.file.full_path();
         if let Some((mapped_file, mapped_line)) = self.lookup(&full_path, sl.line) {
             sl.file.name = mapped_file.into();
             sl.file.comp_dir = None;
@@ -98,3 +103,56 @@ impl Transformer for LineMapping {
         sl
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use symbolic_symcache::SymCacheWriter;
+    use symbolic_symcache::{AddrRange, NewSymCache};
+
+    use super::*;
+
+    const MAPPING: &[u8] = br#"{
+        "/il2cppOutput/Assembly-CSharp.cpp": {
+            "/Assets/Script.cs": { "149": 10, "177": 17 }
+        }
+    }"#;
+
+    #[test]
+    fn test_lookup() {
+        let mapping = LineMapping::parse(MAPPING).unwrap();
+        let cpp_file = "/il2cppOutput/Assembly-CSharp.cpp";
+        assert_eq!(mapping.lookup(cpp_file, 7), None);
+        assert_eq!(
+            mapping.lookup(cpp_file, 150),
+            Some(("/Assets/Script.cs", 10))
+        );
+        assert_eq!(
+            mapping.lookup(cpp_file, 177),
+            Some(("/Assets/Script.cs", 17))
+        );
+        assert_eq!(mapping.lookup("/other/Assembly-CSharp.cpp", 177), None);
+    }
+
+    #[test]
+    fn test_transform() {
+        let mut writer = SymCacheWriter::new(Vec::new()).unwrap();
+        writer.add_transformer(LineMapping::parse(MAPPING).unwrap());
+        let range = |start, end| AddrRange { start, end };
+        writer.add_synthetic_function("Script_Start", &[range(0x1000, 0x1020)]);
+        assert!(writer.add_source_location(
+            range(0x1000, 0x1010),
+            "/il2cppOutput/Assembly-CSharp.cpp",
+            177
+        ));
+        assert!(writer.add_source_location(range(0x1010, 0x1020), "/il2cppOutput/Other.cpp", 177));
+        let buf = writer.finish().unwrap();
+        let cache = NewSymCache::parse(&buf).unwrap();
+
+        let location = |addr| {
+            let sl = cache.lookup(addr).next().unwrap();
+            (sl.file().unwrap().full_path(), sl.line())
+        };
+        assert_eq!(location(0x1000), ("/Assets/Script.cs".into(), 17));
+        assert_eq!(location(0x1010), ("/il2cppOutput/Other.cpp".into(), 177));
+    }
+}
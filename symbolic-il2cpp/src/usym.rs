@@ -3,17 +3,25 @@
 //! This format can map il2cpp instruction addresses to managed file names and line numbers.
 
 use std::borrow::Cow;
+use std::collections::{BTreeSet, HashMap};
 use std::error::Error;
 use std::fmt;
+use std::io::{self, Write};
 use std::mem;
-use std::ptr;
 use std::str::FromStr;
 
-use symbolic_common::Arch;
-use symbolic_common::DebugId;
+use symbolic_common::{Arch, CodeId, DebugId, Language, Name, NameMangling};
+use symbolic_debuginfo::{
+    DebugSession, FileEntry, FileFormat, FileInfo, Function, LineInfo, ObjectKind, ObjectLike,
+    Symbol, SymbolMap,
+};
+use symbolic_symcache::AddrRange;
+use symbolic_symcache::SymCacheWriter;
 use thiserror::Error;
+use zerocopy::LayoutVerified;
 
 /// The error type for [`UsymError`].
+#[non_exhaustive]
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum UsymErrorKind {
     /// Buffer to usym file is misaligned.
@@ -37,7 +45,7 @@ pub enum UsymErrorKind {
     BadId,
     /// The assembly name is missing or can't be read.
     BadName,
-    /// The architecture is missing or can't be read.
+    /// The operating system is missing or can't be read.
     BadOperatingSystem,
     /// The architecture is missing or can't be read.
     BadArchitecture,
@@ -106,9 +114,10 @@ impl From<UsymErrorKind> for UsymError {
 
 /// The raw C structures.
 mod raw {
+    use zerocopy::FromBytes;
 
     /// The header of the usym file format.
-    #[derive(Debug, Clone)]
+    #[derive(Debug, Clone, FromBytes)]
     #[repr(C)]
     pub(super) struct Header {
         /// Magic number identifying the file, `b"usym"`.
@@ -139,7 +148,7 @@ mod raw {
     ///
     /// This is the raw record as it appears in the file, see [`UsymRecord`] for a record with
     /// the names resolved.
-    #[derive(Debug, Clone, Copy)]
+    #[derive(Debug, Clone, Copy, FromBytes)]
     #[repr(C, packed)]
     pub(super) struct SourceRecord {
         /// Instruction pointer address, relative to base address of assembly.
@@ -164,7 +173,8 @@ mod raw {
         pub(super) managed_file: u32,
         /// Managed code line number. This is 0 if the record does not map to any managed code.
         pub(super) managed_line: u32,
-        pub(super) _unknown: u32,
+        /// Trailing data of unknown meaning, see [`super::UsymSourceRecord::unknown`].
+        pub(super) unknown: u32,
     }
 }
 
@@ -188,10 +198,16 @@ pub struct UsymSourceRecord<'a> {
     pub managed_file: Option<Cow<'a, str>>,
     /// Line number of the managed code.
     pub managed_line: Option<u32>,
+    /// The last field of the record, whose meaning is not known.
+    ///
+    /// All files seen so far have `u32::MAX` here for every record, which is reported as `None`.
+    /// It is exposed so that other values can be inspected once they show up.
+    pub unknown: Option<u32>,
 }
 
 /// A usym file containing data on how to map native code generated by Unity's IL2CPP back to their
 /// C# (i.e. managed) equivalents.
+#[derive(Clone)]
 pub struct UsymSymbols<'a> {
     /// File header.
     header: &'a raw::Header,
@@ -212,6 +228,8 @@ pub struct UsymSymbols<'a> {
     os: &'a str,
     /// The architecture.
     arch: &'a str,
+    /// The address at which the assembly is loaded.
+    image_base: u64,
 }
 
 impl<'a> UsymSymbols<'a> {
@@ -219,10 +237,9 @@ impl<'a> UsymSymbols<'a> {
 
     /// Parse a usym file.
     ///
-    /// # Panics
-    ///
-    /// If `std::mem::size_of::<usize>()` is smaller than `std::mem::size_of::<u32>()` on
-    /// the machine being run on.
+    /// Malformed files result in errors rather than panics. Records with string offsets outside
+    /// of the strings section are only detected when they are read, in which case they are
+    /// skipped.
     pub fn parse(buf: &'a [u8]) -> Result<UsymSymbols<'a>, UsymError> {
         if buf.as_ptr().align_offset(8) != 0 {
             return Err(UsymErrorKind::MisalignedBuffer.into());
@@ -234,8 +251,9 @@ impl<'a> UsymSymbols<'a> {
             return Err(UsymErrorKind::BadMagic.into());
         }
 
-        // SAFETY: We checked the buffer is large enough above.
-        let header = unsafe { &*(buf.as_ptr() as *const raw::Header) };
+        let (header, rest) = LayoutVerified::<_, raw::Header>::new_from_prefix(buf)
+            .ok_or_else(|| UsymError::from(UsymErrorKind::BadHeader))?;
+        let header = header.into_ref();
         if header.version != 2 {
             return Err(UsymErrorKind::BadVersion.into());
         }
@@ -246,53 +264,45 @@ impl<'a> UsymSymbols<'a> {
             .map_err(|e| UsymError::new(UsymErrorKind::BadRecordCount, e))?;
         // TODO: consider trying to just grab the records and give up on their strings if something
         // is wrong with the strings section
-        let strings_offset =
-            mem::size_of::<raw::Header>() + record_count * mem::size_of::<raw::SourceRecord>();
+        let strings_offset = record_count
+            .checked_mul(mem::size_of::<raw::SourceRecord>())
+            .and_then(|size| size.checked_add(mem::size_of::<raw::Header>()))
+            .ok_or_else(|| UsymError::from(UsymErrorKind::BufferSmallerThanAdvertised))?;
         if buf.len() < strings_offset {
             return Err(UsymErrorKind::BufferSmallerThanAdvertised.into());
         }
 
-        // SAFETY: We checked the buffer is at least the size_of::<UsymHeader>() above.
-        let first_record_ptr = unsafe { buf.as_ptr().add(mem::size_of::<raw::Header>()) };
-
-        // SAFETY: We checked the buffer has enough space for all the source records above.
-        let records = unsafe {
-            let first_record_ptr: *const raw::SourceRecord = first_record_ptr.cast();
-            let records_ptr = ptr::slice_from_raw_parts(first_record_ptr, record_count);
-            records_ptr
-                .as_ref()
-                .ok_or_else(|| UsymError::from(UsymErrorKind::BadRecords))
-        }?;
+        let records =
+            LayoutVerified::<_, [raw::SourceRecord]>::new_slice_from_prefix(rest, record_count)
+                .ok_or_else(|| UsymError::from(UsymErrorKind::BadRecords))?
+                .0
+                .into_slice();
 
         let strings = buf
             .get(strings_offset..)
             .ok_or_else(|| UsymError::from(UsymErrorKind::MissingStrings))?;
 
-        let id_offset = header.id.try_into().unwrap();
-        let id = match Self::get_string_from_offset(strings, id_offset)
+        let id = match Self::get_string_from_offset(strings, header.id)
             .ok_or_else(|| UsymError::from(UsymErrorKind::BadId))?
         {
             Cow::Borrowed(id) => id,
             Cow::Owned(_) => return Err(UsymErrorKind::BadEncoding.into()),
         };
-        let name_offset = header.name.try_into().unwrap();
-        let name = match Self::get_string_from_offset(strings, name_offset)
+        let name = match Self::get_string_from_offset(strings, header.name)
             .ok_or_else(|| UsymError::from(UsymErrorKind::BadName))?
         {
             Cow::Borrowed(name) => name,
             Cow::Owned(_) => return Err(UsymErrorKind::BadEncoding.into()),
         };
 
-        let os_offset = header.os.try_into().unwrap();
-        let os = match Self::get_string_from_offset(strings, os_offset)
+        let os = match Self::get_string_from_offset(strings, header.os)
             .ok_or_else(|| UsymError::from(UsymErrorKind::BadOperatingSystem))?
         {
             Cow::Borrowed(name) => name,
             Cow::Owned(_) => return Err(UsymErrorKind::BadEncoding.into()),
         };
 
-        let arch_offset = header.arch.try_into().unwrap();
-        let arch = match Self::get_string_from_offset(strings, arch_offset)
+        let arch = match Self::get_string_from_offset(strings, header.arch)
             .ok_or_else(|| UsymError::from(UsymErrorKind::BadArchitecture))?
         {
             Cow::Borrowed(name) => name,
@@ -310,32 +320,56 @@ impl<'a> UsymSymbols<'a> {
             name,
             os,
             arch,
+            image_base: 0,
         })
     }
 
+    /// Sets the address at which the assembly is loaded.
+    ///
+    /// Record addresses are relative to the base of the assembly. Lookups subtract the image base
+    /// from instruction addresses, and [`add_to_symcache`](Self::add_to_symcache) adds it to record
+    /// addresses. Defaults to zero, in which case addresses are treated as relative.
+    pub fn set_image_base(&mut self, image_base: u64) {
+        self.image_base = image_base;
+    }
+
+    /// Returns the address at which the assembly is loaded.
+    pub fn image_base(&self) -> u64 {
+        self.image_base
+    }
+
     /// Returns the version of the usym file these symbols were read from.
     pub fn version(&self) -> u32 {
         self.header.version
     }
 
-    fn get_string_from_offset(data: &[u8], offset: usize) -> Option<Cow<str>> {
-        let size_bytes = data.get(offset..offset + 2)?;
+    fn get_bytes_from_offset(data: &[u8], offset: u32) -> Option<&[u8]> {
+        let offset = usize::try_from(offset).ok()?;
+        let start_offset = offset.checked_add(2)?;
+        let size_bytes = data.get(offset..start_offset)?;
         let size: usize = u16::from_le_bytes([size_bytes[0], size_bytes[1]]).into();
 
-        let start_offset = offset + 2;
-        let end_offset = start_offset + size;
+        let end_offset = start_offset.checked_add(size)?;
+
+        data.get(start_offset..end_offset)
+    }
 
-        let string_bytes = data.get(start_offset..end_offset)?;
-        Some(String::from_utf8_lossy(string_bytes))
+    fn get_string_from_offset(data: &[u8], offset: u32) -> Option<Cow<'_, str>> {
+        Self::get_bytes_from_offset(data, offset).map(String::from_utf8_lossy)
     }
 
     /// Returns a string from the strings section at the given offset.
     ///
     /// Offsets are as provided by some [`UsymLiteHeader`] and [`UsymLiteLine`] fields.
-    fn get_string(&self, offset: usize) -> Option<Cow<'a, str>> {
+    fn get_string(&self, offset: u32) -> Option<Cow<'a, str>> {
         Self::get_string_from_offset(self.strings, offset)
     }
 
+    /// Returns the raw bytes of a string from the strings section at the given offset.
+    fn get_bytes(&self, offset: u32) -> Option<&'a [u8]> {
+        Self::get_bytes_from_offset(self.strings, offset)
+    }
+
     /// The ID of the assembly.
     ///
     /// This should match the ID of the debug symbols.
@@ -358,16 +392,32 @@ impl<'a> UsymSymbols<'a> {
         Arch::from_str(self.arch).map_err(|e| UsymError::new(UsymErrorKind::BadArchitecture, e))
     }
 
+    /// The number of source records.
+    pub fn record_count(&self) -> usize {
+        self.records.len()
+    }
+
+    /// Returns the first address after the record at the given index.
+    ///
+    /// Each record covers the addresses up to the next record, the last record only covers its
+    /// own address.
+    fn record_end(&self, index: usize) -> u64 {
+        match self.records.get(index + 1) {
+            Some(next) => next.address,
+            None => self.records[index].address.saturating_add(1),
+        }
+    }
+
     /// Returns a [`UsymSourceRecord`] at the given index it was stored.
     ///
     /// Not that useful, you have no idea what index you want.
-    pub fn get_record(&self, index: usize) -> Option<UsymSourceRecord> {
+    pub fn get_record(&self, index: usize) -> Option<UsymSourceRecord<'a>> {
         let raw = self.records.get(index)?;
 
-        let native_symbol = self.get_string(raw.native_symbol.try_into().unwrap())?;
-        let native_file = self.get_string(raw.native_file.try_into().unwrap())?;
+        let native_symbol = self.get_string(raw.native_symbol)?;
+        let native_file = self.get_string(raw.native_file)?;
 
-        let managed_symbol = self.get_string(raw.managed_symbol.try_into().unwrap())?;
+        let managed_symbol = self.get_string(raw.managed_symbol)?;
         let managed_symbol = match managed_symbol.is_empty() {
             true => None,
             false => Some(managed_symbol),
@@ -377,7 +427,7 @@ impl<'a> UsymSymbols<'a> {
             println!("Native entry: {}::{}", native_file, native_symbol);
         }
 
-        let managed_file = self.get_string(raw.managed_file.try_into().unwrap())?;
+        let managed_file = self.get_string(raw.managed_file)?;
         let managed_file = match managed_file.is_empty() {
             true => None,
             false => Some(managed_file),
@@ -390,6 +440,10 @@ impl<'a> UsymSymbols<'a> {
             0 => None,
             n => Some(n),
         };
+        let unknown = match raw.unknown {
+            u32::MAX => None,
+            n => Some(n),
+        };
 
         Some(UsymSourceRecord {
             address: raw.address,
@@ -399,25 +453,485 @@ impl<'a> UsymSymbols<'a> {
             managed_symbol,
             managed_file,
             managed_line,
+            unknown,
         })
     }
 
     /// Lookup the managed code source location for an IL2CPP instruction pointer.
+    ///
+    /// The instruction pointer is absolute, see [`set_image_base`](Self::set_image_base). Each
+    /// record covers the addresses up to the next record, and the last record only covers its own
+    /// address. Returns `None` for addresses outside of the records.
     pub fn lookup_source_record(&self, ip: u64) -> Option<UsymSourceRecord> {
-        // TODO: need to subtract the image base to get relative address
-        match self.records.binary_search_by_key(&ip, |r| r.address) {
+        let address = ip.checked_sub(self.image_base)?;
+        match self.records.binary_search_by_key(&address, |r| r.address) {
             Ok(index) => self.get_record(index),
+            Err(0) => None,
+            Err(index) if index == self.records.len() => None,
             Err(index) => self.get_record(index - 1),
         }
     }
 
-    // TODO: Add iterator over records?
+    /// Adds the functions and source locations of all records to a SymCache.
+    ///
+    /// Each record covers the addresses up to the next record, the last record only covers its
+    /// own address. Consecutive records with the same native symbol make up a single function.
+    /// Records that map to managed code resolve to the managed file and line, all others to the
+    /// native file and line.
+    ///
+    /// Record addresses are offset by the image base of these symbols, see
+    /// [`set_image_base`](Self::set_image_base), which should match the image base of the writer.
+    /// Its debug identifier and architecture are left unchanged.
+    pub fn add_to_symcache<W: Write>(&self, writer: &mut SymCacheWriter<W>) {
+        let record_range = |index: usize| AddrRange {
+            start: self.records[index].address.saturating_add(self.image_base),
+            end: self.record_end(index).saturating_add(self.image_base),
+        };
+
+        let mut function: Option<(Cow<'_, str>, AddrRange)> = None;
+        for index in 0..self.records.len() {
+            let record = match self.get_record(index) {
+                Some(record) => record,
+                None => continue,
+            };
+            let range = record_range(index);
+
+            match function {
+                Some((ref name, ref mut function_range))
+                    if *name == record.native_symbol && function_range.end == range.start =>
+                {
+                    function_range.end = range.end;
+                }
+                _ => {
+                    if let Some((name, function_range)) = function.take() {
+                        writer.add_synthetic_function(&name, &[function_range]);
+                    }
+                    if !record.native_symbol.is_empty() {
+                        function = Some((record.native_symbol, range));
+                    }
+                }
+            }
+        }
+        if let Some((name, function_range)) = function {
+            writer.add_synthetic_function(&name, &[function_range]);
+        }
+
+        for index in 0..self.records.len() {
+            let record = match self.get_record(index) {
+                Some(record) => record,
+                None => continue,
+            };
+            let (file, line) = match (record.managed_file, record.managed_line) {
+                (Some(file), Some(line)) => (file, line),
+                _ => (record.native_file, record.native_line),
+            };
+            if !file.is_empty() {
+                writer.add_source_location(record_range(index), &file, line);
+            }
+        }
+    }
+
+    /// Returns the native functions covered by the records, with their source locations.
+    ///
+    /// Functions and source locations are built like in
+    /// [`add_to_symcache`](Self::add_to_symcache), addresses are absolute.
+    fn functions(&self) -> Vec<Function<'a>> {
+        let mut functions: Vec<Function<'a>> = Vec::new();
+        for (index, raw) in self.records.iter().enumerate() {
+            let name = match self.get_string(raw.native_symbol) {
+                Some(name) if !name.is_empty() => name,
+                _ => continue,
+            };
+            let file = match self.get_bytes(raw.managed_file) {
+                Some(file) if !file.is_empty() && raw.managed_line != 0 => (file, raw.managed_line),
+                _ => match self.get_bytes(raw.native_file) {
+                    Some(file) => (file, raw.native_line),
+                    None => continue,
+                },
+            };
+
+            let address = raw.address.saturating_add(self.image_base);
+            let size = self.record_end(index).saturating_sub(raw.address);
+            let line = LineInfo {
+                address,
+                size: Some(size),
+                file: FileInfo {
+                    name: file.0,
+                    dir: b"",
+                },
+                line: file.1.into(),
+                column: 0,
+                prologue_end: false,
+                is_stmt: true,
+                basic_block: false,
+            };
+
+            match functions.last_mut() {
+                Some(function)
+                    if function.name.as_str() == name && function.end_address() == address =>
+                {
+                    function.size += size;
+                    function.lines.push(line);
+                }
+                _ => functions.push(Function {
+                    address,
+                    size,
+                    name: Name::new(name, NameMangling::Unknown, Language::Unknown),
+                    compilation_dir: b"",
+                    lines: vec![line],
+                    inlinees: Vec::new(),
+                    inline: false,
+                    decl_file: None,
+                    decl_line: 0,
+                }),
+            }
+        }
+
+        functions
+    }
+
+    /// Returns all native and managed files referenced by the records, sorted by name.
+    fn files(&self) -> Vec<FileEntry<'a>> {
+        let mut names = BTreeSet::new();
+        for raw in self.records {
+            names.extend(self.get_bytes(raw.native_file));
+            names.extend(self.get_bytes(raw.managed_file));
+        }
+
+        names
+            .into_iter()
+            .filter(|name| !name.is_empty())
+            .map(|name| FileEntry {
+                compilation_dir: b"",
+                info: FileInfo { name, dir: b"" },
+            })
+            .collect()
+    }
+
+    /// Constructs a debug session for the functions and files of the records.
+    pub fn debug_session(&self) -> Result<UsymDebugSession<'a>, UsymError> {
+        Ok(UsymDebugSession {
+            symbols: self.clone(),
+        })
+    }
+
+    /// Returns an iterator over all records, in address order.
+    ///
+    /// Records whose strings cannot be read are skipped.
+    pub fn records(&self) -> UsymRecordsIter<'_, 'a> {
+        UsymRecordsIter {
+            symbols: self,
+            index: 0,
+        }
+    }
+}
+
+/// An iterator over the records in [`UsymSymbols`], see [`UsymSymbols::records`].
+pub struct UsymRecordsIter<'s, 'a> {
+    symbols: &'s UsymSymbols<'a>,
+    index: usize,
+}
+
+impl<'s, 'a> Iterator for UsymRecordsIter<'s, 'a> {
+    type Item = UsymSourceRecord<'s>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.index < self.symbols.records.len() {
+            let record = self.symbols.get_record(self.index);
+            self.index += 1;
+            if record.is_some() {
+                return record;
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.symbols.records.len() - self.index))
+    }
+}
+
+impl<'data: 'object, 'object> ObjectLike<'data, 'object> for UsymSymbols<'data> {
+    type Error = UsymError;
+    type Session = UsymDebugSession<'data>;
+    type SymbolIterator = UsymSymbolIterator<'data>;
+
+    fn file_format(&self) -> FileFormat {
+        FileFormat::Usym
+    }
+
+    fn code_id(&self) -> Option<CodeId> {
+        None
+    }
+
+    fn debug_id(&self) -> DebugId {
+        self.id().unwrap_or_default()
+    }
+
+    fn arch(&self) -> Arch {
+        self.arch().unwrap_or_default()
+    }
+
+    fn kind(&self) -> ObjectKind {
+        ObjectKind::Debug
+    }
+
+    fn load_address(&self) -> u64 {
+        self.image_base
+    }
+
+    fn has_symbols(&self) -> bool {
+        !self.records.is_empty()
+    }
+
+    fn symbols(&self) -> Self::SymbolIterator {
+        let symbols: Vec<_> = self
+            .functions()
+            .into_iter()
+            .map(|function| Symbol {
+                name: Some(function.name.into_cow()),
+                address: function.address,
+                size: function.size,
+            })
+            .collect();
+        UsymSymbolIterator(symbols.into_iter())
+    }
+
+    fn symbol_map(&self) -> SymbolMap<'data> {
+        self.symbols().collect()
+    }
+
+    fn has_debug_info(&self) -> bool {
+        !self.records.is_empty()
+    }
+
+    fn debug_session(&self) -> Result<Self::Session, Self::Error> {
+        self.debug_session()
+    }
+
+    fn has_unwind_info(&self) -> bool {
+        false
+    }
+
+    fn has_sources(&self) -> bool {
+        false
+    }
+
+    fn is_malformed(&self) -> bool {
+        false
+    }
+}
+
+/// An iterator over the native functions in [`UsymSymbols`] as symbols.
+pub struct UsymSymbolIterator<'a>(std::vec::IntoIter<Symbol<'a>>);
+
+impl<'a> Iterator for UsymSymbolIterator<'a> {
+    type Item = Symbol<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+}
+
+/// A debug session for [`UsymSymbols`].
+///
+/// Each native function covered by the records is a function, whose line records resolve to the
+/// managed source location where there is one, and to the native source location otherwise.
+pub struct UsymDebugSession<'a> {
+    symbols: UsymSymbols<'a>,
+}
+
+impl<'a> UsymDebugSession<'a> {
+    /// Returns an iterator over all functions.
+    pub fn functions(&self) -> UsymFunctionIterator<'a> {
+        UsymFunctionIterator(self.symbols.functions().into_iter())
+    }
+
+    /// Returns an iterator over all native and managed source files.
+    pub fn files(&self) -> UsymFileIterator<'a> {
+        UsymFileIterator(self.symbols.files().into_iter())
+    }
+
+    /// Usym files do not contain sources, so this always returns `None`.
+    pub fn source_by_path(&self, _path: &str) -> Result<Option<Cow<'_, str>>, UsymError> {
+        Ok(None)
+    }
+}
+
+impl<'a, 'session> DebugSession<'session> for UsymDebugSession<'a> {
+    type Error = UsymError;
+    type FunctionIterator = UsymFunctionIterator<'session>;
+    type FileIterator = UsymFileIterator<'session>;
+
+    fn functions(&'session self) -> Self::FunctionIterator {
+        self.functions()
+    }
+
+    fn files(&'session self) -> Self::FileIterator {
+        self.files()
+    }
+
+    fn source_by_path(&self, path: &str) -> Result<Option<Cow<'_, str>>, Self::Error> {
+        self.source_by_path(path)
+    }
+}
+
+/// An iterator over functions in a [`UsymDebugSession`].
+pub struct UsymFunctionIterator<'a>(std::vec::IntoIter<Function<'a>>);
+
+impl<'a> Iterator for UsymFunctionIterator<'a> {
+    type Item = Result<Function<'a>, UsymError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(Ok)
+    }
+}
+
+/// An iterator over source files in a [`UsymDebugSession`].
+pub struct UsymFileIterator<'a>(std::vec::IntoIter<FileEntry<'a>>);
+
+impl<'a> Iterator for UsymFileIterator<'a> {
+    type Item = Result<FileEntry<'a>, UsymError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(Ok)
+    }
+}
+
+/// A writer for usym files.
+///
+/// This creates files that can be read by [`UsymSymbols`], from header metadata and a list of
+/// records. Records can be taken from existing files, for example to strip or redact them:
+///
+/// ```
+/// # fn test(usyms: symbolic_il2cpp::usym::UsymSymbols) -> std::io::Result<()> {
+/// use symbolic_il2cpp::usym::UsymWriter;
+///
+/// let id = "153d10d10db033d6aacda4e1948da97b";
+/// let mut writer = UsymWriter::new(id, "UnityFramework", "mac", "arm64");
+/// for record in usyms.records().filter(|r| r.managed_symbol.is_some()) {
+///     writer.add_record(&record);
+/// }
+/// let mut buf = Vec::new();
+/// writer.write(&mut buf)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct UsymWriter {
+    id: String,
+    name: String,
+    os: String,
+    arch: String,
+    records: Vec<UsymSourceRecord<'static>>,
+}
+
+impl UsymWriter {
+    /// Creates a new writer with the given header metadata.
+    ///
+    /// The `id` is written as-is, usym files contain the plain hex string of the UUID.
+    pub fn new(id: &str, name: &str, os: &str, arch: &str) -> Self {
+        Self {
+            id: id.to_owned(),
+            name: name.to_owned(),
+            os: os.to_owned(),
+            arch: arch.to_owned(),
+            records: Vec::new(),
+        }
+    }
+
+    /// Adds a record.
+    ///
+    /// Records are written in address order, regardless of the order in which they are added.
+    pub fn add_record(&mut self, record: &UsymSourceRecord<'_>) {
+        let owned = |s: &Cow<'_, str>| Cow::Owned(s.as_ref().to_owned());
+        self.records.push(UsymSourceRecord {
+            address: record.address,
+            native_symbol: owned(&record.native_symbol),
+            native_file: owned(&record.native_file),
+            native_line: record.native_line,
+            managed_symbol: record.managed_symbol.as_ref().map(owned),
+            managed_file: record.managed_file.as_ref().map(owned),
+            managed_line: record.managed_line,
+            unknown: record.unknown,
+        });
+    }
+
+    /// Writes the usym file.
+    ///
+    /// Fails with [`io::ErrorKind::InvalidInput`] if a string is longer than `u16::MAX` bytes, or
+    /// if there are more than `u32::MAX` records or bytes of strings.
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let mut strings = StringsBuilder::default();
+        // The strings section always starts with the empty string, which is referred to by
+        // records without managed info.
+        strings.insert("")?;
+
+        let id = strings.insert(&self.id)?;
+        let name = strings.insert(&self.name)?;
+        let os = strings.insert(&self.os)?;
+        let arch = strings.insert(&self.arch)?;
+
+        let mut records = Vec::with_capacity(self.records.len());
+        for record in &self.records {
+            records.push([
+                strings.insert(&record.native_symbol)?,
+                strings.insert(&record.native_file)?,
+                record.native_line,
+                strings.insert(record.managed_symbol.as_deref().unwrap_or(""))?,
+                strings.insert(record.managed_file.as_deref().unwrap_or(""))?,
+                record.managed_line.unwrap_or(0),
+                record.unknown.unwrap_or(u32::MAX),
+            ]);
+        }
+
+        let mut order: Vec<_> = (0..records.len()).collect();
+        order.sort_by_key(|&index| self.records[index].address);
+
+        let record_count = u32::try_from(records.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "too many records"))?;
+        writer.write_all(UsymSymbols::MAGIC)?;
+        for field in [2, record_count, id, name, os, arch] {
+            writer.write_all(&field.to_le_bytes())?;
+        }
+        for index in order {
+            writer.write_all(&self.records[index].address.to_le_bytes())?;
+            for field in records[index] {
+                writer.write_all(&field.to_le_bytes())?;
+            }
+        }
+        writer.write_all(&strings.data)
+    }
+}
+
+/// The strings section of a usym file that is being written.
+#[derive(Debug, Default)]
+struct StringsBuilder {
+    offsets: HashMap<String, u32>,
+    data: Vec<u8>,
+}
+
+impl StringsBuilder {
+    /// Inserts a length-prefixed string, returning its offset.
+    fn insert(&mut self, s: &str) -> io::Result<u32> {
+        if let Some(&offset) = self.offsets.get(s) {
+            return Ok(offset);
+        }
+
+        let invalid = |msg| io::Error::new(io::ErrorKind::InvalidInput, msg);
+        let offset = u32::try_from(self.data.len()).map_err(|_| invalid("too many strings"))?;
+        let len = u16::try_from(s.len()).map_err(|_| invalid("string is too long"))?;
+        self.data.extend_from_slice(&len.to_le_bytes());
+        self.data.extend_from_slice(s.as_bytes());
+        self.offsets.insert(s.to_owned(), offset);
+
+        Ok(offset)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use std::fs::File;
-    use std::io::Write;
 
     use symbolic_common::ByteView;
     use symbolic_testutils::fixture;
@@ -426,97 +940,59 @@ mod tests {
 
     #[test]
     fn test_write_usym() {
-        // Not really a test but rather a quick and dirty way to write a small usym file
-        // given a large one.  This was used to generate a small enough usym file to use as
-        // a test fixture, however this still tests the reader and writer can round-trip.
-
-        // let file = File::open(
-        //     "/Users/flub/code/sentry-unity-il2cpp-line-numbers/Builds/iOS/UnityFramework.usym",
-        // )
-        // .unwrap();
+        // This was used to generate a small enough usym file to use as a test fixture from a
+        // large one, by only keeping some of the records. Writing all records round-trips.
         let file = File::open(fixture("il2cpp/managed.usym")).unwrap();
-
         let orig_data = ByteView::map_file_ref(&file).unwrap();
         let usyms = UsymSymbols::parse(&orig_data).unwrap();
 
-        // Our strings and helper to build it by pushing new strings.  We keep strings and
-        // strings_offsets so we can de-duplicate, raw_strings is the thing we are really
-        // building.
-        let mut strings: Vec<String> = Vec::new();
-        let mut raw_strings: Vec<u8> = Vec::new();
-        let mut string_offsets: Vec<u64> = Vec::new();
-
-        let mut push_string = |s: Cow<'_, str>| match strings.iter().position(|i| i == s.as_ref()) {
-            Some(pos) => string_offsets[pos],
-            None => {
-                let offset = raw_strings.len() as u64;
-                let len = s.len() as u16;
-                raw_strings.extend_from_slice(&len.to_le_bytes());
-                raw_strings.extend_from_slice(s.as_bytes());
-
-                strings.push(s.to_string());
-                string_offsets.push(offset);
-
-                offset
-            }
-        };
-
-        // The string table always starts with an entry for the empty string.
-        push_string(Cow::Borrowed(""));
-
-        // Construct new header.
-        let mut header = usyms.header.clone();
-        header.id = push_string(usyms.get_string(header.id as usize).unwrap()) as u32;
-        header.name = push_string(usyms.get_string(header.name as usize).unwrap()) as u32;
-        header.os = push_string(usyms.get_string(header.os as usize).unwrap()) as u32;
-        header.arch = push_string(usyms.get_string(header.arch as usize).unwrap()) as u32;
-
-        // Construct new records. Skims the top 5 records, then grabs the 3 records that have
-        // mappings to managed symbols.
-        header.record_count = 5 + 3;
-        let first_five = usyms.records.iter().take(5);
-        let actual_mappings = usyms.records.iter().filter(|r| r.managed_symbol != 0);
-        let mut records = Vec::new();
-        for mut record in first_five.chain(actual_mappings).cloned() {
-            if record.native_symbol > 0 {
-                record.native_symbol =
-                    push_string(usyms.get_string(record.native_symbol as usize).unwrap()) as u32;
-            }
-            if record.native_file > 0 {
-                record.native_file =
-                    push_string(usyms.get_string(record.native_file as usize).unwrap()) as u32;
-            }
-            if record.managed_symbol > 0 {
-                record.managed_symbol =
-                    push_string(usyms.get_string(record.managed_symbol as usize).unwrap()) as u32;
-            }
-            if record.managed_file > 0 {
-                record.managed_file =
-                    push_string(usyms.get_string(record.managed_file as usize).unwrap()) as u32;
-            }
-            records.push(record);
+        let mut writer = UsymWriter::new(usyms.id, usyms.name(), usyms.os(), usyms.arch);
+        for record in usyms.records() {
+            writer.add_record(&record);
         }
-
-        // let mut dest = File::create(fixture("il2cpp/artificial.usym")).unwrap();
         let mut dest = Vec::new();
+        writer.write(&mut dest).unwrap();
 
-        // Write the header.
-        let data = &[header];
-        let ptr = data.as_ptr() as *const u8;
-        let len = std::mem::size_of_val(data);
-        let buf = unsafe { std::slice::from_raw_parts(ptr, len) };
-        dest.write_all(buf).unwrap();
+        assert_eq!(orig_data.as_ref(), dest);
+    }
 
-        // Write the records.
-        let ptr = records.as_ptr() as *const u8;
-        let len = records.len() * std::mem::size_of::<raw::SourceRecord>();
-        let buf = unsafe { std::slice::from_raw_parts(ptr, len) };
-        dest.write_all(buf).unwrap();
+    #[test]
+    fn test_write_redacted() {
+        let file = File::open(fixture("il2cpp/managed.usym")).unwrap();
+        let data = ByteView::map_file_ref(&file).unwrap();
+        let usyms = UsymSymbols::parse(&data).unwrap();
 
-        // Write the strings.
-        dest.write_all(&raw_strings).unwrap();
+        let mut writer = UsymWriter::new(usyms.id, "Redacted", usyms.os(), usyms.arch);
+        // added out of order on purpose
+        for record in usyms.records().filter(|r| r.managed_symbol.is_some()) {
+            let mut record = record.clone();
+            record.native_file = Cow::Borrowed("");
+            writer.add_record(&record);
+        }
+        writer.add_record(&usyms.get_record(0).unwrap());
+        let mut buf = Vec::new();
+        writer.write(&mut buf).unwrap();
+
+        let data = ByteView::from_vec(buf);
+        let written = UsymSymbols::parse(&data).unwrap();
+        assert_eq!(written.name(), "Redacted");
+        assert_eq!(written.id().unwrap(), usyms.id().unwrap());
+        assert_eq!(written.record_count(), 4);
+
+        let addresses: Vec<_> = written.records().map(|r| r.address).collect();
+        assert_eq!(addresses, [0x7290, 0x7df188, 0x7df18c, 0x7df190]);
+        let record = written.lookup_source_record(0x7df18c).unwrap();
+        assert_eq!(record.native_file, "");
+        assert_eq!(record.managed_symbol.unwrap(), "NewBehaviourScript.Start()");
+        assert_eq!(record.managed_line, Some(10));
+    }
 
-        assert_eq!(orig_data.as_ref(), dest);
+    #[test]
+    fn test_write_long_string() {
+        let long = "x".repeat(usize::from(u16::MAX) + 1);
+        let writer = UsymWriter::new("id", &long, "mac", "arm64");
+        let err = writer.write(Vec::new()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
     }
 
     #[test]
@@ -533,9 +1009,11 @@ mod tests {
         assert_eq!(usyms.name(), "UnityFramework");
         assert_eq!(usyms.os(), "mac");
         assert_eq!(usyms.arch().unwrap(), Arch::Arm64);
+        assert_eq!(usyms.record_count(), 5);
 
         for i in 0..5 {
-            assert!(usyms.get_record(i).is_some());
+            let record = usyms.get_record(i).unwrap();
+            assert_eq!(record.unknown, None);
         }
     }
 
@@ -553,6 +1031,7 @@ mod tests {
         assert_eq!(usyms.name(), "UnityFramework");
         assert_eq!(usyms.os(), "mac");
         assert_eq!(usyms.arch().unwrap(), Arch::Arm64);
+        assert_eq!(usyms.record_count(), 8);
 
         let first_mapping = usyms.lookup_source_record(8253832).unwrap();
         assert_eq!(
@@ -588,6 +1067,225 @@ mod tests {
         assert_eq!(third_mapping.managed_line.unwrap(), 17);
     }
 
+    #[test]
+    fn test_lookup_image_base() {
+        let file = File::open(fixture("il2cpp/managed.usym")).unwrap();
+        let data = ByteView::map_file_ref(&file).unwrap();
+        let mut usyms = UsymSymbols::parse(&data).unwrap();
+
+        // before the first record and after the last one
+        assert!(usyms.lookup_source_record(0).is_none());
+        assert!(usyms.lookup_source_record(0x728c).is_none());
+        assert!(usyms.lookup_source_record(0x7df190).is_some());
+        assert!(usyms.lookup_source_record(0x7df194).is_none());
+
+        usyms.set_image_base(0x1_0000_0000);
+        assert_eq!(usyms.image_base(), 0x1_0000_0000);
+        assert!(usyms.lookup_source_record(0x7290).is_none());
+        let record = usyms.lookup_source_record(0x1_0000_72bc).unwrap();
+        assert_eq!(record.address, 0x72b8);
+        assert_eq!(record.native_line, 42);
+    }
+
+    #[test]
+    fn test_add_to_symcache() {
+        let file = File::open(fixture("il2cpp/managed.usym")).unwrap();
+        let data = ByteView::map_file_ref(&file).unwrap();
+        let usyms = UsymSymbols::parse(&data).unwrap();
+
+        let mut writer = SymCacheWriter::new(Vec::new()).unwrap();
+        usyms.add_to_symcache(&mut writer);
+        let buf = writer.finish().unwrap();
+        let cache = symbolic_symcache::NewSymCache::parse(&buf).unwrap();
+
+        let frames: Vec<_> = cache
+            .lookup(0x72bc)
+            .map(|sl| {
+                let function = sl.function().and_then(|f| f.name());
+                (function, sl.file().map(|f| f.full_path()), sl.line())
+            })
+            .collect();
+        assert_eq!(
+            frames,
+            [(
+                Some("+[UnityURLRequest requestForTask:]"),
+                Some(
+                    "/Users/bitfox/_Workspace/IL2CPP/Builds/iOS/Xcode/Classes/Unity/UnityWebRequest.mm"
+                        .into()
+                ),
+                42
+            )]
+        );
+
+        let sl = cache.lookup(0x7df18c).next().unwrap();
+        assert_eq!(
+            sl.function().and_then(|f| f.name()),
+            Some("NewBehaviourScript_Update_m68CAC9D7524B9FA1DFCA3F99BA694CD73147209B")
+        );
+        assert_eq!(
+            sl.file().unwrap().full_path(),
+            "/Users/bitfox/_Workspace/IL2CPP/Assets/NewBehaviourScript.cs"
+        );
+        assert_eq!(sl.line(), 10);
+
+        // the last record only covers its own address
+        assert_eq!(cache.lookup(0x7df190).count(), 1);
+        assert_eq!(cache.lookup(0x7df191).count(), 0);
+    }
+
+    #[test]
+    fn test_records() {
+        let file = File::open(fixture("il2cpp/managed.usym")).unwrap();
+        let data = ByteView::map_file_ref(&file).unwrap();
+        let usyms = UsymSymbols::parse(&data).unwrap();
+
+        let addresses: Vec<_> = usyms.records().map(|r| r.address).collect();
+        assert_eq!(
+            addresses,
+            [0x7290, 0x72b8, 0x72c0, 0x72d0, 0x72f0, 0x7df188, 0x7df18c, 0x7df190]
+        );
+        let managed = usyms.records().filter(|r| r.managed_symbol.is_some());
+        assert_eq!(managed.count(), 3);
+    }
+
+    #[test]
+    fn test_object_like() {
+        let file = File::open(fixture("il2cpp/managed.usym")).unwrap();
+        let data = ByteView::map_file_ref(&file).unwrap();
+        let usyms = UsymSymbols::parse(&data).unwrap();
+
+        assert_eq!(ObjectLike::file_format(&usyms), FileFormat::Usym);
+        assert_eq!(ObjectLike::arch(&usyms), Arch::Arm64);
+        assert_eq!(ObjectLike::debug_id(&usyms), usyms.id().unwrap());
+        assert!(usyms.has_debug_info());
+
+        let symbols: Vec<_> = usyms.symbols().map(|s| (s.address, s.size)).collect();
+        assert_eq!(
+            symbols,
+            [
+                (0x7290, 0x7df188 - 0x7290),
+                (0x7df188, 4),
+                (0x7df18c, 4),
+                (0x7df190, 1)
+            ]
+        );
+
+        let session = ObjectLike::debug_session(&usyms).unwrap();
+        let files: Vec<_> = session
+            .files()
+            .map(|file| file.unwrap().abs_path_str())
+            .collect();
+        assert_eq!(files.len(), 3);
+        assert!(
+            files.contains(&"/Users/bitfox/_Workspace/IL2CPP/Assets/NewBehaviourScript.cs".into())
+        );
+
+        let functions: Vec<_> = session.functions().map(Result::unwrap).collect();
+        assert_eq!(functions.len(), 4);
+        assert_eq!(functions[0].name, "+[UnityURLRequest requestForTask:]");
+        assert_eq!(functions[0].lines.len(), 5);
+        let line = &functions[2].lines[0];
+        assert_eq!(
+            line.file.name_str(),
+            "/Users/bitfox/_Workspace/IL2CPP/Assets/NewBehaviourScript.cs"
+        );
+        assert_eq!(line.line, 10);
+
+        // converting the object is the same as adding the records
+        let buf = SymCacheWriter::write_object(&usyms, Vec::new()).unwrap();
+        let cache = symbolic_symcache::NewSymCache::parse(&buf).unwrap();
+        assert_eq!(cache.debug_id(), usyms.id().unwrap());
+        let sl = cache.lookup(0x7df18c).next().unwrap();
+        assert_eq!(sl.line(), 10);
+        assert_eq!(
+            sl.function().and_then(|f| f.name()),
+            Some("NewBehaviourScript_Update_m68CAC9D7524B9FA1DFCA3F99BA694CD73147209B")
+        );
+        assert_eq!(cache.lookup(0x72bc).next().unwrap().line(), 42);
+    }
+
+    fn managed_usym() -> Vec<u8> {
+        std::fs::read(fixture("il2cpp/managed.usym")).unwrap()
+    }
+
+    fn exercise(usyms: &UsymSymbols<'_>) {
+        let _ = usyms.id();
+        let _ = usyms.arch();
+        for record in usyms.records() {
+            let _ = usyms.lookup_source_record(record.address);
+            let _ = usyms.lookup_source_record(record.address.wrapping_sub(1));
+        }
+        let _ = usyms.lookup_source_record(u64::MAX);
+        let mut writer = SymCacheWriter::new(Vec::new()).unwrap();
+        usyms.add_to_symcache(&mut writer);
+    }
+
+    #[test]
+    fn test_truncated() {
+        let data = managed_usym();
+        for len in 0..data.len() {
+            let data = ByteView::from_vec(data[..len].to_vec());
+            if let Ok(usyms) = UsymSymbols::parse(&data) {
+                exercise(&usyms);
+            }
+        }
+    }
+
+    #[test]
+    fn test_error_kinds() {
+        let parse_err = |data: Vec<u8>| {
+            let data = ByteView::from_vec(data);
+            UsymSymbols::parse(&data).err().unwrap().kind()
+        };
+
+        let data = managed_usym();
+        assert_eq!(parse_err(data[..20].to_vec()), UsymErrorKind::BadHeader);
+
+        let mut bad_magic = data.clone();
+        bad_magic[..4].copy_from_slice(b"sym-");
+        assert_eq!(parse_err(bad_magic), UsymErrorKind::BadMagic);
+
+        let mut bad_version = data.clone();
+        bad_version[4..8].copy_from_slice(&3u32.to_le_bytes());
+        assert_eq!(parse_err(bad_version), UsymErrorKind::BadVersion);
+
+        let mut misaligned = vec![0; data.len() + 1];
+        misaligned[1..].copy_from_slice(&data);
+        let misaligned = ByteView::from_vec(misaligned);
+        let err = UsymSymbols::parse(&misaligned[1..]).err().unwrap();
+        assert_eq!(err.kind(), UsymErrorKind::MisalignedBuffer);
+        assert_eq!(err.to_string(), "misaligned pointer to buffer");
+    }
+
+    #[test]
+    fn test_bad_record_count() {
+        let mut data = managed_usym();
+        data[8..12].copy_from_slice(&u32::MAX.to_le_bytes());
+        let data = ByteView::from_vec(data);
+        let err = UsymSymbols::parse(&data).err().unwrap();
+        assert_eq!(err.kind(), UsymErrorKind::BufferSmallerThanAdvertised);
+    }
+
+    #[test]
+    fn test_bad_string_offsets() {
+        let mut data = managed_usym();
+        // the header's id and the first record's native file
+        data[12..16].copy_from_slice(&u32::MAX.to_le_bytes());
+        let err = UsymSymbols::parse(&ByteView::from_vec(data.clone()))
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), UsymErrorKind::BadId);
+
+        let mut data = managed_usym();
+        data[28 + 12..28 + 16].copy_from_slice(&u32::MAX.to_le_bytes());
+        let data = ByteView::from_vec(data);
+        let usyms = UsymSymbols::parse(&data).unwrap();
+        assert!(usyms.get_record(0).is_none());
+        assert!(usyms.lookup_source_record(0x7290).is_none());
+        assert_eq!(usyms.records().count(), 7);
+        exercise(&usyms);
+    }
+
     #[test]
     fn test_sorted_addresses() {
         let file = File::open(fixture("il2cpp/artificial.usym")).unwrap();
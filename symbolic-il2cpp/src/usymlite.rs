@@ -10,12 +10,12 @@ use std::error::Error;
 use std::ffi::CStr;
 use std::fmt;
 use std::mem;
-use std::os::raw::c_char;
-use std::ptr;
 
 use thiserror::Error;
+use zerocopy::{FromBytes, LayoutVerified};
 
 /// The error type for [`UsymLiteError`].
+#[non_exhaustive]
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum UsymLiteErrorKind {
     /// Buffer to usym file is misaligned.
@@ -41,7 +41,7 @@ pub enum UsymLiteErrorKind {
     BadId,
     /// The assembly name is missing or can't be read.
     BadName,
-    /// The architecture is missing or can't be read.
+    /// The operating system is missing or can't be read.
     BadOperatingSystem,
     /// The architecture is missing or can't be read.
     BadArchitecture,
@@ -116,7 +116,7 @@ impl From<UsymLiteErrorKind> for UsymLiteError {
 
 // TODO: Follow the same structure as usyms and introduce a raw module and other
 // types to distinguish between raw and parsed reps?
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, FromBytes)]
 #[repr(C)]
 struct UsymLiteHeader {
     /// Magic number identifying the file, `b"sym-"`.
@@ -135,7 +135,7 @@ struct UsymLiteHeader {
     arch: u32,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, FromBytes)]
 #[repr(C)]
 pub struct UsymLiteLine {
     address: u64,
@@ -169,8 +169,9 @@ impl<'a> UsymLiteSymbols<'a> {
             return Err(UsymLiteError::from(UsymLiteErrorKind::BadMagic));
         }
 
-        // SAFETY: We checked the buffer is large enough above.
-        let header = unsafe { &*(buf.as_ptr() as *const UsymLiteHeader) };
+        let (header, rest) = LayoutVerified::<_, UsymLiteHeader>::new_from_prefix(buf)
+            .ok_or_else(|| UsymLiteError::from(UsymLiteErrorKind::BadHeader))?;
+        let header = header.into_ref();
         if header.version != 2 {
             return Err(UsymLiteError::from(UsymLiteErrorKind::BadVersion));
         }
@@ -178,25 +179,20 @@ impl<'a> UsymLiteSymbols<'a> {
             .line_count
             .try_into()
             .map_err(|e| UsymLiteError::new(UsymLiteErrorKind::BadLineCount, e))?;
-        let stringtable_offset =
-            mem::size_of::<UsymLiteHeader>() + line_count * mem::size_of::<UsymLiteLine>();
+        let stringtable_offset = line_count
+            .checked_mul(mem::size_of::<UsymLiteLine>())
+            .and_then(|size| size.checked_add(mem::size_of::<UsymLiteHeader>()))
+            .ok_or_else(|| UsymLiteError::from(UsymLiteErrorKind::BufferSmallerThanAdvertised))?;
         if buf.len() < stringtable_offset {
             return Err(UsymLiteError::from(
                 UsymLiteErrorKind::BufferSmallerThanAdvertised,
             ));
         }
 
-        // SAFETY: We checked the buffer is at least the size_of::<UsymLiteHeader>() above.
-        let lines_ptr = unsafe { buf.as_ptr().add(mem::size_of::<UsymLiteHeader>()) };
-
-        // SAFETY: We checked the buffer has enough space for all the line records above.
-        let lines = unsafe {
-            let lines_ptr: *const UsymLiteLine = lines_ptr.cast();
-            let lines_ptr = ptr::slice_from_raw_parts(lines_ptr, line_count);
-            lines_ptr
-                .as_ref()
-                .ok_or_else(|| UsymLiteError::from(UsymLiteErrorKind::BadLines))
-        }?;
+        let lines = LayoutVerified::<_, [UsymLiteLine]>::new_slice_from_prefix(rest, line_count)
+            .ok_or_else(|| UsymLiteError::from(UsymLiteErrorKind::BadLines))?
+            .0
+            .into_slice();
 
         let stringtable = buf
             .get(stringtable_offset..)
@@ -218,21 +214,10 @@ impl<'a> UsymLiteSymbols<'a> {
     ///
     /// Offsets are as provided by some [`UsymLiteHeader`] and [`UsymLiteLine`] fields.
     fn get_string(&self, offset: u32) -> Option<&'a CStr> {
-        // Panic if size_of::<usize>() < size_of::<u32>().
-        let offset: usize = offset.try_into().unwrap();
-        if offset >= self.string_table.len() {
-            return None;
-        }
-
-        let table_ptr = self.string_table.as_ptr();
-
-        // SAFETY: We checked offset is inside the stringtable.
-        let string_ptr = unsafe { table_ptr.add(offset) as *const c_char };
-
-        // SAFETY: the stringtable is guaranteed to end in a NULL byte by
-        // [`UsymSymbols::parse`].
-        let string = unsafe { CStr::from_ptr(string_ptr) };
-        Some(string)
+        let offset = usize::try_from(offset).ok()?;
+        let bytes = self.string_table.get(offset..)?;
+        let len = bytes.iter().position(|&byte| byte == 0)?;
+        CStr::from_bytes_with_nul(&bytes[..=len]).ok()
     }
 
     pub fn id(&self) -> Result<Cow<'a, str>, UsymLiteError> {
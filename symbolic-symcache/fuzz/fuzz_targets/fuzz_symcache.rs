@@ -0,0 +1,42 @@
+#![no_main]
+
+use symbolic_symcache::{swap_endianness, NewSymCache};
+
+// cargo +nightly fuzz run fuzz_symcache -j 12
+libfuzzer_sys::fuzz_target!(|data: &[u8]| {
+    if let Ok(symcache) = NewSymCache::parse(data) {
+        test_symcache(&symcache);
+    }
+
+    if let Ok(swapped) = swap_endianness(data) {
+        if let Ok(symcache) = NewSymCache::parse(&swapped) {
+            test_symcache(&symcache);
+        }
+    }
+});
+
+fn test_symcache(symcache: &NewSymCache) {
+    let _ = symcache.version();
+    let _ = symcache.arch();
+    let _ = symcache.debug_id();
+    let _ = symcache.image_base();
+    let _ = symcache.verify();
+
+    // lookups follow inline chains, which may be cyclic in unvalidated caches
+    if symcache.validate().is_err() {
+        return;
+    }
+
+    for _ in symcache.functions() {}
+    for _ in symcache.files() {}
+
+    for (range, _) in symcache.ranges() {
+        for location in symcache.lookup(range.start) {
+            let _ = location.line();
+            let _ = location.column();
+            let _ = location.file().map(|file| file.full_path());
+            let _ = location.function().map(|function| function.name());
+        }
+        let _ = symcache.lookup(range.start.wrapping_sub(1)).count();
+    }
+}
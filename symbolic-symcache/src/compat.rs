@@ -6,13 +6,19 @@ use crate::{new, old, preamble, SymCacheError};
 
 /// The cutoff version between the old and new SymCache formats.
 /// Versions *greater than* this one will use the new binary format.
-pub(crate) const SYMCACHE_VERSION_CUTOFF: u32 = 6;
+pub(crate) const SYMCACHE_VERSION_CUTOFF: u32 = new::raw::SYMCACHE_VERSION_MIN - 1;
 
 impl From<new::Error> for SymCacheError {
     fn from(new_error: new::Error) -> Self {
         let kind = match new_error {
             new::Error::BufferNotAligned
             | new::Error::BadFormatLength
+            | new::Error::BadSectionAlignment
+            | new::Error::BadStringReference
+            | new::Error::BadChecksum
+            | new::Error::BadIndexReference
+            | new::Error::CyclicInlineChain
+            | new::Error::UnsortedRanges
             | new::Error::WrongEndianness => old::SymCacheErrorKind::BadCacheFile,
             new::Error::HeaderTooSmall => old::SymCacheErrorKind::BadFileHeader,
             new::Error::WrongFormat => old::SymCacheErrorKind::BadFileMagic,
@@ -26,7 +32,7 @@ impl From<new::Error> for SymCacheError {
 #[derive(Debug)]
 enum SymCacheInner<'data> {
     Old(old::SymCache<'data>),
-    New(new::SymCache<'data>),
+    New(Box<new::SymCache<'data>>),
 }
 
 /// A platform independent symbolication cache.
@@ -40,7 +46,8 @@ impl<'data> SymCache<'data> {
     pub fn parse(data: &'data [u8]) -> Result<Self, SymCacheError> {
         let preamble = preamble::Preamble::parse(data)?;
         if preamble.version > SYMCACHE_VERSION_CUTOFF {
-            Ok(Self(SymCacheInner::New(new::SymCache::parse(data)?)))
+            let symcache = new::SymCache::parse(data)?;
+            Ok(Self(SymCacheInner::New(Box::new(symcache))))
         } else {
             Ok(Self(SymCacheInner::Old(old::SymCache::parse(data)?)))
         }
@@ -101,7 +108,7 @@ impl<'data> SymCache<'data> {
         match &self.0 {
             #[allow(deprecated)]
             SymCacheInner::New(symc) => {
-                Functions(FunctionsInner::New(symc.functions().enumerate()))
+                Functions(FunctionsInner::New(Box::new(symc.functions().enumerate())))
             }
             SymCacheInner::Old(symc) => Functions(FunctionsInner::Old(symc.functions())),
         }
@@ -117,6 +124,7 @@ impl<'data> SymCache<'data> {
             SymCacheInner::New(symc) => Ok(Lookup(LookupInner::New {
                 iter: symc.lookup(addr),
                 lookup_addr: addr,
+                depth: 0,
             })),
             SymCacheInner::Old(symc) => {
                 let lookup = symc.lookup(addr)?;
@@ -178,7 +186,7 @@ impl<'data> Function<'data> {
     pub fn address(&self) -> u64 {
         match &self.0 {
             FunctionInner::Old(function) => function.address(),
-            FunctionInner::New((_, function)) => function.entry_pc() as u64,
+            FunctionInner::New((_, function)) => function.entry_pc(),
         }
     }
 
@@ -242,7 +250,7 @@ impl<'data> fmt::Debug for Function<'data> {
 #[derive(Clone, Debug)]
 enum FunctionsInner<'data> {
     Old(old::Functions<'data>),
-    New(std::iter::Enumerate<new::Functions<'data>>),
+    New(Box<std::iter::Enumerate<new::Functions<'data>>>),
 }
 
 /// An iterator over all functions in a `SymCache`.
@@ -284,6 +292,8 @@ enum LookupInner<'data, 'cache> {
     New {
         iter: new::SourceLocationIter<'data, 'cache>,
         lookup_addr: u64,
+        /// The number of frames yielded so far.
+        depth: usize,
     },
 }
 
@@ -307,17 +317,30 @@ impl<'data, 'cache> Iterator for Lookup<'data, 'cache> {
     fn next(&mut self) -> Option<Self::Item> {
         match &mut self.0 {
             LookupInner::Old(lookup) => lookup.next(),
-            LookupInner::New { iter, lookup_addr } => {
+            LookupInner::New {
+                iter,
+                lookup_addr,
+                depth,
+            } => {
                 let sl = iter.next()?;
+                let (line_addr, call_addr) = sl
+                    .cache
+                    .legacy_frame_starts(*lookup_addr, *depth)
+                    .unwrap_or((*lookup_addr, *lookup_addr));
+                *depth += 1;
+
+                // inlined functions do not have an entry pc, so report where they were inlined
+                let sym_addr = if sl.is_inlined() {
+                    call_addr
+                } else {
+                    sl.function().map(|f| f.entry_pc()).unwrap_or(u64::MAX)
+                };
 
                 Some(Ok(old::LineInfo {
                     arch: sl.cache.arch(),
                     debug_id: sl.cache.debug_id(),
-                    sym_addr: sl
-                        .function()
-                        .map(|f| f.entry_pc() as u64)
-                        .unwrap_or(u64::MAX),
-                    line_addr: *lookup_addr,
+                    sym_addr,
+                    line_addr,
                     instr_addr: *lookup_addr,
                     line: sl.line(),
                     lang: sl.function().map(|f| f.language()).unwrap_or_default(),
@@ -9,13 +9,26 @@ pub(crate) mod preamble;
 
 pub use compat::*;
 pub use new::transform;
-pub use new::SymCacheWriter;
+pub use new::{
+    swap_endianness, AddrRange, CallEdge, Candidate, ConversionError, ConversionErrorKind,
+    DiffFrame, DiffReport, ErrorSink, ExplainedRange, Explanation, File, FileSize, Files,
+    FunctionIdentity, FunctionRanges, FunctionSize, GroupingKey, Module, MultiSymCache,
+    MultiSymCacheWriter, OwnedSymCache, Provenance, RangeDiff, RangeFlags, RangeIter, SectionSize,
+    Segment, Severity, SourceLocation, SourceLocationIter, Stats, SymCacheWriter, UnitStats,
+    DEFAULT_MAX_INLINE_DEPTH,
+};
+// The reader of the range based format shares these names with the types above, which read
+// both formats.
+pub use new::{
+    Error as NewSymCacheError, Function as NewFunction, Functions as NewFunctions,
+    SymCache as NewSymCache,
+};
 #[allow(deprecated)]
 pub use old::format;
 pub use old::{Line, LineInfo, SymCacheError, SymCacheErrorKind, ValueKind};
 
 /// The latest version of the file format.
-pub const SYMCACHE_VERSION: u32 = 7;
+pub const SYMCACHE_VERSION: u32 = 8;
 
 // Version history:
 //
@@ -26,3 +39,6 @@ pub const SYMCACHE_VERSION: u32 = 7;
 // 5: PR #221: Invalid inlinee nesting leading to wrong stack traces
 // 6: PR #319: Correct line offsets and spacer line records
 // 7: PR #459: A new binary format fundamentally based on addr ranges
+// 8: The reserved header space holds the section alignment, flags, an optional CRC32 checksum
+//    and the number of entries in a section table, which describes the extension sections
+//    following the string data. All integers are stored in little-endian.
@@ -0,0 +1,388 @@
+//! Analysis of the code covered by a [`SymCache`].
+
+use std::mem;
+use std::ops::Range;
+
+use super::{raw, File, Function, SymCache};
+
+impl<'data> SymCache<'data> {
+    /// Computes the total code size of every function in the SymCache, largest first.
+    ///
+    /// Every range is attributed to its outermost function, so the size of a function includes
+    /// the code of all functions that were inlined into it. Functions that do not cover any
+    /// range are omitted. The last range is open-ended and does not contribute to any size.
+    pub fn function_sizes(&self) -> Vec<FunctionSize<'data>> {
+        let mut sizes = vec![0u64; self.functions.len()];
+
+        let source_location_start = self.source_locations.len() - self.ranges.len();
+        for range_idx in 0..self.ranges.len().saturating_sub(1) {
+            let source_location_idx = source_location_start + range_idx;
+            let mut source_location = match self.source_locations.get(source_location_idx) {
+                Some(sl) if !sl.is_none() => sl,
+                _ => continue,
+            };
+
+            // walk up to the outermost function, bounded in case of cyclic inline chains
+            for _ in 0..self.source_locations.len() {
+                match self
+                    .source_locations
+                    .get(source_location.inlined_into_idx.get() as usize)
+                {
+                    Some(caller) => source_location = caller,
+                    None => break,
+                }
+            }
+
+            if let Some(size) = sizes.get_mut(source_location.function_idx.get() as usize) {
+                *size += self
+                    .range(range_idx)
+                    .map_or(0, |range| range.end - range.start);
+            }
+        }
+
+        let mut function_sizes: Vec<_> = sizes
+            .into_iter()
+            .enumerate()
+            .filter(|&(_, size)| size > 0)
+            .filter_map(|(function_idx, size)| {
+                let function = self.get_function(function_idx as u32)?;
+                Some(FunctionSize { function, size })
+            })
+            .collect();
+
+        function_sizes.sort_by_key(|function_size| std::cmp::Reverse(function_size.size));
+        function_sizes
+    }
+
+    /// Computes the total code size attributed to every source file in the SymCache, largest
+    /// first.
+    ///
+    /// Every range is attributed to the file of its innermost frame, so inlined code counts
+    /// towards the file it was written in. Files that do not cover any range are omitted. Like in
+    /// [`function_sizes`](Self::function_sizes), the last range does not contribute to any size.
+    pub fn file_sizes(&self) -> Vec<FileSize<'data>> {
+        let mut sizes = vec![0u64; self.files.len()];
+
+        let source_location_start = self.source_locations.len() - self.ranges.len();
+        for range_idx in 0..self.ranges.len().saturating_sub(1) {
+            let source_location_idx = source_location_start + range_idx;
+            let file_idx = match self.source_locations.get(source_location_idx) {
+                Some(sl) if !sl.is_none() => sl.file_idx.get(),
+                _ => continue,
+            };
+
+            if let Some(size) = sizes.get_mut(file_idx as usize) {
+                *size += self
+                    .range(range_idx)
+                    .map_or(0, |range| range.end - range.start);
+            }
+        }
+
+        let mut file_sizes: Vec<_> = sizes
+            .into_iter()
+            .enumerate()
+            .filter(|&(_, size)| size > 0)
+            .filter_map(|(file_idx, size)| {
+                let file = self.get_file(file_idx as u32)?;
+                Some(FileSize { file, size })
+            })
+            .collect();
+
+        file_sizes.sort_by_key(|file_size| std::cmp::Reverse(file_size.size));
+        file_sizes
+    }
+
+    /// Returns the size of every section in the SymCache, in the order they are stored.
+    ///
+    /// The sizes do not include the header and the padding between sections. Extension sections
+    /// are only listed if they are present, with a name of `"unknown"` for unknown kinds.
+    pub fn section_sizes(&self) -> Vec<SectionSize> {
+        fn size_of<T>(name: &'static str, elements: &[T]) -> SectionSize {
+            SectionSize {
+                name,
+                count: elements.len(),
+                bytes: mem::size_of_val(elements),
+            }
+        }
+
+        let mut sizes = vec![
+            size_of("files", self.files),
+            size_of("functions", self.functions),
+            size_of("source_locations", self.source_locations),
+            size_of("ranges", self.ranges),
+            size_of("strings", self.string_bytes),
+            size_of("section_table", self.sections),
+        ];
+
+        for section in self.sections {
+            let name = match section.kind.get() {
+                raw::SECTION_PROVENANCE => "provenance",
+                raw::SECTION_RANGE_FLAGS => "range_flags",
+                raw::SECTION_COLUMNS => "columns",
+                raw::SECTION_LINKAGE_NAMES => "linkage_names",
+                raw::SECTION_RANGES_HIGH => "ranges_high",
+                raw::SECTION_ENTRY_PCS_HIGH => "entry_pcs_high",
+                raw::SECTION_IMAGE_BASE => "image_base",
+                raw::SECTION_NAME_INDEX => "name_index",
+                raw::SECTION_CALL_EDGES => "call_edges",
+                raw::SECTION_DECLARATIONS => "declarations",
+                raw::SECTION_CANDIDATES => "candidates",
+                raw::SECTION_SEGMENTS => "segments",
+                _ => "unknown",
+            };
+            let bytes = section.len.get() as usize;
+            // call edges, candidates and segments consist of several elements each
+            let element_size = match section.kind.get() {
+                raw::SECTION_CALL_EDGES => mem::size_of::<raw::CallEdge>(),
+                raw::SECTION_CANDIDATES => mem::size_of::<raw::Candidate>(),
+                raw::SECTION_SEGMENTS => mem::size_of::<raw::Segment>(),
+                _ => section.element_size.get() as usize,
+            };
+            sizes.push(SectionSize {
+                name,
+                count: bytes / element_size.max(1),
+                bytes,
+            });
+        }
+
+        sizes
+    }
+
+    /// Returns every function in the SymCache together with the address ranges it covers.
+    ///
+    /// A range is attributed to every function in its inline chain, so the ranges of a function
+    /// include the code of its inlinees, and the ranges of an inlined function are the places it
+    /// was inlined at. Adjacent ranges are merged. Functions are returned in the order of
+    /// [`functions`](Self::functions), and functions without any ranges are omitted. The last
+    /// range is open-ended and is not attributed to any function.
+    pub fn functions_with_ranges(&self) -> Vec<FunctionRanges<'data>> {
+        let mut ranges: Vec<Vec<Range<u64>>> = vec![Vec::new(); self.functions.len()];
+
+        self.for_each_function_range(|function_idx, range| {
+            if let Some(function_ranges) = ranges.get_mut(function_idx as usize) {
+                merge_range(function_ranges, range);
+            }
+        });
+
+        ranges
+            .into_iter()
+            .enumerate()
+            .filter(|(_, ranges)| !ranges.is_empty())
+            .filter_map(|(function_idx, ranges)| {
+                let function = self.get_function(function_idx as u32)?;
+                Some(FunctionRanges { function, ranges })
+            })
+            .collect()
+    }
+
+    /// Returns the address ranges covered by a single function, see
+    /// [`functions_with_ranges`](Self::functions_with_ranges).
+    pub(crate) fn function_ranges(&self, function_idx: u32) -> Vec<Range<u64>> {
+        let mut ranges = Vec::new();
+
+        self.for_each_function_range(|idx, range| {
+            if idx == function_idx {
+                merge_range(&mut ranges, range);
+            }
+        });
+
+        ranges
+    }
+
+    /// Calls `f` for every range with each function in its inline chain, innermost first.
+    fn for_each_function_range<F>(&self, mut f: F)
+    where
+        F: FnMut(u32, Range<u64>),
+    {
+        let source_location_start = self.source_locations.len() - self.ranges.len();
+        for range_idx in 0..self.ranges.len().saturating_sub(1) {
+            let range = match self.range(range_idx) {
+                Some(range) => range,
+                None => continue,
+            };
+            let mut source_location_idx = source_location_start + range_idx;
+
+            // bounded in case of cyclic inline chains
+            for _ in 0..self.source_locations.len() {
+                let source_location = match self.source_locations.get(source_location_idx) {
+                    Some(sl) if !sl.is_none() => sl,
+                    _ => break,
+                };
+
+                f(source_location.function_idx.get(), range.clone());
+                source_location_idx = source_location.inlined_into_idx.get() as usize;
+            }
+        }
+    }
+
+    /// Returns the address range at the given index, unless it is the last, open-ended range.
+    ///
+    /// Unsorted ranges in corrupted files are treated as empty.
+    fn range(&self, range_idx: usize) -> Option<Range<u64>> {
+        let start = self.range_start(range_idx)?;
+        let end = self.range_start(range_idx + 1)?;
+        Some(start..end.max(start))
+    }
+}
+
+/// Appends a range in ascending order, merging adjacent ranges as well as ranges attributed twice
+/// by recursion.
+fn merge_range(ranges: &mut Vec<Range<u64>>, range: Range<u64>) {
+    match ranges.last_mut() {
+        Some(last) if last.end >= range.start => last.end = range.end,
+        _ => ranges.push(range),
+    }
+}
+
+/// The total code size of a [`Function`], see [`SymCache::function_sizes`].
+#[derive(Clone, Debug)]
+pub struct FunctionSize<'data> {
+    /// The function.
+    pub function: Function<'data>,
+    /// The number of code bytes attributed to the function, including inlined code.
+    pub size: u64,
+}
+
+/// The total code size of a [`File`], see [`SymCache::file_sizes`].
+#[derive(Clone, Debug)]
+pub struct FileSize<'data> {
+    /// The file.
+    pub file: File<'data>,
+    /// The number of code bytes attributed to the file.
+    pub size: u64,
+}
+
+/// The size of a section in a SymCache, see [`SymCache::section_sizes`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SectionSize {
+    /// The name of the section, such as `"functions"` or `"strings"`.
+    pub name: &'static str,
+    /// The number of elements in the section, or the number of bytes for the string section.
+    pub count: usize,
+    /// The size of the section in bytes.
+    pub bytes: usize,
+}
+
+/// The address ranges covered by a [`Function`], see [`SymCache::functions_with_ranges`].
+#[derive(Clone, Debug)]
+pub struct FunctionRanges<'data> {
+    /// The function.
+    pub function: Function<'data>,
+    /// The covered address ranges in ascending order.
+    pub ranges: Vec<Range<u64>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use symbolic_debuginfo::Function;
+
+    use super::*;
+
+    use crate::new::testutils::{function, inlined_functions, line, serialize};
+    use crate::new::writer::SymCacheConverter;
+
+    #[test]
+    fn test_function_sizes() {
+        let line = |address, size, number| line(address, size, "", "main.c", number);
+        let inlined = Function {
+            inline: true,
+            ..function(0x1000, 8, "inlined", vec![line(0x1000, 8, 10)])
+        };
+        let main = Function {
+            inlinees: vec![inlined],
+            ..function(
+                0x1000,
+                0x10,
+                "main",
+                vec![line(0x1000, 8, 1), line(0x1008, 8, 2)],
+            )
+        };
+        let other = function(0x1010, 0x20, "other", vec![line(0x1010, 0x20, 20)]);
+
+        let mut converter = SymCacheConverter::new();
+        converter.process_symbolic_function(&main);
+        converter.process_symbolic_function(&other);
+        let buf = serialize(converter);
+        let cache = SymCache::parse(&buf).unwrap();
+
+        let sizes: Vec<_> = cache
+            .function_sizes()
+            .into_iter()
+            .map(|fs| (fs.function.name(), fs.size))
+            .collect();
+        assert_eq!(sizes, [(Some("other"), 0x20), (Some("main"), 0x10)]);
+
+        let bounds = |ranges: &[std::ops::Range<u64>]| -> Vec<_> {
+            ranges
+                .iter()
+                .map(|range| (range.start, range.end))
+                .collect()
+        };
+        let ranges: Vec<_> = cache
+            .functions_with_ranges()
+            .into_iter()
+            .map(|fr| (fr.function.name(), bounds(&fr.ranges)))
+            .collect();
+        assert_eq!(
+            ranges,
+            [
+                (Some("main"), vec![(0x1000, 0x1010)]),
+                (Some("inlined"), vec![(0x1000, 0x1008)]),
+                (Some("other"), vec![(0x1010, 0x1030)]),
+            ]
+        );
+
+        let frames: Vec<_> = cache
+            .lookup(0x1004)
+            .map(|sl| (sl.function().unwrap().name(), bounds(&sl.function_ranges())))
+            .collect();
+        assert_eq!(
+            frames,
+            [
+                (Some("inlined"), vec![(0x1000, 0x1008)]),
+                (Some("main"), vec![(0x1000, 0x1010)]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_file_and_section_sizes() {
+        let mut converter = SymCacheConverter::new();
+        converter.process_symbolic_function(&inlined_functions());
+        let buf = serialize(converter);
+        let cache = SymCache::parse(&buf).unwrap();
+
+        let mut file_sizes: Vec<_> = cache
+            .file_sizes()
+            .iter()
+            .map(|file_size| (file_size.file.full_path(), file_size.size))
+            .collect();
+        file_sizes.sort();
+        assert_eq!(
+            file_sizes,
+            [
+                ("/build/src/helper.h".to_owned(), 8),
+                ("/build/src/main.c".to_owned(), 8)
+            ]
+        );
+
+        let sections = cache.section_sizes();
+        let names: Vec<_> = sections.iter().map(|section| section.name).collect();
+        assert_eq!(
+            names,
+            [
+                "files",
+                "functions",
+                "source_locations",
+                "ranges",
+                "strings",
+                "section_table",
+                "provenance"
+            ]
+        );
+        assert_eq!(sections[1].count, 2);
+        assert_eq!(sections[3].count, cache.ranges.len());
+        assert_eq!(sections[4].bytes, cache.string_bytes.len());
+        assert_eq!(sections[6].count, cache.ranges.len());
+    }
+}
@@ -0,0 +1,103 @@
+//! The static call graph between the functions of a [`SymCache`].
+
+use super::{File, Function, SymCache};
+
+impl<'data> SymCache<'data> {
+    /// Returns whether this SymCache contains call edges between functions.
+    ///
+    /// The edges are written with [`SymCacheWriter::set_call_edges`](super::SymCacheWriter::set_call_edges).
+    /// Without them, [`callees_of`](Self::callees_of) never returns any calls.
+    pub fn has_call_edges(&self) -> bool {
+        !self.call_edges.is_empty()
+    }
+
+    /// Returns the calls made by the outermost function at the given address.
+    ///
+    /// The outermost function is the one that was actually called, like in
+    /// [`grouping_key`](Self::grouping_key). Calls are derived from the functions inlined into it,
+    /// and every distinct call site yields one [`CallEdge`]. Returns an empty list if the address
+    /// cannot be resolved.
+    pub fn callees_of(&self, addr: u64) -> Vec<CallEdge<'data>> {
+        let function_idx = match self.lookup(addr).last() {
+            Some(outermost) => outermost.source_location.function_idx.get(),
+            None => return Vec::new(),
+        };
+
+        // edges are sorted by their caller
+        let start = self
+            .call_edges
+            .partition_point(|edge| edge.caller_idx.get() < function_idx);
+        self.call_edges[start..]
+            .iter()
+            .take_while(|edge| edge.caller_idx.get() == function_idx)
+            .filter_map(|edge| {
+                Some(CallEdge {
+                    caller: self.get_function(edge.caller_idx.get())?,
+                    callee: self.get_function(edge.callee_idx.get())?,
+                    file: self.get_file(edge.file_idx.get()),
+                    line: edge.line.get(),
+                })
+            })
+            .collect()
+    }
+}
+
+/// A static call from one function to another, see [`SymCache::callees_of`].
+#[derive(Clone, Debug)]
+pub struct CallEdge<'data> {
+    /// The calling function.
+    pub caller: Function<'data>,
+    /// The called function.
+    pub callee: Function<'data>,
+    /// The source file of the call site, if known.
+    pub file: Option<File<'data>>,
+    /// The line of the call site, `0` if unknown.
+    pub line: u32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::new::testutils::{inlined_functions, serialize};
+    use crate::new::writer::SymCacheConverter;
+    use crate::new::{endian, swap_endianness};
+
+    #[test]
+    fn test_call_edges() {
+        let write = |call_edges| {
+            let mut converter = SymCacheConverter::new();
+            converter.set_call_edges(call_edges);
+            converter.process_symbolic_function(&inlined_functions());
+            serialize(converter)
+        };
+
+        let buf = write(false);
+        let cache = SymCache::parse(&buf).unwrap();
+        assert!(!cache.has_call_edges());
+        assert!(cache.callees_of(0x1000).is_empty());
+
+        let buf = write(true);
+        let cache = SymCache::parse(&buf).unwrap();
+        assert!(cache.has_call_edges());
+        cache.validate().unwrap();
+
+        let edges = cache.callees_of(0x1000);
+        assert_eq!(edges.len(), 1);
+        assert_eq!(edges[0].caller.name(), Some("main"));
+        assert_eq!(edges[0].callee.name(), Some("helper"));
+        assert_eq!(
+            edges[0].file.as_ref().unwrap().full_path(),
+            "/build/src/main.c"
+        );
+        assert_eq!(edges[0].line, 2);
+        // addresses within the inlinee resolve to the same outermost function
+        assert_eq!(cache.callees_of(0x100c).len(), 1);
+        assert!(cache.callees_of(0x2000).is_empty());
+
+        // the edges consist of `u32`s, which are swapped individually
+        let mut foreign = buf.clone();
+        endian::swap_in_place(&mut foreign, false).unwrap();
+        assert_eq!(swap_endianness(&foreign).unwrap(), buf);
+    }
+}
@@ -0,0 +1,178 @@
+//! Alternative attributions of addresses that were resolved differently by multiple inputs.
+
+use super::raw;
+use super::{Provenance, SourceLocationIter, SymCache};
+
+impl<'data> SymCache<'data> {
+    /// Returns whether this SymCache contains alternative candidates for any address.
+    ///
+    /// The candidates are written with
+    /// [`SymCacheWriter::set_keep_candidates`](super::SymCacheWriter::set_keep_candidates).
+    /// Without them, [`candidates`](Self::candidates) only returns the result of
+    /// [`lookup`](Self::lookup).
+    pub fn has_candidates(&self) -> bool {
+        !self.candidates.is_empty()
+    }
+
+    /// Returns all attributions of the given address, starting with the one that
+    /// [`lookup`](Self::lookup) yields.
+    ///
+    /// Inputs may attribute the same address differently, for example a symbol table and debug
+    /// information, or two compilation units after identical code folding. Only one of them is
+    /// resolved by [`lookup`](Self::lookup), and the others follow in the order they were
+    /// dropped during conversion. Returns an empty list if the address cannot be resolved.
+    pub fn candidates(&self, addr: u64) -> Vec<Candidate<'data, '_>> {
+        let range_idx = match self.find_range(addr) {
+            Some(range_idx) => range_idx,
+            None => return Vec::new(),
+        };
+        let source_locations = self.lookup_range(range_idx);
+        if source_locations.clone().next().is_none() {
+            return Vec::new();
+        }
+
+        let provenance = self.provenance.get(range_idx).copied();
+        let provenance = provenance.unwrap_or(raw::PROVENANCE_UNKNOWN);
+        let mut candidates = vec![Candidate {
+            provenance: Provenance::from_raw(provenance),
+            source_locations,
+        }];
+
+        // candidates are sorted by their range
+        let range_idx = range_idx as u32;
+        let start = self
+            .candidates
+            .partition_point(|candidate| candidate.range_idx.get() < range_idx);
+        let alternatives = self.candidates[start..]
+            .iter()
+            .take_while(|candidate| candidate.range_idx.get() == range_idx)
+            .map(|candidate| Candidate {
+                provenance: Provenance::from_raw(candidate.provenance.get() as u8),
+                source_locations: SourceLocationIter {
+                    cache: self,
+                    source_location_idx: candidate.source_location_idx.get(),
+                    remaining: self.source_locations.len(),
+                },
+            });
+        candidates.extend(alternatives);
+        candidates
+    }
+
+    /// Looks up an address like [`lookup`](Self::lookup), but prefers an attribution produced by
+    /// the given kind of input.
+    ///
+    /// This yields the frames of the first of the [`candidates`](Self::candidates) with the given
+    /// provenance, for example to prefer debug information over a symbol table. If there is no
+    /// such candidate, this yields the same frames as [`lookup`](Self::lookup).
+    pub fn lookup_preferring(
+        &self,
+        addr: u64,
+        provenance: Provenance,
+    ) -> SourceLocationIter<'data, '_> {
+        let mut candidates = self.candidates(addr);
+        if candidates.is_empty() {
+            return self.lookup(addr);
+        }
+        let preferred = candidates
+            .iter()
+            .position(|candidate| candidate.provenance == provenance);
+        candidates
+            .swap_remove(preferred.unwrap_or(0))
+            .source_locations
+    }
+}
+
+/// An attribution of an address, see [`SymCache::candidates`].
+#[derive(Clone, Debug)]
+pub struct Candidate<'data, 'cache> {
+    /// The kind of input that produced this attribution.
+    pub provenance: Provenance,
+    /// The frames of this attribution, from the innermost to the outermost function.
+    pub source_locations: SourceLocationIter<'data, 'cache>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::new::testutils::{function, line, serialize, symbol};
+    use crate::new::writer::SymCacheConverter;
+
+    #[test]
+    fn test_candidates() {
+        let function = |name, file, number| {
+            function(
+                0x1000,
+                0x10,
+                name,
+                vec![line(0x1000, 0x10, "", file, number)],
+            )
+        };
+        let convert = |keep_candidates| {
+            let mut converter = SymCacheConverter::new();
+            converter.set_keep_candidates(keep_candidates);
+            converter.process_symbolic_function(&function("a", "a.c", 1));
+            converter.process_symbolic_function(&function("b", "b.c", 2));
+            // a symbol of the same name as the debug information is not a candidate
+            for name in ["a", "sym"] {
+                converter.process_symbolic_symbol(&symbol(name, 0x1000, 0x10));
+            }
+            converter
+        };
+        let frames = |source_locations: SourceLocationIter<'_, '_>| {
+            source_locations
+                .map(|sl| {
+                    let name = sl.function().unwrap().name().unwrap().to_owned();
+                    (name, sl.line(), sl.is_synthetic())
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let buf = serialize(convert(true));
+        let cache = SymCache::parse(&buf).unwrap();
+        cache.validate().unwrap();
+        assert!(cache.has_candidates());
+        let candidates: Vec<_> = cache
+            .candidates(0x1008)
+            .into_iter()
+            .map(|candidate| (candidate.provenance, frames(candidate.source_locations)))
+            .collect();
+        assert_eq!(
+            candidates,
+            [
+                (Provenance::DebugInfo, vec![("a".into(), 1, false)]),
+                (Provenance::DebugInfo, vec![("b".into(), 2, false)]),
+                (Provenance::SymbolTable, vec![("sym".into(), 0, true)]),
+            ]
+        );
+        assert_eq!(
+            frames(cache.lookup_preferring(0x1008, Provenance::SymbolTable)),
+            [("sym".into(), 0, true)]
+        );
+        assert_eq!(
+            frames(cache.lookup_preferring(0x1008, Provenance::DebugInfo)),
+            frames(cache.lookup(0x1008))
+        );
+        assert!(cache.candidates(0x2000).is_empty());
+
+        // candidates survive a round trip through another converter
+        let mut converter = SymCacheConverter::new();
+        converter.set_keep_candidates(true);
+        converter.process_symcache(&cache).unwrap();
+        assert_eq!(serialize(converter), buf);
+
+        // candidates are dropped first when limiting the size
+        let without = serialize(convert(false));
+        let mut converter = convert(true);
+        assert!(converter.limit_size(without.len() as u64));
+        assert_eq!(converter.stats().dropped_inline_frames, 0);
+        let buf = serialize(converter);
+        let cache = SymCache::parse(&buf).unwrap();
+        assert!(!cache.has_candidates());
+        assert_eq!(cache.candidates(0x1008).len(), 1);
+        assert_eq!(
+            frames(cache.lookup_preferring(0x1008, Provenance::SymbolTable)),
+            [("a".into(), 1, false)]
+        );
+    }
+}
@@ -1,9 +1,20 @@
 //! Types & Definitions needed to keep compatibility with existing API
 
-use std::io::{Seek, Write};
+use std::io::Write;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::Instant;
 
 use symbolic_common::{Arch, DebugId};
-use symbolic_debuginfo::{Function as SymbolicFunction, ObjectLike, Symbol};
+use symbolic_debuginfo::dwarf::Dwarf;
+use symbolic_debuginfo::elf::ElfObject;
+use symbolic_debuginfo::macho::{BcSymbolMap, MachObject};
+use symbolic_debuginfo::pdb::PdbObject;
+use symbolic_debuginfo::pe::PeObject;
+use symbolic_debuginfo::wasm::WasmObject;
+use symbolic_debuginfo::{
+    Archive, Function as SymbolicFunction, Object, ObjectDebugSession, ObjectLike, Symbol,
+};
 
 use super::writer::SymCacheConverter;
 use super::*;
@@ -12,7 +23,7 @@ use crate::{SymCacheError, SymCacheErrorKind};
 impl<'data> SymCache<'data> {
     /// Returns true if line information is included.
     pub fn has_line_info(&self) -> bool {
-        self.has_file_info() && self.source_locations.iter().any(|sl| sl.line > 0)
+        self.has_file_info() && self.source_locations.iter().any(|sl| sl.line.get() > 0)
     }
 
     /// Returns true if file information is included.
@@ -20,6 +31,60 @@ impl<'data> SymCache<'data> {
         !self.files.is_empty()
     }
 
+    /// Returns the addresses at which the line and the call of a frame start, as reported by
+    /// legacy SymCaches.
+    ///
+    /// `depth` counts the frames yielded by [`lookup`](Self::lookup) for `addr`, starting with the
+    /// innermost one. The line starts at the first of the adjacent ranges that resolve to the same
+    /// source location at this depth. The call starts at the first of the adjacent ranges that
+    /// resolve to the same function with the same caller, and is only computed for inlined frames.
+    pub(crate) fn legacy_frame_starts(&self, addr: u64, depth: usize) -> Option<(u64, u64)> {
+        let range_idx = self.find_range(addr)?;
+
+        // frames are compared by their level from the outermost frame, so the frames of every
+        // range are yielded from the level of the found frame outwards. a call is identified by
+        // the functions of the frame and all its callers, since the lines of the callers may
+        // change while the call continues.
+        let level = self
+            .lookup_range(range_idx)
+            .count()
+            .checked_sub(depth + 1)?;
+        let frames = |range_idx| {
+            let inner = self
+                .lookup_range(range_idx)
+                .count()
+                .checked_sub(level + 1)?;
+            let frames = self.lookup_range(range_idx).skip(inner);
+            Some(frames.map(|source_location| source_location.source_location))
+        };
+        let found = frames(range_idx)?;
+        let line = found.clone().next()?;
+
+        let (mut line_idx, mut call_idx) = (range_idx, range_idx);
+        let mut same_line = true;
+        for idx in (0..range_idx).rev() {
+            if !same_line && level == 0 {
+                break;
+            }
+            let frames = match frames(idx) {
+                Some(frames) => frames,
+                None => break,
+            };
+            let functions = frames.clone().map(|frame| frame.function_idx);
+            if !functions.eq(found.clone().map(|frame| frame.function_idx)) {
+                break;
+            }
+
+            call_idx = idx;
+            same_line = same_line && frames.clone().next() == Some(line);
+            if same_line {
+                line_idx = idx;
+            }
+        }
+
+        Some((self.range_start(line_idx)?, self.range_start(call_idx)?))
+    }
+
     /// An iterator over the functions in this SymCache.
     pub fn functions(&self) -> Functions<'data> {
         Functions {
@@ -27,8 +92,19 @@ impl<'data> SymCache<'data> {
             function_idx: 0,
         }
     }
+
+    /// An iterator over the source files in this SymCache.
+    ///
+    /// Use [`File::full_path`] to join the path fragments of each file.
+    pub fn files(&self) -> Files<'data> {
+        Files {
+            cache: self.clone(),
+            file_idx: 0,
+        }
+    }
 }
 
+/// An iterator over the [`Function`]s in a [`SymCache`].
 #[derive(Debug, Clone)]
 pub struct Functions<'data> {
     cache: SymCache<'data>,
@@ -46,12 +122,35 @@ impl<'data> Iterator for Functions<'data> {
     }
 }
 
+/// An iterator over the [`File`]s in a [`SymCache`].
+#[derive(Debug, Clone)]
+pub struct Files<'data> {
+    cache: SymCache<'data>,
+    file_idx: u32,
+}
+
+impl<'data> Iterator for Files<'data> {
+    type Item = File<'data>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let file = self.cache.get_file(self.file_idx)?;
+        self.file_idx += 1;
+        Some(file)
+    }
+}
+
 /// A high level writer that can construct SymCaches.
 ///
 /// When using this writer directly, make sure to call [`finish`](SymCacheWriter::finish)
 /// at the end, so that all segments are
 /// written to the underlying writer and the header is fixed up with the references. Since segments
 /// are consecutive chunks of memory, this can only be done once at the end of the writing process.
+///
+/// Inputs can be added incrementally by calling the `process_*` methods several times, for example
+/// as a build pipeline produces the stripped binary and its debug companion file. The writer
+/// copies all data it needs, so every input can be dropped as soon as it has been processed.
+/// To collect the SymCaches of several modules in a single file, append each writer to a
+/// [`MultiSymCacheWriter`] instead of finishing it.
 pub struct SymCacheWriter<W> {
     converter: SymCacheConverter,
     writer: W,
@@ -59,7 +158,7 @@ pub struct SymCacheWriter<W> {
 
 impl<W> SymCacheWriter<W>
 where
-    W: Write + Seek,
+    W: Write,
 {
     /// Converts an entire object into a SymCache.
     ///
@@ -83,6 +182,69 @@ where
         .finish()
     }
 
+    /// Converts the object of the given architecture in an archive, such as a fat Mach-O.
+    ///
+    /// Returns an error of kind [`MissingArchitecture`](SymCacheErrorKind::MissingArchitecture)
+    /// if the archive does not contain an object for `arch`.
+    pub fn write_archive_object(
+        archive: &Archive<'_>,
+        arch: Arch,
+        target: W,
+    ) -> Result<W, SymCacheError> {
+        for object in archive.objects() {
+            let object =
+                object.map_err(|e| SymCacheError::new(SymCacheErrorKind::BadDebugFile, e))?;
+            if object.arch() == arch {
+                return Self::write_object(&object, target);
+            }
+        }
+
+        Err(SymCacheErrorKind::MissingArchitecture.into())
+    }
+
+    /// Converts every object in an archive, such as a fat Mach-O, into a separate SymCache.
+    ///
+    /// The SymCache of each object is written into the target returned by `target` for the
+    /// object's architecture. Returns the written targets in the order of the archive.
+    pub fn write_archive<F>(
+        archive: &Archive<'_>,
+        mut target: F,
+    ) -> Result<Vec<(Arch, W)>, SymCacheError>
+    where
+        F: FnMut(Arch) -> W,
+    {
+        let mut targets = Vec::with_capacity(archive.object_count());
+        for object in archive.objects() {
+            let object =
+                object.map_err(|e| SymCacheError::new(SymCacheErrorKind::BadDebugFile, e))?;
+            let arch = object.arch();
+            targets.push((arch, Self::write_object(&object, target(arch))?));
+        }
+
+        Ok(targets)
+    }
+
+    /// Merges several SymCaches of the same module into a single SymCache.
+    ///
+    /// This is a shortcut for [`SymCacheWriter::process_symcache`] for every SymCache followed
+    /// by [`SymCacheWriter::finish`]. The debug identifier and architecture are taken from the
+    /// first SymCache.
+    pub fn write_merged<'a, 'd: 'a, I>(caches: I, target: W) -> Result<W, SymCacheError>
+    where
+        I: IntoIterator<Item = &'a SymCache<'d>>,
+    {
+        let mut converter = SymCacheConverter::new();
+        for cache in caches {
+            converter.process_symcache(cache)?;
+        }
+
+        Self {
+            converter,
+            writer: target,
+        }
+        .finish()
+    }
+
     /// Constructs a new `SymCacheWriter` and writes the preamble.
     pub fn new(writer: W) -> Result<Self, SymCacheError> {
         Ok(SymCacheWriter {
@@ -102,6 +264,62 @@ where
         self.converter.add_transformer(t)
     }
 
+    /// Drops functions and symbols for which `filter` returns `false`.
+    ///
+    /// The filter receives each function after all transformers have been applied. Addresses of
+    /// a dropped function do not resolve to any function, rather than to the preceding one.
+    /// Dropped inlined functions resolve to their caller instead.
+    pub fn set_function_filter<F>(&mut self, filter: F)
+    where
+        F: Fn(&transform::Function<'_>) -> bool + 'static,
+    {
+        self.converter.set_function_filter(filter)
+    }
+
+    /// Drops source files for which `filter` returns `false`.
+    ///
+    /// The filter receives each file after all transformers have been applied. Line records in
+    /// dropped files are kept without file and line information.
+    pub fn set_file_filter<F>(&mut self, filter: F)
+    where
+        F: Fn(&transform::File<'_>) -> bool + 'static,
+    {
+        self.converter.set_file_filter(filter)
+    }
+
+    /// Adds a function that transforms every string before it is written to the SymCache.
+    ///
+    /// The function returns the replacement for a string, or `None` to keep it unchanged.
+    pub fn transform_strings<F>(&mut self, f: F)
+    where
+        F: Fn(&str) -> Option<String> + 'static,
+    {
+        self.converter.transform_strings(f)
+    }
+
+    /// Resolves obfuscated function names and file paths using the given [`BcSymbolMap`].
+    pub fn apply_bcsymbolmap(&mut self, bcsymbolmap: &BcSymbolMap<'_>) {
+        self.converter.apply_bcsymbolmap(bcsymbolmap)
+    }
+
+    /// Replaces the prefix `from` of file paths and compilation directories with `to`, like the
+    /// `-fdebug-prefix-map=from=to` compiler option.
+    ///
+    /// If several prefixes match, the one added last wins.
+    pub fn remap_path_prefix(&mut self, from: &str, to: &str) {
+        self.converter.remap_path_prefix(from, to)
+    }
+
+    /// Removes the prefix from file paths and compilation directories, which makes them relative.
+    pub fn strip_path_prefix(&mut self, prefix: &str) {
+        self.converter.strip_path_prefix(prefix)
+    }
+
+    /// Replaces backslashes in file paths and compilation directories with forward slashes.
+    pub fn set_normalize_path_separators(&mut self, normalize: bool) {
+        self.converter.set_normalize_path_separators(normalize)
+    }
+
     /// Processes the [`ObjectLike`], writing its functions, line information and symbols into the
     /// SymCache.
     pub fn process_object<'d, 'o, O>(&mut self, object: &'o O) -> Result<(), SymCacheError>
@@ -117,6 +335,257 @@ where
         Ok(())
     }
 
+    /// Processes a MachO object like [`process_object`](Self::process_object), and records its
+    /// code segments.
+    ///
+    /// Kernels and some hardened binaries map their code in a `__TEXT_EXEC` segment besides
+    /// `__TEXT`, and each segment may be slid separately at runtime. The recorded segments let
+    /// [`SymCache::lookup_segment_offset`] resolve offsets into a segment. Images with a single
+    /// code segment do not need them, so no segments are recorded for these.
+    pub fn process_macho(&mut self, object: &MachObject<'_>) -> Result<(), SymCacheError> {
+        let segments = object.code_segments();
+        if segments.len() > 1 {
+            for segment in segments {
+                let start = segment.address;
+                let end = start.saturating_add(segment.size);
+                self.converter
+                    .add_segment(segment.name, AddrRange { start, end });
+            }
+        }
+
+        self.process_object(object)
+    }
+
+    /// Processes a PDB that was located separately for the given PE file.
+    ///
+    /// The PDB is matched to the PE via the signature and age in the PE's debug directory, and
+    /// an error of kind [`MismatchedDebugFile`](SymCacheErrorKind::MismatchedDebugFile) is
+    /// returned if they do not belong together. The SymCache is identified by the PE's debug
+    /// identifier and architecture.
+    pub fn process_pe_with_pdb(
+        &mut self,
+        pe: &PeObject<'_>,
+        pdb_data: &[u8],
+    ) -> Result<(), SymCacheError> {
+        let debug_id = pe.debug_id();
+        if debug_id.is_nil() {
+            return Err(SymCacheErrorKind::MissingDebugInfo.into());
+        }
+
+        let pdb = PdbObject::parse(pdb_data)
+            .map_err(|e| SymCacheError::new(SymCacheErrorKind::BadDebugFile, e))?;
+        if pdb.debug_id() != debug_id {
+            return Err(SymCacheErrorKind::MismatchedDebugFile.into());
+        }
+
+        self.converter.set_arch(pe.arch());
+        self.converter.set_debug_id(debug_id);
+
+        self.converter.process_object(&pdb)?;
+
+        Ok(())
+    }
+
+    /// Processes an ELF object whose debug information was split into `.dwo` or `.dwp` files.
+    ///
+    /// Objects compiled with `-gsplit-dwarf` only retain line information, while functions and
+    /// inlinees are stored in the split files. Pass all `.dwo` files or the `.dwp` package of the
+    /// object in `split`. Functions of compilation units without a matching split file are only
+    /// recovered from the symbol table.
+    pub fn process_split_dwarf<'d>(
+        &mut self,
+        object: &ElfObject<'d>,
+        split: &[ElfObject<'d>],
+    ) -> Result<(), SymCacheError> {
+        let session = object
+            .debug_session_with_split_dwarf(split)
+            .map_err(|e| SymCacheError::new(SymCacheErrorKind::BadDebugFile, e))?;
+
+        self.converter.set_arch(object.arch());
+        self.converter.set_debug_id(object.debug_id());
+
+        self.converter.process_dwarf(&session, object.symbols())
+    }
+
+    /// Processes a WebAssembly module with DWARF debug information in its custom sections.
+    ///
+    /// Addresses in the DWARF of WebAssembly modules are offsets into the code section. They are
+    /// converted to offsets into the module file, which is how browsers and other runtimes report
+    /// instruction addresses. Pass either the module itself or its separate debug file, as long
+    /// as it retains the code section. Modules without DWARF only produce symbols.
+    pub fn process_wasm(&mut self, data: &[u8]) -> Result<(), SymCacheError> {
+        let object = WasmObject::parse(data)
+            .map_err(|e| SymCacheError::new(SymCacheErrorKind::BadDebugFile, e))?;
+        let session = object
+            .debug_session()
+            .map_err(|e| SymCacheError::new(SymCacheErrorKind::BadDebugFile, e))?;
+
+        self.converter.set_arch(object.arch());
+        self.converter.set_debug_id(object.debug_id());
+
+        self.converter.process_dwarf(&session, object.symbols())
+    }
+
+    /// Processes an ELF object whose debug information references a supplementary file.
+    ///
+    /// Debug files processed with `dwz`, as shipped in the debuginfo packages of many Linux
+    /// distributions, store shared function names in a supplementary file. Pass that file in
+    /// `sup`, otherwise these functions are only named from the symbol table, if at all.
+    pub fn process_dwarf_with_sup<'d>(
+        &mut self,
+        object: &ElfObject<'d>,
+        sup: &ElfObject<'d>,
+    ) -> Result<(), SymCacheError> {
+        let session = object
+            .debug_session_with_sup(sup)
+            .map_err(|e| SymCacheError::new(SymCacheErrorKind::BadDebugFile, e))?;
+
+        self.converter.set_arch(object.arch());
+        self.converter.set_debug_id(object.debug_id());
+
+        self.converter.process_dwarf(&session, object.symbols())
+    }
+
+    /// Processes the pc/line table embedded in a Go binary.
+    ///
+    /// This recovers function names and line information from Go binaries that have been
+    /// stripped of DWARF, which is common for released Go services. Inlined frames are not
+    /// recovered. Returns an error of kind [`MissingDebugInfo`](SymCacheErrorKind::MissingDebugInfo)
+    /// if the object does not contain a `gopclntab` section.
+    pub fn process_gopclntab(&mut self, object: &Object<'_>) -> Result<(), SymCacheError> {
+        let section = match object {
+            Object::Elf(elf) => elf.section("gopclntab"),
+            Object::MachO(macho) => macho.section("gopclntab"),
+            _ => None,
+        }
+        .ok_or(SymCacheErrorKind::MissingDebugInfo)?;
+
+        self.converter.set_arch(object.arch());
+        self.converter.set_debug_id(object.debug_id());
+
+        self.converter
+            .process_gopclntab(&section.data, object.load_address())
+    }
+
+    /// Processes an LLVM GSYM file, as created by `llvm-gsymutil`.
+    ///
+    /// All functions of the GSYM file are added along with their line information and inlinees.
+    /// Addresses in GSYM files are absolute, and are made relative to the given `load_address`,
+    /// which is usually the load address of the object the GSYM file was created from.
+    pub fn process_gsym(&mut self, data: &[u8], load_address: u64) -> Result<(), SymCacheError> {
+        self.converter.process_gsym(data, load_address)
+    }
+
+    /// Processes a Portable PDB, the debug information format of .NET assemblies.
+    ///
+    /// Methods are stored by their metadata token and IL offsets, instead of addresses, and can
+    /// be resolved with [`SymCache::lookup_il_offset`]. Since Portable PDBs do not contain method
+    /// names, functions are named after their metadata tokens, such as `0x06000001`.
+    pub fn process_portable_pdb(&mut self, data: &[u8]) -> Result<(), SymCacheError> {
+        self.converter.process_portable_pdb(data)
+    }
+
+    /// Processes a perf map written by a JIT compiler, such as V8, the JVM or .NET.
+    ///
+    /// A perf map is the `/tmp/perf-<pid>.map` text file with one `START SIZE NAME` line per
+    /// compiled function, where start address and size are hexadecimal. It declares function
+    /// names only, without line information.
+    pub fn process_perf_map(&mut self, data: &[u8]) -> Result<(), SymCacheError> {
+        self.converter.process_perf_map(data)
+    }
+
+    /// Processes a jitdump written by a JIT compiler, such as V8, the JVM or .NET.
+    ///
+    /// A jitdump is the binary `jit-<pid>.dump` file read by `perf inject`. In addition to
+    /// function names, it may contain line information for the compiled code. The architecture
+    /// is taken from its header.
+    pub fn process_jitdump(&mut self, data: &[u8]) -> Result<(), SymCacheError> {
+        self.converter.process_jitdump(data)
+    }
+
+    /// Processes an [`Object`] of any format, choosing the best available source of information.
+    ///
+    /// DWARF, PDB and Breakpad debug information as well as the symbol table are processed like
+    /// in [`process_object`](Self::process_object). With the `parallel` feature, the compilation
+    /// units of DWARF are converted in parallel. Objects without debug information additionally
+    /// fall back to the pc/line table of Go binaries, see
+    /// [`process_gopclntab`](Self::process_gopclntab).
+    pub fn process_any_object(&mut self, object: &Object<'_>) -> Result<(), SymCacheError> {
+        if !object.has_debug_info() {
+            match self.process_gopclntab(object) {
+                Err(e) if e.kind() != SymCacheErrorKind::MissingDebugInfo => return Err(e),
+                _ => {}
+            }
+        }
+
+        self.converter.set_arch(object.arch());
+        self.converter.set_debug_id(object.debug_id());
+
+        let session = object
+            .debug_session()
+            .map_err(|e| SymCacheError::new(SymCacheErrorKind::BadDebugFile, e))?;
+        match session {
+            ObjectDebugSession::Dwarf(ref dwarf) => {
+                self.converter.process_dwarf(dwarf, object.symbols())
+            }
+            _ => self
+                .converter
+                .process_debug_session(&session, object.symbols()),
+        }
+    }
+
+    /// Drops data until the serialized SymCache fits into `max_size` bytes.
+    ///
+    /// Data is dropped in the following order, stopping as soon as the SymCache is small enough:
+    ///
+    /// 1. Inline frames: the most deeply nested inline frames are dropped first, one level at a
+    ///    time, until every address resolves to its outermost function only.
+    /// 2. Line information: every address resolves to a function without file and line.
+    ///
+    /// The number of dropped records is reported in [`stats`](Self::stats). Function names are
+    /// never dropped, so this returns `false` if the SymCache still exceeds `max_size`. Call this
+    /// right before [`finish`](Self::finish).
+    pub fn limit_size(&mut self, max_size: u64) -> bool {
+        self.converter.limit_size(max_size)
+    }
+
+    /// Merges ranges that resolve to the same function, file, line and inline chain as the range
+    /// before them.
+    ///
+    /// Redundant ranges are always left out by [`finish`](Self::finish), so this only needs to be
+    /// called to inspect [`Stats::coalesced_ranges`]. Call this right before `finish`.
+    pub fn coalesce_ranges(&mut self) {
+        self.converter.coalesce_ranges()
+    }
+
+    /// Returns statistics about the data processed so far.
+    pub fn stats(&self) -> &Stats {
+        self.converter.stats()
+    }
+
+    /// Sets a deadline after which processing is aborted with [`SymCacheErrorKind::Cancelled`].
+    ///
+    /// Everything processed up to that point can still be written with [`finish`](Self::finish).
+    pub fn set_deadline(&mut self, deadline: Instant) {
+        self.converter.set_deadline(deadline)
+    }
+
+    /// Sets a flag that aborts processing with [`SymCacheErrorKind::Cancelled`] once it is set.
+    pub fn set_cancel_flag(&mut self, flag: Arc<AtomicBool>) {
+        self.converter.set_cancel_flag(flag)
+    }
+
+    /// Sets a sink that receives errors in DWARF debug information.
+    ///
+    /// Errors of [`Severity::Warning`] are reported and skip the rest of their compilation unit
+    /// instead of failing the conversion.
+    pub fn set_error_sink<S>(&mut self, sink: S)
+    where
+        S: ErrorSink + 'static,
+    {
+        self.converter.set_error_sink(sink)
+    }
+
     /// Sets the CPU architecture of this SymCache.
     pub fn set_arch(&mut self, arch: Arch) {
         self.converter.set_arch(arch)
@@ -127,6 +596,108 @@ where
         self.converter.set_debug_id(debug_id)
     }
 
+    /// Sets an image base that is subtracted from all addresses during conversion.
+    ///
+    /// Functions, line records and symbols below the image base are skipped. The image base is
+    /// recorded in the SymCache.
+    pub fn set_image_base(&mut self, image_base: u64) {
+        self.converter.set_image_base(image_base)
+    }
+
+    /// Sets how functions are identified and thus merged, see [`FunctionIdentity`].
+    pub fn set_function_identity(&mut self, identity: FunctionIdentity) {
+        self.converter.set_function_identity(identity)
+    }
+
+    /// Writes an index of function names into the SymCache.
+    ///
+    /// This speeds up [`SymCache::functions_named`] and [`SymCache::functions_with_prefix`] at
+    /// the cost of four bytes per function.
+    pub fn set_name_index(&mut self, enabled: bool) {
+        self.converter.set_name_index(enabled)
+    }
+
+    /// Writes the static call graph between functions into the SymCache.
+    ///
+    /// The edges are derived from inlined functions and can be queried with
+    /// [`SymCache::callees_of`].
+    pub fn set_call_edges(&mut self, enabled: bool) {
+        self.converter.set_call_edges(enabled)
+    }
+
+    /// Keeps the attributions that are dropped when inputs attribute the same address differently.
+    ///
+    /// They can be queried with [`SymCache::candidates`] and [`SymCache::lookup_preferring`],
+    /// for example to prefer debug information over a symbol table.
+    pub fn set_keep_candidates(&mut self, enabled: bool) {
+        self.converter.set_keep_candidates(enabled)
+    }
+
+    /// Limits how deeply inlined functions are nested, defaults to [`DEFAULT_MAX_INLINE_DEPTH`].
+    ///
+    /// Deeper inlinees are skipped, so that their addresses resolve to the innermost inlined
+    /// function that is kept. This guards against corrupted debug information.
+    pub fn set_max_inline_depth(&mut self, depth: usize) {
+        self.converter.set_max_inline_depth(depth)
+    }
+
+    /// Demangles function names with the given options before they are written to the SymCache.
+    #[cfg(feature = "demangle")]
+    pub fn set_demangling(&mut self, options: symbolic_demangle::DemangleOptions) {
+        self.converter.set_demangling(options)
+    }
+
+    /// Keeps the linkage names of functions whose names were demangled, see
+    /// [`set_demangling`](Self::set_demangling).
+    #[cfg(feature = "demangle")]
+    pub fn set_keep_linkage_names(&mut self, keep: bool) {
+        self.converter.set_keep_linkage_names(keep)
+    }
+
+    /// Sets the alignment of the header and each section of this SymCache in bytes.
+    ///
+    /// The alignment is rounded up to the next power of two, and to a minimum of `8`.
+    pub fn set_section_alignment(&mut self, alignment: u32) {
+        self.converter.set_section_alignment(alignment)
+    }
+
+    /// Limits the memory used for strings to roughly `budget` bytes.
+    ///
+    /// Strings exceeding the budget are moved to a temporary file, which keeps the memory usage
+    /// down when converting very large debug files. Only strings are moved, all other data
+    /// remains in memory.
+    #[cfg(feature = "spill")]
+    pub fn set_memory_budget(&mut self, budget: usize) {
+        self.converter.set_memory_budget(budget)
+    }
+
+    /// Merges all data added to `other` into this SymCache.
+    ///
+    /// This combines several inputs for the same module, such as a dSYM and additional symbols,
+    /// into a single SymCache. The target of `other` is dropped without writing to it. Line
+    /// records at the same address are resolved as described in [`Stats::conflicting_lines`].
+    pub fn merge<O>(&mut self, other: SymCacheWriter<O>) {
+        self.converter.merge(other.converter)
+    }
+
+    /// Merges all data of a parsed SymCache into this SymCache.
+    ///
+    /// Strings, files and functions are deduplicated, and line records at the same address are
+    /// resolved as described in [`merge`](Self::merge). The SymCache must use the same image
+    /// base as this writer.
+    pub fn process_symcache(&mut self, cache: &SymCache<'_>) -> Result<(), SymCacheError> {
+        self.converter.process_symcache(cache)
+    }
+
+    /// Re-encodes a SymCache in the legacy format and merges it into this SymCache.
+    ///
+    /// This upgrades existing SymCaches without the original debug file, see
+    /// [`SymCache::is_latest`](crate::SymCache::is_latest). SymCaches in the current format are
+    /// merged as with [`process_symcache`](Self::process_symcache).
+    pub fn process_symcache_v7(&mut self, data: &[u8]) -> Result<(), SymCacheError> {
+        self.converter.process_symcache_v7(data)
+    }
+
     /// Adds a new symbol to this SymCache.
     ///
     /// Symbols **must** be added in ascending order using this method. This will emit a function
@@ -147,7 +718,44 @@ where
         Ok(())
     }
 
+    /// Adds a function that does not come from any debug format, such as JIT compiled code.
+    ///
+    /// The function covers the given absolute address ranges, unless another function starts
+    /// at the same address, and starts at the lowest of them. Its frames carry no file or line.
+    pub fn add_synthetic_function(&mut self, name: &str, ranges: &[AddrRange]) {
+        self.converter.add_synthetic_function(name, ranges)
+    }
+
+    /// Adds a file and line to the addresses in `range`, which must be covered by a function.
+    ///
+    /// All frames within the range are replaced by a single frame of the function covering its
+    /// start. Returns `false` if there is no such function.
+    pub fn add_source_location(&mut self, range: AddrRange, file: &str, line: u32) -> bool {
+        self.converter.add_source_location(range, file, line)
+    }
+
+    /// Adds a code segment of the image, which is mapped separately at runtime.
+    ///
+    /// The addresses are absolute, like those of synthetic functions. Segments are recorded
+    /// automatically by [`process_macho`](Self::process_macho).
+    pub fn add_segment(&mut self, name: &str, range: AddrRange) {
+        self.converter.add_segment(name, range)
+    }
+
+    /// Streams a SymCache of the data processed so far into `writer`, and returns the number of
+    /// bytes written.
+    ///
+    /// Unlike [`finish`](Self::finish), this keeps the data, so processing can continue
+    /// afterwards. The output is the same as the one written by `finish` for the same data.
+    pub fn serialize_to<V: Write>(&self, writer: V) -> Result<u64, SymCacheError> {
+        self.converter
+            .serialize_to(writer)
+            .map_err(|err| SymCacheError::new(SymCacheErrorKind::WriteFailed, err))
+    }
+
     /// Persists all open segments to the writer and fixes up the header.
+    ///
+    /// The SymCache is streamed into the writer, which does not need to be seekable.
     pub fn finish(self) -> Result<W, SymCacheError> {
         let SymCacheWriter {
             converter,
@@ -158,4 +766,9 @@ where
             .map_err(|err| SymCacheError::new(SymCacheErrorKind::WriteFailed, err))?;
         Ok(writer)
     }
+
+    /// Returns the converter holding the data processed so far, dropping the target writer.
+    pub(crate) fn into_converter(self) -> SymCacheConverter {
+        self.converter
+    }
 }
@@ -0,0 +1,257 @@
+//! Comparison of two [`SymCache`]s of the same module.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::{AddrRange, File, Function, SymCache};
+
+impl<'data> SymCache<'data> {
+    /// Compares this SymCache with `other`, a SymCache of the same module.
+    ///
+    /// This is useful to check that two conversions, for example from different writers or
+    /// versions, produce equivalent results. Everything that only exists in `other` is reported
+    /// as added, and everything that only exists in `self` as removed:
+    ///
+    ///  - Functions are identified by their name and entry pc. A function with the same identity
+    ///    but a different compilation directory, language or linkage name is reported as changed.
+    ///  - Files are identified by their [full path](File::full_path).
+    ///  - Addresses are compared by the frames they resolve to via [`lookup`](Self::lookup).
+    ///    Adjacent addresses with the same difference are reported as a single range.
+    pub fn diff(&self, other: &SymCache<'data>) -> DiffReport<'data> {
+        let mut report = DiffReport::default();
+        self.diff_functions(other, &mut report);
+        self.diff_files(other, &mut report);
+        self.diff_ranges(other, &mut report);
+        report
+    }
+
+    fn diff_functions(&self, other: &SymCache<'data>, report: &mut DiffReport<'data>) {
+        type FunctionsByKey<'data> = BTreeMap<(Option<&'data str>, u64), Vec<Function<'data>>>;
+        let by_key = |cache: &SymCache<'data>| {
+            let mut functions = FunctionsByKey::new();
+            for function in cache.functions() {
+                let key = (function.name(), function.entry_pc());
+                functions.entry(key).or_default().push(function);
+            }
+            functions
+        };
+        let attributes = |function: &Function<'data>| {
+            (
+                function.comp_dir(),
+                function.language() as u32,
+                function.linkage_name(),
+            )
+        };
+
+        let mut new_functions = by_key(other);
+        for (key, mut old) in by_key(self) {
+            let mut new = new_functions.remove(&key).unwrap_or_default();
+            old.retain(|function| {
+                match new
+                    .iter()
+                    .position(|f| attributes(f) == attributes(function))
+                {
+                    Some(idx) => {
+                        new.remove(idx);
+                        false
+                    }
+                    None => true,
+                }
+            });
+
+            let num_changed = old.len().min(new.len());
+            let removed = old.split_off(num_changed);
+            let added = new.split_off(num_changed);
+            report.changed_functions.extend(old.into_iter().zip(new));
+            report.removed_functions.extend(removed);
+            report.added_functions.extend(added);
+        }
+        report
+            .added_functions
+            .extend(new_functions.into_values().flatten());
+    }
+
+    fn diff_files(&self, other: &SymCache<'data>, report: &mut DiffReport<'data>) {
+        let by_path = |cache: &SymCache<'data>| {
+            cache
+                .files()
+                .map(|file| (file.full_path(), file))
+                .collect::<BTreeMap<_, _>>()
+        };
+
+        let mut new_files = by_path(other);
+        for (path, file) in by_path(self) {
+            if new_files.remove(&path).is_none() {
+                report.removed_files.push(file);
+            }
+        }
+        report.added_files.extend(new_files.into_values());
+    }
+
+    fn diff_ranges(&self, other: &SymCache<'data>, report: &mut DiffReport<'data>) {
+        let mut starts = BTreeSet::new();
+        for cache in [self, other] {
+            starts.extend((0..cache.ranges.len()).filter_map(|idx| cache.range_start(idx)));
+        }
+        let address_space_end = self.address_space_end().max(other.address_space_end());
+        let ends = starts
+            .iter()
+            .skip(1)
+            .copied()
+            .chain(std::iter::once(address_space_end));
+
+        for (start, end) in starts.iter().copied().zip(ends) {
+            let old = self.diff_frames(start);
+            let new = other.diff_frames(start);
+            if old == new {
+                continue;
+            }
+
+            match report.changed_ranges.last_mut() {
+                Some(last) if last.range.end == start && last.old == old && last.new == new => {
+                    last.range.end = end;
+                }
+                _ => report.changed_ranges.push(RangeDiff {
+                    range: AddrRange { start, end },
+                    old,
+                    new,
+                }),
+            }
+        }
+    }
+
+    /// Resolves the frames of an address for comparison.
+    fn diff_frames(&self, addr: u64) -> Vec<DiffFrame<'data>> {
+        self.lookup(addr)
+            .map(|source_location| DiffFrame {
+                function: source_location.function().and_then(|f| f.name()),
+                file: source_location.file().map(|file| file.full_path()),
+                line: source_location.line(),
+            })
+            .collect()
+    }
+}
+
+/// The differences between two SymCaches of the same module, see [`SymCache::diff`].
+#[derive(Clone, Debug, Default)]
+pub struct DiffReport<'data> {
+    /// Functions that only exist in the other SymCache.
+    pub added_functions: Vec<Function<'data>>,
+    /// Functions that only exist in this SymCache.
+    pub removed_functions: Vec<Function<'data>>,
+    /// Functions whose attributes differ, as pairs of the function in this and the other SymCache.
+    pub changed_functions: Vec<(Function<'data>, Function<'data>)>,
+    /// Files that only exist in the other SymCache.
+    pub added_files: Vec<File<'data>>,
+    /// Files that only exist in this SymCache.
+    pub removed_files: Vec<File<'data>>,
+    /// Address ranges that resolve to different frames, in ascending order.
+    ///
+    /// Ranges that only resolve in the other SymCache have no `old` frames, and ranges that only
+    /// resolve in this SymCache have no `new` frames.
+    pub changed_ranges: Vec<RangeDiff<'data>>,
+}
+
+impl DiffReport<'_> {
+    /// Returns `true` if both SymCaches are equivalent.
+    pub fn is_empty(&self) -> bool {
+        self.added_functions.is_empty()
+            && self.removed_functions.is_empty()
+            && self.changed_functions.is_empty()
+            && self.added_files.is_empty()
+            && self.removed_files.is_empty()
+            && self.changed_ranges.is_empty()
+    }
+}
+
+/// An address range that resolves to different frames in two SymCaches, see
+/// [`DiffReport::changed_ranges`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RangeDiff<'data> {
+    /// The addresses that resolve to the same frames within each SymCache.
+    pub range: AddrRange,
+    /// The frames in this SymCache, innermost first.
+    pub old: Vec<DiffFrame<'data>>,
+    /// The frames in the other SymCache, innermost first.
+    pub new: Vec<DiffFrame<'data>>,
+}
+
+/// A frame that an address resolves to, as compared by [`SymCache::diff`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DiffFrame<'data> {
+    /// The name of the function.
+    pub function: Option<&'data str>,
+    /// The full path of the source file.
+    pub file: Option<String>,
+    /// The line number, `0` if unknown.
+    pub line: u32,
+}
+
+#[cfg(test)]
+mod tests {
+    use symbolic_debuginfo::Function;
+
+    use super::*;
+
+    use crate::new::testutils::{inlined_functions, serialize, symbol};
+    use crate::new::writer::SymCacheConverter;
+
+    #[test]
+    fn test_diff() {
+        let convert = |function: &Function<'_>, symbols: &[(&'static str, u64)]| {
+            let mut converter = SymCacheConverter::new();
+            converter.process_symbolic_function(function);
+            for &(name, address) in symbols {
+                converter.process_symbolic_symbol(&symbol(name, address, 0x10));
+            }
+            serialize(converter)
+        };
+
+        let old = convert(&inlined_functions(), &[("removed", 0x2000)]);
+        let mut function = inlined_functions();
+        function.lines[0].line = 3;
+        function.inlinees[0].lines[0].file.name = b"other.h";
+        let new = convert(&function, &[("added", 0x3000)]);
+
+        let old = SymCache::parse(&old).unwrap();
+        let new = SymCache::parse(&new).unwrap();
+        assert!(old.diff(&old).is_empty());
+
+        let report = old.diff(&new);
+        let names = |functions: &[super::Function<'_>]| {
+            functions
+                .iter()
+                .map(|f| f.name().unwrap().to_owned())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(&report.added_functions), ["added"]);
+        assert_eq!(names(&report.removed_functions), ["removed"]);
+        assert!(report.changed_functions.is_empty());
+        let paths =
+            |files: &[super::File<'_>]| files.iter().map(|f| f.full_path()).collect::<Vec<_>>();
+        assert_eq!(paths(&report.added_files), ["/build/src/other.h"]);
+        assert_eq!(paths(&report.removed_files), ["/build/src/helper.h"]);
+
+        let ranges: Vec<_> = report
+            .changed_ranges
+            .iter()
+            .map(|diff| {
+                (
+                    diff.range.start,
+                    diff.range.end,
+                    diff.old.len(),
+                    diff.new.len(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            ranges,
+            [
+                (0x1000, 0x1008, 1, 1),
+                (0x1008, 0x1010, 2, 2),
+                (0x2000, 0x2010, 1, 0),
+                (0x3000, 0x3010, 0, 1),
+            ]
+        );
+        assert_eq!(report.changed_ranges[0].new[0].line, 3);
+    }
+}
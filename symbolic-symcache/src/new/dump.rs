@@ -0,0 +1,77 @@
+//! A human readable listing of all address ranges in a [`SymCache`].
+
+use std::fmt;
+
+use super::{SourceLocation, SymCache};
+
+impl<'data> SymCache<'data> {
+    /// Writes a listing of all address ranges along with the frames they resolve to.
+    ///
+    /// Similar to the output of `addr2line`, every range is printed on its own line as
+    /// `start..end function @ file:line`, followed by the functions it was inlined into, each on
+    /// its own line and indented by their inline depth. Unknown names and files are printed as
+    /// `?`. This is meant for troubleshooting conversions and for snapshot tests.
+    ///
+    /// # Example
+    ///
+    /// ```text
+    /// 0x1000..0x1008 main @ /build/src/main.c:1
+    /// 0x1008..0x1010 helper @ /build/src/helper.h:5
+    ///   main @ /build/src/main.c:2
+    /// ```
+    pub fn dump<W: fmt::Write>(&self, writer: &mut W) -> fmt::Result {
+        for (range, _) in self.ranges() {
+            write!(writer, "{:#x}..{:#x}", range.start, range.end)?;
+            for (depth, source_location) in self.lookup(range.start).enumerate() {
+                if depth == 0 {
+                    write!(writer, " ")?;
+                } else {
+                    write!(writer, "{:indent$}", "", indent = 2 * depth)?;
+                }
+                write_frame(writer, &source_location)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Writes a single frame as `function @ file:line`, terminated by a newline.
+fn write_frame<W: fmt::Write>(
+    writer: &mut W,
+    source_location: &SourceLocation<'_, '_>,
+) -> fmt::Result {
+    let function = source_location.function();
+    let name = function.as_ref().and_then(|f| f.name()).unwrap_or("?");
+    let path = match source_location.file() {
+        Some(file) => file.full_path(),
+        None => "?".to_owned(),
+    };
+    writeln!(writer, "{} @ {}:{}", name, path, source_location.line())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::new::testutils::{inlined_functions, serialize, symbol};
+    use crate::new::writer::SymCacheConverter;
+
+    #[test]
+    fn test_dump() {
+        let mut converter = SymCacheConverter::new();
+        converter.process_symbolic_function(&inlined_functions());
+        converter.process_symbolic_symbol(&symbol("exit", 0x2000, 0x10));
+        let buf = serialize(converter);
+        let cache = SymCache::parse(&buf).unwrap();
+
+        let mut dump = String::new();
+        cache.dump(&mut dump).unwrap();
+        assert_eq!(
+            dump,
+            "0x1000..0x1008 main @ /build/src/main.c:1\n\
+             0x1008..0x1010 helper @ /build/src/helper.h:5\n  \
+             main @ /build/src/main.c:2\n\
+             0x2000..0x2010 exit @ ?:0\n"
+        );
+    }
+}
@@ -0,0 +1,281 @@
+//! Conversion of SymCaches written by earlier versions on big-endian systems.
+//!
+//! The SymCache format is always little-endian, but earlier versions wrote it in the native
+//! endianness of the system that created it. Apart from the UUID part of the debug identifier and
+//! the string contents, all core data is made up of `u32`s, which can be swapped one by one.
+//! Extension sections declare the size of their elements, so that even sections of unknown kinds
+//! can be converted.
+
+use std::convert::TryFrom;
+use std::mem;
+use std::ops::Range;
+
+use zerocopy::LayoutVerified;
+
+use super::raw::{self, align_to};
+use super::{Error, Result, SymCache};
+
+/// Byte ranges of the `u32` fields in the [`raw::Header`] that need to be swapped.
+const HEADER_U32_FIELDS: &[Range<usize>] = &[
+    // magic, version
+    0..8,
+    // debug_id.appendix
+    24..28,
+    // arch, num_files, num_functions, num_source_locations, num_ranges, string_bytes,
+    // section_alignment, flags, checksum, num_sections
+    40..80,
+];
+
+/// Byte range of [`raw::Header::checksum`].
+const CHECKSUM_FIELD: Range<usize> = 72..76;
+
+/// Byte ranges of the fields in a [`raw::Section`] that need to be swapped individually.
+const SECTION_FIELDS: &[Range<usize>] = &[
+    // kind
+    0..4,
+    // element_size
+    4..8,
+    // offset
+    8..16,
+    // len
+    16..24,
+];
+
+/// Converts a big-endian SymCache written by an earlier version to little-endian.
+///
+/// [`SymCache::parse`] rejects such files with [`Error::WrongEndianness`]. The returned buffer
+/// holds an eagerly converted copy of the SymCache, which can be parsed in turn.
+///
+/// Returns [`Error::WrongFormat`] if the buffer is not a big-endian SymCache.
+pub fn swap_endianness(buf: &[u8]) -> Result<Vec<u8>> {
+    if buf.len() < mem::size_of::<raw::Header>() {
+        return Err(Error::HeaderTooSmall);
+    }
+    if read_u32(buf, 0) != raw::SYMCACHE_MAGIC_FLIPPED {
+        return Err(Error::WrongFormat);
+    }
+
+    let mut swapped = buf.to_vec();
+    swap_in_place(&mut swapped, true)?;
+
+    // make sure the result is well formed
+    SymCache::parse(&swapped)?;
+    Ok(swapped)
+}
+
+/// Swaps the endianness of a SymCache in place.
+///
+/// If `to_little_endian` is `true`, the buffer is expected to be big-endian, otherwise it is
+/// expected to be little-endian.
+pub(crate) fn swap_in_place(buf: &mut [u8], to_little_endian: bool) -> Result<()> {
+    if to_little_endian {
+        swap_header(buf);
+    }
+    let header = read_header(buf).clone();
+    if !to_little_endian {
+        swap_header(buf);
+    }
+
+    let alignment = match header.section_alignment.get() {
+        0 => raw::DEFAULT_SECTION_ALIGNMENT as usize,
+        alignment if alignment.is_power_of_two() && alignment >= 8 => alignment as usize,
+        _ => return Err(Error::BadSectionAlignment),
+    };
+    // none of the offsets may overflow on 32-bit targets
+    let aligned = |size: usize| size.checked_add(align_to(size, alignment));
+    let section_size = |size: usize, count: u32| size.checked_mul(count as usize).and_then(aligned);
+
+    // all sections but the string bytes only consist of `u32`s
+    let sections_start = aligned(mem::size_of::<raw::Header>()).ok_or(Error::BadFormatLength)?;
+    let sections_size = [
+        section_size(mem::size_of::<raw::File>(), header.num_files.get()),
+        section_size(mem::size_of::<raw::Function>(), header.num_functions.get()),
+        section_size(
+            mem::size_of::<raw::SourceLocation>(),
+            header.num_source_locations.get(),
+        ),
+        section_size(mem::size_of::<raw::Range>(), header.num_ranges.get()),
+    ]
+    .iter()
+    .try_fold(0usize, |total, size| total.checked_add((*size)?))
+    .ok_or(Error::BadFormatLength)?;
+    let strings_start = sections_start
+        .checked_add(sections_size)
+        .ok_or(Error::BadFormatLength)?;
+    let strings_end = strings_start
+        .checked_add(header.string_bytes.get() as usize)
+        .ok_or(Error::BadFormatLength)?;
+    if buf.len() < strings_end {
+        return Err(Error::BadFormatLength);
+    }
+
+    // the section table follows the string data, read it in little-endian
+    let table_start = aligned(strings_end).ok_or(Error::BadFormatLength)?;
+    let num_sections = header.num_sections.get() as usize;
+    let table_end = mem::size_of::<raw::Section>()
+        .checked_mul(num_sections)
+        .and_then(|size| table_start.checked_add(size))
+        .ok_or(Error::BadFormatLength)?;
+    if num_sections > 0 && buf.len() < table_end {
+        return Err(Error::BadFormatLength);
+    }
+    let mut payload_end = if num_sections > 0 {
+        table_end
+    } else {
+        strings_end
+    };
+
+    let mut sections = Vec::with_capacity(num_sections);
+    for entry_start in (table_start..table_end).step_by(mem::size_of::<raw::Section>()) {
+        let read_u32 = |offset: usize| match read_u32(buf, entry_start + offset) {
+            value if to_little_endian => value.swap_bytes(),
+            value => value,
+        };
+        let read_u64 = |offset: usize| match read_u64(buf, entry_start + offset) {
+            value if to_little_endian => value.swap_bytes(),
+            value => value,
+        };
+
+        let element_size = read_u32(4) as usize;
+        let start = usize::try_from(read_u64(8)).map_err(|_| Error::BadFormatLength)?;
+        let end = usize::try_from(read_u64(16))
+            .ok()
+            .and_then(|len| start.checked_add(len))
+            .ok_or(Error::BadFormatLength)?;
+        if start < table_end || end > buf.len() {
+            return Err(Error::BadFormatLength);
+        }
+        if ![1, 2, 4, 8].contains(&element_size) || (end - start) % element_size != 0 {
+            return Err(Error::BadFormatLength);
+        }
+        payload_end = payload_end.max(end);
+        sections.push((entry_start, start..end, element_size));
+    }
+
+    // the original checksum is verified before swapping, and recomputed at the end
+    let has_checksum = header.flags.get() & raw::FLAG_CHECKSUM != 0;
+    if has_checksum && crc32fast::hash(&buf[sections_start..payload_end]) != header.checksum.get() {
+        return Err(Error::BadChecksum);
+    }
+
+    for word in buf[sections_start..strings_start].chunks_exact_mut(4) {
+        word.reverse();
+    }
+
+    // strings are prefixed with their length, and stored back to back
+    let mut offset = strings_start;
+    while offset < strings_end {
+        let len_end = offset + mem::size_of::<u32>();
+        if len_end > strings_end {
+            return Err(Error::BadStringReference);
+        }
+        let len = if !to_little_endian {
+            let len = read_u32(buf, offset) as usize;
+            buf[offset..len_end].reverse();
+            len
+        } else {
+            buf[offset..len_end].reverse();
+            read_u32(buf, offset) as usize
+        };
+        offset = len_end.checked_add(len).ok_or(Error::BadStringReference)?;
+    }
+
+    // the elements of every section, even of unknown kinds, are swapped individually
+    for (entry_start, range, element_size) in sections {
+        for field in SECTION_FIELDS {
+            buf[entry_start + field.start..entry_start + field.end].reverse();
+        }
+        for element in buf[range].chunks_exact_mut(element_size) {
+            element.reverse();
+        }
+    }
+
+    if has_checksum {
+        let checksum = crc32fast::hash(&buf[sections_start..payload_end]);
+        let checksum = if to_little_endian {
+            checksum.to_le_bytes()
+        } else {
+            checksum.to_be_bytes()
+        };
+        buf[CHECKSUM_FIELD].copy_from_slice(&checksum);
+    }
+
+    Ok(())
+}
+
+fn swap_header(buf: &mut [u8]) {
+    for field in HEADER_U32_FIELDS {
+        for word in buf[field.clone()].chunks_exact_mut(4) {
+            word.reverse();
+        }
+    }
+}
+
+fn read_header(buf: &[u8]) -> &raw::Header {
+    LayoutVerified::<_, raw::Header>::new_from_prefix(buf)
+        .expect("buffer must hold a complete header")
+        .0
+        .into_ref()
+}
+
+fn read_u32(buf: &[u8], offset: usize) -> u32 {
+    let mut bytes = [0; 4];
+    bytes.copy_from_slice(&buf[offset..offset + 4]);
+    u32::from_le_bytes(bytes)
+}
+
+fn read_u64(buf: &[u8], offset: usize) -> u64 {
+    let mut bytes = [0; 8];
+    bytes.copy_from_slice(&buf[offset..offset + 8]);
+    u64::from_le_bytes(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use symbolic_debuginfo::Symbol;
+
+    use super::super::writer::SymCacheConverter;
+    use super::*;
+
+    #[test]
+    fn test_swap_endianness() {
+        let debug_id = "67e9247c-814e-392b-a027-dbde6748fcbf-1".parse().unwrap();
+        let mut converter = SymCacheConverter::new();
+        converter.set_debug_id(debug_id);
+        converter.set_section_alignment(64);
+        for (name, address) in [("a", 0x1000), ("bb", 0x1010)] {
+            converter.process_symbolic_symbol(&Symbol {
+                name: Some(name.into()),
+                address,
+                size: 0x10,
+            });
+        }
+        let mut native = Vec::new();
+        converter.serialize(&mut native).unwrap();
+
+        let mut foreign = native.clone();
+        swap_in_place(&mut foreign, false).unwrap();
+        assert!(matches!(
+            SymCache::parse(&foreign),
+            Err(Error::WrongEndianness)
+        ));
+        assert!(matches!(swap_endianness(&native), Err(Error::WrongFormat)));
+
+        let swapped = swap_endianness(&foreign).unwrap();
+        assert_eq!(swapped, native);
+        SymCache::parse_verified(&swapped).unwrap();
+
+        let mut corrupted = foreign.clone();
+        *corrupted.last_mut().unwrap() ^= 1;
+        assert!(matches!(
+            swap_endianness(&corrupted),
+            Err(Error::BadChecksum)
+        ));
+
+        let cache = SymCache::parse(&swapped).unwrap();
+        assert_eq!(cache.section_alignment(), 64);
+        assert_eq!(cache.debug_id(), debug_id);
+        let name = cache.lookup(0x1014).next().unwrap().function().unwrap();
+        assert_eq!(name.name(), Some("bb"));
+    }
+}
@@ -9,12 +9,16 @@ use thiserror::Error;
 #[non_exhaustive]
 pub enum Error {
     /// The buffer is not correctly aligned.
+    ///
+    /// This is no longer returned, since SymCaches can be parsed from buffers of any alignment.
     #[error("source buffer is not correctly aligned")]
     BufferNotAligned,
     /// The header's size doesn't match our expected size.
     #[error("header is too small")]
     HeaderTooSmall,
-    /// The file was generated by a system with different endianness.
+    /// The file was written in big-endian by an earlier version.
+    ///
+    /// Such files can be converted with [`swap_endianness`](crate::new::swap_endianness).
     #[error("endianness mismatch")]
     WrongEndianness,
     /// The file magic does not match.
@@ -26,4 +30,23 @@ pub enum Error {
     /// The self-advertised size of the buffer is not correct.
     #[error("incorrect buffer length")]
     BadFormatLength,
+    /// The section alignment in the header is not a power of two of at least 8.
+    #[error("invalid section alignment")]
+    BadSectionAlignment,
+    /// A string reference is out of bounds or does not point to valid UTF-8.
+    #[error("invalid string reference")]
+    BadStringReference,
+    /// The checksum in the header does not match the contents, see
+    /// [`SymCache::verify`](crate::new::SymCache::verify).
+    #[error("checksum mismatch")]
+    BadChecksum,
+    /// A source location refers to a file, function or source location that does not exist.
+    #[error("invalid index reference")]
+    BadIndexReference,
+    /// A source location is transitively inlined into itself.
+    #[error("cyclic inline chain")]
+    CyclicInlineChain,
+    /// The address ranges are not sorted in strictly ascending order.
+    #[error("unsorted address ranges")]
+    UnsortedRanges,
 }
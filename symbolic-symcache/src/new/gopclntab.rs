@@ -0,0 +1,406 @@
+//! Reading of the Go runtime's `.gopclntab` section.
+//!
+//! Go binaries carry a "pc/line table" that the runtime uses to produce tracebacks. Unlike DWARF,
+//! this table cannot be stripped, so it is the only source of function names and line
+//! information for Go binaries built with `-ldflags=-s -w`.
+//!
+//! The table starts with a header, followed by a function table that maps entry addresses to
+//! function records. Each function record references a name and two "pc-value" tables, which map
+//! address ranges to file indexes and line numbers, respectively.
+//!
+//! This supports the layouts introduced in Go 1.16, 1.18 and 1.20. Inlined frames, which are
+//! stored in the runtime's `FUNCDATA_InlTree`, are not recovered.
+
+use std::convert::{TryFrom, TryInto};
+
+use symbolic_common::{Language, Name, NameMangling};
+use symbolic_debuginfo::{FileInfo, Function, LineInfo};
+use thiserror::Error;
+
+const MAGIC_GO116: u32 = 0xffff_fffa;
+const MAGIC_GO118: u32 = 0xffff_fff0;
+const MAGIC_GO120: u32 = 0xffff_fff1;
+
+/// An error encountered while reading a `.gopclntab` section.
+#[derive(Debug, Error)]
+#[error("invalid gopclntab: {0}")]
+pub(crate) struct GoPclnError(&'static str);
+
+type Result<T, E = GoPclnError> = std::result::Result<T, E>;
+
+/// The layout version of a pc/line table.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Version {
+    Go116,
+    Go118,
+}
+
+/// A parsed `.gopclntab` section.
+pub(crate) struct GoPclnTab<'data> {
+    version: Version,
+    big_endian: bool,
+    quantum: u64,
+    ptr_size: usize,
+    num_functions: usize,
+    text_start: u64,
+    funcname_tab: &'data [u8],
+    cu_tab: &'data [u8],
+    file_tab: &'data [u8],
+    pc_tab: &'data [u8],
+    func_tab: &'data [u8],
+}
+
+impl<'data> GoPclnTab<'data> {
+    /// Parses the header of the given `.gopclntab` section.
+    pub fn parse(data: &'data [u8]) -> Result<Self> {
+        let magic = data.get(..4).ok_or(GoPclnError("header too small"))?;
+        let magic: [u8; 4] = magic.try_into().unwrap();
+        let (version, big_endian) = match (u32::from_le_bytes(magic), u32::from_be_bytes(magic)) {
+            (MAGIC_GO116, _) => (Version::Go116, false),
+            (MAGIC_GO118 | MAGIC_GO120, _) => (Version::Go118, false),
+            (_, MAGIC_GO116) => (Version::Go116, true),
+            (_, MAGIC_GO118 | MAGIC_GO120) => (Version::Go118, true),
+            _ => return Err(GoPclnError("unsupported version")),
+        };
+
+        let quantum = u64::from(*data.get(6).ok_or(GoPclnError("header too small"))?);
+        let ptr_size = usize::from(*data.get(7).ok_or(GoPclnError("header too small"))?);
+        if quantum == 0 || (ptr_size != 4 && ptr_size != 8) {
+            return Err(GoPclnError("invalid header"));
+        }
+
+        // Go 1.18 inserted the start of the text section after the number of files.
+        let mut offset = 8;
+        let mut next = || {
+            let field = read_uintptr(data, offset, ptr_size, big_endian);
+            offset += ptr_size;
+            field
+        };
+        let num_functions = next()?;
+        let _num_files = next()?;
+        let text_start = match version {
+            Version::Go116 => 0,
+            Version::Go118 => next()?,
+        };
+        let funcname_offset = next()?;
+        let cu_offset = next()?;
+        let file_offset = next()?;
+        let pc_offset = next()?;
+        let func_offset = next()?;
+
+        let section = |offset: u64| {
+            usize::try_from(offset)
+                .ok()
+                .and_then(|offset| data.get(offset..))
+                .ok_or(GoPclnError("section out of bounds"))
+        };
+
+        Ok(Self {
+            version,
+            big_endian,
+            quantum,
+            ptr_size,
+            num_functions: usize::try_from(num_functions)
+                .map_err(|_| GoPclnError("too many functions"))?,
+            text_start,
+            funcname_tab: section(funcname_offset)?,
+            cu_tab: section(cu_offset)?,
+            file_tab: section(file_offset)?,
+            pc_tab: section(pc_offset)?,
+            func_tab: section(func_offset)?,
+        })
+    }
+
+    /// Returns all functions contained in this table, including their line information.
+    ///
+    /// Addresses are made relative to the given `load_address`.
+    pub fn functions(&self, load_address: u64) -> Result<Vec<Function<'data>>> {
+        (0..self.num_functions)
+            .map(|index| self.function(index, load_address))
+            .collect()
+    }
+
+    fn function(&self, index: usize, load_address: u64) -> Result<Function<'data>> {
+        // The function table consists of pairs of entry address and function offset, followed by
+        // the end address of the last function. Go 1.18 switched from pointer-sized addresses to
+        // `u32` offsets from the start of the text section.
+        let (entry, func_offset, end) = match self.version {
+            Version::Go116 => {
+                let pair = 2 * self.ptr_size;
+                (
+                    self.read_uintptr(self.func_tab, index * pair)?,
+                    self.read_uintptr(self.func_tab, index * pair + self.ptr_size)?,
+                    self.read_uintptr(self.func_tab, (index + 1) * pair)?,
+                )
+            }
+            Version::Go118 => (
+                self.text_start
+                    .wrapping_add(u64::from(self.read_u32(self.func_tab, index * 8)?)),
+                u64::from(self.read_u32(self.func_tab, index * 8 + 4)?),
+                self.text_start
+                    .wrapping_add(u64::from(self.read_u32(self.func_tab, (index + 1) * 8)?)),
+            ),
+        };
+
+        // Skip the entry address at the start of the function record, which we already know.
+        let record = usize::try_from(func_offset)
+            .ok()
+            .and_then(|offset| self.func_tab.get(offset..))
+            .ok_or(GoPclnError("function out of bounds"))?;
+        let fields = match self.version {
+            Version::Go116 => self.ptr_size,
+            Version::Go118 => 4,
+        };
+        let name_offset = self.read_u32(record, fields)?;
+        let pcfile = self.read_u32(record, fields + 16)?;
+        let pcln = self.read_u32(record, fields + 20)?;
+        let cu_offset = self.read_u32(record, fields + 28)?;
+
+        let name = read_cstr(self.funcname_tab, name_offset as usize)?;
+
+        let files = self.pc_values(pcfile, entry)?;
+        let mut lines = Vec::new();
+        for (start, end, line) in self.pc_values(pcln, entry)? {
+            // Files change at most as often as lines, so look up the file at the line's start.
+            let file_index = files
+                .iter()
+                .find(|(file_start, file_end, _)| (*file_start..*file_end).contains(&start))
+                .map(|(_, _, file)| *file);
+            let name = match file_index {
+                Some(file) => self.file_name(cu_offset, file)?,
+                None => b"",
+            };
+
+            lines.push(LineInfo {
+                address: start.saturating_sub(load_address),
+                size: Some(end - start),
+                file: FileInfo { name, dir: b"" },
+                line: line.max(0) as u64,
+                column: 0,
+                prologue_end: false,
+                is_stmt: true,
+                basic_block: false,
+            });
+        }
+
+        Ok(Function {
+            address: entry.saturating_sub(load_address),
+            size: end.saturating_sub(entry),
+            name: Name::new(
+                String::from_utf8_lossy(name),
+                NameMangling::Unmangled,
+                Language::Go,
+            ),
+            compilation_dir: b"",
+            lines,
+            inlinees: Vec::new(),
+            inline: false,
+            decl_file: None,
+            decl_line: 0,
+        })
+    }
+
+    /// Resolves the name of a file index relative to the given compilation unit.
+    fn file_name(&self, cu_offset: u32, file: i32) -> Result<&'data [u8]> {
+        let index = u32::try_from(file)
+            .ok()
+            .and_then(|file| cu_offset.checked_add(file))
+            .ok_or(GoPclnError("invalid file index"))?;
+        let file_offset = self.read_u32(self.cu_tab, index as usize * 4)?;
+        read_cstr(self.file_tab, file_offset as usize)
+    }
+
+    /// Decodes the pc-value table at the given offset into `(start, end, value)` ranges.
+    ///
+    /// Each entry of the table is a zig-zag encoded value delta, followed by an address delta in
+    /// multiples of the instruction size quantum. The table ends with a zero value delta.
+    fn pc_values(&self, offset: u32, entry: u64) -> Result<Vec<(u64, u64, i32)>> {
+        let mut values = Vec::new();
+        if offset == 0 {
+            return Ok(values);
+        }
+
+        let mut data = self
+            .pc_tab
+            .get(offset as usize..)
+            .ok_or(GoPclnError("pc-value table out of bounds"))?;
+        let mut pc = entry;
+        let mut value = -1i32;
+        loop {
+            let value_delta = read_uvarint(&mut data)?;
+            if value_delta == 0 && !values.is_empty() {
+                break;
+            }
+
+            let value_delta = if value_delta & 1 != 0 {
+                !(value_delta >> 1)
+            } else {
+                value_delta >> 1
+            };
+            let pc_delta = u64::from(read_uvarint(&mut data)?) * self.quantum;
+
+            let start = pc;
+            pc = pc
+                .checked_add(pc_delta)
+                .ok_or(GoPclnError("address overflow"))?;
+            value = value.wrapping_add(value_delta as i32);
+            values.push((start, pc, value));
+        }
+
+        Ok(values)
+    }
+
+    fn read_u32(&self, data: &[u8], offset: usize) -> Result<u32> {
+        read_u32(data, offset, self.big_endian)
+    }
+
+    fn read_uintptr(&self, data: &[u8], offset: usize) -> Result<u64> {
+        read_uintptr(data, offset, self.ptr_size, self.big_endian)
+    }
+}
+
+fn read_u32(data: &[u8], offset: usize, big_endian: bool) -> Result<u32> {
+    let bytes = data
+        .get(offset..offset + 4)
+        .ok_or(GoPclnError("unexpected end of data"))?;
+    let bytes = bytes.try_into().unwrap();
+    Ok(if big_endian {
+        u32::from_be_bytes(bytes)
+    } else {
+        u32::from_le_bytes(bytes)
+    })
+}
+
+fn read_uintptr(data: &[u8], offset: usize, ptr_size: usize, big_endian: bool) -> Result<u64> {
+    if ptr_size == 4 {
+        return read_u32(data, offset, big_endian).map(u64::from);
+    }
+
+    let bytes = data
+        .get(offset..offset + 8)
+        .ok_or(GoPclnError("unexpected end of data"))?;
+    let bytes = bytes.try_into().unwrap();
+    Ok(if big_endian {
+        u64::from_be_bytes(bytes)
+    } else {
+        u64::from_le_bytes(bytes)
+    })
+}
+
+/// Reads a NUL-terminated string at the given offset.
+fn read_cstr(data: &[u8], offset: usize) -> Result<&[u8]> {
+    let data = data
+        .get(offset..)
+        .ok_or(GoPclnError("string out of bounds"))?;
+    let len = data
+        .iter()
+        .position(|&b| b == 0)
+        .ok_or(GoPclnError("unterminated string"))?;
+    Ok(&data[..len])
+}
+
+/// Reads a LEB128 encoded `u32` and advances the data past it.
+fn read_uvarint(data: &mut &[u8]) -> Result<u32> {
+    let mut result = 0u32;
+    let mut shift = 0;
+    loop {
+        let (&byte, rest) = data
+            .split_first()
+            .ok_or(GoPclnError("unexpected end of data"))?;
+        *data = rest;
+
+        if shift >= 32 {
+            return Err(GoPclnError("varint overflow"));
+        }
+        result |= u32::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(result);
+        }
+        shift += 7;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a Go 1.18 pc/line table with a single function.
+    fn pclntab() -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend(MAGIC_GO118.to_le_bytes());
+        data.extend([0, 0, 1, 8]);
+
+        let header_size = 8 + 8 * 8u64;
+        let funcname_offset = header_size;
+        let funcname_tab = b"main.main\0";
+        let cu_offset = funcname_offset + funcname_tab.len() as u64;
+        let cu_tab = 0u32.to_le_bytes();
+        let file_offset = cu_offset + cu_tab.len() as u64;
+        let file_tab = b"/src/main.go\0";
+        let pc_offset = file_offset + file_tab.len() as u64;
+        // A leading padding byte, since offset `0` denotes a missing table. The file table maps
+        // the entire function to file `0`, the line table maps two halves to lines 5 and 6.
+        let pc_tab = [0, 2, 0x10, 0, 12, 8, 2, 8, 0];
+        let func_offset = pc_offset + pc_tab.len() as u64;
+
+        for field in [
+            1,
+            1,
+            0x1000,
+            funcname_offset,
+            cu_offset,
+            file_offset,
+            pc_offset,
+            func_offset,
+        ] {
+            data.extend(u64::to_le_bytes(field));
+        }
+        data.extend(funcname_tab);
+        data.extend(cu_tab);
+        data.extend(file_tab);
+        data.extend(pc_tab);
+
+        // function table: one function at 0x1000 with its record right behind the table
+        for field in [0u32, 16, 0x10, 0] {
+            data.extend(field.to_le_bytes());
+        }
+        // entryoff, nameoff, args, deferreturn, pcsp, pcfile, pcln, npcdata, cu_offset
+        for field in [0u32, 0, 0, 0, 0, 1, 4, 0, 0] {
+            data.extend(field.to_le_bytes());
+        }
+        data
+    }
+
+    #[test]
+    fn test_functions() {
+        let data = pclntab();
+        let table = GoPclnTab::parse(&data).unwrap();
+        let functions = table.functions(0x1000).unwrap();
+
+        assert_eq!(functions.len(), 1);
+        let function = &functions[0];
+        assert_eq!(function.name.as_str(), "main.main");
+        assert_eq!(function.address, 0);
+        assert_eq!(function.size, 0x10);
+
+        let lines: Vec<_> = function
+            .lines
+            .iter()
+            .map(|line| (line.address, line.size, line.file.name, line.line))
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                (0, Some(8), &b"/src/main.go"[..], 5),
+                (8, Some(8), &b"/src/main.go"[..], 6),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unsupported() {
+        let mut data = pclntab();
+        data[0..4].copy_from_slice(&0xffff_fffbu32.to_le_bytes());
+        assert!(GoPclnTab::parse(&data).is_err());
+    }
+}
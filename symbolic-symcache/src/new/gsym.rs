@@ -0,0 +1,613 @@
+//! Reading of LLVM's GSYM format.
+//!
+//! A GSYM file starts with a header, followed by a sorted table of function addresses relative
+//! to a base address, a parallel table of offsets to the function infos, a file table and a string
+//! table. Each file table entry is a pair of string table offsets for the directory and the base
+//! name, where file index `0` denotes an unknown file.
+//!
+//! A function info consists of the function's size and name, followed by a list of typed info
+//! records. The line table info is a compact line program that maps addresses to the innermost
+//! file and line. The inline info is a tree of inlined functions, each with its address ranges and
+//! the location it was called from.
+
+use std::convert::{TryFrom, TryInto};
+
+use symbolic_common::{Language, Name, NameMangling};
+use symbolic_debuginfo::{FileInfo, Function, LineInfo};
+use thiserror::Error;
+
+const GSYM_MAGIC: u32 = 0x4753_594d;
+const GSYM_VERSION: u16 = 1;
+const HEADER_SIZE: usize = 48;
+
+const INFO_END_OF_LIST: u32 = 0;
+const INFO_LINE_TABLE: u32 = 1;
+const INFO_INLINE: u32 = 2;
+
+const OP_END_SEQUENCE: u8 = 0;
+const OP_SET_FILE: u8 = 1;
+const OP_ADVANCE_PC: u8 = 2;
+const OP_ADVANCE_LINE: u8 = 3;
+const OP_FIRST_SPECIAL: u8 = 4;
+
+/// An error encountered while reading a GSYM file.
+#[derive(Debug, Error)]
+#[error("invalid gsym: {0}")]
+pub(crate) struct GsymError(&'static str);
+
+type Result<T, E = GsymError> = std::result::Result<T, E>;
+
+/// A parsed GSYM file.
+pub(crate) struct Gsym<'data> {
+    data: &'data [u8],
+    big_endian: bool,
+    addr_off_size: usize,
+    base_address: u64,
+    num_addresses: usize,
+    addr_offsets: &'data [u8],
+    info_offsets: &'data [u8],
+    files: &'data [u8],
+    strtab: &'data [u8],
+}
+
+/// A row of a line table, with an absolute address and an index into the file table.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Row {
+    address: u64,
+    file: u32,
+    line: u64,
+}
+
+/// A node of the inline tree of a function.
+///
+/// The root of the tree describes the function itself, and only its ranges and children are
+/// meaningful.
+#[derive(Debug, Default)]
+struct InlineInfo {
+    ranges: Vec<(u64, u64)>,
+    name: u32,
+    call_file: u32,
+    call_line: u64,
+    children: Vec<InlineInfo>,
+}
+
+impl InlineInfo {
+    fn contains(&self, address: u64) -> bool {
+        self.ranges
+            .iter()
+            .any(|&(start, end)| (start..end).contains(&address))
+    }
+}
+
+impl<'data> Gsym<'data> {
+    /// Parses the header and tables of the given GSYM file.
+    pub fn parse(data: &'data [u8]) -> Result<Self> {
+        let magic = data.get(..4).ok_or(GsymError("header too small"))?;
+        let magic: [u8; 4] = magic.try_into().unwrap();
+        let big_endian = if u32::from_le_bytes(magic) == GSYM_MAGIC {
+            false
+        } else if u32::from_be_bytes(magic) == GSYM_MAGIC {
+            true
+        } else {
+            return Err(GsymError("wrong magic"));
+        };
+
+        let mut header = Cursor::new(data.get(4..HEADER_SIZE), big_endian)?;
+        let version = header.u16()?;
+        if version != GSYM_VERSION {
+            return Err(GsymError("unsupported version"));
+        }
+        let addr_off_size = usize::from(header.u8()?);
+        if !matches!(addr_off_size, 1 | 2 | 4 | 8) {
+            return Err(GsymError("invalid address offset size"));
+        }
+        let _uuid_size = header.u8()?;
+        let base_address = header.u64()?;
+        let num_addresses = header.u32()? as usize;
+        let strtab_offset = header.u32()? as usize;
+        let strtab_size = header.u32()? as usize;
+
+        let table = |offset: usize, len: usize| {
+            len.checked_add(offset)
+                .and_then(|end| data.get(offset..end))
+                .ok_or(GsymError("table out of bounds"))
+        };
+
+        // the header is aligned to all address offset sizes, the other tables are aligned to 4
+        let addr_offsets = table(HEADER_SIZE, num_addresses * addr_off_size)?;
+        let info_offset = align_to_four(HEADER_SIZE + addr_offsets.len());
+        let info_offsets = table(info_offset, num_addresses * 4)?;
+        let files_offset = info_offset + info_offsets.len();
+        let num_files = read_u32(data, files_offset, big_endian)? as usize;
+        let files = table(files_offset + 4, num_files * 8)?;
+        let strtab = table(strtab_offset, strtab_size)?;
+
+        Ok(Self {
+            data,
+            big_endian,
+            addr_off_size,
+            base_address,
+            num_addresses,
+            addr_offsets,
+            info_offsets,
+            files,
+            strtab,
+        })
+    }
+
+    /// Returns all functions contained in this file, including their line information and
+    /// inlinees.
+    ///
+    /// Addresses are made relative to the given `load_address`.
+    pub fn functions(&self, load_address: u64) -> Result<Vec<Function<'data>>> {
+        (0..self.num_addresses)
+            .map(|index| self.function(index, load_address))
+            .collect()
+    }
+
+    fn function(&self, index: usize, load_address: u64) -> Result<Function<'data>> {
+        let addr_offset = match self.addr_off_size {
+            1 => u64::from(self.addr_offsets[index]),
+            2 => {
+                let bytes = self.addr_offsets[index * 2..index * 2 + 2]
+                    .try_into()
+                    .unwrap();
+                u64::from(if self.big_endian {
+                    u16::from_be_bytes(bytes)
+                } else {
+                    u16::from_le_bytes(bytes)
+                })
+            }
+            4 => u64::from(read_u32(self.addr_offsets, index * 4, self.big_endian)?),
+            _ => read_u64(self.addr_offsets, index * 8, self.big_endian)?,
+        };
+        let address = self.base_address.wrapping_add(addr_offset);
+
+        let info_offset = read_u32(self.info_offsets, index * 4, self.big_endian)? as usize;
+        let mut info = Cursor::new(self.data.get(info_offset..), self.big_endian)?;
+        let size = u64::from(info.u32()?);
+        let name = info.u32()?;
+
+        let mut rows = Vec::new();
+        let mut inline_info = None;
+        loop {
+            let info_type = info.u32()?;
+            let info_len = info.u32()? as usize;
+            let mut data = Cursor::new(Some(info.bytes(info_len)?), self.big_endian)?;
+            match info_type {
+                INFO_END_OF_LIST => break,
+                INFO_LINE_TABLE => rows = line_table(&mut data, address)?,
+                INFO_INLINE => inline_info = Some(inline_info_tree(&mut data, address)?),
+                // unknown info types are skipped
+                _ => {}
+            }
+        }
+
+        // without inline info, the function covers its entire range without inlinees
+        let mut root = inline_info.unwrap_or_default();
+        root.ranges = vec![(address, address.saturating_add(size))];
+
+        let mut function = self.inline_function(&root, &rows, load_address)?;
+        function.name = self.name(name)?;
+        function.inline = false;
+        Ok(function)
+    }
+
+    /// Creates a function for a node of the inline tree, with line records for all of its ranges.
+    ///
+    /// The lines of the node are taken from the line table, except where one of its children is
+    /// inlined. There, the node's lines are the child's call location instead.
+    fn inline_function(
+        &self,
+        node: &InlineInfo,
+        rows: &[Row],
+        load_address: u64,
+    ) -> Result<Function<'data>> {
+        let mut lines = Vec::new();
+        for &(start, end) in &node.ranges {
+            let mut boundaries = vec![start, end];
+            boundaries.extend(rows.iter().map(|row| row.address));
+            for child in &node.children {
+                for &(child_start, child_end) in &child.ranges {
+                    boundaries.extend([child_start, child_end]);
+                }
+            }
+            boundaries.retain(|&address| address >= start && address <= end);
+            boundaries.sort_unstable();
+            boundaries.dedup();
+
+            for segment in boundaries.windows(2) {
+                let (file, line) = match node.children.iter().find(|c| c.contains(segment[0])) {
+                    Some(child) => (child.call_file, child.call_line),
+                    None => {
+                        // the row that is in effect at the start of this segment, if any
+                        let index = rows.partition_point(|row| row.address <= segment[0]);
+                        match index.checked_sub(1).map(|index| rows[index]) {
+                            Some(row) => (row.file, row.line),
+                            None => (0, 0),
+                        }
+                    }
+                };
+
+                lines.push(LineInfo {
+                    address: segment[0].saturating_sub(load_address),
+                    size: Some(segment[1] - segment[0]),
+                    file: self.file(file)?,
+                    line,
+                    column: 0,
+                    prologue_end: false,
+                    is_stmt: true,
+                    basic_block: false,
+                });
+            }
+        }
+
+        let mut inlinees = Vec::with_capacity(node.children.len());
+        for child in &node.children {
+            inlinees.push(self.inline_function(child, rows, load_address)?);
+        }
+
+        let start = node.ranges.iter().map(|&(start, _)| start).min();
+        let end = node.ranges.iter().map(|&(_, end)| end).max();
+        let address = start.unwrap_or_default();
+        Ok(Function {
+            address: address.saturating_sub(load_address),
+            size: end.unwrap_or_default().saturating_sub(address),
+            name: self.name(node.name)?,
+            compilation_dir: b"",
+            lines,
+            inlinees,
+            inline: true,
+            decl_file: None,
+            decl_line: 0,
+        })
+    }
+
+    /// Resolves a function name from the string table.
+    fn name(&self, offset: u32) -> Result<Name<'data>> {
+        let name = read_cstr(self.strtab, offset as usize)?;
+        Ok(Name::new(
+            String::from_utf8_lossy(name),
+            NameMangling::Unknown,
+            Language::Unknown,
+        ))
+    }
+
+    /// Resolves an index into the file table, where `0` denotes an unknown file.
+    fn file(&self, index: u32) -> Result<FileInfo<'data>> {
+        if index == 0 {
+            return Ok(FileInfo::default());
+        }
+
+        let offset = index as usize * 8;
+        let dir = read_u32(self.files, offset, self.big_endian)?;
+        let name = read_u32(self.files, offset + 4, self.big_endian)?;
+        Ok(FileInfo {
+            name: read_cstr(self.strtab, name as usize)?,
+            dir: read_cstr(self.strtab, dir as usize)?,
+        })
+    }
+}
+
+/// Decodes the line table of a function starting at `address`.
+///
+/// The table starts with the minimum and maximum line delta of the special opcodes and the first
+/// line. Special opcodes advance both address and line, and emit a row.
+fn line_table(data: &mut Cursor<'_>, address: u64) -> Result<Vec<Row>> {
+    let min_delta = data.sleb128()?;
+    let max_delta = data.sleb128()?;
+    let line_range = max_delta
+        .checked_sub(min_delta)
+        .and_then(|range| range.checked_add(1))
+        .filter(|&range| range > 0)
+        .ok_or(GsymError("invalid line range"))?;
+
+    let mut row = Row {
+        address,
+        file: 1,
+        line: data.uleb128()?,
+    };
+    let mut rows = Vec::new();
+    loop {
+        match data.u8()? {
+            OP_END_SEQUENCE => break,
+            OP_SET_FILE => {
+                row.file =
+                    u32::try_from(data.uleb128()?).map_err(|_| GsymError("invalid file index"))?;
+            }
+            OP_ADVANCE_PC => row.address = row.address.wrapping_add(data.uleb128()?),
+            OP_ADVANCE_LINE => row.line = row.line.wrapping_add(data.sleb128()? as u64),
+            op => {
+                let op = i64::from(op - OP_FIRST_SPECIAL);
+                row.line = row
+                    .line
+                    .wrapping_add((min_delta.wrapping_add(op % line_range)) as u64);
+                row.address = row.address.wrapping_add((op / line_range) as u64);
+                rows.push(row);
+            }
+        }
+    }
+
+    Ok(rows)
+}
+
+/// Decodes a node of an inline tree and its children, with ranges relative to `base_address`.
+///
+/// A node without ranges terminates the list of children of its parent.
+fn inline_info_tree(data: &mut Cursor<'_>, base_address: u64) -> Result<InlineInfo> {
+    let num_ranges = data.uleb128()?;
+    let mut info = InlineInfo::default();
+    for _ in 0..num_ranges {
+        let start = base_address.wrapping_add(data.uleb128()?);
+        let size = data.uleb128()?;
+        info.ranges.push((start, start.saturating_add(size)));
+    }
+    if info.ranges.is_empty() {
+        return Ok(info);
+    }
+
+    let has_children = data.u8()? != 0;
+    info.name = data.u32()?;
+    info.call_file = u32::try_from(data.uleb128()?).map_err(|_| GsymError("invalid file index"))?;
+    info.call_line = data.uleb128()?;
+
+    if has_children {
+        let child_base = info.ranges[0].0;
+        loop {
+            let child = inline_info_tree(data, child_base)?;
+            if child.ranges.is_empty() {
+                break;
+            }
+            info.children.push(child);
+        }
+    }
+
+    Ok(info)
+}
+
+fn align_to_four(offset: usize) -> usize {
+    (offset + 3) & !3
+}
+
+/// Reads variable-length data of the given endianness.
+struct Cursor<'data> {
+    data: &'data [u8],
+    big_endian: bool,
+}
+
+impl<'data> Cursor<'data> {
+    fn new(data: Option<&'data [u8]>, big_endian: bool) -> Result<Self> {
+        let data = data.ok_or(GsymError("unexpected end of data"))?;
+        Ok(Self { data, big_endian })
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'data [u8]> {
+        if len > self.data.len() {
+            return Err(GsymError("unexpected end of data"));
+        }
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        let bytes = self.bytes(2)?.try_into().unwrap();
+        Ok(if self.big_endian {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        })
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        let value = read_u32(self.data, 0, self.big_endian)?;
+        self.data = &self.data[4..];
+        Ok(value)
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        let value = read_u64(self.data, 0, self.big_endian)?;
+        self.data = &self.data[8..];
+        Ok(value)
+    }
+
+    fn uleb128(&mut self) -> Result<u64> {
+        let mut result = 0u64;
+        let mut shift = 0;
+        loop {
+            let byte = self.u8()?;
+            if shift >= 64 {
+                return Err(GsymError("varint overflow"));
+            }
+            result |= u64::from(byte & 0x7f) << shift;
+            shift += 7;
+            if byte & 0x80 == 0 {
+                return Ok(result);
+            }
+        }
+    }
+
+    fn sleb128(&mut self) -> Result<i64> {
+        let mut result = 0i64;
+        let mut shift = 0;
+        loop {
+            let byte = self.u8()?;
+            if shift >= 64 {
+                return Err(GsymError("varint overflow"));
+            }
+            result |= i64::from(byte & 0x7f) << shift;
+            shift += 7;
+            if byte & 0x80 == 0 {
+                // sign-extend negative values
+                if shift < 64 && byte & 0x40 != 0 {
+                    result |= -1 << shift;
+                }
+                return Ok(result);
+            }
+        }
+    }
+}
+
+fn read_u32(data: &[u8], offset: usize, big_endian: bool) -> Result<u32> {
+    let bytes = data
+        .get(offset..offset + 4)
+        .ok_or(GsymError("unexpected end of data"))?;
+    let bytes = bytes.try_into().unwrap();
+    Ok(if big_endian {
+        u32::from_be_bytes(bytes)
+    } else {
+        u32::from_le_bytes(bytes)
+    })
+}
+
+fn read_u64(data: &[u8], offset: usize, big_endian: bool) -> Result<u64> {
+    let bytes = data
+        .get(offset..offset + 8)
+        .ok_or(GsymError("unexpected end of data"))?;
+    let bytes = bytes.try_into().unwrap();
+    Ok(if big_endian {
+        u64::from_be_bytes(bytes)
+    } else {
+        u64::from_le_bytes(bytes)
+    })
+}
+
+/// Reads a NUL-terminated string at the given offset.
+fn read_cstr(data: &[u8], offset: usize) -> Result<&[u8]> {
+    let data = data
+        .get(offset..)
+        .ok_or(GsymError("string out of bounds"))?;
+    let len = data
+        .iter()
+        .position(|&b| b == 0)
+        .ok_or(GsymError("unterminated string"))?;
+    Ok(&data[..len])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::new::writer::SymCacheConverter;
+    use crate::new::SymCache;
+
+    /// Builds a GSYM file with a single function `main` at 0x1000, which inlines `helper` in its
+    /// second half.
+    fn gsym() -> Vec<u8> {
+        let strtab = b"\0main\0helper\0/src\0main.c\0helper.h\0";
+
+        let mut data = Vec::new();
+        data.extend(GSYM_MAGIC.to_le_bytes());
+        data.extend(GSYM_VERSION.to_le_bytes());
+        // address offset size, uuid size, base address
+        data.extend([2, 0]);
+        data.extend(0x1000u64.to_le_bytes());
+        // number of addresses, string table offset and size
+        for field in [1, 84, strtab.len() as u32] {
+            data.extend(field.to_le_bytes());
+        }
+        data.extend([0; 20]);
+
+        // address offsets, padding and function info offsets
+        data.extend(0u16.to_le_bytes());
+        data.extend([0; 2]);
+        data.extend(120u32.to_le_bytes());
+
+        // file table: the unknown file, main.c and helper.h
+        for field in [3u32, 0, 0, 13, 18, 13, 25] {
+            data.extend(field.to_le_bytes());
+        }
+        data.extend(strtab);
+        data.resize(120, 0);
+
+        // function info: size and name
+        data.extend(0x10u32.to_le_bytes());
+        data.extend(1u32.to_le_bytes());
+
+        // line table: main.c:10 at 0x1000, helper.h:20 at 0x1008
+        let line_table = [0, 0, 10, 4, 1, 2, 3, 10, 2, 8, 4, 0];
+        data.extend(INFO_LINE_TABLE.to_le_bytes());
+        data.extend((line_table.len() as u32).to_le_bytes());
+        data.extend(line_table);
+
+        // inline info: helper is inlined at 0x1008 from main.c:11
+        let mut inline_info = vec![1, 0, 0x10, 1, 0, 0, 0, 0, 0, 0];
+        inline_info.extend([1, 8, 8, 0]);
+        inline_info.extend(6u32.to_le_bytes());
+        inline_info.extend([1, 11, 0]);
+        data.extend(INFO_INLINE.to_le_bytes());
+        data.extend((inline_info.len() as u32).to_le_bytes());
+        data.extend(inline_info);
+
+        data.extend(INFO_END_OF_LIST.to_le_bytes());
+        data.extend(0u32.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn test_functions() {
+        let data = gsym();
+        let gsym = Gsym::parse(&data).unwrap();
+        let functions = gsym.functions(0x1000).unwrap();
+
+        assert_eq!(functions.len(), 1);
+        let function = &functions[0];
+        assert_eq!(function.name.as_str(), "main");
+        assert_eq!((function.address, function.size), (0, 0x10));
+        assert!(!function.inline);
+
+        let lines = |function: &Function<'_>| -> Vec<_> {
+            function
+                .lines
+                .iter()
+                .map(|line| (line.address, line.size, line.file.path_str(), line.line))
+                .collect()
+        };
+        assert_eq!(
+            lines(function),
+            [
+                (0, Some(8), "/src/main.c".to_owned(), 10),
+                (8, Some(8), "/src/main.c".to_owned(), 11),
+            ]
+        );
+
+        assert_eq!(function.inlinees.len(), 1);
+        let inlinee = &function.inlinees[0];
+        assert_eq!(inlinee.name.as_str(), "helper");
+        assert_eq!((inlinee.address, inlinee.size), (8, 8));
+        assert!(inlinee.inline);
+        assert_eq!(
+            lines(inlinee),
+            [(8, Some(8), "/src/helper.h".to_owned(), 20)]
+        );
+    }
+
+    #[test]
+    fn test_lookup() {
+        let data = gsym();
+        let mut converter = SymCacheConverter::new();
+        for function in Gsym::parse(&data).unwrap().functions(0x1000).unwrap() {
+            converter.process_symbolic_function(&function);
+        }
+        let mut buf = Vec::new();
+        converter.serialize(&mut buf).unwrap();
+
+        let cache = SymCache::parse(&buf).unwrap();
+        let frames: Vec<_> = cache
+            .lookup(0xc)
+            .map(|sl| (sl.function().unwrap().name(), sl.line()))
+            .collect();
+        assert_eq!(frames, [(Some("helper"), 20), (Some("main"), 11)]);
+    }
+
+    #[test]
+    fn test_unsupported() {
+        let mut data = gsym();
+        data[4..6].copy_from_slice(&2u16.to_le_bytes());
+        assert!(Gsym::parse(&data).is_err());
+    }
+}
@@ -0,0 +1,407 @@
+//! Reading of the symbol files that JIT compilers emit for profilers.
+//!
+//! JIT compilers such as V8, the JVM or the .NET runtime can describe the code they generate in
+//! two formats understood by `perf`:
+//!
+//!  - A perf map is a text file named `/tmp/perf-<pid>.map`, with one line of the form
+//!    `START SIZE NAME` per function. Start address and size are hexadecimal.
+//!  - A jitdump is a binary file named `jit-<pid>.dump`, which consists of a header followed by
+//!    records. Code load records contain the address, size and name of a function, and debug info
+//!    records preceding them map the function's addresses to files and lines. Code move records
+//!    relocate previously loaded functions.
+//!
+//! In both formats, code that is loaded later at the same address supersedes earlier code.
+
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryInto;
+
+use symbolic_common::{Arch, Language, Name, NameMangling};
+use symbolic_debuginfo::{FileInfo, Function, LineInfo, Symbol};
+use thiserror::Error;
+
+const JITDUMP_MAGIC: u32 = 0x4a69_5444;
+const JITDUMP_HEADER_SIZE: usize = 40;
+const RECORD_HEADER_SIZE: usize = 16;
+
+const JIT_CODE_LOAD: u32 = 0;
+const JIT_CODE_MOVE: u32 = 1;
+const JIT_CODE_DEBUG_INFO: u32 = 2;
+
+/// An error encountered while reading a perf map or jitdump.
+#[derive(Debug, Error)]
+#[error("invalid JIT symbols: {0}")]
+pub(crate) struct JitError(&'static str);
+
+type Result<T, E = JitError> = std::result::Result<T, E>;
+
+/// Parses a perf map into symbols, sorted by address.
+pub(crate) fn parse_perf_map(data: &[u8]) -> Result<Vec<Symbol<'_>>> {
+    let mut symbols = BTreeMap::new();
+
+    for line in data.split(|&b| b == b'\n') {
+        let line = String::from_utf8_lossy(line);
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        // the name is everything after the size, and may itself contain spaces
+        let mut parts = line.splitn(3, char::is_whitespace);
+        let (start, size, name) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(start), Some(size), Some(name)) => (start, size, name.trim_start()),
+            _ => return Err(JitError("malformed perf map line")),
+        };
+
+        let address = parse_hex(start)?;
+        let size = parse_hex(size)?;
+        symbols.insert(
+            address,
+            Symbol {
+                name: Some(Cow::Owned(name.to_owned())),
+                address,
+                size,
+            },
+        );
+    }
+
+    Ok(symbols.into_values().collect())
+}
+
+/// Parses a hexadecimal number with an optional `0x` prefix.
+fn parse_hex(s: &str) -> Result<u64> {
+    let digits = s.strip_prefix("0x").unwrap_or(s);
+    u64::from_str_radix(digits, 16).map_err(|_| JitError("invalid hexadecimal number"))
+}
+
+/// A parsed jitdump file.
+pub(crate) struct JitDump<'data> {
+    /// The architecture of the generated code.
+    pub arch: Arch,
+    /// The loaded functions, sorted by address.
+    pub functions: Vec<Function<'data>>,
+}
+
+/// A line record in a debug info record, before it is attached to a function.
+struct DebugEntry<'data> {
+    address: u64,
+    line: u64,
+    file: &'data [u8],
+}
+
+impl<'data> JitDump<'data> {
+    /// Parses a jitdump file.
+    pub fn parse(data: &'data [u8]) -> Result<Self> {
+        let magic = data.get(..4).ok_or(JitError("header too small"))?;
+        let magic: [u8; 4] = magic.try_into().unwrap();
+        let big_endian = if u32::from_le_bytes(magic) == JITDUMP_MAGIC {
+            false
+        } else if u32::from_be_bytes(magic) == JITDUMP_MAGIC {
+            true
+        } else {
+            return Err(JitError("wrong magic"));
+        };
+        let reader = Reader { data, big_endian };
+
+        let header_size = reader.u32(8)? as usize;
+        if header_size < JITDUMP_HEADER_SIZE {
+            return Err(JitError("header too small"));
+        }
+        let arch = arch_from_elf_machine(reader.u32(12)?);
+
+        let mut functions = BTreeMap::new();
+        let mut debug_info = HashMap::new();
+        let mut offset = header_size;
+        while offset < data.len() {
+            let id = reader.u32(offset)?;
+            let record_size = reader.u32(offset + 4)? as usize;
+            if record_size < RECORD_HEADER_SIZE {
+                return Err(JitError("record too small"));
+            }
+            let record = data
+                .get(offset..offset + record_size)
+                .ok_or(JitError("record out of bounds"))?;
+            let record = Reader {
+                data: record,
+                big_endian,
+            };
+            offset += record_size;
+
+            let body = RECORD_HEADER_SIZE;
+            match id {
+                JIT_CODE_LOAD => {
+                    let address = record.u64(body + 16)?;
+                    let size = record.u64(body + 24)?;
+                    let name = record.cstr(body + 40)?;
+                    let entries = debug_info.remove(&address).unwrap_or_default();
+                    let function = function(name, address, size, entries);
+                    functions.insert(address, function);
+                }
+                JIT_CODE_MOVE => {
+                    let old_address = record.u64(body + 16)?;
+                    let new_address = record.u64(body + 24)?;
+                    if let Some(mut function) = functions.remove(&old_address) {
+                        function.address = new_address;
+                        for line in &mut function.lines {
+                            line.address = line.address - old_address + new_address;
+                        }
+                        functions.insert(new_address, function);
+                    }
+                }
+                JIT_CODE_DEBUG_INFO => {
+                    let address = record.u64(body)?;
+                    let num_entries = record.u64(body + 8)?;
+                    let mut entries = Vec::new();
+                    let mut entry_offset = body + 16;
+                    for _ in 0..num_entries {
+                        let name = record.cstr(entry_offset + 16)?;
+                        let file = match name {
+                            // a file name of `0xff` repeats the previous file name
+                            b"\xff" => entries.last().map_or(&[][..], |e: &DebugEntry| e.file),
+                            _ => name,
+                        };
+                        entries.push(DebugEntry {
+                            address: record.u64(entry_offset)?,
+                            line: u64::from(record.u32(entry_offset + 8)?),
+                            file,
+                        });
+                        // address, line and discriminator, followed by the name and its NUL
+                        entry_offset += 16 + name.len() + 1;
+                    }
+                    debug_info.insert(address, entries);
+                }
+                // unwinding info and unknown records are skipped
+                _ => {}
+            }
+        }
+
+        Ok(Self {
+            arch,
+            functions: functions.into_values().collect(),
+        })
+    }
+}
+
+/// Creates a function from a code load record and the debug info preceding it.
+fn function<'data>(
+    name: &'data [u8],
+    address: u64,
+    size: u64,
+    mut entries: Vec<DebugEntry<'data>>,
+) -> Function<'data> {
+    let end = address.saturating_add(size);
+    entries.retain(|entry| entry.address >= address && entry.address < end);
+    entries.sort_by_key(|entry| entry.address);
+
+    let mut lines: Vec<LineInfo<'data>> = Vec::with_capacity(entries.len());
+    for (index, entry) in entries.iter().enumerate() {
+        let next = entries.get(index + 1).map_or(end, |next| next.address);
+        if next == entry.address {
+            continue;
+        }
+        lines.push(LineInfo {
+            address: entry.address,
+            size: Some(next - entry.address),
+            file: FileInfo {
+                name: entry.file,
+                dir: b"",
+            },
+            line: entry.line,
+            column: 0,
+            prologue_end: false,
+            is_stmt: true,
+            basic_block: false,
+        });
+    }
+
+    Function {
+        address,
+        size,
+        name: Name::new(
+            String::from_utf8_lossy(name),
+            NameMangling::Unknown,
+            Language::Unknown,
+        ),
+        compilation_dir: &[],
+        lines,
+        inlinees: Vec::new(),
+        inline: false,
+        decl_file: None,
+        decl_line: 0,
+    }
+}
+
+/// Converts an ELF machine number into our `Arch` type.
+fn arch_from_elf_machine(machine: u32) -> Arch {
+    match machine {
+        3 => Arch::X86,
+        40 => Arch::Arm,
+        62 => Arch::Amd64,
+        183 => Arch::Arm64,
+        _ => Arch::Unknown,
+    }
+}
+
+/// Reads fields of the given endianness from a jitdump.
+struct Reader<'data> {
+    data: &'data [u8],
+    big_endian: bool,
+}
+
+impl<'data> Reader<'data> {
+    fn u32(&self, offset: usize) -> Result<u32> {
+        let bytes = self
+            .data
+            .get(offset..offset + 4)
+            .ok_or(JitError("unexpected end of data"))?;
+        let bytes = bytes.try_into().unwrap();
+        Ok(if self.big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        })
+    }
+
+    fn u64(&self, offset: usize) -> Result<u64> {
+        let bytes = self
+            .data
+            .get(offset..offset + 8)
+            .ok_or(JitError("unexpected end of data"))?;
+        let bytes = bytes.try_into().unwrap();
+        Ok(if self.big_endian {
+            u64::from_be_bytes(bytes)
+        } else {
+            u64::from_le_bytes(bytes)
+        })
+    }
+
+    /// Reads a NUL-terminated string at the given offset.
+    fn cstr(&self, offset: usize) -> Result<&'data [u8]> {
+        let data = self
+            .data
+            .get(offset..)
+            .ok_or(JitError("string out of bounds"))?;
+        let len = data
+            .iter()
+            .position(|&b| b == 0)
+            .ok_or(JitError("unterminated string"))?;
+        Ok(&data[..len])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_perf_map() {
+        let data = b"7f0000001000 20 LazyCompile:~main app.js:1\n\
+            0x7f0000001040 0x10 stub\n\
+            \n\
+            7f0000001000 30 LazyCompile:*main app.js:1\n";
+        let symbols = parse_perf_map(data).unwrap();
+
+        let symbols: Vec<_> = symbols
+            .iter()
+            .map(|s| (s.address, s.size, s.name.as_deref().unwrap()))
+            .collect();
+        assert_eq!(
+            symbols,
+            [
+                (0x7f00_0000_1000, 0x30, "LazyCompile:*main app.js:1"),
+                (0x7f00_0000_1040, 0x10, "stub"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_perf_map_malformed() {
+        assert!(parse_perf_map(b"1000 main\n").is_err());
+        assert!(parse_perf_map(b"1000 xyz main\n").is_err());
+    }
+
+    /// Appends a record with the given id and body to a jitdump.
+    fn record(data: &mut Vec<u8>, id: u32, body: &[u8]) {
+        data.extend(id.to_le_bytes());
+        data.extend((RECORD_HEADER_SIZE as u32 + body.len() as u32).to_le_bytes());
+        data.extend(0u64.to_le_bytes());
+        data.extend(body);
+    }
+
+    /// Builds a jitdump with a single function at 0x1000, which is then moved to 0x3000.
+    fn jitdump() -> Vec<u8> {
+        let mut data = Vec::new();
+        // magic, version, header size, elf machine, padding, pid, timestamp, flags
+        for field in [JITDUMP_MAGIC, 1, JITDUMP_HEADER_SIZE as u32, 62, 0, 1] {
+            data.extend(field.to_le_bytes());
+        }
+        data.extend([0; 16]);
+
+        // debug info: two entries for the function, the second one repeating the file name
+        let mut body = Vec::new();
+        for field in [0x1000u64, 2, 0x1000] {
+            body.extend(field.to_le_bytes());
+        }
+        body.extend([5u32, 0].iter().flat_map(|field| field.to_le_bytes()));
+        body.extend(b"/app/main.js\0");
+        body.extend(0x1008u64.to_le_bytes());
+        body.extend([6u32, 0].iter().flat_map(|field| field.to_le_bytes()));
+        body.extend(b"\xff\0");
+        record(&mut data, JIT_CODE_DEBUG_INFO, &body);
+
+        // code load: pid, tid, vma, code address, size, index, name and code
+        let mut body = Vec::new();
+        body.extend([1u32, 1].iter().flat_map(|field| field.to_le_bytes()));
+        for field in [0x1000u64, 0x1000, 0x10, 0] {
+            body.extend(field.to_le_bytes());
+        }
+        body.extend(b"main\0");
+        body.extend([0xcc; 0x10]);
+        record(&mut data, JIT_CODE_LOAD, &body);
+
+        // code move: pid, tid, vma, old address, new address, size, index
+        let mut body = Vec::new();
+        body.extend([1u32, 1].iter().flat_map(|field| field.to_le_bytes()));
+        for field in [0x3000u64, 0x1000, 0x3000, 0x10, 0] {
+            body.extend(field.to_le_bytes());
+        }
+        record(&mut data, JIT_CODE_MOVE, &body);
+
+        // an unknown record, which is skipped
+        record(&mut data, 42, &[0; 8]);
+        data
+    }
+
+    #[test]
+    fn test_jitdump() {
+        let data = jitdump();
+        let dump = JitDump::parse(&data).unwrap();
+
+        assert_eq!(dump.arch, Arch::Amd64);
+        assert_eq!(dump.functions.len(), 1);
+        let function = &dump.functions[0];
+        assert_eq!(function.name.as_str(), "main");
+        assert_eq!(function.address, 0x3000);
+        assert_eq!(function.size, 0x10);
+
+        let lines: Vec<_> = function
+            .lines
+            .iter()
+            .map(|line| (line.address, line.size, line.file.path_str(), line.line))
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                (0x3000, Some(8), "/app/main.js".to_owned(), 5),
+                (0x3008, Some(8), "/app/main.js".to_owned(), 6),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_jitdump_truncated() {
+        let data = jitdump();
+        assert!(JitDump::parse(&data[..data.len() - 4]).is_err());
+        assert!(JitDump::parse(&data[4..]).is_err());
+    }
+}
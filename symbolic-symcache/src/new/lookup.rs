@@ -1,61 +1,378 @@
+use std::ops::Range;
+
 use symbolic_common::Language;
 
+use super::ppdb::il_address;
 use super::{raw, SymCache};
 
 impl<'data> SymCache<'data> {
     /// Looks up an instruction address in the SymCache, yielding an iterator of [`SourceLocation`]s.
     ///
+    /// The iterator yields one [`SourceLocation`] per inline frame, starting with the innermost
+    /// inlined function and ending with the outermost function that contains the instruction.
+    ///
     /// This always returns an iterator, however that iterator might be empty in case no [`SourceLocation`]
     /// was found for the given `addr`.
     pub fn lookup(&self, addr: u64) -> SourceLocationIter<'data, '_> {
-        use std::convert::TryFrom;
-        let addr = match u32::try_from(addr) {
-            Ok(addr) => addr,
-            Err(_) => {
-                return SourceLocationIter {
-                    cache: self,
-                    source_location_idx: u32::MAX,
-                }
+        match self.find_range(addr) {
+            Some(range_idx) => self.lookup_range(range_idx),
+            None => self.lookup_nothing(),
+        }
+    }
+
+    /// Looks up a batch of instruction addresses, yielding one iterator per address like
+    /// [`lookup`](Self::lookup).
+    ///
+    /// The addresses can be in any order and may contain duplicates. They are resolved in
+    /// ascending order in a single pass over the ranges, which is considerably faster than
+    /// separate lookups for large batches, such as all samples of a profile. The results are
+    /// returned in the order of `addrs`.
+    pub fn lookup_many(&self, addrs: &[u64]) -> Vec<SourceLocationIter<'data, '_>> {
+        let mut order: Vec<usize> = (0..addrs.len()).collect();
+        order.sort_unstable_by_key(|&idx| addrs[idx]);
+
+        let mut range_indices = vec![None; addrs.len()];
+        // the number of ranges starting at or before the previous address
+        let mut cursor = 0;
+        for idx in order {
+            let addr = addrs[idx];
+            // like `find_range`, addresses beyond 32 bits only resolve if there are such ranges
+            if self.ranges_high.is_empty() && addr > u64::from(u32::MAX) {
+                break;
             }
-        };
+            cursor = self.count_ranges_from(addr, cursor);
+            range_indices[idx] = cursor.checked_sub(1);
+        }
 
-        let source_location_start = (self.source_locations.len() - self.ranges.len()) as u32;
-        let mut source_location_idx = match self.ranges.binary_search_by_key(&addr, |r| r.0) {
-            Ok(idx) => source_location_start + idx as u32,
-            Err(idx) if idx == 0 => u32::MAX,
-            Err(idx) => source_location_start + idx as u32 - 1,
-        };
+        range_indices
+            .into_iter()
+            .map(|range_idx| match range_idx {
+                Some(range_idx) => self.lookup_range(range_idx),
+                None => self.lookup_nothing(),
+            })
+            .collect()
+    }
 
-        if let Some(source_location) = self.source_locations.get(source_location_idx as usize) {
-            if *source_location == raw::NO_SOURCE_LOCATION {
-                source_location_idx = u32::MAX;
+    /// Returns the number of ranges starting at or before `addr`, which must be at least `start`.
+    ///
+    /// This gallops forward from `start` and then searches the last step, so that ascending
+    /// addresses close to each other only need few comparisons.
+    fn count_ranges_from(&self, addr: u64, start: usize) -> usize {
+        let starts_before = |idx: usize| matches!(self.range_start(idx), Some(s) if s <= addr);
+
+        // find an upper bound that starts after `addr`, doubling the distance in every step
+        let (mut low, mut high, mut step) = (start, start, 1usize);
+        while high < self.ranges.len() && starts_before(high) {
+            low = high + 1;
+            high = low.saturating_add(step);
+            step = step.saturating_mul(2);
+        }
+
+        let mut high = high.min(self.ranges.len());
+        while low < high {
+            let mid = low + (high - low) / 2;
+            if starts_before(mid) {
+                low = mid + 1;
+            } else {
+                high = mid;
             }
         }
+        low
+    }
+
+    /// Returns an iterator that does not yield any [`SourceLocation`]s.
+    pub(crate) fn lookup_nothing(&self) -> SourceLocationIter<'data, '_> {
+        SourceLocationIter {
+            cache: self,
+            source_location_idx: u32::MAX,
+            remaining: 0,
+        }
+    }
+
+    /// Looks up an IL offset within a .NET method, yielding an iterator of [`SourceLocation`]s.
+    ///
+    /// This resolves managed frames in SymCaches that were converted from Portable PDBs. The
+    /// method is identified by its row index in the `MethodDef` table, which is its metadata token
+    /// without the table tag, for example `1` for the token `0x06000001`.
+    pub fn lookup_il_offset(
+        &self,
+        method_index: u32,
+        il_offset: u32,
+    ) -> SourceLocationIter<'data, '_> {
+        self.lookup(il_address(method_index, il_offset))
+    }
+
+    /// Yields the [`SourceLocation`]s of the range at the given index, see [`lookup`](Self::lookup).
+    pub(crate) fn lookup_range(&self, range_idx: usize) -> SourceLocationIter<'data, '_> {
+        let source_location_idx = self.source_location_start() + range_idx as u32;
+        let source_location_idx = match self.source_locations.get(source_location_idx as usize) {
+            Some(source_location) if source_location.is_none() => u32::MAX,
+            _ => source_location_idx,
+        };
 
         SourceLocationIter {
             cache: self,
             source_location_idx,
+            remaining: self.source_locations.len(),
+        }
+    }
+
+    /// An iterator over all address ranges in the SymCache along with their innermost
+    /// [`SourceLocation`].
+    ///
+    /// Ranges that do not resolve to a source location, such as gaps between functions, are
+    /// skipped. Since the SymCache only stores start addresses, the last range extends to the end
+    /// of the address space, just like [`lookup`](Self::lookup) resolves all addresses past its
+    /// start. The address space spans 32 bits, unless any address in the SymCache is larger.
+    pub fn ranges(&self) -> RangeIter<'data, '_> {
+        RangeIter {
+            cache: self,
+            range_idx: 0,
+        }
+    }
+
+    /// Returns the kind of input that produced the range covering the given address.
+    ///
+    /// This helps to debug SymCaches that were merged from multiple inputs, such as a symbol
+    /// table and debug information. Returns [`Provenance::Unknown`] for SymCaches written without
+    /// provenance, and `None` if no range covers the address.
+    pub fn provenance(&self, addr: u64) -> Option<Provenance> {
+        let range_idx = self.find_range(addr)?;
+        let provenance = self.provenance.get(range_idx).copied();
+        Some(Provenance::from_raw(
+            provenance.unwrap_or(raw::PROVENANCE_UNKNOWN),
+        ))
+    }
+
+    /// Returns the line program flags of the range covering the given address.
+    ///
+    /// SymCaches written without flags mark all ranges as statements, and `None` is returned if no
+    /// range covers the address.
+    pub fn range_flags(&self, addr: u64) -> Option<RangeFlags> {
+        let range_idx = self.find_range(addr)?;
+        let flags = self.range_flags.get(range_idx).copied().unwrap_or(0);
+        Some(RangeFlags {
+            prologue_end: flags & raw::RANGE_FLAG_PROLOGUE_END != 0,
+            is_stmt: flags & raw::RANGE_FLAG_NOT_STMT == 0,
+            basic_block: flags & raw::RANGE_FLAG_BASIC_BLOCK != 0,
+        })
+    }
+
+    /// Returns the address at which the prologue of the function covering `addr` ends.
+    ///
+    /// The first line record of a function often points at its signature, since it covers the
+    /// prologue. If a crash happened in the prologue, looking up the returned address instead
+    /// yields the line of the first statement in the function body. Returns `None` if `addr` lies
+    /// past the end of the prologue, or if the SymCache does not record where prologues end.
+    pub fn prologue_end(&self, addr: u64) -> Option<u64> {
+        if self.range_flags.is_empty() {
+            return None;
+        }
+
+        let range_idx = self.find_range(addr)?;
+        let function_idx = self.outermost_function_idx(range_idx)?;
+        let in_function = |idx: &usize| self.outermost_function_idx(*idx) == Some(function_idx);
+        let is_prologue_end = |idx: usize| {
+            let flags = self.range_flags.get(idx).copied().unwrap_or(0);
+            flags & raw::RANGE_FLAG_PROLOGUE_END != 0
+        };
+
+        let mut before = (0..=range_idx).rev().take_while(in_function);
+        if before.any(is_prologue_end) {
+            return None;
+        }
+        let mut after = (range_idx + 1..self.ranges.len()).take_while(in_function);
+        let end_idx = after.find(|&idx| is_prologue_end(idx))?;
+        self.range_start(end_idx)
+    }
+
+    /// Returns the index of the outermost function of the range at the given index.
+    fn outermost_function_idx(&self, range_idx: usize) -> Option<u32> {
+        let outermost = self.lookup_range(range_idx).last()?;
+        Some(outermost.source_location.function_idx.get())
+    }
+
+    /// Returns a stable key to group crashes at the given instruction address.
+    ///
+    /// The key consists of the outermost function at the address, which is the function that
+    /// was actually called, and the name of its source file. It deliberately uses the raw,
+    /// possibly mangled function name and strips all directories from the file, so that the key
+    /// does not change with demangling options or across build machines.
+    ///
+    /// Returns `None` if the address cannot be resolved.
+    pub fn grouping_key(&self, addr: u64) -> Option<GroupingKey<'data>> {
+        let outermost = self.lookup(addr).last()?;
+        let function = outermost.function().and_then(|f| f.name());
+        let file = outermost.file().and_then(|file| {
+            let (_, file_name) = symbolic_common::split_path(file.path_name());
+            if file_name.is_empty() {
+                None
+            } else {
+                Some(file_name)
+            }
+        });
+
+        Some(GroupingKey { function, file })
+    }
+
+    /// Explains how an instruction address is resolved in the SymCache.
+    ///
+    /// This exposes the internal indices of the matched range, its inline chain and its
+    /// neighboring ranges, and is meant for troubleshooting wrong or missing lookup results.
+    pub fn explain(&self, addr: u64) -> Explanation {
+        let range_idx = self.find_range(addr);
+
+        let mut inline_chain = Vec::new();
+        let mut source_location_idx = match range_idx {
+            Some(range_idx) => self.source_location_start() + range_idx as u32,
+            None => u32::MAX,
+        };
+        while let Some(source_location) = self.source_locations.get(source_location_idx as usize) {
+            // guard against cyclic inline chains in corrupted files
+            if inline_chain.contains(&source_location_idx) {
+                break;
+            }
+            inline_chain.push(source_location_idx);
+            source_location_idx = source_location.inlined_into_idx.get();
+        }
+
+        let neighbors = match range_idx {
+            Some(range_idx) => range_idx.saturating_sub(2)..(range_idx + 3).min(self.ranges.len()),
+            None => 0..self.ranges.len().min(2),
+        };
+        let ranges = neighbors
+            .map(|idx| {
+                let source_location_idx = self.source_location_start() + idx as u32;
+                let source_location_idx =
+                    match self.source_locations.get(source_location_idx as usize) {
+                        Some(sl) if !sl.is_none() => Some(source_location_idx),
+                        _ => None,
+                    };
+                ExplainedRange {
+                    range_idx: idx as u32,
+                    start: self.range_start(idx).unwrap_or_default(),
+                    end: self.range_start(idx + 1),
+                    source_location_idx,
+                }
+            })
+            .collect();
+
+        Explanation {
+            addr,
+            range_idx: range_idx.map(|idx| idx as u32),
+            inline_chain,
+            ranges,
+        }
+    }
+
+    /// Returns the index of the range covering `addr`, if any.
+    pub(crate) fn find_range(&self, addr: u64) -> Option<usize> {
+        use std::convert::TryFrom;
+
+        let idx = if self.ranges_high.is_empty() {
+            let addr = u32::try_from(addr).ok()?;
+            self.ranges.partition_point(|r| r.0.get() <= addr)
+        } else {
+            // binary search for the first range starting after `addr`
+            let (mut low, mut high) = (0, self.ranges.len());
+            while low < high {
+                let mid = low + (high - low) / 2;
+                match self.range_start(mid) {
+                    Some(start) if start <= addr => low = mid + 1,
+                    _ => high = mid,
+                }
+            }
+            low
+        };
+
+        idx.checked_sub(1)
+    }
+
+    /// Returns the start address of the range at the given index.
+    pub(crate) fn range_start(&self, range_idx: usize) -> Option<u64> {
+        let low = self.ranges.get(range_idx)?.0.get();
+        let high = self.ranges_high.get(range_idx).map_or(0, |high| high.get());
+        Some(u64::from(high) << 32 | u64::from(low))
+    }
+
+    /// Returns the first address after the last range, which is the end of the address space.
+    pub(crate) fn address_space_end(&self) -> u64 {
+        if self.ranges_high.is_empty() {
+            u64::from(u32::MAX) + 1
+        } else {
+            u64::MAX
         }
     }
 
+    /// The index of the first source location that directly corresponds to a range.
+    fn source_location_start(&self) -> u32 {
+        (self.source_locations.len() - self.ranges.len()) as u32
+    }
+
+    /// Resolves the given string references, counting malformed ones as missing strings, see
+    /// [`bad_string_references`](Self::bad_string_references).
+    fn resolve_strings_lossy<'slf>(
+        &'slf self,
+        offsets: &'slf [u32],
+    ) -> impl Iterator<Item = Option<&'data str>> + 'slf {
+        self.resolve_strings(offsets).map(move |string| {
+            string.unwrap_or_else(|_| {
+                self.bad_strings.increment();
+                None
+            })
+        })
+    }
+
+    /// Returns the file at the given index, or `None` if the index is out of bounds.
+    ///
+    /// Malformed string references resolve to missing strings instead of failing, as lookups
+    /// should still yield the remaining information of a frame. They are counted in
+    /// [`bad_string_references`](Self::bad_string_references), and [`validate`](Self::validate)
+    /// reports these errors.
     pub(crate) fn get_file(&self, file_idx: u32) -> Option<File<'data>> {
         let raw_file = self.files.get(file_idx as usize)?;
+        let offsets = [
+            raw_file.comp_dir_offset.get(),
+            raw_file.directory_offset.get(),
+            raw_file.path_name_offset.get(),
+        ];
+        let mut strings = self.resolve_strings_lossy(&offsets);
         Some(File {
-            comp_dir: self.get_string(raw_file.comp_dir_offset),
-            directory: self.get_string(raw_file.directory_offset),
-            path_name: self
-                .get_string(raw_file.path_name_offset)
-                .unwrap_or_default(),
+            comp_dir: strings.next().flatten(),
+            directory: strings.next().flatten(),
+            path_name: strings.next().flatten().unwrap_or_default(),
         })
     }
 
+    /// Returns the function at the given index, or `None` if the index is out of bounds.
+    ///
+    /// Like in [`get_file`](Self::get_file), malformed string references resolve to missing
+    /// strings.
     pub(crate) fn get_function(&self, function_idx: u32) -> Option<Function<'data>> {
         let raw_function = self.functions.get(function_idx as usize)?;
+        let entry_pc_high = self
+            .entry_pcs_high
+            .get(function_idx as usize)
+            .map_or(0, |high| high.get());
+        let linkage_name_offset = self
+            .linkage_names
+            .get(function_idx as usize)
+            .map_or(u32::MAX, |offset| offset.get());
+        let offsets = [
+            raw_function.name_offset.get(),
+            raw_function.comp_dir_offset.get(),
+            linkage_name_offset,
+        ];
+        let mut strings = self.resolve_strings_lossy(&offsets);
+        let declaration = self.declarations.get(function_idx as usize);
         Some(Function {
-            name: self.get_string(raw_function.name_offset),
-            comp_dir: self.get_string(raw_function.comp_dir_offset),
-            entry_pc: raw_function.entry_pc,
-            language: Language::from_u32(raw_function.lang),
+            name: strings.next().flatten(),
+            comp_dir: strings.next().flatten(),
+            linkage_name: strings.next().flatten(),
+            entry_pc: u64::from(entry_pc_high) << 32 | u64::from(raw_function.entry_pc.get()),
+            language: Language::from_u32(raw_function.lang.get()),
+            decl_file: declaration.and_then(|decl| self.get_file(decl.file_idx.get())),
+            decl_line: declaration.map_or(0, |decl| decl.line.get()),
         })
     }
 }
@@ -80,6 +397,8 @@ impl<'data> SymCache<'data> {
 ///   - directory: /usr/include/
 ///   - path_name: pthread.h
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde_::Serialize))]
+#[cfg_attr(feature = "serde", serde(crate = "serde_"))]
 pub struct File<'data> {
     /// The optional compilation directory prefix.
     pub comp_dir: Option<&'data str>,
@@ -106,27 +425,29 @@ impl<'data> File<'data> {
     }
 
     /// Resolves and concatenates the full path based on its individual fragments.
-    #[allow(dead_code)]
+    ///
+    /// See [`join_file_path`](symbolic_common::join_file_path) for how the fragments are joined.
     pub fn full_path(&self) -> String {
         let comp_dir = self.comp_dir().unwrap_or_default();
         let directory = self.directory().unwrap_or_default();
-        let path_name = self.path_name();
-
-        let prefix = symbolic_common::join_path(comp_dir, directory);
-        let full_path = symbolic_common::join_path(&prefix, path_name);
-        let full_path = symbolic_common::clean_path(&full_path).into_owned();
-
-        full_path
+        symbolic_common::join_file_path(comp_dir, directory, self.path_name())
     }
 }
 
 /// A Function definition as included in the SymCache.
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde_::Serialize))]
+#[cfg_attr(feature = "serde", serde(crate = "serde_"))]
 pub struct Function<'data> {
     name: Option<&'data str>,
     comp_dir: Option<&'data str>,
-    entry_pc: u32,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    linkage_name: Option<&'data str>,
+    entry_pc: u64,
     language: Language,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    decl_file: Option<File<'data>>,
+    decl_line: u32,
 }
 
 impl<'data> Function<'data> {
@@ -140,8 +461,17 @@ impl<'data> Function<'data> {
         self.comp_dir
     }
 
+    /// The linkage name of this function, if its [name](Self::name) was demangled.
+    ///
+    /// Linkage names are only available if they were kept when writing the SymCache.
+    pub fn linkage_name(&self) -> Option<&'data str> {
+        self.linkage_name
+    }
+
     /// The entry pc of the function.
-    pub fn entry_pc(&self) -> u32 {
+    ///
+    /// Inlined functions do not have an entry pc, which is signified by `u32::MAX`.
+    pub fn entry_pc(&self) -> u64 {
         self.entry_pc
     }
 
@@ -149,6 +479,19 @@ impl<'data> Function<'data> {
     pub fn language(&self) -> Language {
         self.language
     }
+
+    /// The source file in which this function is declared, if known.
+    pub fn decl_file(&self) -> Option<&File<'data>> {
+        self.decl_file.as_ref()
+    }
+
+    /// The source line at which this function is declared.
+    ///
+    /// This returns `0` when the declaration is unknown, which is always the case for SymCaches
+    /// written without declaration information.
+    pub fn decl_line(&self) -> u32 {
+        self.decl_line
+    }
 }
 
 /// A Source Location as included in the SymCache.
@@ -159,6 +502,8 @@ impl<'data> Function<'data> {
 pub struct SourceLocation<'data, 'cache> {
     pub(crate) cache: &'cache SymCache<'data>,
     pub(crate) source_location: &'data raw::SourceLocation,
+    /// The index of `source_location` in the source locations of the SymCache.
+    pub(crate) index: usize,
 }
 
 impl<'data, 'cache> SourceLocation<'data, 'cache> {
@@ -166,46 +511,619 @@ impl<'data, 'cache> SourceLocation<'data, 'cache> {
     ///
     /// This might return `0` when no line information can be found.
     pub fn line(&self) -> u32 {
-        self.source_location.line
+        self.source_location.line.get()
+    }
+
+    /// The source column corresponding to the instruction.
+    ///
+    /// This returns `0` when the column is unknown, which is always the case for SymCaches
+    /// written without column information.
+    pub fn column(&self) -> u32 {
+        self.cache
+            .columns
+            .get(self.index)
+            .map_or(0, |column| column.get())
     }
 
     /// The source file corresponding to the instruction.
     pub fn file(&self) -> Option<File<'data>> {
-        self.cache.get_file(self.source_location.file_idx)
+        self.cache.get_file(self.source_location.file_idx.get())
     }
 
     /// The function corresponding to the instruction.
     pub fn function(&self) -> Option<Function<'data>> {
-        self.cache.get_function(self.source_location.function_idx)
+        self.cache
+            .get_function(self.source_location.function_idx.get())
+    }
+
+    /// The address ranges covered by the function of this source location, in ascending order.
+    ///
+    /// These are all places the function was inlined at if this location [is
+    /// inlined](Self::is_inlined), and include the code of its own inlinees. The sum of the
+    /// ranges is the size of the function. This scans the entire SymCache, use
+    /// [`SymCache::functions_with_ranges`] to list the ranges of all functions at once.
+    pub fn function_ranges(&self) -> Vec<Range<u64>> {
+        self.cache
+            .function_ranges(self.source_location.function_idx.get())
+    }
+
+    /// The source location this location was inlined into, if any.
+    ///
+    /// This is the next frame yielded by [`SymCache::lookup`].
+    pub fn caller(&self) -> Option<SourceLocation<'data, 'cache>> {
+        let index = self.source_location.inlined_into_idx.get() as usize;
+        let source_location = self.cache.source_locations.get(index)?;
+        Some(SourceLocation {
+            cache: self.cache,
+            source_location,
+            index,
+        })
+    }
+
+    /// Whether this frame was inlined into a caller.
+    ///
+    /// This is `true` for all but the last frame yielded by [`SymCache::lookup`].
+    pub fn is_inlined(&self) -> bool {
+        self.source_location.inlined_into_idx.get() != u32::MAX
+    }
+
+    /// Whether this frame was synthesized from a symbol table instead of debug information.
+    ///
+    /// Synthesized frames only carry a function name, but no file or line. This is always
+    /// `false` for SymCaches written without [provenance](SymCache::provenance). Frames of
+    /// [candidates](SymCache::candidates) are synthetic if the candidate came from a symbol table.
+    pub fn is_synthetic(&self) -> bool {
+        let range_idx = match self
+            .index
+            .checked_sub(self.cache.source_location_start() as usize)
+        {
+            Some(range_idx) => range_idx,
+            None => {
+                let symbol_table = u32::from(raw::PROVENANCE_SYMBOL_TABLE);
+                return self.cache.candidates.iter().any(|candidate| {
+                    candidate.source_location_idx.get() as usize == self.index
+                        && candidate.provenance.get() == symbol_table
+                });
+            }
+        };
+        self.cache.provenance.get(range_idx) == Some(&raw::PROVENANCE_SYMBOL_TABLE)
     }
 
     // TODO: maybe forward some of the `File` and `Function` accessors, such as:
     // `function_name` or `full_path` for convenience.
 }
 
+/// A range of addresses, see [`SymCache::ranges`].
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde_::Serialize))]
+#[cfg_attr(feature = "serde", serde(crate = "serde_"))]
+pub struct AddrRange {
+    /// The first address covered by the range.
+    pub start: u64,
+    /// The first address after the range.
+    pub end: u64,
+}
+
+/// An iterator over the address ranges of a [`SymCache`], see [`SymCache::ranges`].
+#[derive(Debug, Clone)]
+pub struct RangeIter<'data, 'cache> {
+    cache: &'cache SymCache<'data>,
+    range_idx: usize,
+}
+
+impl<'data, 'cache> Iterator for RangeIter<'data, 'cache> {
+    type Item = (AddrRange, SourceLocation<'data, 'cache>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let cache = self.cache;
+        let source_location_start = cache.source_location_start() as usize;
+        while let Some(start) = cache.range_start(self.range_idx) {
+            let end = match cache.range_start(self.range_idx + 1) {
+                Some(next) => next,
+                None => cache.address_space_end(),
+            };
+            let index = source_location_start + self.range_idx;
+            self.range_idx += 1;
+
+            match cache.source_locations.get(index) {
+                Some(source_location) if !source_location.is_none() => {
+                    let range = AddrRange { start, end };
+                    let source_location = SourceLocation {
+                        cache,
+                        source_location,
+                        index,
+                    };
+                    return Some((range, source_location));
+                }
+                _ => continue,
+            }
+        }
+
+        None
+    }
+}
+
+/// The kind of input that produced an address range, see [`SymCache::provenance`].
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Provenance {
+    /// The provenance was not recorded.
+    Unknown,
+    /// The range was produced from debug information, such as DWARF or PDB.
+    DebugInfo,
+    /// The range was produced from a symbol table.
+    SymbolTable,
+}
+
+impl Provenance {
+    /// Converts a provenance value of the SymCache, such as [`raw::PROVENANCE_DEBUG_INFO`].
+    pub(crate) fn from_raw(provenance: u8) -> Self {
+        match provenance {
+            raw::PROVENANCE_DEBUG_INFO => Self::DebugInfo,
+            raw::PROVENANCE_SYMBOL_TABLE => Self::SymbolTable,
+            _ => Self::Unknown,
+        }
+    }
+}
+
+/// The line program flags of an address range, see [`SymCache::range_flags`].
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct RangeFlags {
+    /// Whether the range starts at the end of the prologue of its function.
+    pub prologue_end: bool,
+    /// Whether the range starts a statement, which is a recommended breakpoint location.
+    ///
+    /// Ranges that are not statements usually belong to the middle of an expression.
+    pub is_stmt: bool,
+    /// Whether the range starts a basic block.
+    pub basic_block: bool,
+}
+
+/// A key to group crashes by, see [`SymCache::grouping_key`].
+#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct GroupingKey<'data> {
+    /// The raw name of the outermost function.
+    pub function: Option<&'data str>,
+    /// The name of the outermost function's source file, without any directories.
+    pub file: Option<&'data str>,
+}
+
+impl std::fmt::Display for GroupingKey<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}@{}",
+            self.function.unwrap_or("?"),
+            self.file.unwrap_or("?")
+        )
+    }
+}
+
+/// Diagnostic information on how an address is resolved, see [`SymCache::explain`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Explanation {
+    /// The address that was looked up.
+    pub addr: u64,
+    /// The index of the range covering the address, if any.
+    pub range_idx: Option<u32>,
+    /// The indices of the [`SourceLocation`]s of the inline chain, from the innermost to the
+    /// outermost frame.
+    pub inline_chain: Vec<u32>,
+    /// The matched range along with its neighboring ranges, in address order.
+    pub ranges: Vec<ExplainedRange>,
+}
+
+/// A range of an [`Explanation`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExplainedRange {
+    /// The index of the range.
+    pub range_idx: u32,
+    /// The first address covered by the range.
+    pub start: u64,
+    /// The first address after the range, or `None` if this is the last range.
+    pub end: Option<u64>,
+    /// The index of the [`SourceLocation`] of the range, or `None` if this range marks the end
+    /// of a function.
+    pub source_location_idx: Option<u32>,
+}
+
 /// An Iterator that yields [`SourceLocation`]s, representing an inlining hierarchy.
+///
+/// Frames are yielded from the innermost to the outermost function.
 #[derive(Debug, Clone)]
 pub struct SourceLocationIter<'data, 'cache> {
     pub(crate) cache: &'cache SymCache<'data>,
     pub(crate) source_location_idx: u32,
+    /// The maximum number of frames left to yield, which guards against cyclic inline chains in
+    /// corrupted files.
+    pub(crate) remaining: usize,
 }
 
 impl<'data, 'cache> Iterator for SourceLocationIter<'data, 'cache> {
     type Item = SourceLocation<'data, 'cache>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.source_location_idx == u32::MAX {
+        if self.source_location_idx == u32::MAX || self.remaining == 0 {
             return None;
         }
+        self.remaining -= 1;
+        let index = self.source_location_idx as usize;
         self.cache
             .source_locations
-            .get(self.source_location_idx as usize)
+            .get(index)
             .map(|source_location| {
-                self.source_location_idx = source_location.inlined_into_idx;
+                self.source_location_idx = source_location.inlined_into_idx.get();
                 SourceLocation {
                     cache: self.cache,
                     source_location,
+                    index,
                 }
             })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use symbolic_debuginfo::{FileInfo, Function, LineInfo};
+
+    use super::*;
+
+    use crate::new::testutils::{function, inlined_functions, line, serialize, symbol};
+    use crate::new::writer::SymCacheConverter;
+    use crate::new::{endian, swap_endianness};
+
+    #[test]
+    fn test_provenance() {
+        let mut converter = SymCacheConverter::new();
+        converter.process_symbolic_function(&inlined_functions());
+        converter.process_symbolic_symbol(&symbol("other", 0x2000, 0x10));
+        let mut buf = serialize(converter);
+
+        let cache = SymCache::parse(&buf).unwrap();
+        assert_eq!(cache.provenance(0x100c), Some(Provenance::DebugInfo));
+        assert_eq!(cache.provenance(0x2000), Some(Provenance::SymbolTable));
+        assert_eq!(cache.provenance(0x10), None);
+
+        let frames: Vec<_> = cache
+            .lookup(0x100c)
+            .map(|sl| (sl.is_inlined(), sl.is_synthetic()))
+            .collect();
+        assert_eq!(frames, [(true, false), (false, false)]);
+        let sl = cache.lookup(0x2000).next().unwrap();
+        assert!(!sl.is_inlined());
+        assert!(sl.is_synthetic());
+
+        // caches without provenance remain readable
+        let num_sections = std::mem::size_of::<raw::Header>() - 4;
+        buf[num_sections..num_sections + 4].copy_from_slice(&0u32.to_le_bytes());
+        let cache = SymCache::parse(&buf).unwrap();
+        assert_eq!(cache.provenance(0x100c), Some(Provenance::Unknown));
+        assert!(!cache.lookup(0x2000).next().unwrap().is_synthetic());
+    }
+
+    #[test]
+    fn test_prologue_end() {
+        let mut converter = SymCacheConverter::new();
+        converter.process_symbolic_function(&inlined_functions());
+        let buf = serialize(converter);
+        let cache = SymCache::parse(&buf).unwrap();
+        assert_eq!(cache.prologue_end(0x1000), None);
+
+        // the prologue ends on the same line, which must not be coalesced into the first range
+        let mut function = inlined_functions();
+        let mut body = function.lines[0].clone();
+        body.address = 0x1004;
+        body.prologue_end = true;
+        function.lines.insert(1, body);
+        let mut converter = SymCacheConverter::new();
+        converter.process_symbolic_function(&function);
+        let buf = serialize(converter);
+
+        let cache = SymCache::parse(&buf).unwrap();
+        cache.validate().unwrap();
+        assert_eq!(cache.prologue_end(0x1000), Some(0x1004));
+        assert_eq!(cache.prologue_end(0x1002), Some(0x1004));
+        assert_eq!(cache.prologue_end(0x1004), None);
+        assert_eq!(cache.prologue_end(0x100c), None);
+        assert_eq!(cache.prologue_end(0x2000), None);
+        let lines: Vec<_> = cache.lookup(0x1004).map(|sl| sl.line()).collect();
+        assert_eq!(lines, [1]);
+
+        // the flags are kept when converting the SymCache again
+        let mut converter = SymCacheConverter::new();
+        converter.process_symcache(&cache).unwrap();
+        let buf = serialize(converter);
+        let cache = SymCache::parse(&buf).unwrap();
+        assert_eq!(cache.prologue_end(0x1000), Some(0x1004));
+    }
+
+    #[test]
+    fn test_range_flags() {
+        let mut function = inlined_functions();
+        function.inlinees.clear();
+        let line = function.lines[0].clone();
+        let row = |address, offset, is_stmt, basic_block| LineInfo {
+            address,
+            size: Some(2),
+            line: line.line + offset,
+            is_stmt,
+            basic_block,
+            ..line.clone()
+        };
+        function.lines = vec![
+            row(0x1000, 0, true, false),
+            row(0x1002, 1, false, false),
+            row(0x1004, 1, false, false),
+            row(0x1006, 1, false, true),
+            row(0x1008, 1, true, false),
+        ];
+
+        let mut converter = SymCacheConverter::new();
+        converter.process_symbolic_function(&function);
+        converter.coalesce_ranges();
+        // coalesced ranges keep the flags of their first range, but never merge into a new block
+        assert_eq!(converter.stats().coalesced_ranges, 2);
+        let buf = serialize(converter);
+
+        let cache = SymCache::parse(&buf).unwrap();
+        cache.validate().unwrap();
+        let flags = |addr| {
+            let flags = cache.range_flags(addr).unwrap();
+            (flags.is_stmt, flags.basic_block)
+        };
+        assert_eq!(flags(0x1000), (true, false));
+        assert_eq!(flags(0x1002), (false, false));
+        assert_eq!(flags(0x1004), (false, false));
+        assert_eq!(flags(0x1006), (false, true));
+        assert_eq!(flags(0x1008), (false, true));
+        assert_eq!(cache.range_flags(0x10), None);
+
+        // without flags, all ranges are statements
+        let mut converter = SymCacheConverter::new();
+        converter.process_symbolic_function(&inlined_functions());
+        let buf = serialize(converter);
+        let cache = SymCache::parse(&buf).unwrap();
+        let flags = cache.range_flags(0x100c).unwrap();
+        assert!(flags.is_stmt && !flags.basic_block && !flags.prologue_end);
+    }
+
+    #[test]
+    fn test_gaps() {
+        let function = |address, name| {
+            function(
+                address,
+                0x10,
+                name,
+                vec![line(address, 0x10, "", "main.c", 1)],
+            )
+        };
+
+        let mut converter = SymCacheConverter::new();
+        converter.process_symbolic_function(&function(0x1000, "a"));
+        converter.process_symbolic_function(&function(0x1010, "b"));
+        converter.process_symbolic_function(&function(0x1030, "c"));
+        converter.process_symbolic_symbol(&symbol("d", 0x1040, 0x10));
+        converter.process_symbolic_symbol(&symbol("e", 0x1060, 0));
+        let buf = serialize(converter);
+        let cache = SymCache::parse(&buf).unwrap();
+
+        let name = |addr| {
+            let sl = cache.lookup(addr).next()?;
+            sl.function()?.name().map(String::from)
+        };
+        assert_eq!(name(0x100c).as_deref(), Some("a"));
+        assert_eq!(name(0x1010).as_deref(), Some("b"));
+        assert_eq!(name(0x1020), None);
+        assert_eq!(name(0x102c), None);
+        assert_eq!(name(0x1030).as_deref(), Some("c"));
+        assert_eq!(name(0x1044).as_deref(), Some("d"));
+        assert_eq!(name(0x1050), None);
+        // symbols without a size extend indefinitely
+        assert_eq!(name(0x2000).as_deref(), Some("e"));
+        assert_eq!(cache.provenance(0x1040), Some(Provenance::SymbolTable));
+    }
+
+    #[test]
+    fn test_comp_dir() {
+        let function = Function {
+            compilation_dir: b"/build",
+            ..function(
+                0x1000,
+                0x10,
+                "main",
+                vec![
+                    line(0x1000, 8, "src", "main.c", 1),
+                    line(0x1008, 8, "/usr/include", "stdio.h", 1),
+                ],
+            )
+        };
+
+        let mut converter = SymCacheConverter::new();
+        converter.process_symbolic_function(&function);
+        let buf = serialize(converter);
+        let cache = SymCache::parse(&buf).unwrap();
+
+        let function = cache.lookup(0x1000).next().unwrap().function().unwrap();
+        assert_eq!(function.comp_dir(), Some("/build"));
+
+        let file = cache.lookup(0x1000).next().unwrap().file().unwrap();
+        assert_eq!(file.comp_dir(), Some("/build"));
+        assert_eq!(file.directory(), Some("src"));
+        assert_eq!(file.full_path(), "/build/src/main.c");
+
+        // absolute directories override the compilation directory
+        let file = cache.lookup(0x1008).next().unwrap().file().unwrap();
+        assert_eq!(file.comp_dir(), Some("/build"));
+        assert_eq!(file.full_path(), "/usr/include/stdio.h");
+    }
+
+    #[test]
+    fn test_explain() {
+        let mut converter = SymCacheConverter::new();
+        for (name, address) in [("a", 0x1000), ("b", 0x1010), ("c", 0x1020)] {
+            converter.process_symbolic_symbol(&symbol(name, address, 0x10));
+        }
+        let buf = serialize(converter);
+        let cache = SymCache::parse(&buf).unwrap();
+
+        let explanation = cache.explain(0x1014);
+        assert_eq!(explanation.range_idx, Some(1));
+        assert_eq!(explanation.inline_chain.len(), 1);
+        let starts: Vec<_> = explanation.ranges.iter().map(|r| r.start).collect();
+        assert_eq!(starts, [0x1000, 0x1010, 0x1020, 0x1030]);
+        assert_eq!(explanation.ranges[1].end, Some(0x1020));
+        assert_eq!(explanation.ranges[3].end, None);
+        assert_eq!(explanation.ranges[3].source_location_idx, None);
+
+        let explanation = cache.explain(0x10);
+        assert_eq!(explanation.range_idx, None);
+        assert!(explanation.inline_chain.is_empty());
+    }
+
+    #[test]
+    fn test_grouping_key() {
+        let line = |address, name, number| line(address, 8, "/build/src", name, number);
+        let inlinee = Function {
+            inline: true,
+            ..function(0x1010, 0x8, "_Z6helperv", vec![line(0x1010, "helper.h", 5)])
+        };
+        let function = Function {
+            inlinees: vec![inlinee],
+            ..function(
+                0x1000,
+                0x20,
+                "main",
+                vec![line(0x1000, "main.c", 1), line(0x1010, "main.c", 2)],
+            )
+        };
+
+        let mut converter = SymCacheConverter::new();
+        converter.process_symbolic_function(&function);
+        let buf = serialize(converter);
+        let cache = SymCache::parse(&buf).unwrap();
+
+        assert_eq!(cache.lookup(0x1010).count(), 2);
+        let key = cache.grouping_key(0x1010).unwrap();
+        assert_eq!(key.function, Some("main"));
+        assert_eq!(key.file, Some("main.c"));
+        assert_eq!(key.to_string(), "main@main.c");
+        assert_eq!(cache.grouping_key(0x1000), Some(key));
+
+        assert_eq!(cache.grouping_key(0x2000), None);
+    }
+
+    #[test]
+    fn test_lookup_inline_frames() {
+        let function = inlined_functions();
+
+        let mut converter = SymCacheConverter::new();
+        converter.process_symbolic_function(&function);
+        let mut buf = serialize(converter);
+        let cache = SymCache::parse(&buf).unwrap();
+
+        let frames: Vec<_> = cache
+            .lookup(0x100c)
+            .map(|sl| {
+                (
+                    sl.function().unwrap().name().unwrap(),
+                    sl.file().unwrap().full_path(),
+                    sl.line(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            frames,
+            [
+                ("helper", "/build/src/helper.h".to_owned(), 5),
+                ("main", "/build/src/main.c".to_owned(), 2),
+            ]
+        );
+
+        let ranges: Vec<_> = cache
+            .ranges()
+            .map(|(range, sl)| (range.start..range.end, sl.line()))
+            .collect();
+        assert_eq!(ranges, [(0x1000..0x1008, 1), (0x1008..0x1010, 5)]);
+
+        let files: Vec<_> = cache.files().map(|file| file.full_path()).collect();
+        assert_eq!(files, ["/build/src/main.c", "/build/src/helper.h"]);
+
+        let innermost = cache.lookup(0x100c).next().unwrap();
+        let caller = innermost.caller().unwrap();
+        assert_eq!(caller, cache.lookup(0x100c).nth(1).unwrap());
+        assert_eq!(caller.caller(), None);
+
+        // a corrupted file with an inline chain pointing at itself must not loop forever
+        let innermost = cache.explain(0x100c).inline_chain[0];
+        let offset = cache.source_locations.as_ptr() as usize - buf.as_ptr() as usize;
+        let field = offset + innermost as usize * std::mem::size_of::<raw::SourceLocation>() + 12;
+        buf[field..field + 4].copy_from_slice(&innermost.to_le_bytes());
+
+        let cache = SymCache::parse(&buf).unwrap();
+        assert_eq!(cache.lookup(0x100c).count(), cache.source_locations.len());
+    }
+
+    #[test]
+    fn test_declarations() {
+        let mut function = inlined_functions();
+        function.decl_file = Some(FileInfo {
+            name: b"main.h",
+            dir: b"/build/include",
+        });
+        function.decl_line = 1;
+        function.inlinees[0].decl_file = Some(FileInfo {
+            name: b"util.h",
+            dir: b"/build/include",
+        });
+        function.inlinees[0].decl_line = 3;
+
+        let write = |function: &Function<'_>, max_size| {
+            let mut converter = SymCacheConverter::new();
+            converter.process_symbolic_function(function);
+            assert!(converter.limit_size(max_size));
+            serialize(converter)
+        };
+
+        let buf = write(&inlined_functions(), u64::MAX);
+        let cache = SymCache::parse(&buf).unwrap();
+        let helper = cache.lookup(0x1008).next().unwrap().function().unwrap();
+        assert!(helper.decl_file().is_none());
+        assert_eq!(helper.decl_line(), 0);
+
+        let buf = write(&function, u64::MAX);
+        let cache = SymCache::parse(&buf).unwrap();
+        cache.validate().unwrap();
+        let frames: Vec<_> = cache.lookup(0x1008).collect();
+        let helper = frames[0].function().unwrap();
+        assert_eq!(helper.name(), Some("helper"));
+        assert_eq!(
+            helper.decl_file().unwrap().full_path(),
+            "/build/include/util.h"
+        );
+        assert_eq!(helper.decl_line(), 3);
+        let main = frames[1].function().unwrap();
+        assert_eq!(
+            main.decl_file().unwrap().full_path(),
+            "/build/include/main.h"
+        );
+        assert_eq!(main.decl_line(), 1);
+
+        // dropping the inline frames compacts the converter, which keeps declared files even if no
+        // line refers to them
+        let compact = write(&function, buf.len() as u64 - 1);
+        let cache = SymCache::parse(&compact).unwrap();
+        cache.validate().unwrap();
+        let main = cache.lookup(0x1008).next().unwrap().function().unwrap();
+        assert_eq!(main.name(), Some("main"));
+        assert_eq!(
+            main.decl_file().unwrap().full_path(),
+            "/build/include/main.h"
+        );
+
+        // the declarations consist of `u32`s, which are swapped individually
+        let mut foreign = buf.clone();
+        endian::swap_in_place(&mut foreign, false).unwrap();
+        assert_eq!(swap_endianness(&foreign).unwrap(), buf);
+    }
+}
@@ -8,10 +8,20 @@
 //! 3. Source Locations
 //! 4. Address Ranges
 //! 5. String Data
+//! 6. Section Table
+//! 7. Extension Sections, such as range provenance, source location columns, and function linkage
+//!    names
+//!
+//! The header and each of the sections are padded to a common section alignment, which is recorded
+//! in the header. It defaults to 8 bytes, but can be raised, for example to page boundaries.
+//!
+//! All integers are stored in little-endian. SymCaches written by earlier versions on big-endian
+//! systems are rejected when parsing, and can be converted with [`swap_endianness`].
 //!
 //! The format uses `u32`s to represent line numbers, addresses, references, and string offsets.
 //! Line numbers use `0` to represent an unknown or invalid value. Addresses, references, and string
-//! offsets instead use `u32::MAX`.
+//! offsets instead use `u32::MAX`. Line numbers that are too large to be represented, including
+//! `u32::MAX` itself, are stored as `0`.
 //!
 //! Strings are saved in one contiguous section with each individual string prefixed by 4 bytes
 //! denoting its length. Functions and files refer to strings by an offset into this string section,
@@ -28,7 +38,43 @@
 //!
 //! ## Address Ranges
 //!
-//! Ranges are saved as a contiguous list of `u32`s, representing their starting addresses.
+//! Ranges are saved as a contiguous list of `u32`s, representing their starting addresses. If any
+//! address does not fit into 32 bits, an extension section holds the upper 32 bits of every range,
+//! and another one those of every function's entry address.
+//!
+//! ## Section Table
+//!
+//! The string data is followed by a table of extension sections, whose length is recorded in the
+//! header. Each entry records the kind of a section, the size of its elements, and its location.
+//! Readers skip sections of unknown kinds, so that new data can be added without breaking them.
+//! SymCaches written before the table was introduced have no extension sections.
+//!
+//! ## Range Provenance
+//!
+//! An extension section with one byte per address range, which records the kind of input that
+//! produced the range, such as debug information or a symbol table.
+//!
+//! ## Range Candidates
+//!
+//! An optional extension section that records alternative attributions of ranges, which were
+//! dropped when multiple inputs attributed the same address differently. Each candidate refers to
+//! a range, the source location it resolves to, and the kind of input that produced it.
+//!
+//! ## Segments
+//!
+//! An optional extension section that lists the code segments of images which map their code in
+//! multiple segments, each with its own slide at runtime. Each segment records its name and the
+//! addresses it covers, so that offsets into a segment can be resolved.
+//!
+//! ## Source Location Columns
+//!
+//! An extension section with one `u32` per source location, which records its column number. Like
+//! line numbers, columns use `0` to represent an unknown value.
+//!
+//! ## Function Linkage Names
+//!
+//! An extension section with one string offset per function, which refers to its linkage name in
+//! case the function name was demangled during conversion.
 //!
 //! ## Source Locations
 //!
@@ -91,23 +137,61 @@
 //!    the root [`lookup::SourceLocation`].
 //!
 //! The returned source locations contain accessor methods for their function, file, and line number.
-use std::convert::TryInto;
-use std::{mem, ptr};
+use std::convert::{TryFrom, TryInto};
+use std::mem;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 
-use symbolic_common::{Arch, DebugId};
+use symbolic_common::{Arch, AsSelf, DebugId};
+use zerocopy::{FromBytes, LayoutVerified};
 
+mod analysis;
+mod calls;
+mod candidates;
 mod compat;
+mod diff;
+mod dump;
+mod endian;
 mod error;
+mod gopclntab;
+mod gsym;
+mod jit;
 mod lookup;
+mod multi;
+mod names;
+mod owned;
+mod ppdb;
 pub(crate) mod raw;
+mod segments;
+#[cfg(feature = "serde")]
+mod serialize;
+mod strings;
+#[cfg(test)]
+mod testutils;
 pub mod transform;
+mod validate;
 mod writer;
 
-pub use compat::*;
+pub use analysis::{FileSize, FunctionRanges, FunctionSize, SectionSize};
+pub use calls::CallEdge;
+pub use candidates::Candidate;
+pub use compat::{Files, Functions, SymCacheWriter};
+pub use diff::{DiffFrame, DiffReport, RangeDiff};
+pub use endian::swap_endianness;
 pub use error::Error;
-pub use lookup::*;
+pub use lookup::{
+    AddrRange, ExplainedRange, Explanation, File, Function, GroupingKey, Provenance, RangeFlags,
+    RangeIter, SourceLocation, SourceLocationIter,
+};
+pub use multi::{Module, MultiSymCache, MultiSymCacheWriter};
+pub use owned::OwnedSymCache;
+pub use segments::Segment;
+pub use writer::{
+    ConversionError, ConversionErrorKind, ErrorSink, FunctionIdentity, Severity, Stats, UnitStats,
+    DEFAULT_MAX_INLINE_DEPTH,
+};
 
-use raw::align_to_eight;
+use raw::align_to;
 
 type Result<T, E = Error> = std::result::Result<T, E>;
 
@@ -123,107 +207,287 @@ pub struct SymCache<'data> {
     source_locations: &'data [raw::SourceLocation],
     ranges: &'data [raw::Range],
     string_bytes: &'data [u8],
+    sections: &'data [raw::Section],
+    provenance: &'data [u8],
+    range_flags: &'data [u8],
+    columns: &'data [raw::U32],
+    candidates: &'data [raw::Candidate],
+    linkage_names: &'data [raw::U32],
+    ranges_high: &'data [raw::U32],
+    entry_pcs_high: &'data [raw::U32],
+    name_index: &'data [raw::U32],
+    call_edges: &'data [raw::CallEdge],
+    segments: &'data [raw::Segment],
+    declarations: &'data [raw::Declaration],
+    image_base: u64,
+    payload: &'data [u8],
+    bad_strings: BadStrings,
 }
 
 impl<'data> std::fmt::Debug for SymCache<'data> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("SymCache")
-            .field("version", &self.header.version)
-            .field("debug_id", &self.header.debug_id)
-            .field("arch", &self.header.arch)
-            .field("files", &self.header.num_files)
-            .field("functions", &self.header.num_functions)
-            .field("source_locations", &self.header.num_source_locations)
-            .field("ranges", &self.header.num_ranges)
-            .field("string_bytes", &self.header.string_bytes)
+            .field("version", &self.version())
+            .field("debug_id", &self.debug_id())
+            .field("arch", &self.arch())
+            .field("files", &self.files.len())
+            .field("functions", &self.functions.len())
+            .field("source_locations", &self.source_locations.len())
+            .field("ranges", &self.ranges.len())
+            .field("string_bytes", &self.string_bytes.len())
             .finish()
     }
 }
 
+impl<'slf, 'd: 'slf> AsSelf<'slf> for SymCache<'d> {
+    type Ref = SymCache<'slf>;
+
+    fn as_self(&'slf self) -> &'slf Self::Ref {
+        self
+    }
+}
+
+// A parsed SymCache and all of its lookup types only borrow immutable data, and are thus
+// `Send + Sync`. This allows to share a single SymCache between threads, e.g. by putting a
+// `SelfCell<ByteView, SymCache>` into an `Arc`. This fails to compile if that guarantee breaks.
+fn _assert_send_sync() {
+    fn assert<T: Send + Sync>() {}
+    assert::<SymCache<'_>>();
+    assert::<SourceLocation<'_, '_>>();
+    assert::<SourceLocationIter<'_, '_>>();
+    assert::<File<'_>>();
+    assert::<Function<'_>>();
+    assert::<Functions<'_>>();
+    assert::<Files<'_>>();
+    assert::<GroupingKey<'_>>();
+    assert::<Provenance>();
+    assert::<RangeIter<'_, '_>>();
+    assert::<Explanation>();
+    assert::<FunctionSize<'_>>();
+    assert::<FileSize<'_>>();
+    assert::<SectionSize>();
+    assert::<FunctionRanges<'_>>();
+    assert::<CallEdge<'_>>();
+    assert::<Candidate<'_, '_>>();
+    assert::<Segment<'_>>();
+    assert::<OwnedSymCache>();
+    assert::<MultiSymCache<'_>>();
+    assert::<Module<'_>>();
+}
+
 impl<'data> SymCache<'data> {
     /// Parse the SymCache binary format into a convenient type that allows safe access and allows
     /// fast lookups.
     ///
-    /// See the [raw module](raw) for an explanation of the binary format.
+    /// See the [module level documentation](self) for an explanation of the binary format.
+    ///
+    /// The buffer may have any alignment, since all data is read byte-wise.
     pub fn parse(buf: &'data [u8]) -> Result<Self> {
-        if align_to_eight(buf.as_ptr() as usize) != 0 {
-            return Err(Error::BufferNotAligned);
-        }
-
-        let mut header_size = mem::size_of::<raw::Header>();
-        header_size += align_to_eight(header_size);
-
-        if buf.len() < header_size {
-            return Err(Error::HeaderTooSmall);
-        }
-        // SAFETY: we checked that the buffer is well aligned and large enough to fit a `raw::Header`.
-        let header = unsafe { &*(buf.as_ptr() as *const raw::Header) };
-        if header.magic == raw::SYMCACHE_MAGIC_FLIPPED {
+        let header = LayoutVerified::<_, raw::Header>::new_from_prefix(buf)
+            .ok_or(Error::HeaderTooSmall)?
+            .0
+            .into_ref();
+        if header.magic.get() == raw::SYMCACHE_MAGIC_FLIPPED {
             return Err(Error::WrongEndianness);
         }
-        if header.magic != raw::SYMCACHE_MAGIC {
+        if header.magic.get() != raw::SYMCACHE_MAGIC {
+            return Err(Error::WrongFormat);
+        }
+        let arch = header.arch.get();
+        if Arch::from_u32(arch) as u32 != arch {
             return Err(Error::WrongFormat);
         }
-        if header.version != raw::SYMCACHE_VERSION {
+        if !(raw::SYMCACHE_VERSION_MIN..=raw::SYMCACHE_VERSION).contains(&header.version.get()) {
             return Err(Error::WrongVersion);
         }
 
-        let mut files_size = mem::size_of::<raw::File>() * header.num_files as usize;
-        files_size += align_to_eight(files_size);
+        let alignment = match header.section_alignment.get() {
+            0 => raw::DEFAULT_SECTION_ALIGNMENT as usize,
+            alignment if alignment.is_power_of_two() && alignment >= 8 => alignment as usize,
+            _ => return Err(Error::BadSectionAlignment),
+        };
 
-        let mut functions_size = mem::size_of::<raw::Function>() * header.num_functions as usize;
-        functions_size += align_to_eight(functions_size);
+        let mut header_size = mem::size_of::<raw::Header>();
+        header_size += align_to(header_size, alignment);
 
-        let mut source_locations_size =
-            mem::size_of::<raw::SourceLocation>() * header.num_source_locations as usize;
-        source_locations_size += align_to_eight(source_locations_size);
+        if buf.len() < header_size {
+            return Err(Error::HeaderTooSmall);
+        }
+
+        // every section is padded to the alignment, and none of this may overflow on 32-bit targets
+        let section_size = |size: usize, count: u32| {
+            let size = size.checked_mul(count as usize)?;
+            size.checked_add(align_to(size, alignment))
+        };
 
-        let mut ranges_size = mem::size_of::<raw::Range>() * header.num_ranges as usize;
-        ranges_size += align_to_eight(ranges_size);
+        let sizes = (
+            section_size(mem::size_of::<raw::File>(), header.num_files.get()),
+            section_size(mem::size_of::<raw::Function>(), header.num_functions.get()),
+            section_size(
+                mem::size_of::<raw::SourceLocation>(),
+                header.num_source_locations.get(),
+            ),
+            section_size(mem::size_of::<raw::Range>(), header.num_ranges.get()),
+        );
+        let (files_size, functions_size, source_locations_size, ranges_size) = match sizes {
+            (Some(files), Some(functions), Some(source_locations), Some(ranges)) => {
+                (files, functions, source_locations, ranges)
+            }
+            _ => return Err(Error::BadFormatLength),
+        };
 
-        let expected_buf_size = header_size
-            + files_size
-            + functions_size
-            + source_locations_size
-            + ranges_size
-            + header.string_bytes as usize;
+        let expected_buf_size = [
+            files_size,
+            functions_size,
+            source_locations_size,
+            ranges_size,
+            header.string_bytes.get() as usize,
+        ]
+        .iter()
+        .try_fold(header_size, |total, &size| total.checked_add(size))
+        .ok_or(Error::BadFormatLength)?;
 
-        if buf.len() < expected_buf_size || source_locations_size < ranges_size {
+        // every range corresponds to one of the trailing source locations
+        if buf.len() < expected_buf_size
+            || header.num_source_locations.get() < header.num_ranges.get()
+        {
             return Err(Error::BadFormatLength);
         }
 
-        // SAFETY: we just made sure that all the pointers we are constructing via pointer
-        // arithmetic are within `buf`
-        let files_start = unsafe { buf.as_ptr().add(header_size) };
-        let functions_start = unsafe { files_start.add(files_size) };
-        let source_locations_start = unsafe { functions_start.add(functions_size) };
-        let ranges_start = unsafe { source_locations_start.add(source_locations_size) };
-        let string_bytes_start = unsafe { ranges_start.add(ranges_size) };
-
-        // SAFETY: the above buffer size check also made sure we are not going out of bounds
-        // here
-        let files = unsafe {
-            &*(ptr::slice_from_raw_parts(files_start, header.num_files as usize)
-                as *const [raw::File])
-        };
-        let functions = unsafe {
-            &*(ptr::slice_from_raw_parts(functions_start, header.num_functions as usize)
-                as *const [raw::Function])
-        };
-        let source_locations = unsafe {
-            &*(ptr::slice_from_raw_parts(
-                source_locations_start,
-                header.num_source_locations as usize,
-            ) as *const [raw::SourceLocation])
+        let files_start = header_size;
+        let functions_start = files_start + files_size;
+        let source_locations_start = functions_start + functions_size;
+        let ranges_start = source_locations_start + source_locations_size;
+        let string_bytes_start = ranges_start + ranges_size;
+
+        let files = read_slice(buf, files_start, header.num_files.get() as usize)?;
+        let functions = read_slice(buf, functions_start, header.num_functions.get() as usize)?;
+        let source_locations = read_slice(
+            buf,
+            source_locations_start,
+            header.num_source_locations.get() as usize,
+        )?;
+        let ranges = read_slice(buf, ranges_start, header.num_ranges.get() as usize)?;
+        let string_bytes = &buf[string_bytes_start..][..header.string_bytes.get() as usize];
+
+        // the section table follows the string data, and describes all extension sections
+        let mut payload_end = expected_buf_size;
+        let sections = if header.num_sections.get() == 0 {
+            &[]
+        } else {
+            let table_start = expected_buf_size + align_to(expected_buf_size, alignment);
+            let table =
+                read_slice::<raw::Section>(buf, table_start, header.num_sections.get() as usize)?;
+            payload_end = table_start + mem::size_of_val(table);
+            table
         };
-        let ranges = unsafe {
-            &*(ptr::slice_from_raw_parts(ranges_start, header.num_ranges as usize)
-                as *const [raw::Range])
+
+        let table_end = payload_end;
+        for section in sections {
+            let start =
+                usize::try_from(section.offset.get()).map_err(|_| Error::BadFormatLength)?;
+            let end = usize::try_from(section.len.get())
+                .ok()
+                .and_then(|len| start.checked_add(len))
+                .ok_or(Error::BadFormatLength)?;
+            if start < table_end || end > buf.len() || align_to(start, alignment) != 0 {
+                return Err(Error::BadFormatLength);
+            }
+            payload_end = payload_end.max(end);
+        }
+
+        // sections of unknown kinds are skipped, and missing sections are empty
+        let extension_section = |kind: u32, element_size: usize, count: u32| {
+            let section = match sections.iter().find(|section| section.kind.get() == kind) {
+                Some(section) => section,
+                None => return Ok(&buf[..0]),
+            };
+            let section_len = section.len.get() as usize;
+            let len = element_size.checked_mul(count as usize);
+            if section.element_size.get() as usize != element_size || Some(section_len) != len {
+                return Err(Error::BadFormatLength);
+            }
+            Ok(&buf[section.offset.get() as usize..][..section_len])
         };
-        let string_bytes = unsafe {
-            &*(ptr::slice_from_raw_parts(string_bytes_start, header.string_bytes as usize)
-                as *const [u8])
+
+        let u32_size = mem::size_of::<u32>();
+        let provenance = extension_section(raw::SECTION_PROVENANCE, 1, header.num_ranges.get())?;
+        let range_flags = extension_section(raw::SECTION_RANGE_FLAGS, 1, header.num_ranges.get())?;
+        let columns = extension_section(
+            raw::SECTION_COLUMNS,
+            u32_size,
+            header.num_source_locations.get(),
+        )?;
+        let linkage_names = extension_section(
+            raw::SECTION_LINKAGE_NAMES,
+            u32_size,
+            header.num_functions.get(),
+        )?;
+        let ranges_high =
+            extension_section(raw::SECTION_RANGES_HIGH, u32_size, header.num_ranges.get())?;
+        let entry_pcs_high = extension_section(
+            raw::SECTION_ENTRY_PCS_HIGH,
+            u32_size,
+            header.num_functions.get(),
+        )?;
+        let name_index = extension_section(
+            raw::SECTION_NAME_INDEX,
+            u32_size,
+            header.num_functions.get(),
+        )?;
+        let declarations = extension_section(
+            raw::SECTION_DECLARATIONS,
+            u32_size,
+            header.num_functions.get().saturating_mul(2),
+        )?;
+        let image_base = extension_section(raw::SECTION_IMAGE_BASE, mem::size_of::<u64>(), 1)?
+            .try_into()
+            .map_or(0, u64::from_le_bytes);
+
+        let columns = read_slice(columns, 0, columns.len() / u32_size)?;
+        let linkage_names = read_slice(linkage_names, 0, linkage_names.len() / u32_size)?;
+        let ranges_high = read_slice(ranges_high, 0, ranges_high.len() / u32_size)?;
+        let entry_pcs_high = read_slice(entry_pcs_high, 0, entry_pcs_high.len() / u32_size)?;
+        let name_index = read_slice(name_index, 0, name_index.len() / u32_size)?;
+        let declaration_size = mem::size_of::<raw::Declaration>();
+        let declarations = read_slice(declarations, 0, declarations.len() / declaration_size)?;
+
+        // the number of call edges, candidates and segments is only recorded by the length of
+        // their section
+        let record_section = |kind: u32, element_size: usize, record_size: usize| match sections
+            .iter()
+            .find(|section| section.kind.get() == kind)
+        {
+            Some(section) => {
+                let len = section.len.get() as usize;
+                if section.element_size.get() as usize != element_size || len % record_size != 0 {
+                    return Err(Error::BadFormatLength);
+                }
+                Ok((section.offset.get() as usize, len / record_size))
+            }
+            None => Ok((0, 0)),
         };
+        let (offset, count) = record_section(
+            raw::SECTION_CALL_EDGES,
+            u32_size,
+            mem::size_of::<raw::CallEdge>(),
+        )?;
+        let call_edges = read_slice(buf, offset, count)?;
+        let (offset, count) = record_section(
+            raw::SECTION_CANDIDATES,
+            u32_size,
+            mem::size_of::<raw::Candidate>(),
+        )?;
+        let candidates = read_slice(buf, offset, count)?;
+        let (offset, count) = record_section(
+            raw::SECTION_SEGMENTS,
+            mem::size_of::<u64>(),
+            mem::size_of::<raw::Segment>(),
+        )?;
+        let segments = read_slice(buf, offset, count)?;
+
+        // the checksum covers everything up to the end of the section table or the last section
+        let payload = &buf[header_size..payload_end];
 
         Ok(SymCache {
             header,
@@ -232,42 +496,405 @@ impl<'data> SymCache<'data> {
             source_locations,
             ranges,
             string_bytes,
+            sections,
+            provenance,
+            candidates,
+            range_flags,
+            columns,
+            linkage_names,
+            ranges_high,
+            entry_pcs_high,
+            name_index,
+            call_edges,
+            segments,
+            declarations,
+            image_base,
+            payload,
+            bad_strings: BadStrings::default(),
         })
     }
 
-    /// Resolves a string reference to the pointed-to `&str` data.
-    fn get_string(&self, offset: u32) -> Option<&'data str> {
-        if offset == u32::MAX {
-            return None;
+    /// Parses the SymCache like [`parse`](Self::parse) and [verifies](Self::verify) its checksum.
+    pub fn parse_verified(buf: &'data [u8]) -> Result<Self> {
+        let symcache = Self::parse(buf)?;
+        symcache.verify()?;
+        Ok(symcache)
+    }
+
+    /// Verifies the checksum of this SymCache to detect truncated or corrupted files.
+    ///
+    /// This reads the entire SymCache once. Returns [`Error::BadChecksum`] if the contents do not
+    /// match the checksum. SymCaches written without a checksum always pass.
+    pub fn verify(&self) -> Result<()> {
+        if self.header.flags.get() & raw::FLAG_CHECKSUM == 0 {
+            return Ok(());
+        }
+        if crc32fast::hash(self.payload) != self.header.checksum.get() {
+            return Err(Error::BadChecksum);
         }
-        let len_offset = offset as usize;
-        let len_size = std::mem::size_of::<u32>();
-        let len = u32::from_ne_bytes(
-            self.string_bytes
-                .get(len_offset..len_offset + len_size)?
-                .try_into()
-                .unwrap(),
-        ) as usize;
-
-        let start_offset = len_offset + len_size;
-        let end_offset = start_offset + len;
-        let bytes = self.string_bytes.get(start_offset..end_offset)?;
-
-        std::str::from_utf8(bytes).ok()
+        Ok(())
+    }
+
+    /// Resolves the given string references to the pointed-to `&str` data.
+    ///
+    /// Yields `Ok(None)` for references that do not point to any string, and an
+    /// [`Error::BadStringReference`] for references that are out of bounds or point to invalid
+    /// UTF-8.
+    pub(crate) fn resolve_strings<'slf>(
+        &'slf self,
+        offsets: &'slf [u32],
+    ) -> impl Iterator<Item = Result<Option<&'data str>>> + 'slf {
+        let string_bytes = self.string_bytes;
+        offsets
+            .iter()
+            .map(move |&offset| read_string(string_bytes, offset))
+    }
+
+    /// Returns how many malformed string references lookups have resolved to missing strings.
+    ///
+    /// Lookups do not fail on malformed strings, so that the remaining information of a frame is
+    /// still available. The count is shared by all clones of this SymCache. Use
+    /// [`validate`](Self::validate) to reject such SymCaches up front instead.
+    pub fn bad_string_references(&self) -> usize {
+        self.bad_strings.0.load(Ordering::Relaxed)
     }
 
     /// The version of the SymCache file format.
+    ///
+    /// This can be any version between the oldest supported one and
+    /// [`SYMCACHE_VERSION`](crate::SYMCACHE_VERSION), as older caches remain readable.
     pub fn version(&self) -> u32 {
-        self.header.version
+        self.header.version.get()
+    }
+
+    /// The alignment of the header and each section in bytes.
+    ///
+    /// SymCaches that do not record an alignment use the default of `8` bytes.
+    pub fn section_alignment(&self) -> u32 {
+        match self.header.section_alignment.get() {
+            0 => raw::DEFAULT_SECTION_ALIGNMENT,
+            alignment => alignment,
+        }
     }
 
     /// The architecture of the symbol file.
     pub fn arch(&self) -> Arch {
-        self.header.arch
+        Arch::from_u32(self.header.arch.get())
     }
 
     /// The debug identifier of the cache file.
     pub fn debug_id(&self) -> DebugId {
-        self.header.debug_id
+        self.header.debug_id.into()
+    }
+
+    /// The image base that was subtracted from all addresses when writing the SymCache.
+    ///
+    /// Lookups use addresses relative to this base. Returns `0` if no image base was set, in which
+    /// case addresses are used as they were processed.
+    pub fn image_base(&self) -> u64 {
+        self.image_base
+    }
+}
+
+/// Reinterprets `len` elements of type `T` at `offset` in the given buffer.
+///
+/// Returns [`Error::BadFormatLength`] if the elements are out of bounds or not properly aligned.
+fn read_slice<T: FromBytes>(buf: &[u8], offset: usize, len: usize) -> Result<&[T]> {
+    let bytes = mem::size_of::<T>()
+        .checked_mul(len)
+        .and_then(|size| buf.get(offset..)?.get(..size))
+        .ok_or(Error::BadFormatLength)?;
+    LayoutVerified::new_slice(bytes)
+        .map(LayoutVerified::into_slice)
+        .ok_or(Error::BadFormatLength)
+}
+
+/// Counts the malformed string references that lookups resolved to missing strings, see
+/// [`SymCache::bad_string_references`].
+///
+/// The count is not part of the contents of a SymCache, so all counters compare equal.
+#[derive(Clone, Default)]
+struct BadStrings(Arc<AtomicUsize>);
+
+impl BadStrings {
+    fn increment(&self) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+impl PartialEq for BadStrings {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for BadStrings {}
+
+/// Reads the length-prefixed string at `offset` from the given string section.
+fn read_string(string_bytes: &[u8], offset: u32) -> Result<Option<&str>> {
+    if offset == u32::MAX {
+        return Ok(None);
+    }
+    let len_offset = offset as usize;
+    let len_size = mem::size_of::<u32>();
+    let start_offset = len_offset
+        .checked_add(len_size)
+        .ok_or(Error::BadStringReference)?;
+    let len = string_bytes
+        .get(len_offset..start_offset)
+        .ok_or(Error::BadStringReference)?;
+    let len = u32::from_le_bytes(len.try_into().unwrap()) as usize;
+
+    let end_offset = start_offset
+        .checked_add(len)
+        .ok_or(Error::BadStringReference)?;
+    let bytes = string_bytes
+        .get(start_offset..end_offset)
+        .ok_or(Error::BadStringReference)?;
+
+    std::str::from_utf8(bytes)
+        .map(Some)
+        .map_err(|_| Error::BadStringReference)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::testutils::{header_mut, inlined_functions, serialize, slice_mut, symbol_cache};
+    use super::writer::SymCacheConverter;
+    use super::*;
+
+    #[test]
+    fn test_version_negotiation() {
+        let mut buf = symbol_cache();
+        assert_eq!(
+            SymCache::parse(&buf).unwrap().version(),
+            raw::SYMCACHE_VERSION
+        );
+
+        buf[4..8].copy_from_slice(&raw::SYMCACHE_VERSION_MIN.to_le_bytes());
+        assert_eq!(
+            SymCache::parse(&buf).unwrap().version(),
+            raw::SYMCACHE_VERSION_MIN
+        );
+
+        buf[4..8].copy_from_slice(&(raw::SYMCACHE_VERSION_MIN - 1).to_le_bytes());
+        assert!(matches!(SymCache::parse(&buf), Err(Error::WrongVersion)));
+
+        buf[4..8].copy_from_slice(&(raw::SYMCACHE_VERSION + 1).to_le_bytes());
+        assert!(matches!(SymCache::parse(&buf), Err(Error::WrongVersion)));
+    }
+
+    #[test]
+    fn test_bad_magic() {
+        let mut buf = symbol_cache();
+        buf[0..4].copy_from_slice(&raw::SYMCACHE_MAGIC_FLIPPED.to_le_bytes());
+        assert!(matches!(SymCache::parse(&buf), Err(Error::WrongEndianness)));
+
+        buf[0..4].copy_from_slice(b"ABCD");
+        assert!(matches!(SymCache::parse(&buf), Err(Error::WrongFormat)));
+    }
+
+    #[test]
+    fn test_bad_arch() {
+        let mut buf = symbol_cache();
+        // the architecture follows the magic, version and debug identifier
+        buf[40..44].copy_from_slice(&0xdead_beef_u32.to_le_bytes());
+        assert!(matches!(SymCache::parse(&buf), Err(Error::WrongFormat)));
+
+        buf[40..44].copy_from_slice(&(Arch::Arm64 as u32).to_le_bytes());
+        assert_eq!(SymCache::parse(&buf).unwrap().arch(), Arch::Arm64);
+    }
+
+    #[test]
+    fn test_unaligned_buffer() {
+        let mut shifted = vec![0];
+        shifted.extend_from_slice(&symbol_cache());
+        let cache = SymCache::parse(&shifted[1..]).unwrap();
+        let sl = cache.lookup(0x1008).next().unwrap();
+        assert_eq!(sl.function().unwrap().name(), Some("main"));
+    }
+
+    /// Parses a copy of `buf` after applying `patch` to its header.
+    fn parse_patched(buf: &[u8], patch: impl FnOnce(&mut raw::Header)) -> Result<()> {
+        let mut buf = buf.to_vec();
+        patch(header_mut(&mut buf));
+        SymCache::parse(&buf).map(|_| ())
+    }
+
+    #[test]
+    fn test_malformed() {
+        let buf = symbol_cache();
+        assert!(parse_patched(&buf, |_| ()).is_ok());
+
+        let truncated = &buf[..10];
+        assert!(matches!(
+            SymCache::parse(truncated),
+            Err(Error::HeaderTooSmall)
+        ));
+
+        let header_only = &buf[..mem::size_of::<raw::Header>()];
+        assert!(matches!(
+            SymCache::parse(header_only),
+            Err(Error::BadFormatLength)
+        ));
+
+        // the provenance section is missing its last byte
+        let truncated = &buf[..buf.len() - 1];
+        assert!(matches!(
+            SymCache::parse(truncated),
+            Err(Error::BadFormatLength)
+        ));
+
+        type Corruption = fn(&mut raw::Header);
+        let cases: &[(&str, Corruption)] = &[
+            ("too many files", |h| h.num_files.set(h.num_files.get() + 1)),
+            ("too many string bytes", |h| {
+                h.string_bytes.set(h.string_bytes.get() + 1)
+            }),
+            ("overflowing files", |h| h.num_files.set(u32::MAX)),
+            ("overflowing ranges", |h| h.num_ranges.set(u32::MAX)),
+            ("overflowing string bytes", |h| h.string_bytes.set(u32::MAX)),
+            ("overflowing counts", |h| {
+                h.num_files.set(u32::MAX);
+                h.num_functions.set(u32::MAX);
+                h.num_source_locations.set(u32::MAX);
+                h.num_ranges.set(u32::MAX);
+                h.string_bytes.set(u32::MAX);
+            }),
+            ("fewer source locations than ranges", |h| {
+                h.num_source_locations.set(h.num_ranges.get() - 1)
+            }),
+        ];
+        for (name, patch) in cases {
+            let result = parse_patched(&buf, patch);
+            assert!(matches!(result, Err(Error::BadFormatLength)), "{}", name);
+        }
+
+        for alignment in [1, 4, 12, u32::MAX] {
+            let result = parse_patched(&buf, |h| h.section_alignment.set(alignment));
+            assert!(matches!(result, Err(Error::BadSectionAlignment)));
+        }
+
+        // caches without an alignment use the default
+        let mut unset = buf.clone();
+        header_mut(&mut unset).section_alignment.set(0);
+        let cache = SymCache::parse(&unset).unwrap();
+        assert_eq!(cache.section_alignment(), raw::DEFAULT_SECTION_ALIGNMENT);
+
+        // the name of `main` is not valid UTF-8
+        let cache = SymCache::parse(&buf).unwrap();
+        let name = cache.functions[0].name_offset.get() as usize + mem::size_of::<u32>();
+        let name = cache.string_bytes.as_ptr() as usize - buf.as_ptr() as usize + name;
+        let mut invalid = buf.clone();
+        invalid[name] = 0xff;
+        let cache = SymCache::parse(&invalid).unwrap();
+        let strings: Vec<_> = cache
+            .resolve_strings(&[cache.functions[0].name_offset.get()])
+            .collect();
+        assert!(matches!(strings[0], Err(Error::BadStringReference)));
+        assert_eq!(
+            cache
+                .lookup(0x1000)
+                .next()
+                .unwrap()
+                .function()
+                .unwrap()
+                .name(),
+            None
+        );
+        assert_eq!(cache.bad_string_references(), 1);
+    }
+
+    #[test]
+    fn test_checksum() {
+        let buf = symbol_cache();
+        let cache = SymCache::parse_verified(&buf).unwrap();
+        assert_ne!(cache.header.flags.get() & raw::FLAG_CHECKSUM, 0);
+
+        // the last byte of the provenance section, which parsing does not check
+        let mut corrupted = buf.clone();
+        *corrupted.last_mut().unwrap() ^= 1;
+        assert!(SymCache::parse(&corrupted).is_ok());
+        assert!(matches!(
+            SymCache::parse_verified(&corrupted),
+            Err(Error::BadChecksum)
+        ));
+
+        // trailing data is not covered
+        let mut trailing = buf.to_vec();
+        trailing.extend_from_slice(b"trailing");
+        assert!(SymCache::parse_verified(&trailing).is_ok());
+
+        // caches without a checksum always pass
+        let mut unchecked = corrupted.clone();
+        let header = header_mut(&mut unchecked);
+        header.flags.set(header.flags.get() & !raw::FLAG_CHECKSUM);
+        assert!(SymCache::parse_verified(&unchecked).is_ok());
+    }
+
+    #[test]
+    fn test_section_table() {
+        let mut converter = SymCacheConverter::new();
+        converter.process_symbolic_function(&inlined_functions());
+        let buf = serialize(converter);
+
+        let cache = SymCache::parse(&buf).unwrap();
+        let kinds: Vec<_> = cache
+            .sections
+            .iter()
+            .map(|section| section.kind.get())
+            .collect();
+        assert_eq!(kinds, [raw::SECTION_PROVENANCE]);
+        let table = cache.sections.as_ptr() as usize - buf.as_ptr() as usize;
+
+        /// Patches the first section table entry, and drops the checksum that no longer matches.
+        fn patched(buf: &[u8], table: usize, patch: impl FnOnce(&mut raw::Section)) -> Vec<u8> {
+            let mut buf = buf.to_vec();
+            let header = header_mut(&mut buf);
+            header.flags.set(header.flags.get() & !raw::FLAG_CHECKSUM);
+            patch(&mut slice_mut(&mut buf, table, 1)[0]);
+            buf
+        }
+
+        // sections of unknown kinds are skipped, but still converted to a different endianness
+        let unknown = patched(&buf, table, |section| {
+            section.kind.set(100);
+            section.element_size.set(4);
+            section.len.set(section.len.get() - section.len.get() % 4);
+        });
+        let cache = SymCache::parse(&unknown).unwrap();
+        assert_eq!(cache.provenance(0x1000), Some(Provenance::Unknown));
+        let mut foreign = unknown.clone();
+        endian::swap_in_place(&mut foreign, false).unwrap();
+        assert_eq!(swap_endianness(&foreign).unwrap(), unknown);
+
+        type Corruption = fn(&mut raw::Section);
+        let cases: &[(&str, Corruption)] = &[
+            ("wrong element size", |s| s.element_size.set(4)),
+            ("wrong length", |s| s.len.set(s.len.get() + 1)),
+            ("out of bounds", |s| s.offset.set(u64::MAX - 1)),
+            ("overlapping the table", |s| s.offset.set(0)),
+            ("unaligned", |s| s.offset.set(s.offset.get() + 1)),
+        ];
+        for (name, patch) in cases {
+            let patched = patched(&buf, table, patch);
+            let result = SymCache::parse(&patched);
+            assert!(matches!(result, Err(Error::BadFormatLength)), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_resolve_strings() {
+        let buf = symbol_cache();
+        let cache = SymCache::parse(&buf).unwrap();
+
+        let name_offset = cache.functions[0].name_offset.get();
+        let strings: Vec<_> = cache
+            .resolve_strings(&[name_offset, u32::MAX, cache.string_bytes.len() as u32])
+            .collect();
+
+        assert_eq!(strings[0].as_ref().unwrap(), &Some("main"));
+        assert_eq!(strings[1].as_ref().unwrap(), &None);
+        assert!(matches!(strings[2], Err(Error::BadStringReference)));
     }
 }
@@ -0,0 +1,355 @@
+//! Files holding the SymCaches of multiple modules.
+//!
+//! A build pipeline often produces the debug information of many modules, such as all libraries of
+//! an application, one after another. Instead of keeping every input in memory until all of them
+//! are available, each module is converted and appended to a [`MultiSymCacheWriter`] as soon as
+//! its inputs are complete. The module table is written once all modules have been appended.
+//!
+//! # Structure of the format
+//!
+//! 1. A [`MultiHeader`](raw::MultiHeader) identifying the file
+//! 2. The SymCaches of all modules, in the order they were appended, each padded to its section
+//!    alignment
+//! 3. The module table, sorted by debug identifier, with the location of each SymCache
+//! 4. A [`MultiTrailer`](raw::MultiTrailer) locating the module table
+//!
+//! Since the location of the module table is only known after all SymCaches were written, it is
+//! recorded at the end of the file. The file can therefore be streamed into any [`Write`].
+
+use std::convert::TryFrom;
+use std::io::{self, Read, Write};
+use std::mem;
+
+use symbolic_common::{Arch, DebugId};
+use symbolic_debuginfo::ObjectLike;
+use zerocopy::{AsBytes, LayoutVerified};
+
+use super::raw::{self, align_to};
+use super::{read_slice, Error, Result, SymCache, SymCacheWriter};
+use crate::{SymCacheError, SymCacheErrorKind};
+
+/// A file holding the SymCaches of multiple modules, see [`MultiSymCacheWriter`].
+///
+/// Modules are looked up by their debug identifier via [`get`](Self::get). Their SymCaches are
+/// only parsed when accessed.
+#[derive(Clone, Debug)]
+pub struct MultiSymCache<'data> {
+    buf: &'data [u8],
+    modules: &'data [raw::Module],
+}
+
+impl<'data> MultiSymCache<'data> {
+    /// Parses the header and module table of a file with multiple SymCaches.
+    pub fn parse(buf: &'data [u8]) -> Result<Self> {
+        let header = LayoutVerified::<_, raw::MultiHeader>::new_from_prefix(buf)
+            .ok_or(Error::HeaderTooSmall)?
+            .0
+            .into_ref();
+        if header.magic.get() != raw::MULTI_SYMCACHE_MAGIC {
+            return Err(Error::WrongFormat);
+        }
+        if header.version.get() != raw::MULTI_SYMCACHE_VERSION {
+            return Err(Error::WrongVersion);
+        }
+
+        let trailer = LayoutVerified::<_, raw::MultiTrailer>::new_from_suffix(buf)
+            .ok_or(Error::BadFormatLength)?
+            .1
+            .into_ref();
+        if trailer.magic.get() != raw::MULTI_SYMCACHE_MAGIC {
+            return Err(Error::BadFormatLength);
+        }
+
+        let table_start =
+            usize::try_from(trailer.modules_offset.get()).map_err(|_| Error::BadFormatLength)?;
+        let table_end = buf.len() - mem::size_of::<raw::MultiTrailer>();
+        let modules: &[raw::Module] =
+            read_slice(buf, table_start, trailer.num_modules.get() as usize)?;
+        if table_start + mem::size_of_val(modules) > table_end {
+            return Err(Error::BadFormatLength);
+        }
+
+        // every SymCache is located between the header and the module table
+        for module in modules {
+            let start = usize::try_from(module.offset.get()).map_err(|_| Error::BadFormatLength)?;
+            let end = usize::try_from(module.len.get())
+                .ok()
+                .and_then(|len| start.checked_add(len))
+                .ok_or(Error::BadFormatLength)?;
+            if start < mem::size_of::<raw::MultiHeader>()
+                || end > table_start
+                || raw::align_to_eight(start) != 0
+            {
+                return Err(Error::BadFormatLength);
+            }
+        }
+
+        Ok(Self { buf, modules })
+    }
+
+    /// Returns the number of modules in this file.
+    pub fn len(&self) -> usize {
+        self.modules.len()
+    }
+
+    /// Returns `true` if this file does not contain any modules.
+    pub fn is_empty(&self) -> bool {
+        self.modules.is_empty()
+    }
+
+    /// Returns all modules in this file, sorted by their debug identifier.
+    pub fn modules(&self) -> Vec<Module<'data>> {
+        self.modules
+            .iter()
+            .map(|module| self.get_module(module))
+            .collect()
+    }
+
+    /// Returns the module with the given debug identifier, if any.
+    pub fn get(&self, debug_id: DebugId) -> Option<Module<'data>> {
+        let idx = self
+            .modules
+            .binary_search_by_key(&debug_id, |module| module.debug_id.into())
+            .ok()?;
+        Some(self.get_module(&self.modules[idx]))
+    }
+
+    /// Resolves the SymCache of a raw module.
+    fn get_module(&self, module: &raw::Module) -> Module<'data> {
+        // the bounds have been checked while parsing
+        let data = &self.buf[module.offset.get() as usize..][..module.len.get() as usize];
+        Module {
+            debug_id: module.debug_id.into(),
+            arch: Arch::from_u32(module.arch.get()),
+            data,
+        }
+    }
+}
+
+/// A module in a [`MultiSymCache`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Module<'data> {
+    debug_id: DebugId,
+    arch: Arch,
+    data: &'data [u8],
+}
+
+impl<'data> Module<'data> {
+    /// The debug identifier of the module.
+    pub fn debug_id(&self) -> DebugId {
+        self.debug_id
+    }
+
+    /// The CPU architecture of the module.
+    pub fn arch(&self) -> Arch {
+        self.arch
+    }
+
+    /// The raw bytes of the module's SymCache.
+    pub fn data(&self) -> &'data [u8] {
+        self.data
+    }
+
+    /// Parses the SymCache of the module.
+    pub fn symcache(&self) -> Result<SymCache<'data>> {
+        SymCache::parse(self.data)
+    }
+}
+
+/// A writer that collects the SymCaches of multiple modules in a single file.
+///
+/// The SymCache of each module is written as soon as it is [appended](Self::append), so that its
+/// inputs and converted data can be dropped before the next module is processed. Call
+/// [`finish`](Self::finish) after the last module to write the module table. The file is read with
+/// [`MultiSymCache`].
+///
+/// # Example
+///
+/// ```no_run
+/// use std::io::{self, Cursor};
+///
+/// use symbolic_common::ByteView;
+/// use symbolic_debuginfo::Object;
+/// use symbolic_symcache::{MultiSymCacheWriter, SymCacheWriter};
+///
+/// let mut writer = MultiSymCacheWriter::new(Cursor::new(Vec::new())).unwrap();
+/// for (binary, debug_file) in [("libfoo.so", "libfoo.debug"), ("libbar.so", "libbar.debug")] {
+///     let mut module = SymCacheWriter::new(io::sink()).unwrap();
+///     for path in [binary, debug_file] {
+///         let view = ByteView::open(path).unwrap();
+///         module.process_object(&Object::parse(&view).unwrap()).unwrap();
+///     }
+///     writer.append(module).unwrap();
+/// }
+/// let buffer = writer.finish().unwrap().into_inner();
+/// ```
+pub struct MultiSymCacheWriter<W> {
+    writer: W,
+    position: u64,
+    modules: Vec<raw::Module>,
+}
+
+impl<W> MultiSymCacheWriter<W>
+where
+    W: Write,
+{
+    /// Constructs a new `MultiSymCacheWriter` and writes the header.
+    pub fn new(mut writer: W) -> Result<Self, SymCacheError> {
+        let header = raw::MultiHeader {
+            magic: raw::MULTI_SYMCACHE_MAGIC.into(),
+            version: raw::MULTI_SYMCACHE_VERSION.into(),
+        };
+        writer
+            .write_all(header.as_bytes())
+            .map_err(|err| SymCacheError::new(SymCacheErrorKind::WriteFailed, err))?;
+
+        Ok(Self {
+            writer,
+            position: mem::size_of::<raw::MultiHeader>() as u64,
+            modules: Vec::new(),
+        })
+    }
+
+    /// Writes the SymCache of a module, and drops its data.
+    ///
+    /// The module is identified by the debug identifier and architecture set on `module`. Its
+    /// target writer is not written to. Returns an error of kind
+    /// [`DuplicateModule`](SymCacheErrorKind::DuplicateModule) if a module with the same debug
+    /// identifier was appended before.
+    pub fn append<V: Write>(&mut self, module: SymCacheWriter<V>) -> Result<(), SymCacheError> {
+        let converter = module.into_converter();
+        let debug_id = converter.debug_id();
+        if self
+            .modules
+            .iter()
+            .any(|module| DebugId::from(module.debug_id) == debug_id)
+        {
+            return Err(SymCacheErrorKind::DuplicateModule.into());
+        }
+
+        let arch = converter.arch();
+        self.align(converter.section_alignment())?;
+        let offset = self.position;
+        let len = converter
+            .serialize(&mut self.writer)
+            .map_err(|err| SymCacheError::new(SymCacheErrorKind::WriteFailed, err))?;
+        self.position += len;
+
+        self.modules.push(raw::Module {
+            debug_id: debug_id.into(),
+            arch: (arch as u32).into(),
+            _reserved: 0.into(),
+            offset: offset.into(),
+            len: len.into(),
+        });
+
+        Ok(())
+    }
+
+    /// Converts an entire object and writes it as a module.
+    ///
+    /// This is a shortcut for [`SymCacheWriter::process_object`] followed by
+    /// [`append`](Self::append).
+    pub fn append_object<'d, 'o, O>(&mut self, object: &'o O) -> Result<(), SymCacheError>
+    where
+        O: ObjectLike<'d, 'o>,
+        O::Error: std::error::Error + Send + Sync + 'static,
+    {
+        let mut module = SymCacheWriter::new(io::sink())?;
+        module.process_object(object)?;
+        self.append(module)
+    }
+
+    /// Writes the module table and the trailer.
+    pub fn finish(mut self) -> Result<W, SymCacheError> {
+        self.align(raw::DEFAULT_SECTION_ALIGNMENT)?;
+
+        let mut modules = mem::take(&mut self.modules);
+        modules.sort_by_key(|module| DebugId::from(module.debug_id));
+        let trailer = raw::MultiTrailer {
+            modules_offset: self.position.into(),
+            num_modules: (modules.len() as u32).into(),
+            magic: raw::MULTI_SYMCACHE_MAGIC.into(),
+        };
+
+        self.writer
+            .write_all(modules.as_bytes())
+            .and_then(|_| self.writer.write_all(trailer.as_bytes()))
+            .map_err(|err| SymCacheError::new(SymCacheErrorKind::WriteFailed, err))?;
+
+        Ok(self.writer)
+    }
+
+    /// Pads the output with zeros to the given alignment.
+    fn align(&mut self, alignment: u32) -> Result<(), SymCacheError> {
+        let len = align_to(self.position as usize, alignment as usize);
+        io::copy(&mut io::repeat(0).take(len as u64), &mut self.writer)
+            .map_err(|err| SymCacheError::new(SymCacheErrorKind::WriteFailed, err))?;
+        self.position += len as u64;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    fn write(debug_ids: &[&str]) -> Vec<u8> {
+        let mut writer = MultiSymCacheWriter::new(Cursor::new(Vec::new())).unwrap();
+        for debug_id in debug_ids {
+            let mut module = SymCacheWriter::new(io::sink()).unwrap();
+            module.set_debug_id(debug_id.parse().unwrap());
+            module.set_section_alignment(64);
+            writer.append(module).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_sorted_modules() {
+        let ids = [
+            "ffffffff-0000-0000-0000-000000000000",
+            "00000000-0000-0000-0000-000000000001",
+        ];
+        let buf = write(&ids);
+        let multi = MultiSymCache::parse(&buf).unwrap();
+
+        let modules = multi.modules();
+        assert_eq!(modules.len(), 2);
+        assert_eq!(modules[0].debug_id(), ids[1].parse().unwrap());
+        assert_eq!(modules[1].debug_id(), ids[0].parse().unwrap());
+
+        for module in modules {
+            // modules are aligned to their section alignment
+            let offset = module.data().as_ptr() as usize - buf.as_ptr() as usize;
+            assert_eq!(raw::align_to(offset, 64), 0);
+            assert_eq!(module.symcache().unwrap().debug_id(), module.debug_id());
+        }
+        assert!(multi.get(DebugId::nil()).is_none());
+    }
+
+    #[test]
+    fn test_empty() {
+        let buf = write(&[]);
+        let multi = MultiSymCache::parse(&buf).unwrap();
+        assert!(multi.is_empty());
+    }
+
+    #[test]
+    fn test_truncated() {
+        let data = write(&["00000000-0000-0000-0000-000000000001"]);
+        let buf = &data[..data.len() - 8];
+        assert!(matches!(
+            MultiSymCache::parse(buf),
+            Err(Error::BadFormatLength)
+        ));
+
+        let buf = &data[..4];
+        assert!(matches!(
+            MultiSymCache::parse(buf),
+            Err(Error::HeaderTooSmall)
+        ));
+    }
+}
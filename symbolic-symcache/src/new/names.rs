@@ -0,0 +1,135 @@
+//! Finding functions in a [`SymCache`] by name.
+
+use std::cmp::Ordering;
+
+use super::{read_string, Function, SymCache};
+
+impl<'data> SymCache<'data> {
+    /// Returns whether this SymCache contains an index of function names.
+    ///
+    /// The index is written with [`SymCacheWriter::set_name_index`](super::SymCacheWriter::set_name_index).
+    /// Without it, searching functions by name scans all functions.
+    pub fn has_name_index(&self) -> bool {
+        !self.name_index.is_empty()
+    }
+
+    /// Returns all functions with the given name.
+    ///
+    /// Names are compared exactly, so demangled names must be searched for as they were written
+    /// into the SymCache. With a [name index](Self::has_name_index), this performs a binary search.
+    /// Functions without a name are treated as having an empty name.
+    pub fn functions_named(&self, name: &str) -> Vec<Function<'data>> {
+        self.find_functions(|function_name| function_name.cmp(name))
+    }
+
+    /// Returns all functions whose name starts with the given prefix.
+    ///
+    /// With a [name index](Self::has_name_index), this performs a binary search, and the functions
+    /// are sorted by name.
+    pub fn functions_with_prefix(&self, prefix: &str) -> Vec<Function<'data>> {
+        // names with a common prefix are adjacent in the index
+        self.find_functions(|function_name| {
+            if function_name.starts_with(prefix) {
+                Ordering::Equal
+            } else {
+                function_name.cmp(prefix)
+            }
+        })
+    }
+
+    /// Returns all functions whose name contains the given pattern.
+    ///
+    /// This always scans all functions, as the name index only helps to find prefixes.
+    pub fn functions_containing(&self, pattern: &str) -> Vec<Function<'data>> {
+        (0..self.functions.len() as u32)
+            .filter(|&idx| self.function_name(idx).contains(pattern))
+            .filter_map(|idx| self.get_function(idx))
+            .collect()
+    }
+
+    /// Returns the functions for whose name `cmp` returns [`Ordering::Equal`].
+    ///
+    /// `cmp` must order names consistently with the name index, so that all matching names are
+    /// adjacent.
+    fn find_functions<F>(&self, cmp: F) -> Vec<Function<'data>>
+    where
+        F: Fn(&str) -> Ordering,
+    {
+        let matches = |idx: u32| cmp(self.function_name(idx)) == Ordering::Equal;
+        if !self.has_name_index() {
+            return (0..self.functions.len() as u32)
+                .filter(|&idx| matches(idx))
+                .filter_map(|idx| self.get_function(idx))
+                .collect();
+        }
+
+        let start = self
+            .name_index
+            .partition_point(|idx| cmp(self.function_name(idx.get())) == Ordering::Less);
+        self.name_index[start..]
+            .iter()
+            .map(|idx| idx.get())
+            .take_while(|&idx| matches(idx))
+            .filter_map(|idx| self.get_function(idx))
+            .collect()
+    }
+
+    /// Returns the name of the function at the given index, or an empty string.
+    fn function_name(&self, function_idx: u32) -> &'data str {
+        self.functions
+            .get(function_idx as usize)
+            .and_then(|function| read_string(self.string_bytes, function.name_offset.get()).ok())
+            .flatten()
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::new::testutils::{serialize, symbol};
+    use crate::new::writer::SymCacheConverter;
+
+    #[test]
+    fn test_functions_by_name() {
+        let write = |name_index| {
+            let mut converter = SymCacheConverter::new();
+            converter.set_name_index(name_index);
+            for (address, name) in [(0x1000, "memset"), (0x2000, "memcpy"), (0x3000, "main")] {
+                converter.process_symbolic_symbol(&symbol(name, address, 0x10));
+            }
+            serialize(converter)
+        };
+
+        for name_index in [false, true] {
+            let buf = write(name_index);
+            let cache = SymCache::parse(&buf).unwrap();
+            assert_eq!(cache.has_name_index(), name_index);
+            cache.validate().unwrap();
+
+            fn names(functions: Vec<Function<'_>>) -> Vec<&str> {
+                let mut names: Vec<_> = functions.iter().filter_map(|f| f.name()).collect();
+                names.sort_unstable();
+                names
+            }
+            let memcpy = cache.functions_named("memcpy");
+            assert_eq!(names(memcpy.clone()), ["memcpy"]);
+            assert_eq!(memcpy[0].entry_pc(), 0x2000);
+            assert!(cache.functions_named("mem").is_empty());
+            assert_eq!(
+                names(cache.functions_with_prefix("mem")),
+                ["memcpy", "memset"]
+            );
+            assert_eq!(
+                names(cache.functions_with_prefix("m")),
+                ["main", "memcpy", "memset"]
+            );
+            assert!(cache.functions_with_prefix("x").is_empty());
+            assert_eq!(
+                names(cache.functions_containing("em")),
+                ["memcpy", "memset"]
+            );
+        }
+    }
+}
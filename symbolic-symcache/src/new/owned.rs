@@ -0,0 +1,72 @@
+//! A [`SymCache`] that owns its underlying buffer.
+
+use std::fmt;
+
+use symbolic_common::{ByteView, SelfCell};
+
+use super::{Result, SymCache};
+
+/// A [`SymCache`] that owns its underlying [`ByteView`].
+///
+/// Unlike [`SymCache`], which borrows the buffer it was parsed from, this type can be stored in
+/// long-lived caches and moved between threads freely. Lookups are performed on the borrowed
+/// [`SymCache`] returned by [`get`](Self::get).
+///
+/// # Example
+///
+/// ```no_run
+/// use symbolic_common::ByteView;
+/// use symbolic_symcache::OwnedSymCache;
+///
+/// let view = ByteView::open("my.symc").unwrap();
+/// let cache = OwnedSymCache::parse(view).unwrap();
+/// for source_location in cache.get().lookup(0x1000) {
+///     println!("{:?}", source_location.function());
+/// }
+/// ```
+pub struct OwnedSymCache {
+    inner: SelfCell<ByteView<'static>, SymCache<'static>>,
+}
+
+impl OwnedSymCache {
+    /// Parses a SymCache from the given byte view and takes ownership of it.
+    pub fn parse(view: ByteView<'static>) -> Result<Self> {
+        let inner = SelfCell::try_new(view, |data| SymCache::parse(unsafe { &*data }))?;
+        Ok(Self { inner })
+    }
+
+    /// Returns the parsed [`SymCache`], which borrows from `self`.
+    pub fn get(&self) -> &SymCache<'_> {
+        self.inner.get()
+    }
+
+    /// Returns the raw bytes of the SymCache.
+    pub fn as_slice(&self) -> &[u8] {
+        self.inner.owner()
+    }
+}
+
+impl fmt::Debug for OwnedSymCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("OwnedSymCache").field(self.get()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::new::testutils::symbol_cache;
+
+    #[test]
+    fn test_owned() {
+        let view = symbolic_common::ByteView::from_vec(symbol_cache());
+        let cache = OwnedSymCache::parse(view).unwrap();
+
+        let name = std::thread::spawn(move || {
+            let sl = cache.get().lookup(0x1008).next().unwrap();
+            sl.function().unwrap().name().map(String::from)
+        });
+        assert_eq!(name.join().unwrap().as_deref(), Some("main"));
+    }
+}
@@ -0,0 +1,605 @@
+//! Reading of Portable PDBs, the debug information format of .NET.
+//!
+//! A Portable PDB is an ECMA-335 metadata blob with a root header, followed by a number of streams.
+//! The `#Pdb` stream identifies the PDB, the `#~` stream contains the metadata tables, and the
+//! `#Blob` heap holds variable-length data referenced by the tables.
+//!
+//! The `Document` table lists all source files, whose names are stored as a list of blobs joined
+//! by a separator. The `MethodDebugInformation` table has one row per method definition of the
+//! assembly, and references a blob with the method's sequence points. Sequence points map IL
+//! offsets within the method to ranges in a document.
+//!
+//! Since managed code has no addresses, methods are converted to functions at
+//! [`il_address`]`(method_index, 0)`, and IL offsets are added to that address.
+
+use std::borrow::Cow;
+use std::convert::TryInto;
+
+use symbolic_common::{DebugId, Language, Name, NameMangling};
+use symbolic_debuginfo::{FileInfo, Function, LineInfo};
+use thiserror::Error;
+
+const METADATA_SIGNATURE: u32 = 0x424a_5342;
+
+const TABLE_DOCUMENT: u32 = 0x30;
+const TABLE_METHOD_DEBUG_INFORMATION: u32 = 0x31;
+
+const HEAP_GUID_WIDE: u8 = 0x02;
+const HEAP_BLOB_WIDE: u8 = 0x04;
+
+/// The table tag of method definition tokens.
+const METHOD_DEF_TOKEN: u32 = 0x0600_0000;
+
+/// An error encountered while reading a Portable PDB.
+#[derive(Debug, Error)]
+#[error("invalid portable pdb: {0}")]
+pub(crate) struct PortablePdbError(&'static str);
+
+type Result<T, E = PortablePdbError> = std::result::Result<T, E>;
+
+/// Returns the address at which an IL offset of a method is stored in a SymCache.
+///
+/// The method is identified by its row index in the `MethodDef` table, which is the method's
+/// metadata token without the table tag. Every method occupies its own 32 bit address space.
+pub(crate) fn il_address(method_index: u32, il_offset: u32) -> u64 {
+    (u64::from(method_index) << 32) | u64::from(il_offset)
+}
+
+/// A parsed Portable PDB.
+pub(crate) struct PortablePdb<'data> {
+    id: &'data [u8],
+    blobs: &'data [u8],
+    documents: &'data [u8],
+    num_documents: usize,
+    methods: &'data [u8],
+    num_methods: usize,
+    guid_size: usize,
+    blob_size: usize,
+    document_size: usize,
+}
+
+/// A sequence point, which is hidden if it has no line and column.
+struct SequencePoint {
+    il_offset: u32,
+    document: u32,
+    location: Option<(u32, u32)>,
+}
+
+impl<'data> PortablePdb<'data> {
+    /// Parses the metadata root and tables of the given Portable PDB.
+    pub fn parse(data: &'data [u8]) -> Result<Self> {
+        if read_u32(data, 0)? != METADATA_SIGNATURE {
+            return Err(PortablePdbError("wrong signature"));
+        }
+
+        // signature, major and minor version and reserved, followed by the version string
+        let version_len = read_u32(data, 12)? as usize;
+        let mut offset = 16 + version_len;
+        let num_streams = read_u16(data, offset + 2)?;
+        offset += 4;
+
+        let (mut pdb, mut tables, mut blobs) = (None, None, None);
+        for _ in 0..num_streams {
+            let stream_offset = read_u32(data, offset)? as usize;
+            let stream_size = read_u32(data, offset + 4)? as usize;
+            let name = read_cstr(data, offset + 8)?;
+            // the name is padded to four bytes, including its terminator
+            offset += 8 + (name.len() + 4) / 4 * 4;
+
+            let stream = stream_size
+                .checked_add(stream_offset)
+                .and_then(|end| data.get(stream_offset..end))
+                .ok_or(PortablePdbError("stream out of bounds"))?;
+            match name {
+                b"#Pdb" => pdb = Some(stream),
+                b"#~" | b"#-" => tables = Some(stream),
+                b"#Blob" => blobs = Some(stream),
+                _ => {}
+            }
+        }
+
+        let pdb = pdb.ok_or(PortablePdbError("missing #Pdb stream"))?;
+        let tables = tables.ok_or(PortablePdbError("missing tables stream"))?;
+        let id = pdb
+            .get(..20)
+            .ok_or(PortablePdbError("#Pdb stream too small"))?;
+
+        let heap_sizes = *tables
+            .get(6)
+            .ok_or(PortablePdbError("tables stream too small"))?;
+        let valid = read_u64(tables, 8)?;
+        // the type system tables are stored in the assembly, so the debug tables come first
+        if valid & ((1 << TABLE_DOCUMENT) - 1) != 0 {
+            return Err(PortablePdbError("unexpected type system tables"));
+        }
+
+        let mut offset = 24;
+        let mut rows = [0; 64];
+        for (table, rows) in rows.iter_mut().enumerate() {
+            if valid & (1 << table) != 0 {
+                *rows = read_u32(tables, offset)? as usize;
+                offset += 4;
+            }
+        }
+        let num_documents = rows[TABLE_DOCUMENT as usize];
+        let num_methods = rows[TABLE_METHOD_DEBUG_INFORMATION as usize];
+
+        let index_size = |wide: bool| if wide { 4 } else { 2 };
+        let guid_size = index_size(heap_sizes & HEAP_GUID_WIDE != 0);
+        let blob_size = index_size(heap_sizes & HEAP_BLOB_WIDE != 0);
+        let document_size = index_size(num_documents > 0xffff);
+
+        let mut table = |row_size: usize, rows: usize| {
+            let start = offset;
+            offset = row_size
+                .checked_mul(rows)
+                .and_then(|len| len.checked_add(start))
+                .ok_or(PortablePdbError("table out of bounds"))?;
+            tables
+                .get(start..offset)
+                .ok_or(PortablePdbError("table out of bounds"))
+        };
+        let documents = table(2 * blob_size + 2 * guid_size, num_documents)?;
+        let methods = table(document_size + blob_size, num_methods)?;
+
+        Ok(Self {
+            id,
+            blobs: blobs.unwrap_or_default(),
+            documents,
+            num_documents,
+            methods,
+            num_methods,
+            guid_size,
+            blob_size,
+            document_size,
+        })
+    }
+
+    /// Returns the debug identifier of this PDB, which matches the CodeView record of its
+    /// assembly.
+    pub fn debug_id(&self) -> DebugId {
+        DebugId::from_guid_age(&self.id[..16], 1).unwrap_or_default()
+    }
+
+    /// Returns the names of all documents, in the order of the `Document` table.
+    pub fn documents(&self) -> Result<Vec<String>> {
+        let row_size = 2 * self.blob_size + 2 * self.guid_size;
+        (0..self.num_documents)
+            .map(|index| {
+                let name = read_index(self.documents, index * row_size, self.blob_size)?;
+                self.document_name(name)
+            })
+            .collect()
+    }
+
+    /// Joins the parts of a document name.
+    ///
+    /// The name blob starts with a separator character, followed by the blob indexes of all
+    /// parts. A separator of `0` joins the parts without separator.
+    fn document_name(&self, index: usize) -> Result<String> {
+        let mut blob = self.blob(index)?;
+        let separator = match blob.split_first() {
+            Some((&0, rest)) => {
+                blob = rest;
+                None
+            }
+            Some((&separator, rest)) => {
+                blob = rest;
+                Some(char::from(separator))
+            }
+            None => return Err(PortablePdbError("empty document name")),
+        };
+
+        let mut name = String::new();
+        let mut first = true;
+        while !blob.is_empty() {
+            if !first {
+                name.extend(separator);
+            }
+            first = false;
+
+            let part = read_compressed_u32(&mut blob)?;
+            name.push_str(&String::from_utf8_lossy(self.blob(part as usize)?));
+        }
+
+        Ok(name)
+    }
+
+    /// Returns one function for every method with sequence points.
+    ///
+    /// Functions are named after their method token, since method names are only stored in the
+    /// assembly. File names are borrowed from `documents`, which must be the result of
+    /// [`documents`](Self::documents).
+    pub fn functions<'d>(&self, documents: &'d [String]) -> Result<Vec<Function<'d>>> {
+        let mut functions = Vec::new();
+        let row_size = self.document_size + self.blob_size;
+        for index in 0..self.num_methods {
+            let document = read_index(self.methods, index * row_size, self.document_size)?;
+            let sequence_points = read_index(
+                self.methods,
+                index * row_size + self.document_size,
+                self.blob_size,
+            )?;
+            if sequence_points == 0 {
+                continue;
+            }
+
+            // method rows are parallel to the `MethodDef` table, whose indexes start at 1
+            let method_index = index as u32 + 1;
+            let points = self.sequence_points(sequence_points, document as u32)?;
+            let address = il_address(method_index, 0);
+            let end = il_address(method_index + 1, 0);
+
+            let mut lines = Vec::with_capacity(points.len());
+            for (point_index, point) in points.iter().enumerate() {
+                let start = il_address(method_index, point.il_offset);
+                let next = points
+                    .get(point_index + 1)
+                    .map_or(end, |next| il_address(method_index, next.il_offset));
+                if next <= start {
+                    continue;
+                }
+
+                let (file, line, column) = match point.location {
+                    Some((line, column)) => {
+                        let name = documents
+                            .get(point.document as usize - 1)
+                            .ok_or(PortablePdbError("invalid document index"))?;
+                        let file = FileInfo {
+                            name: name.as_bytes(),
+                            dir: b"",
+                        };
+                        (file, line, column)
+                    }
+                    None => (FileInfo::default(), 0, 0),
+                };
+                lines.push(LineInfo {
+                    address: start,
+                    size: Some(next - start),
+                    file,
+                    line: line.into(),
+                    column: column.into(),
+                    prologue_end: false,
+                    is_stmt: true,
+                    basic_block: false,
+                });
+            }
+
+            functions.push(Function {
+                address,
+                size: end - address,
+                name: Name::new(
+                    Cow::Owned(format!("{:#010x}", METHOD_DEF_TOKEN | method_index)),
+                    NameMangling::Unmangled,
+                    Language::Unknown,
+                ),
+                compilation_dir: b"",
+                lines,
+                inlinees: Vec::new(),
+                inline: false,
+                decl_file: None,
+                decl_line: 0,
+            });
+        }
+
+        Ok(functions)
+    }
+
+    /// Decodes the sequence points blob of a method.
+    ///
+    /// Sequence points are delta-encoded against their predecessor. A delta of `0` in the IL
+    /// offset of any but the first record instead switches to another document. If the method's
+    /// row does not reference a document, the initial document follows the local signature.
+    fn sequence_points(&self, index: usize, document: u32) -> Result<Vec<SequencePoint>> {
+        let mut blob = self.blob(index)?;
+        let _local_signature = read_compressed_u32(&mut blob)?;
+        let mut document = match document {
+            0 => read_compressed_u32(&mut blob)?,
+            document => document,
+        };
+
+        let mut points: Vec<SequencePoint> = Vec::new();
+        let mut previous: Option<(u32, u32)> = None;
+        while !blob.is_empty() {
+            let il_delta = read_compressed_u32(&mut blob)?;
+            let il_offset = match points.last() {
+                Some(_) if il_delta == 0 => {
+                    document = read_compressed_u32(&mut blob)?;
+                    continue;
+                }
+                Some(point) => point
+                    .il_offset
+                    .checked_add(il_delta)
+                    .ok_or(PortablePdbError("IL offset overflow"))?,
+                None => il_delta,
+            };
+
+            let line_delta = read_compressed_u32(&mut blob)?;
+            let column_delta = match line_delta {
+                0 => read_compressed_u32(&mut blob)? as i64,
+                _ => read_compressed_i32(&mut blob)?.into(),
+            };
+            if line_delta == 0 && column_delta == 0 {
+                points.push(SequencePoint {
+                    il_offset,
+                    document,
+                    location: None,
+                });
+                continue;
+            }
+
+            // the first visible sequence point stores absolute start lines and columns
+            let location = match previous {
+                None => (
+                    read_compressed_u32(&mut blob)?,
+                    read_compressed_u32(&mut blob)?,
+                ),
+                Some((line, column)) => (
+                    line.wrapping_add(read_compressed_i32(&mut blob)? as u32),
+                    column.wrapping_add(read_compressed_i32(&mut blob)? as u32),
+                ),
+            };
+            if document == 0 || document as usize > self.num_documents {
+                return Err(PortablePdbError("invalid document index"));
+            }
+            previous = Some(location);
+            points.push(SequencePoint {
+                il_offset,
+                document,
+                location: Some(location),
+            });
+        }
+
+        Ok(points)
+    }
+
+    /// Returns the blob at the given index of the `#Blob` heap.
+    fn blob(&self, index: usize) -> Result<&'data [u8]> {
+        let mut data = self
+            .blobs
+            .get(index..)
+            .ok_or(PortablePdbError("blob out of bounds"))?;
+        let len = read_compressed_u32(&mut data)? as usize;
+        data.get(..len)
+            .ok_or(PortablePdbError("blob out of bounds"))
+    }
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Result<u16> {
+    let bytes = data
+        .get(offset..offset + 2)
+        .ok_or(PortablePdbError("unexpected end of data"))?;
+    Ok(u16::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32> {
+    let bytes = data
+        .get(offset..offset + 4)
+        .ok_or(PortablePdbError("unexpected end of data"))?;
+    Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Result<u64> {
+    let bytes = data
+        .get(offset..offset + 8)
+        .ok_or(PortablePdbError("unexpected end of data"))?;
+    Ok(u64::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+/// Reads a heap or table index, which is either 2 or 4 bytes wide.
+fn read_index(data: &[u8], offset: usize, size: usize) -> Result<usize> {
+    match size {
+        2 => read_u16(data, offset).map(usize::from),
+        _ => read_u32(data, offset).map(|index| index as usize),
+    }
+}
+
+/// Reads a NUL-terminated string at the given offset.
+fn read_cstr(data: &[u8], offset: usize) -> Result<&[u8]> {
+    let data = data
+        .get(offset..)
+        .ok_or(PortablePdbError("string out of bounds"))?;
+    let len = data
+        .iter()
+        .position(|&b| b == 0)
+        .ok_or(PortablePdbError("unterminated string"))?;
+    Ok(&data[..len])
+}
+
+/// Reads a compressed unsigned integer and advances the data past it.
+///
+/// The two most significant bits of the first byte determine whether the integer takes up 1, 2 or
+/// 4 bytes in big endian.
+fn read_compressed_u32(data: &mut &[u8]) -> Result<u32> {
+    let first = *data
+        .first()
+        .ok_or(PortablePdbError("unexpected end of data"))?;
+    let (len, value) = match first {
+        0x00..=0x7f => (1, u32::from(first)),
+        0x80..=0xbf => (2, u32::from(first & 0x3f)),
+        0xc0..=0xdf => (4, u32::from(first & 0x1f)),
+        _ => return Err(PortablePdbError("invalid compressed integer")),
+    };
+    let bytes = data
+        .get(1..len)
+        .ok_or(PortablePdbError("unexpected end of data"))?;
+    *data = &data[len..];
+    Ok(bytes
+        .iter()
+        .fold(value, |value, &byte| (value << 8) | u32::from(byte)))
+}
+
+/// Reads a compressed signed integer and advances the data past it.
+///
+/// The value is stored like an unsigned integer, rotated left by one bit within its encoded width
+/// so that the sign ends up in the least significant bit.
+fn read_compressed_i32(data: &mut &[u8]) -> Result<i32> {
+    let len = match data.first() {
+        Some(0x00..=0x7f) => 1,
+        Some(0x80..=0xbf) => 2,
+        _ => 4,
+    };
+    let value = read_compressed_u32(data)?;
+    let sign_mask = match len {
+        1 => 0xffff_ffc0,
+        2 => 0xffff_e000,
+        _ => 0xf000_0000,
+    };
+    Ok(if value & 1 != 0 {
+        ((value >> 1) | sign_mask) as i32
+    } else {
+        (value >> 1) as i32
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::new::writer::SymCacheConverter;
+    use crate::new::SymCache;
+
+    /// Builds a metadata stream header with a name padded to four bytes.
+    fn stream_header(data: &mut Vec<u8>, offset: usize, size: usize, name: &[u8]) {
+        data.extend((offset as u32).to_le_bytes());
+        data.extend((size as u32).to_le_bytes());
+        data.extend(name);
+        data.extend(vec![0; 4 - name.len() % 4]);
+    }
+
+    /// Builds a Portable PDB with a single document and two methods, only the first of which has
+    /// sequence points.
+    fn portable_pdb() -> Vec<u8> {
+        let mut pdb = Vec::new();
+        pdb.extend(1..=20u8);
+        // entry point and referenced type system tables
+        pdb.extend([0; 12]);
+
+        // blobs: "src" at 1, "Program.cs" at 5, the document name at 16, sequence points at 21
+        let mut blobs = vec![0];
+        blobs.extend(b"\x03src");
+        blobs.extend(b"\x0aProgram.cs");
+        blobs.extend([4, b'/', 0, 1, 5]);
+        // line 5 column 9 at 0, line 7 column 5 at 6 and a hidden sequence point at 10
+        let sequence_points = [0, 0, 0, 10, 5, 9, 6, 1, 4, 4, 0x79, 4, 0, 0];
+        blobs.push(sequence_points.len() as u8);
+        blobs.extend(sequence_points);
+        blobs.resize(40, 0);
+
+        let mut tables = Vec::new();
+        tables.extend([0, 0, 0, 0, 2, 0, 0, 1]);
+        let valid = (1u64 << TABLE_DOCUMENT) | (1u64 << TABLE_METHOD_DEBUG_INFORMATION);
+        tables.extend(valid.to_le_bytes());
+        tables.extend(0u64.to_le_bytes());
+        tables.extend(1u32.to_le_bytes());
+        tables.extend(2u32.to_le_bytes());
+        // document: name, hash algorithm, hash and language
+        for field in [16u16, 0, 0, 0] {
+            tables.extend(field.to_le_bytes());
+        }
+        // methods: document and sequence points
+        for field in [1u16, 21, 0, 0] {
+            tables.extend(field.to_le_bytes());
+        }
+
+        let version = b"PDB v1.0\0\0\0\0";
+        let headers_len = 16 + version.len() + 4 + 16 + 12 + 16;
+        let mut data = Vec::new();
+        data.extend(METADATA_SIGNATURE.to_le_bytes());
+        data.extend([1, 0, 1, 0, 0, 0, 0, 0]);
+        data.extend((version.len() as u32).to_le_bytes());
+        data.extend(version);
+        data.extend([0, 0, 3, 0]);
+        stream_header(&mut data, headers_len, pdb.len(), b"#Pdb");
+        stream_header(&mut data, headers_len + pdb.len(), tables.len(), b"#~");
+        let blobs_offset = headers_len + pdb.len() + tables.len();
+        stream_header(&mut data, blobs_offset, blobs.len(), b"#Blob");
+        assert_eq!(data.len(), headers_len);
+
+        data.extend(pdb);
+        data.extend(tables);
+        data.extend(blobs);
+        data
+    }
+
+    #[test]
+    fn test_functions() {
+        let data = portable_pdb();
+        let pdb = PortablePdb::parse(&data).unwrap();
+        assert_eq!(
+            pdb.debug_id(),
+            "04030201-0605-0807-090a-0b0c0d0e0f10-1".parse().unwrap()
+        );
+
+        let documents = pdb.documents().unwrap();
+        assert_eq!(documents, ["/src/Program.cs"]);
+
+        let functions = pdb.functions(&documents).unwrap();
+        assert_eq!(functions.len(), 1);
+        let function = &functions[0];
+        assert_eq!(function.name.as_str(), "0x06000001");
+        assert_eq!((function.address, function.size), (1 << 32, 1 << 32));
+
+        let lines: Vec<_> = function
+            .lines
+            .iter()
+            .map(|line| {
+                (
+                    line.address - (1 << 32),
+                    line.file.name,
+                    line.line,
+                    line.column,
+                )
+            })
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                (0, &b"/src/Program.cs"[..], 5, 9),
+                (6, &b"/src/Program.cs"[..], 7, 5),
+                (10, &b""[..], 0, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lookup_il_offset() {
+        let data = portable_pdb();
+        let mut converter = SymCacheConverter::new();
+        converter.process_portable_pdb(&data).unwrap();
+        let mut buf = Vec::new();
+        converter.serialize(&mut buf).unwrap();
+
+        let cache = SymCache::parse(&buf).unwrap();
+        assert_eq!(
+            cache.debug_id(),
+            PortablePdb::parse(&data).unwrap().debug_id()
+        );
+        let frame = |method_index, il_offset| {
+            let sl = cache.lookup_il_offset(method_index, il_offset).next()?;
+            let file = sl.file().map(|file| file.full_path());
+            Some((sl.function()?.name()?, file, sl.line()))
+        };
+        assert_eq!(
+            frame(1, 8),
+            Some(("0x06000001", Some("/src/Program.cs".into()), 7))
+        );
+        assert_eq!(frame(1, 12), Some(("0x06000001", Some("".into()), 0)));
+        assert_eq!(frame(2, 0), None);
+    }
+
+    #[test]
+    fn test_compressed_integers() {
+        let mut data = &[0x03, 0xbf, 0xff, 0xc0, 0x00, 0x40, 0x00][..];
+        assert_eq!(read_compressed_u32(&mut data).unwrap(), 3);
+        assert_eq!(read_compressed_u32(&mut data).unwrap(), 0x3fff);
+        assert_eq!(read_compressed_u32(&mut data).unwrap(), 0x4000);
+        assert!(data.is_empty());
+
+        let mut data = &[0x06, 0x7b, 0x80, 0x80, 0xdf, 0xff, 0xff, 0xff][..];
+        assert_eq!(read_compressed_i32(&mut data).unwrap(), 3);
+        assert_eq!(read_compressed_i32(&mut data).unwrap(), -3);
+        assert_eq!(read_compressed_i32(&mut data).unwrap(), 64);
+        assert_eq!(read_compressed_i32(&mut data).unwrap(), -1);
+    }
+}
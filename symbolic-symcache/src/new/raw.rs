@@ -1,82 +1,270 @@
 //! The raw SymCache binary file format internals.
 //!
-use symbolic_common::{Arch, DebugId};
+//! All structs in this module can be read from and written to arbitrary bytes, which is checked
+//! by the `zerocopy` derives. They must therefore only consist of integers and arrays thereof, and
+//! must not contain any padding.
+//!
+//! The format is little-endian on disk, regardless of the system that writes or reads it. All
+//! integers are therefore stored as [`U32`] and [`U64`], which convert from and to native integers
+//! on access. Since these have no alignment requirements, the structs do not either.
+use symbolic_common::Uuid;
+use zerocopy::byteorder::{self, LittleEndian};
+use zerocopy::{AsBytes, FromBytes, Unaligned};
 
 pub use crate::SYMCACHE_VERSION;
 
+/// The oldest version of the binary format that can still be read.
+///
+/// Versions between this one and [`SYMCACHE_VERSION`] only differ in fields that were carved out
+/// of previously reserved, zero-initialized header space. Zero values of these fields must
+/// therefore always retain the behavior of the older versions.
+pub const SYMCACHE_VERSION_MIN: u32 = 7;
+
 /// The magic file preamble as individual bytes.
 const SYMCACHE_MAGIC_BYTES: [u8; 4] = *b"SYMC";
 
 /// The magic file preamble to identify SymCache files.
 ///
-/// Serialized as ASCII "SYMC" on little-endian (x64) systems.
+/// Serialized as ASCII "SYMC".
 pub const SYMCACHE_MAGIC: u32 = u32::from_le_bytes(SYMCACHE_MAGIC_BYTES);
-/// The byte-flipped magic, which indicates an endianness mismatch.
+/// The byte-flipped magic, which indicates a SymCache that was written in big-endian by an older
+/// version on a big-endian system.
 pub const SYMCACHE_MAGIC_FLIPPED: u32 = SYMCACHE_MAGIC.swap_bytes();
 
-/// This [`SourceLocation`] is a sentinel value that says that no source location is present here.
-/// This is used to push an "end" range that does not resolve to a valid source location.
-/// Otherwise, the ranges would implicitly extend to infinity.
-pub const NO_SOURCE_LOCATION: SourceLocation = SourceLocation {
-    file_idx: u32::MAX,
-    line: u32::MAX,
-    function_idx: u32::MAX,
-    inlined_into_idx: u32::MAX,
-};
-
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// A `u32` stored in little-endian byte order.
+pub type U32 = byteorder::U32<LittleEndian>;
+/// A `u64` stored in little-endian byte order.
+pub type U64 = byteorder::U64<LittleEndian>;
+
+/// The largest line number that can be stored in a [`SourceLocation`].
+///
+/// `u32::MAX` is reserved for the sentinel, see [`SourceLocation::is_none`]. Larger line numbers
+/// are stored as `0`, the value for an unknown line.
+pub const MAX_LINE: u32 = u32::MAX - 1;
+
+#[derive(Debug, Clone, PartialEq, Eq, FromBytes, AsBytes, Unaligned)]
 #[repr(C)]
 pub struct Header {
     /// The file magic representing the file format and endianness.
-    pub magic: u32,
+    pub magic: U32,
     /// The SymCache Format Version.
-    pub version: u32,
+    pub version: U32,
 
     /// Debug identifier of the object file.
     pub debug_id: DebugId,
-    /// CPU architecture of the object file.
-    pub arch: Arch,
+    /// CPU architecture of the object file, see [`Arch`](symbolic_common::Arch).
+    pub arch: U32,
 
     /// Number of included [`File`]s.
-    pub num_files: u32,
+    pub num_files: U32,
     /// Number of included [`Function`]s.
-    pub num_functions: u32,
+    pub num_functions: U32,
     /// Number of included [`SourceLocation`]s.
-    pub num_source_locations: u32,
+    pub num_source_locations: U32,
     /// Number of included [`Range`]s.
-    pub num_ranges: u32,
+    pub num_ranges: U32,
     /// Total number of bytes used for string data.
-    pub string_bytes: u32,
+    pub string_bytes: U32,
+
+    /// The alignment of the header and each section in bytes.
+    ///
+    /// A value of `0` means the [`DEFAULT_SECTION_ALIGNMENT`].
+    pub section_alignment: U32,
+
+    /// Flags describing optional header fields, see [`FLAG_CHECKSUM`].
+    pub flags: U32,
+
+    /// The checksum of all data following the header, see [`FLAG_CHECKSUM`].
+    pub checksum: U32,
+
+    /// Number of [`Section`]s in the section table following the string data.
+    pub num_sections: U32,
+}
+
+/// The serialized form of a [`DebugId`](symbolic_common::DebugId).
+///
+/// This matches the in-memory layout of `DebugId`, which was previously written verbatim.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromBytes, AsBytes, Unaligned)]
+#[repr(C)]
+pub struct DebugId {
+    /// The bytes of the UUID part.
+    pub uuid: [u8; 16],
+    /// The appendix, such as the age of a PDB.
+    pub appendix: U32,
+    /// Reserved space, which is always zero.
+    pub _padding: [u8; 11],
+    /// The type of identifier, see [`DEBUG_ID_PDB20`].
+    pub typ: u8,
+}
+
+/// [`DebugId::typ`] of an identifier built from a PDB 2.0 timestamp and age.
+pub const DEBUG_ID_PDB20: u8 = 1;
+
+impl From<symbolic_common::DebugId> for DebugId {
+    fn from(debug_id: symbolic_common::DebugId) -> Self {
+        Self {
+            uuid: *debug_id.uuid().as_bytes(),
+            appendix: debug_id.appendix().into(),
+            _padding: [0; 11],
+            typ: if debug_id.is_pdb20() {
+                DEBUG_ID_PDB20
+            } else {
+                0
+            },
+        }
+    }
+}
 
-    /// Some reserved space in the header for future extensions that would not require a
-    /// completely new parsing method.
-    pub _reserved: [u8; 16],
+impl From<DebugId> for symbolic_common::DebugId {
+    fn from(debug_id: DebugId) -> Self {
+        if debug_id.typ == DEBUG_ID_PDB20 {
+            // PDB 2.0 identifiers store the timestamp big-endian in the first four UUID bytes
+            let mut timestamp = [0; 4];
+            timestamp.copy_from_slice(&debug_id.uuid[..4]);
+            Self::from_pdb20(u32::from_be_bytes(timestamp), debug_id.appendix.get())
+        } else {
+            Self::from_parts(Uuid::from_bytes(debug_id.uuid), debug_id.appendix.get())
+        }
+    }
 }
 
+/// Header flag indicating that the header holds a checksum.
+///
+/// The checksum is the CRC32 of all bytes following the header, up to the end of the last
+/// section. Caches written before the flag was introduced carry no checksum.
+pub const FLAG_CHECKSUM: u32 = 1;
+
+/// An entry in the section table, which describes an extension section.
+///
+/// Extension sections hold data beyond the files, functions, source locations, ranges and strings
+/// that every SymCache contains. Readers skip sections of unknown kinds, so new data can be added
+/// without breaking existing readers.
+#[derive(Debug, Clone, PartialEq, Eq, FromBytes, AsBytes, Unaligned)]
+#[repr(C)]
+pub struct Section {
+    /// The kind of data in the section, such as [`SECTION_PROVENANCE`].
+    pub kind: U32,
+    /// The size of each element in bytes, which is needed to convert the endianness of unknown
+    /// sections. Must be one of `1`, `2`, `4` or `8`.
+    pub element_size: U32,
+    /// The offset of the section from the start of the SymCache, aligned to the section alignment.
+    pub offset: U64,
+    /// The length of the section in bytes.
+    pub len: U64,
+}
+
+/// Section containing one byte per [`Range`], see [`PROVENANCE_DEBUG_INFO`] and
+/// [`PROVENANCE_SYMBOL_TABLE`].
+pub const SECTION_PROVENANCE: u32 = 1;
+
+/// Section containing one `u32` column number per [`SourceLocation`].
+///
+/// A value of `0` means that the column is unknown. Without this section, all columns are unknown.
+pub const SECTION_COLUMNS: u32 = 2;
+
+/// Section containing one string offset per [`Function`].
+///
+/// The offsets reference the linkage name of functions whose name was demangled during conversion,
+/// and are `u32::MAX` otherwise.
+pub const SECTION_LINKAGE_NAMES: u32 = 3;
+
+/// Section containing the alternative [`Candidate`]s of ranges, sorted by range.
+///
+/// The elements of this section are the `u32` fields of the candidates, so its element size is
+/// `4`, and its length is a multiple of the size of a [`Candidate`]. Without this section, every
+/// range has a single attribution.
+pub const SECTION_CANDIDATES: u32 = 4;
+
+/// Section containing the upper 32 bits of the start address of each [`Range`] as `u32`.
+///
+/// Without this section, all addresses fit into 32 bits.
+pub const SECTION_RANGES_HIGH: u32 = 5;
+
+/// Section containing the upper 32 bits of the entry pc of each [`Function`] as `u32`.
+///
+/// Without this section, all entry pcs fit into 32 bits.
+pub const SECTION_ENTRY_PCS_HIGH: u32 = 6;
+
+/// Section containing a single `u64` with the image base that was subtracted from all addresses.
+///
+/// Without this section, addresses were written as they were processed.
+pub const SECTION_IMAGE_BASE: u32 = 7;
+
+/// Section containing the index of every [`Function`] as `u32`, sorted by function name.
+///
+/// Names are compared bytewise, functions without a name sort first, and functions with equal
+/// names are ordered by index. This allows to find functions by name without scanning all of them.
+pub const SECTION_NAME_INDEX: u32 = 8;
+
+/// Section containing [`CallEdge`]s between functions, sorted by caller, callee, file and line.
+///
+/// The elements of this section are the `u32` fields of the edges, so its element size is `4`,
+/// and its length is a multiple of the size of a [`CallEdge`]. Without this section, the static
+/// call graph is unknown.
+pub const SECTION_CALL_EDGES: u32 = 9;
+
+/// Section containing one [`Declaration`] for every [`Function`].
+///
+/// The elements of this section are the `u32` fields of the declarations, so its element size is
+/// `4` and it has twice as many elements as there are functions. Without this section, the
+/// declarations of all functions are unknown.
+pub const SECTION_DECLARATIONS: u32 = 10;
+
+/// Section containing one byte of flags per [`Range`], see [`RANGE_FLAG_PROLOGUE_END`],
+/// [`RANGE_FLAG_NOT_STMT`] and [`RANGE_FLAG_BASIC_BLOCK`].
+///
+/// Without this section, no flags are set on any range.
+pub const SECTION_RANGE_FLAGS: u32 = 11;
+
+/// Section containing the code [`Segment`]s of the image, sorted by address.
+///
+/// The elements of this section are the `u64` fields of the segments, so its element size is `8`,
+/// and its length is a multiple of the size of a [`Segment`]. Without this section, the image is
+/// treated as a single segment.
+pub const SECTION_SEGMENTS: u32 = 12;
+
+/// The range was produced by an input without recorded provenance, such as an end marker.
+pub const PROVENANCE_UNKNOWN: u8 = 0;
+/// The range was produced from debug information, such as DWARF or PDB.
+pub const PROVENANCE_DEBUG_INFO: u8 = 1;
+/// The range was produced from a symbol table.
+pub const PROVENANCE_SYMBOL_TABLE: u8 = 2;
+
+/// The range starts at the end of the prologue of its function, as marked by the line program.
+pub const RANGE_FLAG_PROLOGUE_END: u8 = 1;
+/// The range does not start a statement, for example because it is in the middle of an expression.
+///
+/// Ranges start statements unless marked otherwise, so that inputs which do not distinguish
+/// statements need no flags.
+pub const RANGE_FLAG_NOT_STMT: u8 = 2;
+/// The range starts a basic block, as marked by the line program.
+pub const RANGE_FLAG_BASIC_BLOCK: u8 = 4;
+
 /// Serialized Function metadata in the SymCache.
-#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[derive(Debug, Clone, Hash, PartialEq, Eq, FromBytes, AsBytes, Unaligned)]
 #[repr(C)]
 pub struct Function {
     /// The functions name (reference to a [`String`]).
-    pub name_offset: u32,
+    pub name_offset: U32,
     /// The compilation directory (reference to a [`String`]).
-    pub comp_dir_offset: u32,
-    /// The first address covered by this function.
-    pub entry_pc: u32,
+    pub comp_dir_offset: U32,
+    /// The lower 32 bits of the first address covered by this function, see
+    /// [`SECTION_ENTRY_PCS_HIGH`].
+    pub entry_pc: U32,
     /// The language of the function.
-    pub lang: u32,
+    pub lang: U32,
 }
 
 /// Serialized File in the SymCache.
-#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[derive(Debug, Clone, Hash, PartialEq, Eq, FromBytes, AsBytes, Unaligned)]
 #[repr(C)]
 pub struct File {
     /// The optional compilation directory prefix (reference to a [`String`]).
-    pub comp_dir_offset: u32,
+    pub comp_dir_offset: U32,
     /// The optional directory prefix (reference to a [`String`]).
-    pub directory_offset: u32,
+    pub directory_offset: U32,
     /// The file path (reference to a [`String`]).
-    pub path_name_offset: u32,
+    pub path_name_offset: U32,
 }
 
 /// A location in a source file, comprising a file, a line, a function, and
@@ -86,36 +274,168 @@ pub struct File {
 /// version of the function, is represented by a distinct `SourceLocation`.
 /// These `SourceLocation`s will all point to the same file, line, and function,
 /// but have different inline information.
-#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+#[derive(Clone, Debug, Hash, PartialEq, Eq, FromBytes, AsBytes, Unaligned)]
 #[repr(C)]
 pub struct SourceLocation {
     /// The optional source file (reference to a [`File`]).
-    pub file_idx: u32,
+    pub file_idx: U32,
     /// The line number.
-    pub line: u32,
+    pub line: U32,
     /// The function (reference to a [`Function`]).
-    pub function_idx: u32,
+    pub function_idx: U32,
     /// The caller source location in case this location was inlined
     /// (reference to another [`SourceLocation`]).
-    pub inlined_into_idx: u32,
+    pub inlined_into_idx: U32,
+}
+
+impl SourceLocation {
+    /// Returns whether this is the sentinel that says that no source location is present.
+    ///
+    /// The sentinel has all fields set to `u32::MAX`. It is used to push an "end" range that does
+    /// not resolve to a valid source location. Otherwise, the ranges would implicitly extend to
+    /// infinity.
+    pub fn is_none(&self) -> bool {
+        self.file_idx.get() == u32::MAX
+            && self.line.get() == u32::MAX
+            && self.function_idx.get() == u32::MAX
+            && self.inlined_into_idx.get() == u32::MAX
+    }
+}
+
+/// A static call from one function to another, see [`SECTION_CALL_EDGES`].
+///
+/// Edges are derived from inlined functions, whose call site is the file and line of the
+/// [`SourceLocation`] they are inlined into.
+#[derive(Debug, Clone, Hash, PartialEq, Eq, FromBytes, AsBytes, Unaligned)]
+#[repr(C)]
+pub struct CallEdge {
+    /// The calling function (reference to a [`Function`]).
+    pub caller_idx: U32,
+    /// The called function (reference to a [`Function`]).
+    pub callee_idx: U32,
+    /// The optional source file of the call site (reference to a [`File`]).
+    pub file_idx: U32,
+    /// The line number of the call site.
+    pub line: U32,
+}
+
+/// A segment of the image that is mapped separately at runtime, see [`SECTION_SEGMENTS`].
+///
+/// The name offset is stored as a `u64`, so that all fields have the same size.
+#[derive(Debug, Clone, Hash, PartialEq, Eq, FromBytes, AsBytes, Unaligned)]
+#[repr(C)]
+pub struct Segment {
+    /// The first address covered by the segment.
+    pub start: U64,
+    /// The size of the segment in bytes.
+    pub size: U64,
+    /// The name of the segment (reference to a [`String`]).
+    pub name_offset: U64,
+}
+
+/// The place where a function is declared, see [`SECTION_DECLARATIONS`].
+#[derive(Debug, Clone, Hash, PartialEq, Eq, FromBytes, AsBytes, Unaligned)]
+#[repr(C)]
+pub struct Declaration {
+    /// The optional source file of the declaration (reference to a [`File`]).
+    pub file_idx: U32,
+    /// The line number of the declaration, or `0` if unknown.
+    pub line: U32,
 }
 
 /// A representation of a code range in the SymCache.
 ///
-/// We only save the start address, the end is implicitly given
-/// by the next range's start.
-#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+/// We only save the lower 32 bits of the start address, see [`SECTION_RANGES_HIGH`]. The end is
+/// implicitly given by the next range's start.
+#[derive(Debug, Clone, Hash, PartialEq, Eq, FromBytes, AsBytes, Unaligned)]
+#[repr(C)]
+pub struct Range(pub U32);
+
+/// An alternative attribution of a range, see [`SECTION_CANDIDATES`].
+///
+/// Candidates are the source locations that lost against the source location of the range when
+/// inputs attributed the same address differently.
+#[derive(Debug, Clone, Hash, PartialEq, Eq, FromBytes, AsBytes, Unaligned)]
+#[repr(C)]
+pub struct Candidate {
+    /// The range the candidate applies to (reference to a [`Range`]).
+    pub range_idx: U32,
+    /// The innermost frame of the candidate (reference to a [`SourceLocation`]).
+    pub source_location_idx: U32,
+    /// The kind of input that produced the candidate, see [`PROVENANCE_DEBUG_INFO`] and
+    /// [`PROVENANCE_SYMBOL_TABLE`].
+    pub provenance: U32,
+}
+
+/// The magic file preamble to identify files with multiple SymCaches, see [`MultiHeader`].
+///
+/// Serialized as ASCII "SYMM".
+pub const MULTI_SYMCACHE_MAGIC: u32 = u32::from_le_bytes(*b"SYMM");
+
+/// The version of the format of files with multiple SymCaches.
+pub const MULTI_SYMCACHE_VERSION: u32 = 1;
+
+/// The header of a file with multiple SymCaches.
+///
+/// The header is followed by the SymCaches of all modules, each aligned to its section alignment,
+/// and the table of [`Module`]s. A [`MultiTrailer`] at the very end of the file locates the table,
+/// so that it can be written after all SymCaches have been streamed out.
+#[derive(Debug, Clone, PartialEq, Eq, FromBytes, AsBytes, Unaligned)]
 #[repr(C)]
-pub struct Range(pub u32);
+pub struct MultiHeader {
+    /// The file magic, see [`MULTI_SYMCACHE_MAGIC`].
+    pub magic: U32,
+    /// The version of the file format, see [`MULTI_SYMCACHE_VERSION`].
+    pub version: U32,
+}
+
+/// An entry in the module table of a file with multiple SymCaches.
+///
+/// The table is sorted by debug identifier, which is unique within a file.
+#[derive(Debug, Clone, PartialEq, Eq, FromBytes, AsBytes, Unaligned)]
+#[repr(C)]
+pub struct Module {
+    /// Debug identifier of the module.
+    pub debug_id: DebugId,
+    /// CPU architecture of the module, see [`Arch`](symbolic_common::Arch).
+    pub arch: U32,
+    /// Reserved space, which is always zero.
+    pub _reserved: U32,
+    /// The offset of the module's SymCache from the start of the file, aligned to 8 bytes.
+    pub offset: U64,
+    /// The length of the module's SymCache in bytes.
+    pub len: U64,
+}
+
+/// The trailer of a file with multiple SymCaches, see [`MultiHeader`].
+#[derive(Debug, Clone, PartialEq, Eq, FromBytes, AsBytes, Unaligned)]
+#[repr(C)]
+pub struct MultiTrailer {
+    /// The offset of the module table from the start of the file, aligned to 8 bytes.
+    pub modules_offset: U64,
+    /// Number of [`Module`]s in the module table.
+    pub num_modules: U32,
+    /// The file magic, repeated to recognize truncated files, see [`MULTI_SYMCACHE_MAGIC`].
+    pub magic: U32,
+}
+
+/// The default alignment of the header and each section.
+pub const DEFAULT_SECTION_ALIGNMENT: u32 = 8;
 
 /// Returns the amount left to add to the remainder to get 8 if
 /// `to_align` isn't a multiple of 8.
 pub fn align_to_eight(to_align: usize) -> usize {
-    let remainder = to_align % 8;
+    align_to(to_align, 8)
+}
+
+/// Returns the amount left to add to the remainder to get `alignment` if
+/// `to_align` isn't a multiple of `alignment`.
+pub fn align_to(to_align: usize, alignment: usize) -> usize {
+    let remainder = to_align % alignment;
     if remainder == 0 {
         remainder
     } else {
-        8 - remainder
+        alignment - remainder
     }
 }
 
@@ -128,18 +448,36 @@ mod tests {
     #[test]
     fn test_sizeof() {
         assert_eq!(mem::size_of::<Header>(), 80);
-        assert_eq!(mem::align_of::<Header>(), 4);
+        assert_eq!(mem::align_of::<Header>(), 1);
 
         assert_eq!(mem::size_of::<Function>(), 16);
-        assert_eq!(mem::align_of::<Function>(), 4);
+        assert_eq!(mem::align_of::<Function>(), 1);
 
         assert_eq!(mem::size_of::<File>(), 12);
-        assert_eq!(mem::align_of::<File>(), 4);
+        assert_eq!(mem::align_of::<File>(), 1);
 
         assert_eq!(mem::size_of::<SourceLocation>(), 16);
-        assert_eq!(mem::align_of::<SourceLocation>(), 4);
+        assert_eq!(mem::align_of::<SourceLocation>(), 1);
 
         assert_eq!(mem::size_of::<Range>(), 4);
-        assert_eq!(mem::align_of::<Range>(), 4);
+        assert_eq!(mem::align_of::<Range>(), 1);
+
+        assert_eq!(mem::size_of::<MultiHeader>(), 8);
+        assert_eq!(mem::size_of::<Module>(), 56);
+        assert_eq!(mem::size_of::<MultiTrailer>(), 16);
+    }
+
+    #[test]
+    fn test_debug_id_roundtrip() {
+        let debug_id = symbolic_common::DebugId::from_pdb20(0x3c3f_9fb2, 7);
+        let raw = DebugId::from(debug_id);
+        assert_eq!(raw.typ, DEBUG_ID_PDB20);
+        assert_eq!(symbolic_common::DebugId::from(raw), debug_id);
+
+        let debug_id: symbolic_common::DebugId =
+            "3249d99d-0c40-4931-8610-f4e4fb0b6936-1".parse().unwrap();
+        let raw = DebugId::from(debug_id);
+        assert_eq!(raw.typ, 0);
+        assert_eq!(symbolic_common::DebugId::from(raw), debug_id);
     }
 }
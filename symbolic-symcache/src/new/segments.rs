@@ -0,0 +1,174 @@
+//! The code segments of images that map their code separately, see [`raw::SECTION_SEGMENTS`].
+
+use std::convert::TryFrom;
+
+use super::{raw, read_string, AddrRange, SourceLocationIter, SymCache};
+
+impl<'data> SymCache<'data> {
+    /// Returns the code segments of the image, in ascending address order.
+    ///
+    /// Segments are written for images that map their code in multiple segments, each of which
+    /// may be slid separately at runtime, such as the `__TEXT` and `__TEXT_EXEC` segments of
+    /// MachO kernels. SymCaches of other images have no segments.
+    pub fn segments(&self) -> Vec<Segment<'data>> {
+        self.segments
+            .iter()
+            .map(|segment| self.get_segment(segment))
+            .collect()
+    }
+
+    /// Returns the code segment that contains the given address, if any.
+    ///
+    /// All addresses of a range belong to the same segment, so this is the segment of the range
+    /// that [`lookup`](Self::lookup) resolves the address with.
+    pub fn segment(&self, addr: u64) -> Option<Segment<'data>> {
+        // segments are sorted by their start address
+        let idx = self
+            .segments
+            .partition_point(|segment| segment.start.get() <= addr);
+        let segment = self.get_segment(self.segments.get(idx.checked_sub(1)?)?);
+        if addr < segment.range.end {
+            Some(segment)
+        } else {
+            None
+        }
+    }
+
+    /// Looks up an offset into the code segment with the given name, yielding an iterator of
+    /// [`SourceLocation`](super::SourceLocation)s like [`lookup`](Self::lookup).
+    ///
+    /// Runtime addresses in images whose segments are slid separately are only meaningful relative
+    /// to the start of their segment. This normalizes the offset to an address of the SymCache
+    /// before looking it up. The iterator is empty if there is no such segment, or if the offset
+    /// lies outside of it.
+    pub fn lookup_segment_offset(
+        &self,
+        segment: &str,
+        offset: u64,
+    ) -> SourceLocationIter<'data, '_> {
+        let segment = self
+            .segments()
+            .into_iter()
+            .find(|s| s.name == Some(segment));
+        let addr = segment.and_then(|segment| {
+            let addr = segment.range.start.checked_add(offset)?;
+            Some(addr).filter(|&addr| addr < segment.range.end)
+        });
+        match addr {
+            Some(addr) => self.lookup(addr),
+            None => self.lookup_nothing(),
+        }
+    }
+
+    /// Resolves the name and addresses of a raw segment.
+    fn get_segment(&self, segment: &raw::Segment) -> Segment<'data> {
+        let name = u32::try_from(segment.name_offset.get())
+            .ok()
+            .and_then(|offset| read_string(self.string_bytes, offset).ok().flatten());
+        let start = segment.start.get();
+        Segment {
+            name,
+            range: AddrRange {
+                start,
+                end: start.saturating_add(segment.size.get()),
+            },
+        }
+    }
+}
+
+/// A code segment of the image, see [`SymCache::segments`].
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub struct Segment<'data> {
+    /// The name of the segment, such as `__TEXT_EXEC`.
+    pub name: Option<&'data str>,
+    /// The addresses covered by the segment.
+    pub range: AddrRange,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::new::testutils::serialize;
+    use crate::new::writer::SymCacheConverter;
+
+    #[test]
+    fn test_segments() {
+        let mut converter = SymCacheConverter::new();
+        converter.set_image_base(0x1_0000_0000);
+        converter.add_synthetic_function(
+            "text",
+            &[AddrRange {
+                start: 0x1_0000_1000,
+                end: 0x1_0000_1100,
+            }],
+        );
+        converter.add_synthetic_function(
+            "text_exec",
+            &[AddrRange {
+                start: 0x1_0000_4100,
+                end: 0x1_0000_4200,
+            }],
+        );
+        converter.add_segment(
+            "__TEXT_EXEC",
+            AddrRange {
+                start: 0x1_0000_4000,
+                end: 0x1_0000_8000,
+            },
+        );
+        converter.add_segment(
+            "__TEXT",
+            AddrRange {
+                start: 0x1_0000_0000,
+                end: 0x1_0000_2000,
+            },
+        );
+        // segments below the image base and duplicates are ignored
+        converter.add_segment(
+            "__LOW",
+            AddrRange {
+                start: 0,
+                end: 0x10,
+            },
+        );
+        converter.add_segment(
+            "__DUP",
+            AddrRange {
+                start: 0x1_0000_4000,
+                end: 0x1_0000_5000,
+            },
+        );
+        let buf = serialize(converter);
+
+        let cache = SymCache::parse(&buf).unwrap();
+        cache.validate().unwrap();
+        let names: Vec<_> = cache.segments().iter().map(|s| s.name).collect();
+        assert_eq!(names, [Some("__TEXT"), Some("__TEXT_EXEC")]);
+        assert_eq!(
+            cache.segment(0x4100).map(|s| s.range),
+            Some(AddrRange {
+                start: 0x4000,
+                end: 0x8000
+            })
+        );
+        assert_eq!(cache.segment(0x3000), None);
+        assert_eq!(cache.segment(0x8000), None);
+
+        let lookup = |segment, offset| {
+            let sl = cache.lookup_segment_offset(segment, offset).next()?;
+            sl.function()?.name().map(String::from)
+        };
+        assert_eq!(lookup("__TEXT", 0x1000).as_deref(), Some("text"));
+        assert_eq!(lookup("__TEXT_EXEC", 0x100).as_deref(), Some("text_exec"));
+        assert_eq!(lookup("__TEXT_EXEC", 0x4000), None);
+        assert_eq!(lookup("__DATA", 0x100), None);
+
+        // segments survive a round trip through another converter
+        let mut converter = SymCacheConverter::new();
+        converter.set_image_base(0x1_0000_0000);
+        converter.process_symcache(&cache).unwrap();
+        let roundtrip = serialize(converter);
+        assert_eq!(roundtrip, buf);
+    }
+}
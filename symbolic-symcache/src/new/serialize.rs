@@ -0,0 +1,82 @@
+//! Serialization of a parsed [`SymCache`] into structured formats, such as JSON.
+
+use serde_::ser::{Serialize, SerializeMap, SerializeSeq, SerializeStruct, Serializer};
+
+use super::{AddrRange, SourceLocation, SymCache};
+
+/// Serializes the contents of a SymCache.
+///
+/// This emits the header fields along with all functions, files and address ranges. Every range
+/// lists its frames from the innermost to the outermost function, like
+/// [`lookup`](SymCache::lookup). Ranges that do not resolve to any frame are omitted.
+impl Serialize for SymCache<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("SymCache", 7)?;
+        state.serialize_field("version", &self.version())?;
+        state.serialize_field("debug_id", &self.debug_id())?;
+        state.serialize_field("arch", &self.arch())?;
+        state.serialize_field("image_base", &self.image_base())?;
+        state.serialize_field("functions", &Seq(|| self.functions()))?;
+        state.serialize_field("files", &Seq(|| self.files()))?;
+        let ranges = || self.ranges().map(|(range, _)| SerializedRange(self, range));
+        state.serialize_field("ranges", &Seq(ranges))?;
+        state.end()
+    }
+}
+
+/// Serializes the items of a lazily created iterator as a sequence.
+struct Seq<F>(F);
+
+impl<F, I> Serialize for Seq<F>
+where
+    F: Fn() -> I,
+    I: Iterator,
+    I::Item: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut seq = serializer.serialize_seq(None)?;
+        for item in (self.0)() {
+            seq.serialize_element(&item)?;
+        }
+        seq.end()
+    }
+}
+
+/// An address range along with all frames of its inline chain.
+struct SerializedRange<'a, 'data>(&'a SymCache<'data>, AddrRange);
+
+impl Serialize for SerializedRange<'_, '_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let SerializedRange(cache, range) = self;
+        let mut map = serializer.serialize_map(Some(3))?;
+        map.serialize_entry("start", &range.start)?;
+        map.serialize_entry("end", &range.end)?;
+        map.serialize_entry("frames", &Seq(|| cache.lookup(range.start)))?;
+        map.end()
+    }
+}
+
+/// Serializes a single frame with its function name, full file path, line and column.
+impl Serialize for SourceLocation<'_, '_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("SourceLocation", 5)?;
+        state.serialize_field("function", &self.function().and_then(|f| f.name()))?;
+        state.serialize_field("file", &self.file().map(|f| f.full_path()))?;
+        state.serialize_field("line", &self.line())?;
+        state.serialize_field("column", &self.column())?;
+        state.serialize_field("inlined", &self.is_inlined())?;
+        state.end()
+    }
+}
@@ -0,0 +1,305 @@
+//! The interned strings of a SymCache while it is being converted.
+
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::fmt;
+use std::fs::File;
+use std::hash::{Hash, Hasher};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+/// The strings of a SymCache, laid out like its string section.
+///
+/// Every string is prefixed by its length as a `u32` and identified by its offset. Strings are
+/// kept in memory until they exceed the [budget](Self::set_budget), after which they are moved
+/// to a temporary file. From then on, only the strings added since are kept in memory, and
+/// strings are deduplicated by a 128 bit hash of their contents instead of the strings
+/// themselves, so that duplicates do not need to be read back from the file. Only strings are
+/// spilled; the files, functions and ranges that refer to them remain in memory.
+///
+/// The whole string section must fit into a `u32`. Strings beyond that are not stored and
+/// record an error, see [`take_error`](Self::take_error).
+#[derive(Default)]
+pub(crate) struct StringTable {
+    /// The strings that have not been spilled, starting at the end of the spilled strings.
+    bytes: Vec<u8>,
+    /// A map from strings to their offsets, as long as no strings have been spilled.
+    offsets: HashMap<String, u32>,
+    /// The strings that have been moved to a temporary file, if any.
+    spill: Option<Spill>,
+    /// The maximum number of string bytes to keep in memory.
+    budget: Option<usize>,
+    /// The first error storing or reading strings, see [`take_error`](Self::take_error).
+    error: Cell<Option<io::Error>>,
+}
+
+/// Strings that have been moved to a temporary file, see [`StringTable::spill`].
+struct Spill {
+    file: File,
+    /// The number of bytes in `file`.
+    len: u32,
+    /// A map from the hashes of all strings to their offsets.
+    offsets: HashMap<u128, u32>,
+}
+
+impl StringTable {
+    /// Creates an empty table with the given budget, see [`set_budget`](Self::set_budget).
+    pub fn with_budget(budget: Option<usize>) -> Self {
+        Self {
+            budget,
+            ..Self::default()
+        }
+    }
+
+    /// Returns the budget of this table, see [`set_budget`](Self::set_budget).
+    pub fn budget(&self) -> Option<usize> {
+        self.budget
+    }
+
+    /// Moves strings to a temporary file once they exceed `budget` bytes in memory.
+    ///
+    /// If the temporary file cannot be written, all strings are kept in memory instead.
+    #[cfg(feature = "spill")]
+    pub fn set_budget(&mut self, budget: usize) {
+        self.budget = Some(budget);
+        self.check_budget();
+    }
+
+    /// Returns the size of all strings in bytes, including their length prefixes.
+    pub fn len(&self) -> usize {
+        self.spilled_len() as usize + self.bytes.len()
+    }
+
+    fn spilled_len(&self) -> u32 {
+        self.spill.as_ref().map_or(0, |spill| spill.len)
+    }
+
+    /// Inserts a string and returns its offset.
+    ///
+    /// If the string was already present, it is not added again. The empty string is not
+    /// stored and always has the offset `u32::MAX`.
+    pub fn insert(&mut self, s: &str) -> u32 {
+        if s.is_empty() {
+            return u32::MAX;
+        }
+
+        let key = match self.spill {
+            Some(ref spill) => {
+                let key = hash(s);
+                if let Some(&offset) = spill.offsets.get(&key) {
+                    return offset;
+                }
+                Some(key)
+            }
+            None => {
+                if let Some(&offset) = self.offsets.get(s) {
+                    return offset;
+                }
+                None
+            }
+        };
+
+        // the end of the string section must be addressable by a `u32`, which also leaves
+        // `u32::MAX` free to mean "no string"
+        let string_end = self.len() as u64 + std::mem::size_of::<u32>() as u64 + s.len() as u64;
+        if string_end > u64::from(u32::MAX) {
+            self.set_error(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "string section exceeds the maximum size of 4 GiB",
+            ));
+            return u32::MAX;
+        }
+
+        let string_offset = self.len() as u32;
+        match (key, &mut self.spill) {
+            (Some(key), Some(spill)) => spill.offsets.insert(key, string_offset),
+            _ => self.offsets.insert(s.to_owned(), string_offset),
+        };
+
+        let string_len = s.len() as u32;
+        self.bytes.extend(string_len.to_le_bytes());
+        self.bytes.extend(s.bytes());
+        // we should have written exactly `string_len + 4` bytes
+        debug_assert_eq!(
+            self.len(),
+            string_offset as usize + string_len as usize + std::mem::size_of::<u32>(),
+        );
+
+        self.check_budget();
+        string_offset
+    }
+
+    /// Reads a string previously inserted with [`insert`](Self::insert).
+    ///
+    /// If a spilled string cannot be read, this returns an empty string and records the error,
+    /// see [`take_error`](Self::take_error).
+    pub fn get(&self, offset: u32) -> Cow<'_, str> {
+        if offset == u32::MAX {
+            return Cow::Borrowed("");
+        }
+
+        self.read(offset).unwrap_or_else(|error| {
+            self.set_error(error);
+            Cow::Borrowed("")
+        })
+    }
+
+    /// Reads the string at the given offset from memory or the temporary file.
+    fn read(&self, offset: u32) -> io::Result<Cow<'_, str>> {
+        match self.spill {
+            Some(ref spill) if offset < spill.len => spill.read(offset).map(Cow::Owned),
+            _ => {
+                let bytes = &self.bytes[(offset - self.spilled_len()) as usize..];
+                let start = std::mem::size_of::<u32>();
+                let len = u32::from_le_bytes(bytes[..start].try_into().unwrap()) as usize;
+                // strings are only ever inserted from `&str`
+                Ok(Cow::Borrowed(
+                    std::str::from_utf8(&bytes[start..start + len]).unwrap(),
+                ))
+            }
+        }
+    }
+
+    /// Returns the first error that occurred storing or reading strings, if any.
+    ///
+    /// Strings that could not be stored or read have been replaced by empty strings, so the data
+    /// derived from them must not be written.
+    pub fn take_error(&self) -> Option<io::Error> {
+        self.error.take()
+    }
+
+    /// Records an error storing or reading strings, unless an earlier one was recorded.
+    pub fn set_error(&self, error: io::Error) {
+        let previous = self.error.take();
+        self.error.set(previous.or(Some(error)));
+    }
+
+    /// Writes the string section.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        if let Some(ref spill) = self.spill {
+            let mut file = &spill.file;
+            file.seek(SeekFrom::Start(0))?;
+            io::copy(&mut file.take(spill.len.into()), writer)?;
+        }
+        writer.write_all(&self.bytes)
+    }
+
+    /// Spills the strings in memory if they exceed the budget.
+    fn check_budget(&mut self) {
+        let over_budget = matches!(self.budget, Some(budget) if self.bytes.len() > budget);
+        if over_budget && self.spill().is_err() {
+            self.budget = None;
+        }
+    }
+
+    /// Moves all strings in memory to the temporary file, creating it if necessary.
+    ///
+    /// If this fails, the strings remain in memory.
+    fn spill(&mut self) -> io::Result<()> {
+        let spill = match self.spill {
+            Some(ref mut spill) => spill,
+            None => {
+                let file = spill_file()?;
+                let offsets = self.offsets.drain().map(|(s, offset)| (hash(&s), offset));
+                self.spill.insert(Spill {
+                    file,
+                    len: 0,
+                    offsets: offsets.collect(),
+                })
+            }
+        };
+
+        spill.file.seek(SeekFrom::Start(spill.len.into()))?;
+        spill.file.write_all(&self.bytes)?;
+        spill.len += self.bytes.len() as u32;
+        self.bytes.clear();
+        Ok(())
+    }
+}
+
+/// Creates the temporary file that strings are moved to.
+#[cfg(feature = "spill")]
+fn spill_file() -> io::Result<File> {
+    tempfile::tempfile()
+}
+
+#[cfg(not(feature = "spill"))]
+fn spill_file() -> io::Result<File> {
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        "spilling strings requires the `spill` feature",
+    ))
+}
+
+impl Spill {
+    /// Reads the string at the given offset from the temporary file.
+    fn read(&self, offset: u32) -> io::Result<String> {
+        let mut file = &self.file;
+        file.seek(SeekFrom::Start(offset.into()))?;
+
+        let mut len = [0; std::mem::size_of::<u32>()];
+        file.read_exact(&mut len)?;
+        let mut bytes = vec![0; u32::from_le_bytes(len) as usize];
+        file.read_exact(&mut bytes)?;
+
+        String::from_utf8(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+impl fmt::Debug for StringTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StringTable")
+            .field("len", &self.len())
+            .field("spilled", &self.spilled_len())
+            .field("budget", &self.budget)
+            .finish()
+    }
+}
+
+/// Computes a 128 bit hash of a string, which identifies spilled strings.
+fn hash(s: &str) -> u128 {
+    let mut std_hasher = DefaultHasher::new();
+    s.hash(&mut std_hasher);
+    let mut fnv_hasher = fnv::FnvHasher::default();
+    s.hash(&mut fnv_hasher);
+    (u128::from(std_hasher.finish()) << 64) | u128::from(fnv_hasher.finish())
+}
+
+#[cfg(all(test, feature = "spill"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spill() {
+        let mut strings = StringTable::with_budget(Some(8));
+        let a = strings.insert("spilled");
+        assert!(strings.spill.is_some());
+        let b = strings.insert("in memory");
+
+        // duplicates resolve to the offsets of the spilled and the remaining strings
+        assert_eq!(strings.insert("spilled"), a);
+        assert_eq!(strings.insert("in memory"), b);
+        assert_eq!(strings.get(a), "spilled");
+        assert_eq!(strings.get(b), "in memory");
+
+        let mut section = Vec::new();
+        strings.write_to(&mut section).unwrap();
+        assert_eq!(section.len(), strings.len());
+        assert!(strings.take_error().is_none());
+    }
+
+    #[test]
+    fn test_overflow() {
+        let mut strings = StringTable::with_budget(Some(0));
+        strings.insert("a");
+        // pretend that the temporary file is almost full, and keep further strings in memory
+        strings.spill.as_mut().unwrap().len = u32::MAX - 8;
+        strings.budget = None;
+
+        assert_eq!(strings.insert("b"), u32::MAX - 8);
+        assert_eq!(strings.insert("too long"), u32::MAX);
+        assert!(strings.take_error().is_some());
+    }
+}
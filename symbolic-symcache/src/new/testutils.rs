@@ -0,0 +1,422 @@
+//! Shared helpers for the unit tests of the SymCache format.
+//!
+//! Most tests declare a few functions and symbols, convert them with a [`SymCacheConverter`] and
+//! look up addresses in the result. [`function`], [`line`] and [`symbol`] declare the inputs, and
+//! [`serialize`] writes the SymCache.
+//!
+//! [`DwarfBuilder`] builds synthetic DWARF debug information. Units, functions, inlinees and line
+//! records are declared with plain structs and written with `gimli::write`, so that tests do not
+//! depend on fixtures and can express edge cases, such as functions with multiple ranges, deeply
+//! nested inlinees, or several compilation units.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::mem;
+
+use gimli::write::{
+    Address, AttributeValue, EndianVec, FileId, LineProgram, LineString, Range, RangeList,
+    Sections, Unit, UnitEntryId,
+};
+use gimli::{constants, Encoding, Format, LineEncoding, LittleEndian};
+use symbolic_common::Name;
+use symbolic_debuginfo::dwarf::{Dwarf, DwarfDebugSession, DwarfSection, Endian};
+use symbolic_debuginfo::{FileInfo, Function, LineInfo, ObjectKind, Symbol, SymbolMap};
+use zerocopy::{AsBytes, FromBytes, LayoutVerified};
+
+use super::raw;
+use super::writer::SymCacheConverter;
+
+/// Serializes the data of a converter into a new buffer.
+pub fn serialize(converter: SymCacheConverter) -> Vec<u8> {
+    let mut buf = Vec::new();
+    converter.serialize(&mut buf).unwrap();
+    buf
+}
+
+/// Returns a line record of `size` bytes at `address` in the file `dir/name`.
+pub fn line(
+    address: u64,
+    size: u64,
+    dir: &'static str,
+    name: &'static str,
+    line: u64,
+) -> LineInfo<'static> {
+    LineInfo {
+        address,
+        size: Some(size),
+        file: FileInfo {
+            name: name.as_bytes(),
+            dir: dir.as_bytes(),
+        },
+        line,
+        column: 0,
+        prologue_end: false,
+        is_stmt: true,
+        basic_block: false,
+    }
+}
+
+/// Returns a function of `size` bytes at `address`, which is not inlined and has no inlinees.
+pub fn function(
+    address: u64,
+    size: u64,
+    name: impl Into<Name<'static>>,
+    lines: Vec<LineInfo<'static>>,
+) -> Function<'static> {
+    Function {
+        address,
+        size,
+        name: name.into(),
+        compilation_dir: b"",
+        lines,
+        inlinees: vec![],
+        inline: false,
+        decl_file: None,
+        decl_line: 0,
+    }
+}
+
+/// Returns a symbol of `size` bytes at `address`.
+pub fn symbol(name: &'static str, address: u64, size: u64) -> Symbol<'static> {
+    Symbol {
+        name: Some(name.into()),
+        address,
+        size,
+    }
+}
+
+/// Returns a function `main` at `0x1000..0x1010` in `main.c`, with `helper` from `helper.h`
+/// inlined at `0x1008..0x1010`.
+///
+/// `main` covers line 1 up to the inlinee, which is called from line 2. `helper` covers line 5.
+pub fn inlined_functions() -> Function<'static> {
+    let inlinee = Function {
+        inline: true,
+        ..function(
+            0x1008,
+            0x8,
+            "helper",
+            vec![line(0x1008, 8, "/build/src", "helper.h", 5)],
+        )
+    };
+    Function {
+        inlinees: vec![inlinee],
+        ..function(
+            0x1000,
+            0x10,
+            "main",
+            vec![
+                line(0x1000, 8, "/build/src", "main.c", 1),
+                line(0x1008, 8, "/build/src", "main.c", 2),
+            ],
+        )
+    }
+}
+
+/// Returns a SymCache with a single symbol `main` at `0x1000..0x1010`.
+pub fn symbol_cache() -> Vec<u8> {
+    let mut converter = SymCacheConverter::new();
+    converter.process_symbolic_symbol(&symbol("main", 0x1000, 0x10));
+    serialize(converter)
+}
+
+/// Reinterprets the start of an aligned buffer as a mutable header.
+pub fn header_mut(buf: &mut [u8]) -> &mut raw::Header {
+    LayoutVerified::<_, raw::Header>::new_from_prefix(buf)
+        .unwrap()
+        .0
+        .into_mut()
+}
+
+/// Reinterprets `len` elements at `offset` in an aligned buffer as a mutable slice.
+pub fn slice_mut<T: FromBytes + AsBytes>(buf: &mut [u8], offset: usize, len: usize) -> &mut [T] {
+    let bytes = &mut buf[offset..][..len * mem::size_of::<T>()];
+    LayoutVerified::new_slice(bytes).unwrap().into_mut_slice()
+}
+
+/// A compilation unit of a [`DwarfBuilder`].
+#[derive(Clone, Debug, Default)]
+pub struct TestUnit {
+    /// The name of the unit, which is usually the path of its primary source file.
+    pub name: &'static str,
+    /// The compilation directory of the unit.
+    pub comp_dir: &'static str,
+    /// The functions defined in this unit.
+    pub functions: Vec<TestFunction>,
+    /// Line records as `(address, file, line)`, sorted by address.
+    ///
+    /// Each record extends to the next one, and the last record to the end of the unit.
+    pub lines: Vec<(u64, &'static str, u64)>,
+}
+
+/// A function or inlinee of a [`TestUnit`].
+#[derive(Clone, Debug, Default)]
+pub struct TestFunction {
+    /// The name of the function.
+    pub name: &'static str,
+    /// The address ranges covered by the function as `(start, length)`.
+    ///
+    /// Functions with a single range are written with `DW_AT_low_pc` and `DW_AT_high_pc`, all
+    /// others with `DW_AT_ranges`.
+    pub ranges: Vec<(u64, u64)>,
+    /// The file and line of the call site if this function is inlined into its parent.
+    pub call_site: Option<(&'static str, u64)>,
+    /// The functions inlined into this function.
+    pub inlinees: Vec<TestFunction>,
+}
+
+/// Writes [`TestUnit`]s into DWARF sections.
+#[derive(Debug, Default)]
+pub struct DwarfBuilder {
+    units: Vec<TestUnit>,
+}
+
+impl DwarfBuilder {
+    /// Creates an empty builder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a compilation unit.
+    pub fn unit(mut self, unit: TestUnit) -> Self {
+        self.units.push(unit);
+        self
+    }
+
+    /// Writes all units as DWARF 4 for a 64-bit little-endian target.
+    pub fn build(self) -> TestDwarf {
+        let encoding = Encoding {
+            format: Format::Dwarf32,
+            version: 4,
+            address_size: 8,
+        };
+
+        let mut dwarf = gimli::write::Dwarf::new();
+        for test_unit in &self.units {
+            let unit_id = dwarf.units.add(Unit::new(encoding, LineProgram::none()));
+            write_unit(dwarf.units.get_mut(unit_id), encoding, test_unit);
+        }
+
+        let mut sections = Sections::new(EndianVec::new(LittleEndian));
+        dwarf.write(&mut sections).unwrap();
+
+        let mut data = HashMap::new();
+        sections
+            .for_each(|id, section| -> gimli::write::Result<()> {
+                if !section.slice().is_empty() {
+                    data.insert(id.name().trim_start_matches('.'), section.slice().to_vec());
+                }
+                Ok(())
+            })
+            .unwrap();
+
+        TestDwarf { sections: data }
+    }
+
+    /// Writes all units and converts them into a SymCache.
+    pub fn convert(self) -> Vec<u8> {
+        let dwarf = self.build();
+        let session =
+            DwarfDebugSession::parse(&dwarf, SymbolMap::new(), 0, ObjectKind::Debug).unwrap();
+
+        let mut converter = SymCacheConverter::new();
+        converter.process_dwarf(&session, None).unwrap();
+        serialize(converter)
+    }
+}
+
+/// Returns the extent of all ranges of the given functions and their inlinees.
+fn extent(functions: &[TestFunction]) -> Option<(u64, u64)> {
+    functions
+        .iter()
+        .flat_map(|function| {
+            let inlinees = extent(&function.inlinees);
+            function
+                .ranges
+                .iter()
+                .map(|&(start, len)| (start, start + len))
+                .chain(inlinees)
+        })
+        .reduce(|(start, end), (s, e)| (start.min(s), end.max(e)))
+}
+
+fn write_unit(unit: &mut Unit, encoding: Encoding, test_unit: &TestUnit) {
+    let (low_pc, high_pc) = extent(&test_unit.functions).unwrap_or((0, 0));
+
+    let mut program = LineProgram::new(
+        encoding,
+        LineEncoding::default(),
+        LineString::String(test_unit.comp_dir.into()),
+        LineString::String(test_unit.name.into()),
+        None,
+    );
+
+    let mut files = HashMap::new();
+    let mut file_id = |program: &mut LineProgram, name: &'static str| -> FileId {
+        *files.entry(name).or_insert_with(|| {
+            let directory = program.default_directory();
+            program.add_file(LineString::String(name.into()), directory, None)
+        })
+    };
+
+    if let Some(&(first, _, _)) = test_unit.lines.first() {
+        program.begin_sequence(Some(Address::Constant(first)));
+        for &(address, file, line) in &test_unit.lines {
+            let file = file_id(&mut program, file);
+            let row = program.row();
+            row.address_offset = address - first;
+            row.file = file;
+            row.line = line;
+            program.generate_row();
+        }
+        program.end_sequence(high_pc.max(first) - first);
+    }
+
+    // resolve call files before the line program is moved into the unit
+    let mut call_files = HashMap::new();
+    collect_call_files(&test_unit.functions, &mut |name| {
+        call_files.insert(name, file_id(&mut program, name));
+    });
+    unit.line_program = program;
+
+    let root = unit.root();
+    let entry = unit.get_mut(root);
+    entry.set(
+        constants::DW_AT_name,
+        AttributeValue::String(test_unit.name.into()),
+    );
+    entry.set(
+        constants::DW_AT_comp_dir,
+        AttributeValue::String(test_unit.comp_dir.into()),
+    );
+    entry.set(
+        constants::DW_AT_language,
+        AttributeValue::Language(constants::DW_LANG_C99),
+    );
+    entry.set(
+        constants::DW_AT_low_pc,
+        AttributeValue::Address(Address::Constant(low_pc)),
+    );
+    entry.set(
+        constants::DW_AT_high_pc,
+        AttributeValue::Udata(high_pc - low_pc),
+    );
+
+    let mut abstract_origins = HashMap::new();
+    for function in &test_unit.functions {
+        write_function(unit, root, function, &call_files, &mut abstract_origins);
+    }
+}
+
+fn collect_call_files(functions: &[TestFunction], f: &mut impl FnMut(&'static str)) {
+    for function in functions {
+        if let Some((file, _)) = function.call_site {
+            f(file);
+        }
+        collect_call_files(&function.inlinees, f);
+    }
+}
+
+fn write_function(
+    unit: &mut Unit,
+    parent: UnitEntryId,
+    function: &TestFunction,
+    call_files: &HashMap<&'static str, FileId>,
+    abstract_origins: &mut HashMap<&'static str, UnitEntryId>,
+) {
+    let id = match function.call_site {
+        Some((call_file, call_line)) => {
+            // inlinees refer to an abstract instance of their function for the name
+            let root = unit.root();
+            let origin = *abstract_origins.entry(function.name).or_insert_with(|| {
+                let origin = unit.add(root, constants::DW_TAG_subprogram);
+                let entry = unit.get_mut(origin);
+                entry.set(
+                    constants::DW_AT_name,
+                    AttributeValue::String(function.name.into()),
+                );
+                entry.set(
+                    constants::DW_AT_inline,
+                    AttributeValue::Inline(constants::DW_INL_inlined),
+                );
+                origin
+            });
+
+            let id = unit.add(parent, constants::DW_TAG_inlined_subroutine);
+            let entry = unit.get_mut(id);
+            entry.set(
+                constants::DW_AT_abstract_origin,
+                AttributeValue::UnitRef(origin),
+            );
+            entry.set(
+                constants::DW_AT_call_file,
+                AttributeValue::FileIndex(Some(call_files[call_file])),
+            );
+            entry.set(constants::DW_AT_call_line, AttributeValue::Udata(call_line));
+            id
+        }
+        None => {
+            let id = unit.add(parent, constants::DW_TAG_subprogram);
+            unit.get_mut(id).set(
+                constants::DW_AT_name,
+                AttributeValue::String(function.name.into()),
+            );
+            id
+        }
+    };
+
+    match function.ranges[..] {
+        [(start, len)] => {
+            let entry = unit.get_mut(id);
+            entry.set(
+                constants::DW_AT_low_pc,
+                AttributeValue::Address(Address::Constant(start)),
+            );
+            entry.set(constants::DW_AT_high_pc, AttributeValue::Udata(len));
+        }
+        ref ranges => {
+            // DWARF 4 range lists are relative to the unit's `DW_AT_low_pc` unless they select a
+            // base address, so reset it to zero to write absolute addresses.
+            let base = Range::BaseAddress {
+                address: Address::Constant(0),
+            };
+            let ranges = std::iter::once(base)
+                .chain(ranges.iter().map(|&(start, len)| Range::StartLength {
+                    begin: Address::Constant(start),
+                    length: len,
+                }))
+                .collect();
+            let ranges = unit.ranges.add(RangeList(ranges));
+            unit.get_mut(id).set(
+                constants::DW_AT_ranges,
+                AttributeValue::RangeListRef(ranges),
+            );
+        }
+    }
+
+    for inlinee in &function.inlinees {
+        write_function(unit, id, inlinee, call_files, abstract_origins);
+    }
+}
+
+/// DWARF sections written by a [`DwarfBuilder`].
+#[derive(Debug)]
+pub struct TestDwarf {
+    sections: HashMap<&'static str, Vec<u8>>,
+}
+
+impl<'data> Dwarf<'data> for TestDwarf {
+    fn endianity(&self) -> Endian {
+        Endian::Little
+    }
+
+    fn raw_section(&self, name: &str) -> Option<DwarfSection<'data>> {
+        let data = self.sections.get(name)?;
+        Some(DwarfSection {
+            address: 0,
+            offset: 0,
+            align: 1,
+            data: Cow::Owned(data.clone()),
+        })
+    }
+}
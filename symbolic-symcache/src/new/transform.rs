@@ -24,6 +24,18 @@ pub struct File<'s> {
     pub comp_dir: Option<Cow<'s, str>>,
 }
 
+impl File<'_> {
+    /// Resolves and concatenates the full path based on its individual fragments.
+    ///
+    /// This is the same path that [`File::full_path`](super::File::full_path) resolves once the
+    /// file is written to the SymCache.
+    pub fn full_path(&self) -> String {
+        let comp_dir = self.comp_dir.as_deref().unwrap_or_default();
+        let directory = self.directory.as_deref().unwrap_or_default();
+        symbolic_common::join_file_path(comp_dir, directory, &self.name)
+    }
+}
+
 /// A Source Location (File + Line) to be written to the SymCache.
 #[non_exhaustive]
 pub struct SourceLocation<'s> {
@@ -62,6 +74,114 @@ impl std::fmt::Debug for Transformers {
     }
 }
 
+/// A [`Transformer`] that applies a function to every string, see
+/// [`SymCacheConverter::transform_strings`](super::writer::SymCacheConverter::transform_strings).
+pub(crate) struct StringTransformer<F>(pub F);
+
+impl<F> StringTransformer<F>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    fn transform<'s>(&self, s: Cow<'s, str>) -> Cow<'s, str> {
+        match (self.0)(&s) {
+            Some(transformed) => Cow::Owned(transformed),
+            None => s,
+        }
+    }
+}
+
+impl<F> Transformer for StringTransformer<F>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    fn transform_function<'f>(&'f self, f: Function<'f>) -> Function<'f> {
+        Function {
+            name: self.transform(f.name),
+            comp_dir: f.comp_dir.map(|dir| self.transform(dir)),
+        }
+    }
+
+    fn transform_source_location<'f>(&'f self, sl: SourceLocation<'f>) -> SourceLocation<'f> {
+        SourceLocation {
+            file: File {
+                name: self.transform(sl.file.name),
+                directory: sl.file.directory.map(|dir| self.transform(dir)),
+                comp_dir: sl.file.comp_dir.map(|dir| self.transform(dir)),
+            },
+            line: sl.line,
+        }
+    }
+}
+
+/// Rewrites the paths of files and compilation directories, see
+/// [`SymCacheConverter::remap_path_prefix`](super::writer::SymCacheConverter::remap_path_prefix).
+#[derive(Clone, Debug, Default)]
+pub(crate) struct PathRewrites {
+    /// Pairs of prefixes and their replacements, in the order they were added.
+    pub prefixes: Vec<(String, String)>,
+    /// Whether backslashes are replaced with forward slashes.
+    pub normalize_separators: bool,
+}
+
+impl PathRewrites {
+    /// Rewrites a single path, or returns it unchanged if no rewrite applies.
+    fn rewrite<'s>(&self, path: Cow<'s, str>) -> Cow<'s, str> {
+        let path = if self.normalize_separators && path.contains('\\') {
+            Cow::Owned(path.replace('\\', "/"))
+        } else {
+            path
+        };
+
+        // like `-fdebug-prefix-map`, the last matching prefix wins
+        for (prefix, replacement) in self.prefixes.iter().rev() {
+            let rest = match path.strip_prefix(prefix.as_str()) {
+                Some(rest) => rest,
+                None => continue,
+            };
+            // prefixes only match entire path components
+            let at_boundary =
+                rest.is_empty() || prefix.ends_with(is_separator) || rest.starts_with(is_separator);
+            if !at_boundary {
+                continue;
+            }
+
+            return if replacement.is_empty() {
+                // stripped paths become relative
+                Cow::Owned(rest.trim_start_matches(is_separator).to_owned())
+            } else {
+                Cow::Owned(format!("{}{}", replacement, rest))
+            };
+        }
+
+        path
+    }
+}
+
+/// Returns whether the character separates path components on any platform.
+fn is_separator(c: char) -> bool {
+    c == '/' || c == '\\'
+}
+
+impl Transformer for PathRewrites {
+    fn transform_function<'f>(&'f self, f: Function<'f>) -> Function<'f> {
+        Function {
+            name: f.name,
+            comp_dir: f.comp_dir.map(|dir| self.rewrite(dir)),
+        }
+    }
+
+    fn transform_source_location<'f>(&'f self, sl: SourceLocation<'f>) -> SourceLocation<'f> {
+        SourceLocation {
+            file: File {
+                name: self.rewrite(sl.file.name),
+                directory: sl.file.directory.map(|dir| self.rewrite(dir)),
+                comp_dir: sl.file.comp_dir.map(|dir| self.rewrite(dir)),
+            },
+            line: sl.line,
+        }
+    }
+}
+
 // This ended up as a macro which "inlines" mapping the `Cow` into the calling function, as using
 // a real function here would lead to the following borrow checker error:
 // error[E0495]: cannot infer an appropriate lifetime for lifetime parameter `'d` due to conflicting requirements
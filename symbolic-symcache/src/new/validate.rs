@@ -0,0 +1,201 @@
+//! Deep consistency checks of a parsed [`SymCache`].
+
+use std::convert::TryFrom;
+
+use super::{read_string, Error, Result, SymCache};
+
+/// The state of a source location while checking inline chains for cycles.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Visit {
+    Unvisited,
+    InProgress,
+    Done,
+}
+
+impl<'data> SymCache<'data> {
+    /// Checks the consistency of all data in the SymCache.
+    ///
+    /// [`parse`](Self::parse) only checks that the sections fit into the buffer, and lookups
+    /// gracefully handle invalid references. This walks every file, function, source location,
+    /// range, call edge and candidate, and returns an error if:
+    ///
+    ///  - an index refers to a file, function, source location or range that does not exist
+    ///    ([`Error::BadIndexReference`]),
+    ///  - a source location is transitively inlined into itself ([`Error::CyclicInlineChain`]),
+    ///  - the ranges are not sorted by address without duplicates ([`Error::UnsortedRanges`]),
+    ///  - a string offset is out of bounds or refers to invalid UTF-8
+    ///    ([`Error::BadStringReference`]).
+    ///
+    /// This is useful to reject untrusted SymCaches up front. It does not check the contents
+    /// against the checksum, see [`verify`](Self::verify).
+    pub fn validate(&self) -> Result<()> {
+        for file in self.files {
+            read_string(self.string_bytes, file.comp_dir_offset.get())?;
+            read_string(self.string_bytes, file.directory_offset.get())?;
+            read_string(self.string_bytes, file.path_name_offset.get())?;
+        }
+
+        for function in self.functions {
+            read_string(self.string_bytes, function.name_offset.get())?;
+            read_string(self.string_bytes, function.comp_dir_offset.get())?;
+        }
+        for linkage_name in self.linkage_names {
+            read_string(self.string_bytes, linkage_name.get())?;
+        }
+        for segment in self.segments {
+            let name_offset =
+                u32::try_from(segment.name_offset.get()).map_err(|_| Error::BadStringReference)?;
+            read_string(self.string_bytes, name_offset)?;
+        }
+
+        let in_bounds = |idx: u32, len: usize| idx == u32::MAX || (idx as usize) < len;
+        if self
+            .name_index
+            .iter()
+            .any(|idx| idx.get() as usize >= self.functions.len())
+        {
+            return Err(Error::BadIndexReference);
+        }
+        for source_location in self.source_locations {
+            if !in_bounds(source_location.file_idx.get(), self.files.len())
+                || !in_bounds(source_location.function_idx.get(), self.functions.len())
+                || !in_bounds(
+                    source_location.inlined_into_idx.get(),
+                    self.source_locations.len(),
+                )
+            {
+                return Err(Error::BadIndexReference);
+            }
+        }
+        for edge in self.call_edges {
+            if !in_bounds(edge.caller_idx.get(), self.functions.len())
+                || !in_bounds(edge.callee_idx.get(), self.functions.len())
+                || !in_bounds(edge.file_idx.get(), self.files.len())
+            {
+                return Err(Error::BadIndexReference);
+            }
+        }
+        for candidate in self.candidates {
+            if candidate.range_idx.get() as usize >= self.ranges.len()
+                || candidate.source_location_idx.get() as usize >= self.source_locations.len()
+            {
+                return Err(Error::BadIndexReference);
+            }
+        }
+        for declaration in self.declarations {
+            if !in_bounds(declaration.file_idx.get(), self.files.len()) {
+                return Err(Error::BadIndexReference);
+            }
+        }
+        self.validate_inline_chains()?;
+
+        let sorted =
+            (1..self.ranges.len()).all(|idx| self.range_start(idx - 1) < self.range_start(idx));
+        if !sorted {
+            return Err(Error::UnsortedRanges);
+        }
+
+        Ok(())
+    }
+
+    /// Checks that no source location is transitively inlined into itself.
+    ///
+    /// Every source location is visited once. All indexes must already be checked.
+    fn validate_inline_chains(&self) -> Result<()> {
+        let mut visits = vec![Visit::Unvisited; self.source_locations.len()];
+        let mut chain = Vec::new();
+
+        for start_idx in 0..self.source_locations.len() {
+            let mut idx = start_idx as u32;
+            while idx != u32::MAX {
+                match visits[idx as usize] {
+                    Visit::Unvisited => {}
+                    Visit::InProgress => return Err(Error::CyclicInlineChain),
+                    Visit::Done => break,
+                }
+                visits[idx as usize] = Visit::InProgress;
+                chain.push(idx);
+                idx = self.source_locations[idx as usize].inlined_into_idx.get();
+            }
+
+            for idx in chain.drain(..) {
+                visits[idx as usize] = Visit::Done;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::new::raw;
+    use crate::new::testutils::{inlined_functions, serialize, slice_mut};
+    use crate::new::writer::SymCacheConverter;
+
+    /// Validates a copy of the SymCache in `buf` after applying `patch` to its sections.
+    fn validate_patched(
+        buf: &[u8],
+        patch: impl FnOnce(&mut [raw::Function], &mut [raw::SourceLocation], &mut [raw::Range]),
+    ) -> Result<()> {
+        let mut buf = buf.to_vec();
+        let cache = SymCache::parse(&buf).unwrap();
+        let offset = |ptr: *const u8| ptr as usize - buf.as_ptr() as usize;
+        let functions = (
+            offset(cache.functions.as_ptr().cast()),
+            cache.functions.len(),
+        );
+        let source_locations = (
+            offset(cache.source_locations.as_ptr().cast()),
+            cache.source_locations.len(),
+        );
+        let ranges = (offset(cache.ranges.as_ptr().cast()), cache.ranges.len());
+
+        // the sections are in this order, so the buffer can be split between them
+        let (rest, ranges_buf) = buf.split_at_mut(ranges.0);
+        let (rest, source_locations_buf) = rest.split_at_mut(source_locations.0);
+        patch(
+            slice_mut(rest, functions.0, functions.1),
+            slice_mut(source_locations_buf, 0, source_locations.1),
+            slice_mut(ranges_buf, 0, ranges.1),
+        );
+
+        SymCache::parse(&buf)?.validate()
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut converter = SymCacheConverter::new();
+        converter.process_symbolic_function(&inlined_functions());
+        let buf = serialize(converter);
+        assert!(validate_patched(&buf, |_, _, _| ()).is_ok());
+
+        let result = validate_patched(&buf, |_, sls, _| sls[0].file_idx.set(100));
+        assert!(matches!(result, Err(Error::BadIndexReference)));
+        let result = validate_patched(&buf, |_, sls, _| sls[0].function_idx.set(100));
+        assert!(matches!(result, Err(Error::BadIndexReference)));
+        let result = validate_patched(&buf, |_, sls, _| sls[0].inlined_into_idx.set(100));
+        assert!(matches!(result, Err(Error::BadIndexReference)));
+
+        let result = validate_patched(&buf, |_, sls, _| sls[0].inlined_into_idx.set(0));
+        assert!(matches!(result, Err(Error::CyclicInlineChain)));
+        let result = validate_patched(&buf, |_, sls, _| {
+            let last = sls.len() as u32 - 1;
+            sls[0].inlined_into_idx.set(last);
+            sls[last as usize].inlined_into_idx.set(0);
+        });
+        assert!(matches!(result, Err(Error::CyclicInlineChain)));
+
+        let result = validate_patched(&buf, |_, _, ranges| ranges.swap(0, 1));
+        assert!(matches!(result, Err(Error::UnsortedRanges)));
+        let result = validate_patched(&buf, |_, _, ranges| ranges[1].0 = ranges[0].0);
+        assert!(matches!(result, Err(Error::UnsortedRanges)));
+
+        let result = validate_patched(&buf, |functions, _, _| {
+            functions[0].name_offset.set(u32::MAX - 1)
+        });
+        assert!(matches!(result, Err(Error::BadStringReference)));
+    }
+}
@@ -1,16 +1,311 @@
 //! Defines the [SymCache Converter](`SymCacheConverter`).
 
+use std::borrow::Cow;
 use std::collections::btree_map;
-use std::collections::{BTreeMap, HashMap};
-use std::io::Write;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::convert::TryFrom;
+use std::io::{Read, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
 
 use indexmap::IndexSet;
-use symbolic_common::{Arch, DebugId};
+use symbolic_common::{Arch, DebugId, Language};
+#[cfg(feature = "demangle")]
+use symbolic_common::{Name, NameMangling};
+use symbolic_debuginfo::dwarf::{
+    DwarfDebugSession, DwarfError, DwarfErrorKind, DwarfFunctionIterator,
+};
+use symbolic_debuginfo::macho::BcSymbolMap;
 use symbolic_debuginfo::{DebugSession, Function, ObjectLike, Symbol};
+#[cfg(feature = "demangle")]
+use symbolic_demangle::{Demangle, DemangleOptions};
+use zerocopy::AsBytes;
 
-use super::{raw, transform};
+use super::gopclntab::GoPclnTab;
+use super::gsym::Gsym;
+use super::jit;
+use super::ppdb::PortablePdb;
+use super::strings::StringTable;
+use super::transform::Transformer;
+use super::{raw, read_string, transform, AddrRange, SymCache};
+use crate::compat::SYMCACHE_VERSION_CUTOFF;
+use crate::old::{self, LineInfo};
+use crate::preamble::Preamble;
 use crate::{SymCacheError, SymCacheErrorKind};
 
+/// The default for
+/// [`SymCacheWriter::set_max_inline_depth`](super::SymCacheWriter::set_max_inline_depth).
+///
+/// Real-world debug information rarely nests inlined functions more than a few dozen levels deep.
+pub const DEFAULT_MAX_INLINE_DEPTH: usize = 256;
+
+/// Statistics about a conversion performed by the [`SymCacheWriter`](super::SymCacheWriter).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Stats {
+    /// The number of line records with a line number too large for the SymCache format.
+    ///
+    /// These line numbers are recorded as unknown, i.e. `0`.
+    pub overflowing_lines: u64,
+    /// The number of inline frames dropped by [`limit_size`](super::SymCacheWriter::limit_size).
+    pub dropped_inline_frames: u64,
+    /// The number of ranges whose file and line were dropped by
+    /// [`limit_size`](super::SymCacheWriter::limit_size).
+    pub dropped_line_info: u64,
+    /// The number of line records that start at the same address as a line record of another
+    /// top-level function, for example due to identical code folding, or of a merged converter.
+    ///
+    /// Only one of the records is kept, see [`merge`](super::SymCacheWriter::merge).
+    pub conflicting_lines: u64,
+    /// The number of top-level functions processed.
+    ///
+    /// If a conversion is cancelled, this tells how far processing got.
+    pub processed_functions: u64,
+    /// The number of functions and symbols dropped by the
+    /// [function filter](super::SymCacheWriter::set_function_filter).
+    pub filtered_functions: u64,
+    /// The number of line records whose file and line were dropped by the
+    /// [file filter](super::SymCacheWriter::set_file_filter).
+    pub filtered_lines: u64,
+    /// The number of ranges merged into the range before them because both resolve to the same
+    /// function, file, line and inline chain, see
+    /// [`coalesce_ranges`](super::SymCacheWriter::coalesce_ranges).
+    pub coalesced_ranges: u64,
+    /// The number of inlined functions skipped because they are nested deeper than the
+    /// [maximum inline depth](super::SymCacheWriter::set_max_inline_depth).
+    ///
+    /// Only the outermost skipped inlinees are counted, not the functions inlined into them.
+    pub skipped_inlinees: u64,
+    /// Statistics about each DWARF compilation unit that contained functions, in the order of
+    /// the units.
+    ///
+    /// This is only recorded by [`process_object`](super::SymCacheWriter::process_object) and helps
+    /// to analyze which parts of the debug information dominate the size of a SymCache.
+    pub units: Vec<UnitStats>,
+}
+
+/// Statistics about a single DWARF compilation unit, see [`Stats::units`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct UnitStats {
+    /// The index of the compilation unit in the debug file.
+    pub index: usize,
+    /// The number of top-level functions.
+    pub functions: u64,
+    /// The number of top-level functions that were skipped because they are empty or below the
+    /// image base.
+    pub skipped_functions: u64,
+    /// The number of inlined functions at any depth.
+    pub inlinees: u64,
+    /// The number of inlined functions by their depth.
+    ///
+    /// The first element counts functions inlined directly into top-level functions, the second
+    /// one functions inlined into those, and so on.
+    pub inline_depths: Vec<u64>,
+    /// The number of line records of all functions and inlinees.
+    pub line_records: u64,
+    /// The number of line records that were dropped or recorded without a line number, see
+    /// [`Stats::conflicting_lines`] and [`Stats::overflowing_lines`].
+    pub dropped_lines: u64,
+    /// The number of bytes this unit added to the strings of the SymCache.
+    ///
+    /// Strings that were already added by an earlier unit are not counted again, unless units
+    /// are converted in parallel.
+    pub string_bytes: u64,
+}
+
+impl UnitStats {
+    /// Counts the inlinees and line records of a function.
+    fn add_function(&mut self, function: &Function<'_>, depth: usize) {
+        self.line_records += function.lines.len() as u64;
+        for inlinee in &function.inlinees {
+            if self.inline_depths.len() <= depth {
+                self.inline_depths.resize(depth + 1, 0);
+            }
+            self.inline_depths[depth] += 1;
+            self.inlinees += 1;
+            self.add_function(inlinee, depth + 1);
+        }
+    }
+}
+
+/// How the [`SymCacheWriter`](super::SymCacheWriter) identifies functions, see
+/// [`set_function_identity`](super::SymCacheWriter::set_function_identity).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FunctionIdentity {
+    /// Functions are identified by their name, compilation directory, language and entry address.
+    ///
+    /// Inlined functions do not have an entry address, so equally named inlined functions from
+    /// the same compilation directory are merged into one. This results in the smallest
+    /// SymCaches.
+    #[default]
+    Name,
+    /// In addition to [`Name`](Self::Name), functions are identified by the source file of their
+    /// first line.
+    ///
+    /// This keeps apart `static` functions with identical names in different compilation
+    /// units, which is useful for per-function analytics.
+    NameAndFile,
+}
+
+/// An error in the debug information encountered during a conversion, see [`ErrorSink`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ConversionError {
+    /// The name of the compilation unit that contains the error, if known.
+    pub unit_name: Option<String>,
+    /// The offset of the debugging information entry that caused the error, if known.
+    pub die_offset: Option<u64>,
+    /// The address in the SymCache at which the error occurred, if known.
+    pub address: Option<u64>,
+    /// The name of the function that caused the error, if known.
+    pub function: Option<String>,
+    /// The kind of error.
+    pub kind: ConversionErrorKind,
+    /// Whether the conversion continued after this error.
+    pub severity: Severity,
+}
+
+impl ConversionError {
+    fn from_dwarf(error: &DwarfError) -> Self {
+        let severity = match error.kind() {
+            DwarfErrorKind::InvalidFileRef(_)
+            | DwarfErrorKind::UnexpectedInline
+            | DwarfErrorKind::InvertedFunctionRange
+            | DwarfErrorKind::InvalidLineSequence => Severity::Warning,
+            _ => Severity::Fatal,
+        };
+
+        Self {
+            unit_name: error.unit_name().map(ToOwned::to_owned),
+            die_offset: error.die_offset(),
+            address: None,
+            function: None,
+            kind: ConversionErrorKind::Dwarf(error.kind()),
+            severity,
+        }
+    }
+
+    fn conflicting_lines(address: u64, function: &str) -> Self {
+        Self {
+            unit_name: None,
+            die_offset: None,
+            address: Some(address),
+            function: Some(function.to_owned()),
+            kind: ConversionErrorKind::ConflictingLines,
+            severity: Severity::Warning,
+        }
+    }
+}
+
+/// The kind of a [`ConversionError`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ConversionErrorKind {
+    /// The DWARF debug information is invalid.
+    Dwarf(DwarfErrorKind),
+    /// A line record of a top-level function starts at the same address as a line record of
+    /// another top-level function, see [`Stats::conflicting_lines`].
+    ///
+    /// The error names the function of the line record that was processed last.
+    ConflictingLines,
+}
+
+/// The severity of a [`ConversionError`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// Invalid debug information was skipped, and the conversion continues.
+    ///
+    /// After an invalid entry, the remaining functions of its compilation unit are skipped.
+    /// After an invalid line sequence, the line program of the unit is truncated, but its
+    /// functions are still converted.
+    Warning,
+    /// The debug information is corrupted, and the conversion fails.
+    Fatal,
+}
+
+/// Receives the errors encountered while converting DWARF, see
+/// [`SymCacheWriter::set_error_sink`](super::SymCacheWriter::set_error_sink).
+///
+/// This is implemented for all closures taking a [`ConversionError`].
+pub trait ErrorSink {
+    /// Reports an error.
+    fn report(&mut self, error: ConversionError);
+}
+
+impl<F> ErrorSink for F
+where
+    F: FnMut(ConversionError),
+{
+    fn report(&mut self, error: ConversionError) {
+        self(error)
+    }
+}
+
+// This is essentially just a newtype in order to implement `Debug`.
+#[derive(Default)]
+struct ErrorSinks(Option<Box<dyn ErrorSink>>);
+
+impl ErrorSinks {
+    /// Reports a line record that conflicts with another one at the same address.
+    fn report_conflicting_lines(&mut self, address: u64, function: &str) {
+        if let Some(ref mut sink) = self.0 {
+            sink.report(ConversionError::conflicting_lines(address, function));
+        }
+    }
+}
+
+impl std::fmt::Debug for ErrorSinks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("ErrorSinks")
+            .field(&self.0.is_some())
+            .finish()
+    }
+}
+
+/// Callbacks that decide which functions and files are kept, see
+/// [`SymCacheConverter::set_function_filter`] and [`SymCacheConverter::set_file_filter`].
+#[derive(Default)]
+struct Filters {
+    function: Option<FunctionFilter>,
+    file: Option<FileFilter>,
+}
+
+type FunctionFilter = Box<dyn Fn(&transform::Function<'_>) -> bool>;
+type FileFilter = Box<dyn Fn(&transform::File<'_>) -> bool>;
+
+impl Filters {
+    #[cfg(feature = "parallel")]
+    fn is_empty(&self) -> bool {
+        self.function.is_none() && self.file.is_none()
+    }
+
+    fn keep_function(&self, function: &transform::Function<'_>) -> bool {
+        match self.function {
+            Some(ref filter) => filter(function),
+            None => true,
+        }
+    }
+
+    fn keep_file(&self, file: &transform::File<'_>) -> bool {
+        match self.file {
+            Some(ref filter) => filter(file),
+            None => true,
+        }
+    }
+}
+
+impl std::fmt::Debug for Filters {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Filters")
+            .field("function", &self.function.is_some())
+            .field("file", &self.file.is_some())
+            .finish()
+    }
+}
+
 /// The SymCache Converter.
 ///
 /// This can convert data in various source formats to an intermediate representation, which can
@@ -22,31 +317,85 @@ pub struct SymCacheConverter {
     /// CPU architecture of the object file.
     arch: Arch,
 
+    /// The alignment of the header and each section, see [`set_section_alignment`](Self::set_section_alignment).
+    section_alignment: u32,
+
+    /// The base address subtracted from all addresses, see [`set_image_base`](Self::set_image_base).
+    image_base: u64,
+
+    /// How functions are identified, see [`set_function_identity`](Self::set_function_identity).
+    function_identity: FunctionIdentity,
+    /// Whether an index of function names is written, see [`set_name_index`](Self::set_name_index).
+    name_index: bool,
+    /// Whether the call edges between functions are written, see
+    /// [`set_call_edges`](Self::set_call_edges).
+    call_edges: bool,
+    /// Whether dropped attributions of conflicting ranges are kept, see
+    /// [`set_keep_candidates`](Self::set_keep_candidates).
+    keep_candidates: bool,
+
+    /// How deeply inlined functions are nested at most, see
+    /// [`set_max_inline_depth`](Self::set_max_inline_depth).
+    max_inline_depth: Option<usize>,
+
+    /// How function names are demangled, see [`set_demangling`](Self::set_demangling).
+    #[cfg(feature = "demangle")]
+    demangle_options: Option<DemangleOptions>,
+    /// Whether linkage names of demangled functions are kept, see
+    /// [`set_keep_linkage_names`](Self::set_keep_linkage_names).
+    #[cfg(feature = "demangle")]
+    keep_linkage_names: bool,
+
     /// A list of transformers that are used to transform each function / source location.
     transformers: transform::Transformers,
+    /// Rewrites of file paths, see [`remap_path_prefix`](Self::remap_path_prefix).
+    path_rewrites: transform::PathRewrites,
+    /// Callbacks that drop functions and files, see [`set_function_filter`](Self::set_function_filter).
+    filters: Filters,
+    /// The start addresses of top-level functions dropped by the function filter, which must not
+    /// be filled in from the symbol table.
+    filtered_addrs: HashSet<u64>,
 
-    /// The concatenation of all strings that have been added to this `Converter`.
-    string_bytes: Vec<u8>,
-    /// A map from [`String`]s that have been added to this `Converter` to their offsets in the `string_bytes` field.
-    strings: HashMap<String, u32>,
-    /// The set of all [`raw::File`]s that have been added to this `Converter`.
-    files: IndexSet<raw::File>,
-    /// The set of all [`raw::Function`]s that have been added to this `Converter`.
-    functions: IndexSet<raw::Function>,
-    /// The set of all [`raw::SourceLocation`]s that have been added to this `Converter` and that
+    /// All strings that have been added to this `Converter`.
+    strings: StringTable,
+    /// The set of all [`native::File`]s that have been added to this `Converter`.
+    files: IndexSet<native::File>,
+    /// The set of all [`FunctionEntry`]s that have been added to this `Converter`.
+    functions: IndexSet<FunctionEntry>,
+    /// The set of all [`SourceLocation`]s that have been added to this `Converter` and that
     /// aren't directly associated with a code range.
-    source_locations: IndexSet<raw::SourceLocation>,
-    /// A map from code ranges to the [`raw::SourceLocation`]s they correspond to.
+    source_locations: IndexSet<SourceLocation>,
+    /// A map from code ranges to the [`SourceLocation`]s they correspond to.
     ///
     /// Only the starting address of a range is saved, the end address is given implicitly
     /// by the start address of the next range.
-    ranges: BTreeMap<u32, raw::SourceLocation>,
+    ranges: BTreeMap<u64, SourceLocation>,
+    /// The kind of input that produced each range, see [`raw::SECTION_PROVENANCE`].
+    range_provenance: HashMap<u64, u8>,
+    /// The flags of ranges that have any, see [`raw::SECTION_RANGE_FLAGS`].
+    range_flags: HashMap<u64, u8>,
+    /// The alternative source locations and their provenance of ranges that were attributed
+    /// differently by multiple inputs, see [`raw::SECTION_CANDIDATES`].
+    candidates: BTreeMap<u64, Vec<(SourceLocation, u8)>>,
+    /// The code segments of the image sorted by address, see [`add_segment`](Self::add_segment).
+    segments: Vec<native::Segment>,
+
+    /// The point in time after which processing is aborted, see [`set_deadline`](Self::set_deadline).
+    deadline: Option<Instant>,
+    /// A flag that aborts processing once set, see [`set_cancel_flag`](Self::set_cancel_flag).
+    cancel_flag: Option<Arc<AtomicBool>>,
+    /// Receives errors in the debug information, see [`set_error_sink`](Self::set_error_sink).
+    error_sink: ErrorSinks,
+
+    /// Statistics about the data processed so far.
+    stats: Stats,
 
     /// This is highest addr that we know is outside of a valid function.
-    /// Functions have an explicit end, while Symbols implicitly extend to infinity.
-    /// In case the highest addr belongs to a Symbol, this will be `None` and the SymCache
-    /// also extends to infinite, otherwise this is the end of the highest function.
-    last_addr: Option<u32>,
+    /// Functions and symbols with a size have an explicit end, while other Symbols implicitly
+    /// extend to infinity. In case the highest addr belongs to such a Symbol, this will be `None`
+    /// and the SymCache also extends to infinite, otherwise this is the end of the highest
+    /// function or symbol.
+    last_addr: Option<u64>,
 }
 
 impl SymCacheConverter {
@@ -66,6 +415,93 @@ impl SymCacheConverter {
         self.transformers.0.push(Box::new(t));
     }
 
+    /// Adds a function that transforms every string before it is written to the SymCache.
+    ///
+    /// The function is applied to function names and compilation directories, as well as to all
+    /// parts of file paths. It returns the replacement for a string, or `None` to keep the string
+    /// unchanged. Like [transformers](Self::add_transformer), it runs in the order it was added.
+    pub fn transform_strings<F>(&mut self, f: F)
+    where
+        F: Fn(&str) -> Option<String> + 'static,
+    {
+        self.add_transformer(transform::StringTransformer(f));
+    }
+
+    /// Drops functions and symbols for which `filter` returns `false`.
+    ///
+    /// This shrinks SymCaches by excluding code that is not of interest, for example third-party
+    /// SDKs. The filter receives each function after all [transformers](Self::add_transformer)
+    /// have been applied, but before its name is demangled.
+    ///
+    /// The addresses of a dropped top-level function do not resolve to any function, rather than
+    /// to the function preceding it, and are not filled in from the symbol table. Dropped inlined
+    /// functions are omitted along with their inlinees, so that their addresses resolve to the
+    /// caller. Dropped functions are counted in [`Stats::filtered_functions`].
+    pub fn set_function_filter<F>(&mut self, filter: F)
+    where
+        F: Fn(&transform::Function<'_>) -> bool + 'static,
+    {
+        self.filters.function = Some(Box::new(filter));
+    }
+
+    /// Drops source files for which `filter` returns `false`.
+    ///
+    /// This shrinks SymCaches by excluding files that are not of interest, for example generated
+    /// code. The filter receives each file after all [transformers](Self::add_transformer) have
+    /// been applied.
+    ///
+    /// Line records in dropped files are kept without file and line information, so that their
+    /// addresses still resolve to their function. Such line records are counted in
+    /// [`Stats::filtered_lines`].
+    pub fn set_file_filter<F>(&mut self, filter: F)
+    where
+        F: Fn(&transform::File<'_>) -> bool + 'static,
+    {
+        self.filters.file = Some(Box::new(filter));
+    }
+
+    /// Resolves obfuscated names using the given [`BcSymbolMap`].
+    ///
+    /// Function names and file paths of the form `__hidden#NNN_` are replaced with the
+    /// corresponding entry of the symbol map. Unlike [`add_transformer`](Self::add_transformer),
+    /// this copies the names, so the symbol map does not need to outlive the converter.
+    pub fn apply_bcsymbolmap(&mut self, bcsymbolmap: &BcSymbolMap<'_>) {
+        let names: Vec<String> = bcsymbolmap.iter().map(String::from).collect();
+        self.transform_strings(move |s| names.get(BcSymbolMap::hidden_index(s)?).cloned());
+    }
+
+    /// Replaces the prefix `from` of file paths and compilation directories with `to`.
+    ///
+    /// This works like the `-fdebug-prefix-map=from=to` compiler option, so that paths are stable
+    /// across build environments, such as CI machines that build in `/home/runner/work`. The
+    /// prefix only matches entire path components, and is matched against the compilation
+    /// directory, the directory and the name of a file separately. If several prefixes match, the
+    /// one added last wins.
+    ///
+    /// Paths are rewritten after all [transformers](Self::add_transformer) ran, and before
+    /// [filters](Self::set_file_filter) are applied.
+    pub fn remap_path_prefix(&mut self, from: &str, to: &str) {
+        let rewrite = (from.to_owned(), to.to_owned());
+        self.path_rewrites.prefixes.push(rewrite);
+    }
+
+    /// Removes the prefix from file paths and compilation directories, which makes them relative.
+    ///
+    /// This is the same as [remapping](Self::remap_path_prefix) the prefix to an empty string, and
+    /// also removes the separators following the prefix.
+    pub fn strip_path_prefix(&mut self, prefix: &str) {
+        self.remap_path_prefix(prefix, "");
+    }
+
+    /// Replaces backslashes in file paths and compilation directories with forward slashes.
+    ///
+    /// This makes paths of files built on Windows look the same as on other platforms. Separators
+    /// are normalized before [prefixes are remapped](Self::remap_path_prefix), so prefixes should
+    /// be given with forward slashes.
+    pub fn set_normalize_path_separators(&mut self, normalize: bool) {
+        self.path_rewrites.normalize_separators = normalize;
+    }
+
     /// Sets the CPU architecture of this SymCache.
     pub fn set_arch(&mut self, arch: Arch) {
         self.arch = arch;
@@ -76,33 +512,199 @@ impl SymCacheConverter {
         self.debug_id = debug_id;
     }
 
-    /// Insert a string into this converter.
-    ///
-    /// If the string was already present, it is not added again. A newly added string
-    /// is prefixed by its length as a `u32`. The returned `u32`
-    /// is the offset into the `string_bytes` field where the string is saved.
-    fn insert_string(
-        string_bytes: &mut Vec<u8>,
-        strings: &mut HashMap<String, u32>,
-        s: &str,
-    ) -> u32 {
-        if s.is_empty() {
-            return u32::MAX;
-        }
-        if let Some(&offset) = strings.get(s) {
-            return offset;
-        }
-        let string_offset = string_bytes.len() as u32;
-        let string_len = s.len() as u32;
-        string_bytes.extend(string_len.to_ne_bytes());
-        string_bytes.extend(s.bytes());
-        // we should have written exactly `string_len + 4` bytes
-        debug_assert_eq!(
-            string_bytes.len(),
-            string_offset as usize + string_len as usize + std::mem::size_of::<u32>(),
-        );
-        strings.insert(s.to_owned(), string_offset);
-        string_offset
+    /// Returns the CPU architecture of this SymCache.
+    pub(crate) fn arch(&self) -> Arch {
+        self.arch
+    }
+
+    /// Returns the debug identifier of this SymCache.
+    pub(crate) fn debug_id(&self) -> DebugId {
+        self.debug_id
+    }
+
+    /// Returns the alignment of the header and each section of this SymCache in bytes.
+    pub(crate) fn section_alignment(&self) -> u32 {
+        self.section_alignment.max(raw::DEFAULT_SECTION_ALIGNMENT)
+    }
+
+    /// Sets the alignment of the header and each section of this SymCache in bytes.
+    ///
+    /// The alignment is rounded up to the next power of two, and to a minimum of `8`. Using the
+    /// page size avoids that lookups on memory mapped SymCaches straddle pages unnecessarily.
+    pub fn set_section_alignment(&mut self, alignment: u32) {
+        self.section_alignment = alignment
+            .max(raw::DEFAULT_SECTION_ALIGNMENT)
+            .next_power_of_two();
+    }
+
+    /// Limits the memory used for strings to roughly `budget` bytes.
+    ///
+    /// Function names and file paths usually make up most of a SymCache. Once they exceed the
+    /// budget, they are moved to a temporary file and only strings added since are kept in
+    /// memory, at the cost of slower conversion. Files, functions and line information remain in
+    /// memory. If the temporary file cannot be written, all strings are kept in memory.
+    #[cfg(feature = "spill")]
+    pub fn set_memory_budget(&mut self, budget: usize) {
+        self.strings.set_budget(budget);
+    }
+
+    /// Sets an image base that is subtracted from all addresses during conversion.
+    ///
+    /// Use this if the processed addresses are absolute, but lookups happen with addresses relative
+    /// to the image, such as with the vmaddr of the `__TEXT` segment. Functions, line records and
+    /// symbols below the image base are skipped. The image base is recorded in the SymCache, see
+    /// [`SymCache::image_base`](super::SymCache::image_base). This only affects data that is
+    /// processed after this call.
+    pub fn set_image_base(&mut self, image_base: u64) {
+        self.image_base = image_base;
+    }
+
+    /// Sets how functions are identified and thus merged, see [`FunctionIdentity`].
+    ///
+    /// This only affects functions that are processed after this call.
+    pub fn set_function_identity(&mut self, identity: FunctionIdentity) {
+        self.function_identity = identity;
+    }
+
+    /// Writes an index of function names into the SymCache.
+    ///
+    /// The index lets [`SymCache::functions_named`] and [`SymCache::functions_with_prefix`] find
+    /// functions by a binary search instead of scanning all of them, at the cost of four bytes per
+    /// function. It is stored in an extension section, which older readers skip.
+    pub fn set_name_index(&mut self, enabled: bool) {
+        self.name_index = enabled;
+    }
+
+    /// Writes the static call graph between functions into the SymCache.
+    ///
+    /// The edges are derived from inlined functions, and record the file and line of every call
+    /// site. They can be queried with [`SymCache::callees_of`], and cost sixteen bytes per distinct
+    /// call site. They are stored in an extension section, which older readers skip.
+    pub fn set_call_edges(&mut self, enabled: bool) {
+        self.call_edges = enabled;
+    }
+
+    /// Keeps the attributions that are dropped when inputs attribute the same address differently.
+    ///
+    /// Such conflicts arise between overlapping top-level functions, between debug information and
+    /// the symbol table, and when [merging](Self::merge) converters. Only one attribution is
+    /// resolved by [`SymCache::lookup`], while all of them can be queried with
+    /// [`SymCache::candidates`] and [`SymCache::lookup_preferring`]. Each candidate costs twelve
+    /// bytes and its source locations. They are stored in an extension section, which older
+    /// readers skip, and are dropped first by [`limit_size`](Self::limit_size).
+    ///
+    /// [`SymCache::lookup`]: super::SymCache::lookup
+    /// [`SymCache::candidates`]: super::SymCache::candidates
+    /// [`SymCache::lookup_preferring`]: super::SymCache::lookup_preferring
+    pub fn set_keep_candidates(&mut self, enabled: bool) {
+        self.keep_candidates = enabled;
+    }
+
+    /// Limits how deeply inlined functions are nested, defaults to [`DEFAULT_MAX_INLINE_DEPTH`].
+    ///
+    /// Inlinees nested deeper than `depth` are skipped along with their own inlinees, so that
+    /// their addresses resolve to the innermost inlined function that is kept. A depth of `0`
+    /// skips all inlinees. This bounds the length of inline chains and the recursion while
+    /// processing corrupted or adversarial debug information. Skipped inlinees are counted in
+    /// [`Stats::skipped_inlinees`].
+    pub fn set_max_inline_depth(&mut self, depth: usize) {
+        self.max_inline_depth = Some(depth);
+    }
+
+    /// Demangles function names with the given options before they are written to the SymCache.
+    ///
+    /// Names are demangled after all [transformers](Self::add_transformer) ran, according to the
+    /// language of their function. Names that cannot be demangled are written unchanged. This
+    /// only affects functions that are processed after this call.
+    #[cfg(feature = "demangle")]
+    pub fn set_demangling(&mut self, options: DemangleOptions) {
+        self.demangle_options = Some(options);
+    }
+
+    /// Keeps the linkage names of functions whose names were [demangled](Self::set_demangling).
+    ///
+    /// Linkage names are stored in addition to the demangled names, which increases the size of
+    /// the SymCache. They are available via [`Function::linkage_name`](super::Function::linkage_name).
+    #[cfg(feature = "demangle")]
+    pub fn set_keep_linkage_names(&mut self, keep: bool) {
+        self.keep_linkage_names = keep;
+    }
+
+    /// Demangles a function name if [demangling](Self::set_demangling) is enabled.
+    ///
+    /// Returns the demangled name along with the linkage name to keep, if any.
+    #[cfg(feature = "demangle")]
+    fn demangle_name<'n>(
+        &self,
+        name: &'n str,
+        language: Language,
+    ) -> Option<(String, Option<&'n str>)> {
+        let options = self.demangle_options?;
+        let demangled = Name::new(name, NameMangling::Unknown, language).demangle(options)?;
+        if demangled == name {
+            return None;
+        }
+
+        let linkage_name = Some(name).filter(|_| self.keep_linkage_names);
+        Some((demangled, linkage_name))
+    }
+
+    #[cfg(not(feature = "demangle"))]
+    fn demangle_name<'n>(
+        &self,
+        _name: &'n str,
+        _language: Language,
+    ) -> Option<(String, Option<&'n str>)> {
+        None
+    }
+
+    /// Sets a deadline after which [`process_object`](Self::process_object) is aborted.
+    ///
+    /// Processing is aborted with [`SymCacheErrorKind::Cancelled`]. Everything processed up to
+    /// that point remains in the converter and can still be serialized.
+    pub fn set_deadline(&mut self, deadline: Instant) {
+        self.deadline = Some(deadline);
+    }
+
+    /// Sets a flag that aborts [`process_object`](Self::process_object) once it is set.
+    ///
+    /// The flag can be set from another thread to cancel a running conversion. Processing is
+    /// aborted with [`SymCacheErrorKind::Cancelled`], just like when exceeding a
+    /// [deadline](Self::set_deadline).
+    pub fn set_cancel_flag(&mut self, flag: Arc<AtomicBool>) {
+        self.cancel_flag = Some(flag);
+    }
+
+    /// Sets a sink that receives errors in DWARF debug information.
+    ///
+    /// By default, the first error in the debug information fails the conversion. With a sink,
+    /// every error is reported as a [`ConversionError`] with the compilation unit and entry
+    /// that caused it. Errors of [`Severity::Warning`] only affect a single entry or line
+    /// sequence, which is skipped, and the conversion continues. Errors of [`Severity::Fatal`]
+    /// still fail the conversion.
+    ///
+    /// Broken line sequences are only reported with a sink. Otherwise, the line program is
+    /// silently truncated. Conflicting line records of different functions at the same address
+    /// are also reported as warnings, see [`Stats::conflicting_lines`].
+    pub fn set_error_sink<S>(&mut self, sink: S)
+    where
+        S: ErrorSink + 'static,
+    {
+        self.error_sink = ErrorSinks(Some(Box::new(sink)));
+    }
+
+    /// Returns an error if processing has been cancelled or exceeded its deadline.
+    fn check_cancelled(&self) -> Result<(), SymCacheError> {
+        if is_cancelled(self.cancel_flag.as_deref(), self.deadline) {
+            Err(SymCacheErrorKind::Cancelled.into())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Returns statistics about the data processed so far.
+    pub fn stats(&self) -> &Stats {
+        &self.stats
     }
 
     // Methods processing symbolic-debuginfo [`ObjectLike`] below:
@@ -110,6 +712,9 @@ impl SymCacheConverter {
 
     /// This processes the given [`ObjectLike`] object, collecting all its functions and line
     /// information into the converter.
+    ///
+    /// This works for all object formats that provide a debug session, including Windows PDBs,
+    /// whose modules, line programs and inlinee records are read via the `pdb` crate.
     pub fn process_object<'d, 'o, O>(&mut self, object: &'o O) -> Result<(), SymCacheError>
     where
         O: ObjectLike<'d, 'o>,
@@ -119,99 +724,527 @@ impl SymCacheConverter {
             .debug_session()
             .map_err(|e| SymCacheError::new(SymCacheErrorKind::BadDebugFile, e))?;
 
-        for function in session.functions() {
-            let function =
-                function.map_err(|e| SymCacheError::new(SymCacheErrorKind::BadDebugFile, e))?;
+        self.process_debug_session(&session, object.symbols())
+    }
 
-            self.process_symbolic_function(&function);
+    /// This processes all functions of the given debug session and the given symbols, collecting
+    /// them into the converter.
+    ///
+    /// This is useful for debug sessions that are not created by
+    /// [`ObjectLike::debug_session`], such as sessions with split DWARF.
+    pub fn process_debug_session<'d, 's, S, I>(
+        &mut self,
+        session: &'s S,
+        symbols: I,
+    ) -> Result<(), SymCacheError>
+    where
+        S: DebugSession<'s>,
+        S::Error: std::error::Error + Send + Sync + 'static,
+        I: IntoIterator<Item = Symbol<'d>>,
+    {
+        self.process_functions(session.functions())?;
+        self.process_symbols(symbols)
+    }
+
+    /// This processes all functions of the given DWARF debug session and the given symbols,
+    /// collecting them into the converter.
+    ///
+    /// With the `parallel` feature, compilation units are converted in parallel and
+    /// [merged](Self::merge) in their original order afterwards. Functions defined at the same
+    /// address in several units are then resolved like overlapping line records. Without the
+    /// feature, or if [transformers](Self::add_transformer) have been added, this is the same as
+    /// [`process_debug_session`](Self::process_debug_session).
+    pub fn process_dwarf<'d, I>(
+        &mut self,
+        session: &DwarfDebugSession<'_>,
+        symbols: I,
+    ) -> Result<(), SymCacheError>
+    where
+        I: IntoIterator<Item = Symbol<'d>>,
+    {
+        #[cfg(feature = "parallel")]
+        if self.transformers.0.is_empty() && self.filters.is_empty() {
+            self.process_dwarf_units(session)?;
+            return self.process_symbols(symbols);
         }
 
-        for symbol in object.symbols() {
-            self.process_symbolic_symbol(&symbol);
+        self.process_dwarf_functions(session.functions())?;
+        self.process_symbols(symbols)
+    }
+
+    /// Converts each compilation unit of a DWARF debug session on the rayon thread pool and
+    /// merges the results in order.
+    ///
+    /// If processing is cancelled, the units before the cancelled one are kept.
+    #[cfg(feature = "parallel")]
+    fn process_dwarf_units(
+        &mut self,
+        session: &DwarfDebugSession<'_>,
+    ) -> Result<(), SymCacheError> {
+        use rayon::prelude::*;
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let image_base = self.image_base;
+        let function_identity = self.function_identity;
+        let max_inline_depth = self.max_inline_depth;
+        let keep_candidates = self.keep_candidates;
+        #[cfg(feature = "demangle")]
+        let demangle_options = self.demangle_options;
+        #[cfg(feature = "demangle")]
+        let keep_linkage_names = self.keep_linkage_names;
+        let path_rewrites = &self.path_rewrites;
+        let budget = self.strings.budget();
+        let deadline = self.deadline;
+        let cancel_flag = &self.cancel_flag;
+        let collect_errors = self.error_sink.0.is_some();
+
+        let units: Vec<_> = (0..session.unit_count())
+            .into_par_iter()
+            .map(|unit| {
+                // errors are collected on the worker and reported in the order of units
+                let errors = Rc::new(RefCell::new(Vec::new()));
+                let mut converter = Self {
+                    image_base,
+                    function_identity,
+                    max_inline_depth,
+                    keep_candidates,
+                    #[cfg(feature = "demangle")]
+                    demangle_options,
+                    #[cfg(feature = "demangle")]
+                    keep_linkage_names,
+                    path_rewrites: path_rewrites.clone(),
+                    strings: StringTable::with_budget(budget),
+                    deadline,
+                    cancel_flag: cancel_flag.clone(),
+                    ..Self::default()
+                };
+                if collect_errors {
+                    let errors = Rc::clone(&errors);
+                    converter.set_error_sink(move |error| errors.borrow_mut().push(error));
+                }
+
+                let functions = session.unit_functions(unit..unit + 1);
+                let result = converter
+                    .check_cancelled()
+                    .and_then(|()| converter.process_dwarf_functions(functions));
+                let tables = UnitTables::from(converter);
+                let errors =
+                    Rc::try_unwrap(errors).map_or_else(|_| Vec::new(), RefCell::into_inner);
+                (tables, errors, result)
+            })
+            .collect();
+
+        for (tables, errors, result) in units {
+            if let Some(ref mut sink) = self.error_sink.0 {
+                for error in errors {
+                    sink.report(error);
+                }
+            }
+            self.merge(tables.into());
+            result?;
         }
 
         Ok(())
     }
 
-    pub fn process_symbolic_function(&mut self, function: &Function<'_>) {
-        // skip over empty functions or functions whose address is too large to fit in a u32
-        if function.size == 0 || function.address > u32::MAX as u64 {
-            return;
+    /// Processes functions yielded by a debug session.
+    fn process_functions<'s, F, E>(&mut self, functions: F) -> Result<(), SymCacheError>
+    where
+        F: IntoIterator<Item = Result<Function<'s>, E>>,
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        for function in functions {
+            self.check_cancelled()?;
+            let function =
+                function.map_err(|e| SymCacheError::new(SymCacheErrorKind::BadDebugFile, e))?;
+
+            self.process_symbolic_function(&function);
         }
 
-        let comp_dir = std::str::from_utf8(function.compilation_dir).ok();
+        Ok(())
+    }
 
-        let entry_pc = if function.inline {
-            u32::MAX
-        } else {
-            function.address as u32
+    /// Processes the functions of DWARF compilation units, recording [`UnitStats`] for each unit.
+    fn process_dwarf_functions(
+        &mut self,
+        functions: DwarfFunctionIterator<'_>,
+    ) -> Result<(), SymCacheError> {
+        let mut functions = match self.error_sink.0 {
+            Some(_) => functions.with_warnings(),
+            None => functions,
         };
+        // units are also checked for cancellation while they are parsed
+        if self.cancel_flag.is_some() || self.deadline.is_some() {
+            let cancel_flag = self.cancel_flag.clone();
+            let deadline = self.deadline;
+            functions =
+                functions.with_interrupt(move || is_cancelled(cancel_flag.as_deref(), deadline));
+        }
 
-        let function_idx = {
-            let language = function.name.language();
-            let mut function = transform::Function {
-                name: function.name.as_str().into(),
-                comp_dir: comp_dir.map(Into::into),
+        while let Some(function) = functions.next() {
+            self.check_cancelled()?;
+            let function = match function {
+                Ok(function) => function,
+                Err(error) if error.kind() == DwarfErrorKind::Interrupted => {
+                    return Err(SymCacheErrorKind::Cancelled.into());
+                }
+                Err(error) => {
+                    self.report_dwarf_error(error)?;
+                    continue;
+                }
             };
-            for transformer in &self.transformers.0 {
-                function = transformer.transform_function(function);
+
+            let string_bytes = self.strings.len();
+            let processed_functions = self.stats.processed_functions;
+            let dropped_lines = self.stats.conflicting_lines + self.stats.overflowing_lines;
+            self.process_symbolic_function(&function);
+
+            let unit = functions.unit_index();
+            let stats = &mut self.stats;
+            if stats.units.last().map(|unit_stats| unit_stats.index) != Some(unit) {
+                stats.units.push(UnitStats {
+                    index: unit,
+                    ..UnitStats::default()
+                });
+            }
+            let unit_stats = stats.units.last_mut().unwrap();
+            if stats.processed_functions > processed_functions {
+                unit_stats.functions += 1;
+            } else {
+                unit_stats.skipped_functions += 1;
             }
+            unit_stats.add_function(&function, 0);
+            unit_stats.dropped_lines +=
+                stats.conflicting_lines + stats.overflowing_lines - dropped_lines;
+            unit_stats.string_bytes += (self.strings.len() - string_bytes) as u64;
+        }
 
-            let string_bytes = &mut self.string_bytes;
-            let strings = &mut self.strings;
-            let name_offset = Self::insert_string(string_bytes, strings, &function.name);
+        Ok(())
+    }
 
-            let comp_dir_offset = function.comp_dir.map_or(u32::MAX, |comp_dir| {
-                Self::insert_string(string_bytes, strings, &comp_dir)
-            });
-            let lang = language as u32;
-            let (fun_idx, _) = self.functions.insert_full(raw::Function {
-                name_offset,
-                comp_dir_offset,
-                entry_pc,
-                lang,
-            });
-            fun_idx as u32
+    /// Reports an error in DWARF debug information to the [error sink](Self::set_error_sink).
+    ///
+    /// Returns the error if it fails the conversion.
+    fn report_dwarf_error(&mut self, error: DwarfError) -> Result<(), SymCacheError> {
+        let sink = match self.error_sink.0 {
+            Some(ref mut sink) => sink,
+            None => return Err(SymCacheError::new(SymCacheErrorKind::BadDebugFile, error)),
         };
 
-        for line in &function.lines {
-            let mut location = transform::SourceLocation {
-                file: transform::File {
-                    name: line.file.name_str(),
-                    directory: Some(line.file.dir_str()),
-                    comp_dir: comp_dir.map(Into::into),
-                },
-                line: line.line as u32,
-            };
-            for transformer in &self.transformers.0 {
-                location = transformer.transform_source_location(location);
-            }
-
-            let string_bytes = &mut self.string_bytes;
-            let strings = &mut self.strings;
-            let path_name_offset = Self::insert_string(string_bytes, strings, &location.file.name);
-            let directory_offset = location
-                .file
-                .directory
-                .map_or(u32::MAX, |d| Self::insert_string(string_bytes, strings, &d));
-            let comp_dir_offset = location.file.comp_dir.map_or(u32::MAX, |cd| {
-                Self::insert_string(string_bytes, strings, &cd)
-            });
+        let conversion_error = ConversionError::from_dwarf(&error);
+        let severity = conversion_error.severity;
+        sink.report(conversion_error);
 
-            let (file_idx, _) = self.files.insert_full(raw::File {
-                path_name_offset,
-                directory_offset,
-                comp_dir_offset,
-            });
+        match severity {
+            Severity::Warning => Ok(()),
+            Severity::Fatal => Err(SymCacheError::new(SymCacheErrorKind::BadDebugFile, error)),
+        }
+    }
 
-            let source_location = raw::SourceLocation {
-                file_idx: file_idx as u32,
-                line: location.line,
-                function_idx,
-                inlined_into_idx: u32::MAX,
+    /// Processes symbols from a symbol table.
+    fn process_symbols<'d, I>(&mut self, symbols: I) -> Result<(), SymCacheError>
+    where
+        I: IntoIterator<Item = Symbol<'d>>,
+    {
+        for symbol in symbols {
+            self.check_cancelled()?;
+            self.process_symbolic_symbol(&symbol);
+        }
+
+        Ok(())
+    }
+
+    /// This processes the pc/line table of a Go binary, collecting all its functions and line
+    /// information into the converter.
+    ///
+    /// Go binaries retain this table even when they are stripped of DWARF. Addresses in the
+    /// table are absolute and are made relative to the given `load_address`.
+    pub fn process_gopclntab(
+        &mut self,
+        data: &[u8],
+        load_address: u64,
+    ) -> Result<(), SymCacheError> {
+        let functions = GoPclnTab::parse(data)
+            .and_then(|table| table.functions(load_address))
+            .map_err(|e| SymCacheError::new(SymCacheErrorKind::BadDebugFile, e))?;
+
+        for function in &functions {
+            self.process_symbolic_function(function);
+        }
+
+        Ok(())
+    }
+
+    /// This processes an LLVM GSYM file, collecting all its functions, line information and
+    /// inlinees into the converter.
+    ///
+    /// Addresses in GSYM files are absolute and are made relative to the given `load_address`.
+    /// Functions without a size are added like symbols.
+    pub fn process_gsym(&mut self, data: &[u8], load_address: u64) -> Result<(), SymCacheError> {
+        let functions = Gsym::parse(data)
+            .and_then(|gsym| gsym.functions(load_address))
+            .map_err(|e| SymCacheError::new(SymCacheErrorKind::BadDebugFile, e))?;
+
+        for function in &functions {
+            self.check_cancelled()?;
+            if function.size == 0 {
+                self.process_symbolic_symbol(&Symbol {
+                    name: Some(Cow::Borrowed(function.name.as_str())),
+                    address: function.address,
+                    size: 0,
+                });
+            } else {
+                self.process_symbolic_function(function);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Processes a Portable PDB, the debug information format of .NET assemblies.
+    ///
+    /// Managed code is identified by a method and an IL offset within it, rather than by an
+    /// address. Every method with sequence points is converted into a function named after its
+    /// metadata token, such as `0x06000001`, whose lines are the method's sequence points. Look
+    /// them up with [`SymCache::lookup_il_offset`]. If no [debug id](Self::set_debug_id) has been
+    /// set, it is taken from the PDB.
+    pub fn process_portable_pdb(&mut self, data: &[u8]) -> Result<(), SymCacheError> {
+        let pdb = PortablePdb::parse(data)
+            .map_err(|e| SymCacheError::new(SymCacheErrorKind::BadDebugFile, e))?;
+        let documents = pdb
+            .documents()
+            .map_err(|e| SymCacheError::new(SymCacheErrorKind::BadDebugFile, e))?;
+        let functions = pdb
+            .functions(&documents)
+            .map_err(|e| SymCacheError::new(SymCacheErrorKind::BadDebugFile, e))?;
+
+        if self.debug_id.is_nil() {
+            self.debug_id = pdb.debug_id();
+        }
+        for function in &functions {
+            self.check_cancelled()?;
+            self.process_symbolic_function(function);
+        }
+
+        Ok(())
+    }
+
+    /// Processes a perf map, the `/tmp/perf-<pid>.map` text file that JIT compilers write for
+    /// `perf`.
+    ///
+    /// Every line of the map declares a function by its start address, size and name. Addresses
+    /// are absolute, so the [image base](Self::set_image_base) is usually left at zero. If a
+    /// map declares several functions at the same address, the last one wins.
+    pub fn process_perf_map(&mut self, data: &[u8]) -> Result<(), SymCacheError> {
+        let symbols = jit::parse_perf_map(data)
+            .map_err(|e| SymCacheError::new(SymCacheErrorKind::BadDebugFile, e))?;
+
+        for symbol in &symbols {
+            self.check_cancelled()?;
+            self.process_symbolic_symbol(symbol);
+        }
+
+        Ok(())
+    }
+
+    /// Processes a jitdump, the `jit-<pid>.dump` binary file that JIT compilers write for
+    /// `perf`.
+    ///
+    /// Functions are collected from the code load records along with the line information of
+    /// their debug info records, and relocated according to code move records. If no
+    /// [architecture](Self::set_arch) has been set, it is taken from the jitdump header.
+    pub fn process_jitdump(&mut self, data: &[u8]) -> Result<(), SymCacheError> {
+        let dump = jit::JitDump::parse(data)
+            .map_err(|e| SymCacheError::new(SymCacheErrorKind::BadDebugFile, e))?;
+
+        if self.arch == Arch::Unknown {
+            self.arch = dump.arch;
+        }
+        for function in &dump.functions {
+            self.check_cancelled()?;
+            self.process_symbolic_function(function);
+        }
+
+        Ok(())
+    }
+
+    /// Processes a single function along with its line records and inlinees.
+    ///
+    /// If line records of two top-level functions start at the same address, the record that
+    /// was processed first is kept, unless only the later one has file and line information.
+    /// Such conflicts are counted in [`Stats::conflicting_lines`] and reported to the
+    /// [error sink](Self::set_error_sink).
+    pub fn process_symbolic_function(&mut self, function: &Function<'_>) {
+        self.process_function_at_depth(function, 0);
+    }
+
+    /// Processes a function that is nested `depth` levels deep in inlined functions.
+    fn process_function_at_depth(&mut self, function: &Function<'_>, depth: usize) {
+        // skip over empty functions, and functions below the image base
+        let address = match function.address.checked_sub(self.image_base) {
+            Some(address) if function.size > 0 => address,
+            _ => return,
+        };
+        if !function.inline {
+            self.stats.processed_functions += 1;
+        }
+
+        let comp_dir = std::str::from_utf8(function.compilation_dir).ok();
+
+        let entry_pc = if function.inline {
+            u64::from(u32::MAX)
+        } else {
+            address
+        };
+
+        let inline = function.inline;
+        let (raw_function, linkage_name_offset) = {
+            let language = function.name.language();
+            let mut function = transform::Function {
+                name: function.name.as_str().into(),
+                comp_dir: comp_dir.map(Into::into),
+            };
+            for transformer in &self.transformers.0 {
+                function = transformer.transform_function(function);
+            }
+            function = self.path_rewrites.transform_function(function);
+
+            if !self.filters.keep_function(&function) {
+                self.stats.filtered_functions += 1;
+                // leave a marker so that the addresses do not resolve to the preceding function.
+                // inlined functions are covered by their caller instead.
+                if !inline {
+                    self.ranges.entry(address).or_insert(NO_SOURCE_LOCATION);
+                    self.filtered_addrs.insert(address);
+                }
+                return;
+            }
+
+            let (name, linkage_name) = match self.demangle_name(&function.name, language) {
+                Some((demangled, linkage_name)) => (Cow::Owned(demangled), linkage_name),
+                None => (Cow::Borrowed(function.name.as_ref()), None),
+            };
+
+            let strings = &mut self.strings;
+            let name_offset = strings.insert(&name);
+            let linkage_name_offset =
+                linkage_name.map_or(u32::MAX, |linkage_name| strings.insert(linkage_name));
+
+            let comp_dir_offset = function
+                .comp_dir
+                .map_or(u32::MAX, |comp_dir| strings.insert(&comp_dir));
+            let lang = language as u32;
+            let raw_function = native::Function {
+                name_offset,
+                comp_dir_offset,
+                entry_pc: entry_pc as u32,
+                lang,
+            };
+            (raw_function, linkage_name_offset)
+        };
+
+        let mut source_locations = Vec::with_capacity(function.lines.len());
+        for line in &function.lines {
+            let line_address = match line.address.checked_sub(self.image_base) {
+                Some(line_address) => line_address,
+                None => continue,
+            };
+
+            let mut location = transform::SourceLocation {
+                file: transform::File {
+                    name: line.file.name_str(),
+                    directory: Some(line.file.dir_str()),
+                    comp_dir: comp_dir.map(Into::into),
+                },
+                // overflowing lines are saturated here, and recorded as unknown below
+                line: u32::try_from(line.line).unwrap_or(u32::MAX),
+            };
+            for transformer in &self.transformers.0 {
+                location = transformer.transform_source_location(location);
+            }
+            location = self.path_rewrites.transform_source_location(location);
+
+            // overflowing columns are recorded as unknown
+            let column = u32::try_from(line.column).unwrap_or(0);
+            let flags = range_flags(line);
+
+            if !self.filters.keep_file(&location.file) {
+                self.stats.filtered_lines += 1;
+                source_locations.push((line_address, u32::MAX, 0, column, flags));
+                continue;
+            }
+
+            let file_idx = insert_file(&mut self.strings, &mut self.files, &location.file);
+
+            let line_number = if location.line > raw::MAX_LINE {
+                self.stats.overflowing_lines += 1;
+                0
+            } else {
+                location.line
+            };
+
+            source_locations.push((line_address, file_idx, line_number, column, flags));
+        }
+
+        // the declaration is transformed and filtered like the line records
+        let (decl_file_idx, decl_line) = match function.decl_file {
+            Some(ref file) => {
+                let mut location = transform::SourceLocation {
+                    file: transform::File {
+                        name: file.name_str(),
+                        directory: Some(file.dir_str()),
+                        comp_dir: comp_dir.map(Into::into),
+                    },
+                    line: u32::try_from(function.decl_line).unwrap_or(u32::MAX),
+                };
+                for transformer in &self.transformers.0 {
+                    location = transformer.transform_source_location(location);
+                }
+                location = self.path_rewrites.transform_source_location(location);
+
+                if self.filters.keep_file(&location.file) {
+                    let file_idx = insert_file(&mut self.strings, &mut self.files, &location.file);
+                    // overflowing lines are recorded as unknown
+                    let line = Some(location.line).filter(|&line| line <= raw::MAX_LINE);
+                    (file_idx, line.unwrap_or(0))
+                } else {
+                    (u32::MAX, 0)
+                }
+            }
+            None => (u32::MAX, 0),
+        };
+
+        let discriminator = match self.function_identity {
+            FunctionIdentity::Name => u32::MAX,
+            FunctionIdentity::NameAndFile => source_locations
+                .first()
+                .map_or(u32::MAX, |&(_, file_idx, _, _, _)| file_idx),
+        };
+        let function_idx = self
+            .functions
+            .insert_full(FunctionEntry {
+                raw: raw_function,
+                discriminator,
+                linkage_name_offset,
+                entry_pc_high: (entry_pc >> 32) as u32,
+                decl_file_idx,
+                decl_line,
+            })
+            .0 as u32;
+
+        for (address, file_idx, line_number, column, flags) in source_locations {
+            let source_location = SourceLocation {
+                raw: native::SourceLocation {
+                    file_idx,
+                    line: line_number,
+                    function_idx,
+                    inlined_into_idx: u32::MAX,
+                },
+                column,
             };
 
-            match self.ranges.entry(line.address as u32) {
+            match self.ranges.entry(address) {
                 btree_map::Entry::Vacant(entry) => {
                     if function.inline {
                         // BUG:
@@ -220,197 +1253,3153 @@ impl SymCacheConverter {
                     }
                     entry.insert(source_location);
                 }
+                btree_map::Entry::Occupied(mut entry) if *entry.get() == NO_SOURCE_LOCATION => {
+                    // replace the end marker of a preceding function
+                    entry.insert(source_location);
+                }
+                btree_map::Entry::Occupied(mut entry) if !function.inline => {
+                    // another top-level function covers the same instruction addr, which happens
+                    // with identical code folding or overlapping compilation units. keep the
+                    // first one, unless only the new one has line information.
+                    self.stats.conflicting_lines += 1;
+                    self.error_sink
+                        .report_conflicting_lines(address, function.name.as_str());
+                    if entry.get().raw.file_idx != u32::MAX || file_idx == u32::MAX {
+                        self.add_candidate(address, source_location, raw::PROVENANCE_DEBUG_INFO);
+                        continue;
+                    }
+                    let dropped = entry.insert(source_location);
+                    let provenance = self.range_provenance.get(&address).copied();
+                    let provenance = provenance.unwrap_or(raw::PROVENANCE_UNKNOWN);
+                    self.add_candidate(address, dropped, provenance);
+                }
                 btree_map::Entry::Occupied(mut entry) => {
-                    if function.inline {
-                        let caller_source_location = entry.get().clone();
+                    let caller_source_location = entry.get().clone();
+                    let caller_inlined_into_idx = caller_source_location.raw.inlined_into_idx;
 
-                        let mut callee_source_location = source_location;
-                        let (inlined_into_idx, _) =
-                            self.source_locations.insert_full(caller_source_location);
+                    let mut callee_source_location = source_location;
+                    let (inlined_into_idx, _) =
+                        self.source_locations.insert_full(caller_source_location);
 
-                        callee_source_location.inlined_into_idx = inlined_into_idx as u32;
-                        entry.insert(callee_source_location);
-                    } else {
-                        // BUG:
-                        // the abstraction yields multiple top-level functions for the same
-                        // instruction addr
-                        entry.insert(source_location);
+                    // callers are inserted before their callees, so a chain only ever points to
+                    // lower indices. anything else would close a cycle, so keep the caller.
+                    if caller_inlined_into_idx != u32::MAX
+                        && caller_inlined_into_idx as usize >= inlined_into_idx
+                    {
+                        continue;
                     }
+
+                    callee_source_location.raw.inlined_into_idx = inlined_into_idx as u32;
+                    entry.insert(callee_source_location);
                 }
             }
+            self.range_provenance
+                .insert(address, raw::PROVENANCE_DEBUG_INFO);
+            self.set_range_flags(address, flags);
         }
 
         // add the bare minimum of information for the function if there isn't any.
-        self.ranges.entry(entry_pc).or_insert(raw::SourceLocation {
-            file_idx: u32::MAX,
-            line: 0,
-            function_idx,
-            inlined_into_idx: u32::MAX,
-        });
+        // inlined functions do not have an entry address, and are always covered by their caller.
+        if !function.inline {
+            let entry = self.ranges.entry(entry_pc).or_insert(NO_SOURCE_LOCATION);
+            if *entry == NO_SOURCE_LOCATION {
+                *entry = SourceLocation {
+                    raw: native::SourceLocation {
+                        file_idx: u32::MAX,
+                        line: 0,
+                        function_idx,
+                        inlined_into_idx: u32::MAX,
+                    },
+                    column: 0,
+                };
+                self.range_provenance
+                    .insert(entry_pc, raw::PROVENANCE_DEBUG_INFO);
+                self.range_flags.remove(&entry_pc);
+            }
+        }
 
-        for inlinee in &function.inlinees {
-            self.process_symbolic_function(inlinee);
+        let max_inline_depth = self.max_inline_depth.unwrap_or(DEFAULT_MAX_INLINE_DEPTH);
+        if depth < max_inline_depth {
+            for inlinee in &function.inlinees {
+                self.process_function_at_depth(inlinee, depth + 1);
+            }
+        } else {
+            self.stats.skipped_inlinees += function.inlinees.len() as u64;
+        }
+
+        // mark the end of the function, so that addresses in a gap before the next function do not
+        // resolve to this one. functions or symbols that start at this address replace the marker.
+        // functions overlapping with this one may still cover the addresses before the end.
+        let function_end = address.saturating_add(function.size);
+        if !function.inline && self.covers_end(function_end, function_idx) {
+            self.ranges
+                .entry(function_end)
+                .or_insert(NO_SOURCE_LOCATION);
         }
 
-        let function_end = function.end_address() as u32;
         let last_addr = self.last_addr.get_or_insert(0);
         if function_end > *last_addr {
             *last_addr = function_end;
         }
     }
 
+    /// Sets the flags of the range starting at `address`, see [`raw::SECTION_RANGE_FLAGS`].
+    fn set_range_flags(&mut self, address: u64, flags: u8) {
+        if flags == 0 {
+            self.range_flags.remove(&address);
+        } else {
+            self.range_flags.insert(address, flags);
+        }
+    }
+
+    /// Keeps a source location that lost against the range starting at `address`, see
+    /// [`set_keep_candidates`](Self::set_keep_candidates).
+    ///
+    /// Candidates that equal the range or another candidate are skipped.
+    fn add_candidate(&mut self, address: u64, source_location: SourceLocation, provenance: u8) {
+        if !self.keep_candidates
+            || source_location == NO_SOURCE_LOCATION
+            || self.ranges.get(&address) == Some(&source_location)
+        {
+            return;
+        }
+        let candidates = self.candidates.entry(address).or_default();
+        let candidate = (source_location, provenance);
+        if !candidates.contains(&candidate) {
+            candidates.push(candidate);
+        }
+    }
+
+    /// Returns whether the range right before `end` resolves to the given top-level function.
+    fn covers_end(&self, end: u64, function_idx: u32) -> bool {
+        let mut source_location = match self.ranges.range(..end).next_back() {
+            Some((_, source_location)) => source_location,
+            None => return false,
+        };
+        // bounded in case of cyclic inline chains
+        for _ in 0..=self.source_locations.len() {
+            match self
+                .source_locations
+                .get_index(source_location.raw.inlined_into_idx as usize)
+            {
+                Some(caller) => source_location = caller,
+                None => break,
+            }
+        }
+        source_location.raw.function_idx == function_idx
+    }
+
     pub fn process_symbolic_symbol(&mut self, symbol: &Symbol<'_>) {
-        let name_idx = {
-            let mut function = transform::Function {
-                name: match symbol.name {
-                    Some(ref name) => name.clone(),
-                    None => return,
-                },
-                comp_dir: None,
-            };
-            for transformer in &self.transformers.0 {
-                function = transformer.transform_function(function);
+        let address = match symbol.address.checked_sub(self.image_base) {
+            Some(address) => address,
+            None => return,
+        };
+        if self.filtered_addrs.contains(&address) {
+            return;
+        }
+
+        let name = match symbol.name {
+            Some(ref name) => name.clone(),
+            None => return,
+        };
+        let (name_idx, linkage_name_idx) = match self.insert_symbol_name(name) {
+            Some(names) => names,
+            None => {
+                self.filter_symbol(address);
+                return;
             }
+        };
 
-            Self::insert_string(&mut self.string_bytes, &mut self.strings, &function.name)
+        let symbol_end = match address.checked_add(symbol.size) {
+            Some(end) if symbol.size > 0 => Some(end),
+            _ => None,
         };
 
-        match self.ranges.entry(symbol.address as u32) {
-            btree_map::Entry::Vacant(entry) => {
-                let function = raw::Function {
-                    name_offset: name_idx,
-                    comp_dir_offset: u32::MAX,
-                    entry_pc: symbol.address as u32,
-                    lang: u32::MAX,
-                };
-                let function_idx = self.functions.insert_full(function).0 as u32;
+        let covered = matches!(self.ranges.get(&address), Some(s) if *s != NO_SOURCE_LOCATION);
+        if !covered {
+            let source_location = self.insert_symbol_location(name_idx, linkage_name_idx, address);
+            let function_idx = source_location.raw.function_idx;
+            self.ranges.insert(address, source_location);
+            self.range_provenance
+                .insert(address, raw::PROVENANCE_SYMBOL_TABLE);
+            self.range_flags.remove(&address);
 
-                entry.insert(raw::SourceLocation {
-                    file_idx: u32::MAX,
-                    line: 0,
-                    function_idx,
-                    inlined_into_idx: u32::MAX,
-                });
-            }
-            btree_map::Entry::Occupied(entry) => {
-                // ASSUMPTION:
-                // the `functions` iterator has already filled in this addr via debug session.
-                // we could trace the caller hierarchy up to the root, and assert that it is
-                // indeed the same function, and maybe update its `entry_pc`, but we don’t do
-                // that for now.
-                let _function_idx = entry.get().function_idx as usize;
+            // symbols with a known size end just like functions. symbols without a size
+            // implicitly extend to the next range.
+            if let Some(end) = symbol_end {
+                if self.covers_end(end, function_idx) {
+                    self.ranges.entry(end).or_insert(NO_SOURCE_LOCATION);
+                }
             }
+        } else if self.keep_candidates && !self.resolves_to_name(address, name_idx) {
+            // the debug information that already covers this address is preferred, and the
+            // symbol only becomes a candidate if it names a different function.
+            let source_location = self.insert_symbol_location(name_idx, linkage_name_idx, address);
+            self.add_candidate(address, source_location, raw::PROVENANCE_SYMBOL_TABLE);
+        } else {
+            // ASSUMPTION:
+            // the `functions` iterator has already filled in this addr via debug session.
+            // we could trace the caller hierarchy up to the root, and assert that it is
+            // indeed the same function, and maybe update its `entry_pc`, but we don’t do
+            // that for now.
         }
 
         let last_addr = self.last_addr.get_or_insert(0);
-        if symbol.address as u32 >= *last_addr {
-            self.last_addr = None;
+        if address >= *last_addr {
+            self.last_addr = symbol_end;
         }
     }
 
-    // Methods for serializing to a [`Write`] below:
-    // Feel free to move these to a separate file.
-
-    /// Serialize the converted data.
-    ///
-    /// This writes the SymCache binary format into the given [`Write`].
-    pub fn serialize<W: Write>(mut self, writer: &mut W) -> std::io::Result<()> {
-        let mut writer = WriteWrapper::new(writer);
+    /// Adds the function of a symbol starting at `address`, and returns its top-level source
+    /// location, which has no file and line.
+    fn insert_symbol_location(
+        &mut self,
+        name_idx: u32,
+        linkage_name_idx: u32,
+        address: u64,
+    ) -> SourceLocation {
+        let function = native::Function {
+            name_offset: name_idx,
+            comp_dir_offset: u32::MAX,
+            entry_pc: address as u32,
+            lang: u32::MAX,
+        };
+        let function_idx = self
+            .functions
+            .insert_full(FunctionEntry {
+                raw: function,
+                discriminator: u32::MAX,
+                linkage_name_offset: linkage_name_idx,
+                entry_pc_high: (address >> 32) as u32,
+                decl_file_idx: u32::MAX,
+                decl_line: 0,
+            })
+            .0 as u32;
 
-        // Insert a trailing sentinel source location in case we have a definite end addr
-        if let Some(last_addr) = self.last_addr {
-            // TODO: to be extra safe, we might check that `last_addr` is indeed larger than
-            // the largest range at some point.
-            match self.ranges.entry(last_addr) {
-                btree_map::Entry::Vacant(entry) => {
-                    entry.insert(raw::NO_SOURCE_LOCATION);
-                }
-                btree_map::Entry::Occupied(_entry) => {
-                    // BUG:
-                    // the last addr should not map to an already defined range
-                }
-            }
+        SourceLocation {
+            raw: native::SourceLocation {
+                file_idx: u32::MAX,
+                line: 0,
+                function_idx,
+                inlined_into_idx: u32::MAX,
+            },
+            column: 0,
         }
+    }
 
-        let num_files = self.files.len() as u32;
-        let num_functions = self.functions.len() as u32;
-        let num_source_locations = (self.source_locations.len() + self.ranges.len()) as u32;
-        let num_ranges = self.ranges.len() as u32;
-        let string_bytes = self.string_bytes.len() as u32;
+    /// Returns whether the outermost function of the range starting at `address` has the name at
+    /// the given string offset.
+    fn resolves_to_name(&self, address: u64, name_idx: u32) -> bool {
+        let source_location = match self.ranges.get(&address) {
+            Some(source_location) => source_location,
+            None => return false,
+        };
+        let callers = inline_callers(&self.source_locations, source_location);
+        let outermost = callers.last().copied().unwrap_or(source_location);
+        let function = self
+            .functions
+            .get_index(outermost.raw.function_idx as usize);
+        function.map_or(false, |function| function.raw.name_offset == name_idx)
+    }
 
-        let header = raw::Header {
-            magic: raw::SYMCACHE_MAGIC,
-            version: raw::SYMCACHE_VERSION,
+    /// Transforms and demangles the name of a symbol and adds it to the strings.
+    ///
+    /// Returns the offsets of the name and linkage name, or `None` if the symbol is dropped by
+    /// the [function filter](Self::set_function_filter).
+    fn insert_symbol_name(&mut self, name: Cow<'_, str>) -> Option<(u32, u32)> {
+        let mut function = transform::Function {
+            name,
+            comp_dir: None,
+        };
+        for transformer in &self.transformers.0 {
+            function = transformer.transform_function(function);
+        }
 
-            debug_id: self.debug_id,
-            arch: self.arch,
+        if !self.filters.keep_function(&function) {
+            return None;
+        }
 
-            num_files,
-            num_functions,
-            num_source_locations,
-            num_ranges,
-            string_bytes,
-            _reserved: [0; 16],
+        let (name, linkage_name) = match self.demangle_name(&function.name, Language::Unknown) {
+            Some((demangled, linkage_name)) => (Cow::Owned(demangled), linkage_name),
+            None => (Cow::Borrowed(function.name.as_ref()), None),
         };
 
-        writer.write(&[header])?;
-        writer.align()?;
+        let strings = &mut self.strings;
+        let name_idx = strings.insert(&name);
+        let linkage_name_idx =
+            linkage_name.map_or(u32::MAX, |linkage_name| strings.insert(linkage_name));
+        Some((name_idx, linkage_name_idx))
+    }
 
-        for f in self.files {
-            writer.write(&[f])?;
-        }
-        writer.align()?;
+    /// Records a symbol that was dropped by the [function filter](Self::set_function_filter).
+    fn filter_symbol(&mut self, address: u64) {
+        self.stats.filtered_functions += 1;
+        // like for filtered functions, the marker keeps the addresses of the symbol from
+        // resolving to the preceding function.
+        self.ranges.entry(address).or_insert(NO_SOURCE_LOCATION);
+        self.filtered_addrs.insert(address);
+    }
 
-        for f in self.functions {
-            writer.write(&[f])?;
-        }
-        writer.align()?;
+    /// Adds a function that does not come from any debug format.
+    ///
+    /// This covers code that is only known at runtime or from external sources, such as JIT
+    /// compiled code, trampolines, or lists of addresses and names provided by a vendor. The
+    /// addresses are absolute, like those of symbols, and the function starts at its lowest
+    /// address. The name is transformed, filtered and demangled like the name of a symbol.
+    ///
+    /// Like symbols, the function only covers ranges that do not start at the same address as a
+    /// previously added function. Its frames carry no file or line, which can be added with
+    /// [`add_source_location`](Self::add_source_location).
+    pub fn add_synthetic_function(&mut self, name: &str, ranges: &[AddrRange]) {
+        let image_base = self.image_base;
+        let mut ranges: Vec<_> = ranges
+            .iter()
+            .filter_map(|range| {
+                let start = range.start.checked_sub(image_base)?;
+                let end = range.end.checked_sub(image_base)?;
+                Some(AddrRange { start, end })
+            })
+            .filter(|range| range.start < range.end)
+            .collect();
+        ranges.sort_by_key(|range| range.start);
 
-        for s in self.source_locations {
-            writer.write(&[s])?;
-        }
-        for s in self.ranges.values() {
-            writer.write(std::slice::from_ref(s))?;
+        let entry_pc = match ranges.first() {
+            Some(range) => range.start,
+            None => return,
+        };
+        if self.filtered_addrs.contains(&entry_pc) {
+            return;
         }
-        writer.align()?;
 
-        for r in self.ranges.keys() {
-            writer.write(&[raw::Range(*r)])?;
-        }
-        writer.align()?;
+        let (name_idx, linkage_name_idx) = match self.insert_symbol_name(name.into()) {
+            Some(names) => names,
+            None => {
+                for range in &ranges {
+                    self.filter_symbol(range.start);
+                }
+                return;
+            }
+        };
 
-        writer.write(&self.string_bytes)?;
+        let function_idx = self
+            .functions
+            .insert_full(FunctionEntry {
+                raw: native::Function {
+                    name_offset: name_idx,
+                    comp_dir_offset: u32::MAX,
+                    entry_pc: entry_pc as u32,
+                    lang: u32::MAX,
+                },
+                discriminator: u32::MAX,
+                linkage_name_offset: linkage_name_idx,
+                entry_pc_high: (entry_pc >> 32) as u32,
+                decl_file_idx: u32::MAX,
+                decl_line: 0,
+            })
+            .0 as u32;
 
-        Ok(())
-    }
-}
+        for range in ranges {
+            let entry = self.ranges.entry(range.start).or_insert(NO_SOURCE_LOCATION);
+            if *entry != NO_SOURCE_LOCATION {
+                continue;
+            }
 
-struct WriteWrapper<W> {
-    writer: W,
-    position: usize,
-}
+            *entry = SourceLocation {
+                raw: native::SourceLocation {
+                    file_idx: u32::MAX,
+                    line: 0,
+                    function_idx,
+                    inlined_into_idx: u32::MAX,
+                },
+                column: 0,
+            };
+            self.range_provenance
+                .insert(range.start, raw::PROVENANCE_SYMBOL_TABLE);
+            self.range_flags.remove(&range.start);
 
-impl<W: Write> WriteWrapper<W> {
-    fn new(writer: W) -> Self {
-        Self {
-            writer,
-            position: 0,
-        }
-    }
+            if self.covers_end(range.end, function_idx) {
+                self.ranges.entry(range.end).or_insert(NO_SOURCE_LOCATION);
+            }
 
-    fn write<T>(&mut self, data: &[T]) -> std::io::Result<usize> {
-        let pointer = data.as_ptr() as *const u8;
-        let len = std::mem::size_of_val(data);
-        // SAFETY: both pointer and len are derived directly from data/T and are valid.
-        let buf = unsafe { std::slice::from_raw_parts(pointer, len) };
-        self.writer.write_all(buf)?;
-        self.position += len;
-        Ok(len)
+            let last_addr = self.last_addr.get_or_insert(0);
+            if range.end > *last_addr {
+                *last_addr = range.end;
+            }
+        }
+    }
+
+    /// Adds a file and line to the addresses in `range`.
+    ///
+    /// The addresses are absolute, and must be covered by a function that was added before, for
+    /// example with [`add_synthetic_function`](Self::add_synthetic_function). All frames within
+    /// the range are replaced by a single frame of the function that covers its start, which is
+    /// recorded as if it came from debug information. The file is transformed and filtered like
+    /// files from debug information.
+    ///
+    /// Returns `false` if no function covers the start of the range, in which case nothing is
+    /// added.
+    pub fn add_source_location(&mut self, range: AddrRange, file: &str, line: u32) -> bool {
+        let (start, end) = match (
+            range.start.checked_sub(self.image_base),
+            range.end.checked_sub(self.image_base),
+        ) {
+            (Some(start), Some(end)) if start < end => (start, end),
+            _ => return false,
+        };
+
+        // the outermost function at the start of the range
+        let mut covering = match self.ranges.range(..=start).next_back() {
+            Some((_, source_location)) if *source_location != NO_SOURCE_LOCATION => source_location,
+            _ => return false,
+        };
+        // bounded in case of cyclic inline chains
+        for _ in 0..=self.source_locations.len() {
+            match self
+                .source_locations
+                .get_index(covering.raw.inlined_into_idx as usize)
+            {
+                Some(caller) => covering = caller,
+                None => break,
+            }
+        }
+        let function_idx = covering.raw.function_idx;
+
+        let mut location = transform::SourceLocation {
+            file: transform::File {
+                name: file.into(),
+                directory: None,
+                comp_dir: None,
+            },
+            line,
+        };
+        for transformer in &self.transformers.0 {
+            location = transformer.transform_source_location(location);
+        }
+        location = self.path_rewrites.transform_source_location(location);
+
+        let (file_idx, line) = if self.filters.keep_file(&location.file) {
+            let strings = &mut self.strings;
+            let path_name_offset = strings.insert(&location.file.name);
+            let directory_offset = location
+                .file
+                .directory
+                .map_or(u32::MAX, |d| strings.insert(&d));
+            let comp_dir_offset = location
+                .file
+                .comp_dir
+                .map_or(u32::MAX, |cd| strings.insert(&cd));
+            let (file_idx, _) = self.files.insert_full(native::File {
+                path_name_offset,
+                directory_offset,
+                comp_dir_offset,
+            });
+
+            if location.line > raw::MAX_LINE {
+                self.stats.overflowing_lines += 1;
+                (file_idx as u32, 0)
+            } else {
+                (file_idx as u32, location.line)
+            }
+        } else {
+            self.stats.filtered_lines += 1;
+            (u32::MAX, 0)
+        };
+
+        // the addresses after the range keep resolving to what they resolved to before
+        if let Some((&after_start, after)) = self.ranges.range(..=end).next_back() {
+            if after_start != end {
+                let after = after.clone();
+                if let Some(&provenance) = self.range_provenance.get(&after_start) {
+                    self.range_provenance.insert(end, provenance);
+                }
+                // the split range starts in the middle of a line record
+                self.range_flags.remove(&end);
+                self.ranges.insert(end, after);
+            }
+        }
+        let inner: Vec<u64> = self.ranges.range(start + 1..end).map(|(&a, _)| a).collect();
+        for address in inner {
+            self.ranges.remove(&address);
+            self.range_provenance.remove(&address);
+            self.range_flags.remove(&address);
+        }
+
+        self.ranges.insert(
+            start,
+            SourceLocation {
+                raw: native::SourceLocation {
+                    file_idx,
+                    line,
+                    function_idx,
+                    inlined_into_idx: u32::MAX,
+                },
+                column: 0,
+            },
+        );
+        self.range_provenance
+            .insert(start, raw::PROVENANCE_DEBUG_INFO);
+        self.range_flags.remove(&start);
+
+        true
+    }
+
+    /// Adds a code segment of the image, which is mapped separately at runtime.
+    ///
+    /// Images such as MachO kernels map their code in multiple segments, each of which may be slid
+    /// by a different amount. The segments let [`SymCache::lookup_segment_offset`] resolve offsets
+    /// into a segment, and [`SymCache::segment`] tell which segment an address belongs to. Like
+    /// the ranges of [synthetic functions](Self::add_synthetic_function), the addresses are
+    /// absolute. Segments that start at the same address as a previously added one are ignored.
+    pub fn add_segment(&mut self, name: &str, range: AddrRange) {
+        let start = range.start.checked_sub(self.image_base);
+        let end = range.end.checked_sub(self.image_base);
+        if let (Some(start), Some(end)) = (start, end) {
+            if start < end {
+                self.insert_segment(name, start, end - start);
+            }
+        }
+    }
+
+    /// Adds a code segment whose start address is already relative to the image base.
+    fn insert_segment(&mut self, name: &str, start: u64, size: u64) {
+        let idx = match self
+            .segments
+            .binary_search_by_key(&start, |segment| segment.start)
+        {
+            Ok(_) => return,
+            Err(idx) => idx,
+        };
+        let name_offset = self.strings.insert(name);
+        let segment = native::Segment {
+            start,
+            size,
+            name_offset,
+        };
+        self.segments.insert(idx, segment);
+    }
+
+    // Methods for merging converters below:
+
+    /// Merges all data processed by `other` into this converter.
+    ///
+    /// This combines several inputs for the same module into a single SymCache, for example a
+    /// dSYM, an il2cpp line mapping and additional symbols, each processed by its own converter.
+    /// Both converters must use the same image base. The debug identifier and architecture of
+    /// `other` are only used if they are not set on this converter.
+    ///
+    /// Line records of `other` that start at the same address as a line record of this converter
+    /// are resolved like overlapping top-level functions: the existing record is kept, unless only
+    /// the merged one has line information. Such conflicts are counted in
+    /// [`Stats::conflicting_lines`], and all other statistics are added up.
+    pub fn merge(&mut self, other: SymCacheConverter) {
+        if self.debug_id.is_nil() {
+            self.debug_id = other.debug_id;
+        }
+        if self.arch == Arch::Unknown {
+            self.arch = other.arch;
+        }
+
+        let strings = &mut self.strings;
+        let mut remap = |offset: u32| strings.insert(&other.strings.get(offset));
+
+        let mut file_indices = Vec::with_capacity(other.files.len());
+        for file in &other.files {
+            let file = native::File {
+                comp_dir_offset: remap(file.comp_dir_offset),
+                directory_offset: remap(file.directory_offset),
+                path_name_offset: remap(file.path_name_offset),
+            };
+            file_indices.push(self.files.insert_full(file).0 as u32);
+        }
+        let remap_file = |idx: u32| file_indices.get(idx as usize).copied().unwrap_or(u32::MAX);
+
+        let mut function_indices = Vec::with_capacity(other.functions.len());
+        for function in &other.functions {
+            let function = FunctionEntry {
+                raw: native::Function {
+                    name_offset: remap(function.raw.name_offset),
+                    comp_dir_offset: remap(function.raw.comp_dir_offset),
+                    ..function.raw
+                },
+                discriminator: remap_file(function.discriminator),
+                linkage_name_offset: remap(function.linkage_name_offset),
+                entry_pc_high: function.entry_pc_high,
+                decl_file_idx: remap_file(function.decl_file_idx),
+                decl_line: function.decl_line,
+            };
+            function_indices.push(self.functions.insert_full(function).0 as u32);
+        }
+
+        // callers are always inserted before the source locations inlined into them, so their
+        // new index is known by the time it is needed.
+        let mut source_location_indices = Vec::with_capacity(other.source_locations.len());
+        let remap_source_location = |source_location: &SourceLocation, indices: &[u32]| {
+            if *source_location == NO_SOURCE_LOCATION {
+                return NO_SOURCE_LOCATION;
+            }
+            let raw = &source_location.raw;
+            SourceLocation {
+                raw: native::SourceLocation {
+                    file_idx: remap_file(raw.file_idx),
+                    line: raw.line,
+                    function_idx: function_indices
+                        .get(raw.function_idx as usize)
+                        .copied()
+                        .unwrap_or(u32::MAX),
+                    inlined_into_idx: indices
+                        .get(raw.inlined_into_idx as usize)
+                        .copied()
+                        .unwrap_or(u32::MAX),
+                },
+                column: source_location.column,
+            }
+        };
+        for source_location in &other.source_locations {
+            let source_location = remap_source_location(source_location, &source_location_indices);
+            let idx = self.source_locations.insert_full(source_location).0 as u32;
+            source_location_indices.push(idx);
+        }
+
+        let other_last_start = other.ranges.keys().next_back().copied();
+        let self_last_start = self.ranges.keys().next_back().copied();
+
+        // addresses at which the range of `other` has been kept
+        let mut merged = HashSet::new();
+        for (address, source_location) in &other.ranges {
+            let source_location = remap_source_location(source_location, &source_location_indices);
+            let kept = match self.ranges.entry(*address) {
+                btree_map::Entry::Vacant(_) if source_location == NO_SOURCE_LOCATION => {
+                    // an end marker within a range of this converter would cut it short
+                    let covers = match self.ranges.range(..*address).next_back() {
+                        Some((start, _)) => merged.contains(start),
+                        None => true,
+                    };
+                    if covers {
+                        self.ranges.insert(*address, source_location);
+                    }
+                    covers
+                }
+                btree_map::Entry::Vacant(entry) => {
+                    entry.insert(source_location);
+                    true
+                }
+                btree_map::Entry::Occupied(mut entry) if *entry.get() == NO_SOURCE_LOCATION => {
+                    entry.insert(source_location);
+                    true
+                }
+                btree_map::Entry::Occupied(entry) if *entry.get() == source_location => true,
+                btree_map::Entry::Occupied(_) if source_location == NO_SOURCE_LOCATION => false,
+                btree_map::Entry::Occupied(mut entry) => {
+                    self.stats.conflicting_lines += 1;
+                    if self.error_sink.0.is_some() {
+                        let strings = &self.strings;
+                        let name = self
+                            .functions
+                            .get_index(source_location.raw.function_idx as usize)
+                            .map(|function| strings.get(function.raw.name_offset));
+                        let name = name.as_deref().unwrap_or_default();
+                        self.error_sink.report_conflicting_lines(*address, name);
+                    }
+                    if entry.get().raw.file_idx != u32::MAX
+                        || source_location.raw.file_idx == u32::MAX
+                    {
+                        let provenance = other.range_provenance.get(address).copied();
+                        let provenance = provenance.unwrap_or(raw::PROVENANCE_UNKNOWN);
+                        self.add_candidate(*address, source_location, provenance);
+                        false
+                    } else {
+                        let dropped = entry.insert(source_location);
+                        let provenance = self.range_provenance.get(address).copied();
+                        let provenance = provenance.unwrap_or(raw::PROVENANCE_UNKNOWN);
+                        self.add_candidate(*address, dropped, provenance);
+                        true
+                    }
+                }
+            };
+
+            if kept {
+                merged.insert(*address);
+                match other.range_provenance.get(address) {
+                    Some(&provenance) => self.range_provenance.insert(*address, provenance),
+                    None => self.range_provenance.remove(address),
+                };
+                let flags = other.range_flags.get(address).copied().unwrap_or(0);
+                self.set_range_flags(*address, flags);
+            }
+        }
+
+        for (address, candidates) in &other.candidates {
+            for (source_location, provenance) in candidates {
+                let source_location =
+                    remap_source_location(source_location, &source_location_indices);
+                self.add_candidate(*address, source_location, *provenance);
+            }
+        }
+        for segment in &other.segments {
+            let name = other.strings.get(segment.name_offset);
+            self.insert_segment(&name, segment.start, segment.size);
+        }
+
+        // `None` means that the highest range extends to the end of the address space
+        self.last_addr = match (self.last_addr, other.last_addr) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            (None, Some(b)) if other_last_start > self_last_start => Some(b),
+            (Some(a), None) if self_last_start > other_last_start => Some(a),
+            _ => None,
+        };
+
+        self.stats.overflowing_lines += other.stats.overflowing_lines;
+        self.stats.dropped_inline_frames += other.stats.dropped_inline_frames;
+        self.stats.dropped_line_info += other.stats.dropped_line_info;
+        self.stats.conflicting_lines += other.stats.conflicting_lines;
+        self.stats.processed_functions += other.stats.processed_functions;
+        self.stats.filtered_functions += other.stats.filtered_functions;
+        self.stats.filtered_lines += other.stats.filtered_lines;
+        self.stats.coalesced_ranges += other.stats.coalesced_ranges;
+        self.stats.skipped_inlinees += other.stats.skipped_inlinees;
+        self.filtered_addrs.extend(other.filtered_addrs);
+        self.stats.units.extend(other.stats.units);
+
+        if let Some(error) = other.strings.take_error() {
+            self.strings.set_error(error);
+        }
+    }
+
+    /// Merges all data of a parsed SymCache into this converter.
+    ///
+    /// This combines partial SymCaches of the same module, for example caches converted in
+    /// parallel from separate compilation units, into a single SymCache. Strings, files and
+    /// functions are deduplicated, and line records at the same address are resolved as
+    /// described in [`merge`](Self::merge). Addresses are taken as they are, so the SymCache
+    /// must use the same image base as this converter.
+    ///
+    /// Returns an error if the SymCache contains invalid string references.
+    pub fn process_symcache(&mut self, cache: &SymCache<'_>) -> Result<(), SymCacheError> {
+        let mut converter = SymCacheConverter::new();
+        converter.set_debug_id(cache.debug_id());
+        converter.set_arch(cache.arch());
+
+        let strings = &mut converter.strings;
+        let mut import = |offset: u32| -> Result<u32, SymCacheError> {
+            let string = read_string(cache.string_bytes, offset)?;
+            Ok(strings.insert(string.unwrap_or_default()))
+        };
+
+        let mut file_indices = Vec::with_capacity(cache.files.len());
+        for file in cache.files {
+            let file = native::File {
+                comp_dir_offset: import(file.comp_dir_offset.get())?,
+                directory_offset: import(file.directory_offset.get())?,
+                path_name_offset: import(file.path_name_offset.get())?,
+            };
+            file_indices.push(converter.files.insert_full(file).0 as u32);
+        }
+
+        let mut function_indices = Vec::with_capacity(cache.functions.len());
+        for (idx, function) in cache.functions.iter().enumerate() {
+            let linkage_name_offset = cache.linkage_names.get(idx).map(|offset| offset.get());
+            let declaration = cache.declarations.get(idx);
+            let mut function = FunctionEntry {
+                raw: native::Function {
+                    name_offset: import(function.name_offset.get())?,
+                    comp_dir_offset: import(function.comp_dir_offset.get())?,
+                    entry_pc: function.entry_pc.get(),
+                    lang: function.lang.get(),
+                },
+                discriminator: u32::MAX,
+                linkage_name_offset: import(linkage_name_offset.unwrap_or(u32::MAX))?,
+                entry_pc_high: cache.entry_pcs_high.get(idx).map_or(0, |high| high.get()),
+                decl_file_idx: declaration.map_or(u32::MAX, |decl| {
+                    let file_idx = decl.file_idx.get() as usize;
+                    file_indices.get(file_idx).copied().unwrap_or(u32::MAX)
+                }),
+                decl_line: declaration.map_or(0, |decl| decl.line.get()),
+            };
+            // functions that were only told apart by their file remain distinct
+            if converter.functions.contains(&function) {
+                function.discriminator = idx as u32;
+            }
+            let (function_idx, _) = converter.functions.insert_full(function);
+            function_indices.push(function_idx as u32);
+        }
+
+        let import_source_location = |idx: usize, caller_indices: &[u32]| {
+            let raw = &cache.source_locations[idx];
+            if raw.is_none() {
+                return NO_SOURCE_LOCATION;
+            }
+            let remap = |indices: &[u32], idx: raw::U32| {
+                indices.get(idx.get() as usize).copied().unwrap_or(u32::MAX)
+            };
+            SourceLocation {
+                raw: native::SourceLocation {
+                    file_idx: remap(&file_indices, raw.file_idx),
+                    line: raw.line.get(),
+                    function_idx: remap(&function_indices, raw.function_idx),
+                    inlined_into_idx: remap(caller_indices, raw.inlined_into_idx),
+                },
+                column: cache.columns.get(idx).map_or(0, |column| column.get()),
+            }
+        };
+
+        // the source locations of all ranges follow the ones that are only referenced as callers,
+        // and callers are always written before the source locations inlined into them.
+        let num_callers = cache
+            .source_locations
+            .len()
+            .saturating_sub(cache.ranges.len());
+        let mut caller_indices = Vec::with_capacity(num_callers);
+        for idx in 0..num_callers {
+            let source_location = import_source_location(idx, &caller_indices);
+            let (caller_idx, _) = converter.source_locations.insert_full(source_location);
+            caller_indices.push(caller_idx as u32);
+        }
+
+        for range_idx in 0..cache.ranges.len() {
+            let address = match cache.range_start(range_idx) {
+                Some(address) => address,
+                None => break,
+            };
+            let source_location = import_source_location(num_callers + range_idx, &caller_indices);
+            // the end of the last function is restored from the trailing end marker
+            converter.last_addr = if source_location == NO_SOURCE_LOCATION {
+                Some(address)
+            } else {
+                None
+            };
+            converter.ranges.insert(address, source_location);
+            if let Some(&provenance) = cache.provenance.get(range_idx) {
+                converter.range_provenance.insert(address, provenance);
+            }
+            if let Some(&flags) = cache.range_flags.get(range_idx) {
+                converter.set_range_flags(address, flags);
+            }
+        }
+
+        // candidates are only referenced by their index, so they are among the callers
+        for candidate in cache.candidates {
+            let address = cache.range_start(candidate.range_idx.get() as usize);
+            let source_location = caller_indices
+                .get(candidate.source_location_idx.get() as usize)
+                .and_then(|&idx| converter.source_locations.get_index(idx as usize));
+            if let (Some(address), Some(source_location)) = (address, source_location) {
+                let candidate = (source_location.clone(), candidate.provenance.get() as u8);
+                converter
+                    .candidates
+                    .entry(address)
+                    .or_default()
+                    .push(candidate);
+            }
+        }
+        for segment in cache.segments() {
+            let range = segment.range;
+            let name = segment.name.unwrap_or_default();
+            converter.insert_segment(name, range.start, range.end - range.start);
+        }
+
+        self.merge(converter);
+        Ok(())
+    }
+
+    /// Re-encodes a SymCache in the legacy format, which was written before version
+    /// [`SYMCACHE_VERSION_MIN`](raw::SYMCACHE_VERSION_MIN), and merges it into this converter.
+    ///
+    /// This upgrades existing SymCaches without the original debug file. Every address resolves
+    /// to the same frames as in the legacy SymCache. Names and paths are taken as they are, so
+    /// transformers and demangling are not applied. SymCaches in the current format are merged
+    /// with [`process_symcache`](Self::process_symcache) instead.
+    ///
+    /// Fails with [`SymCacheErrorKind::BadCacheFile`] if an inlined function refers to a parent
+    /// that is not stored before it.
+    pub fn process_symcache_v7(&mut self, data: &[u8]) -> Result<(), SymCacheError> {
+        if Preamble::parse(data)?.version > SYMCACHE_VERSION_CUTOFF {
+            return self.process_symcache(&SymCache::parse(data)?);
+        }
+
+        let legacy = old::SymCache::parse(data)?;
+        let mut converter = SymCacheConverter::new();
+        converter.set_debug_id(legacy.debug_id());
+        converter.set_arch(legacy.arch());
+
+        for function in legacy.functions() {
+            if function?.parent_id().is_none() {
+                converter.stats.processed_functions += 1;
+            }
+        }
+
+        // the frames only change at the boundaries of functions and line records
+        let mut frames = Vec::new();
+        for address in legacy.lookup_boundaries()? {
+            self.check_cancelled()?;
+            frames.clear();
+            for frame in legacy.lookup(address)? {
+                frames.push(frame?);
+            }
+
+            let mut source_location = NO_SOURCE_LOCATION;
+            for frame in frames.iter().rev() {
+                // every frame is inlined into the one resolved before it
+                let inlined_into_idx = if source_location == NO_SOURCE_LOCATION {
+                    u32::MAX
+                } else {
+                    converter.source_locations.insert_full(source_location).0 as u32
+                };
+                source_location = converter.insert_legacy_frame(frame, inlined_into_idx);
+            }
+
+            if converter.ranges.values().next_back() != Some(&source_location) {
+                converter.ranges.insert(address, source_location);
+            }
+        }
+
+        converter.last_addr = match converter.ranges.iter().next_back() {
+            Some((&address, source_location)) if *source_location == NO_SOURCE_LOCATION => {
+                Some(address)
+            }
+            _ => None,
+        };
+
+        self.merge(converter);
+        Ok(())
+    }
+
+    /// Inserts the function and file of a frame resolved from a legacy SymCache.
+    fn insert_legacy_frame(
+        &mut self,
+        frame: &LineInfo<'_>,
+        inlined_into_idx: u32,
+    ) -> SourceLocation {
+        let strings = &mut self.strings;
+        let name_offset = strings.insert(frame.symbol.unwrap_or_default());
+        let comp_dir_offset = strings.insert(frame.comp_dir);
+
+        // inlined functions do not have an entry address
+        let entry_pc = if inlined_into_idx == u32::MAX {
+            frame.sym_addr
+        } else {
+            u64::from(u32::MAX)
+        };
+        let (function_idx, _) = self.functions.insert_full(FunctionEntry {
+            raw: native::Function {
+                name_offset,
+                comp_dir_offset,
+                entry_pc: entry_pc as u32,
+                lang: frame.lang as u32,
+            },
+            discriminator: u32::MAX,
+            linkage_name_offset: u32::MAX,
+            entry_pc_high: (entry_pc >> 32) as u32,
+            decl_file_idx: u32::MAX,
+            decl_line: 0,
+        });
+
+        let file_idx = if frame.filename.is_empty() {
+            u32::MAX
+        } else {
+            let file = native::File {
+                comp_dir_offset,
+                directory_offset: strings.insert(frame.base_dir),
+                path_name_offset: strings.insert(frame.filename),
+            };
+            self.files.insert_full(file).0 as u32
+        };
+
+        SourceLocation {
+            raw: native::SourceLocation {
+                file_idx,
+                line: frame.line,
+                function_idx: function_idx as u32,
+                inlined_into_idx,
+            },
+            column: 0,
+        }
+    }
+
+    // Methods for limiting the size of the SymCache below:
+
+    /// Drops data until the serialized SymCache fits into `max_size` bytes.
+    ///
+    /// Data is dropped in the following order, stopping as soon as the SymCache is small enough:
+    ///
+    /// 1. [Candidates](Self::set_keep_candidates): all addresses resolve to a single attribution.
+    /// 2. Inline frames: the most deeply nested inline frames are dropped first, one level at a
+    ///    time, until every address resolves to its outermost function only.
+    /// 3. Line information: every address resolves to a function without file and line.
+    ///
+    /// The number of dropped records is reported in [`stats`](Self::stats). Function names are
+    /// never dropped, so this returns `false` if the SymCache still exceeds `max_size`. Call this
+    /// after all data has been processed.
+    pub fn limit_size(&mut self, max_size: u64) -> bool {
+        if self.serialized_size() <= max_size {
+            return true;
+        }
+
+        if !self.candidates.is_empty() {
+            self.candidates.clear();
+            self.compact();
+            if self.serialized_size() <= max_size {
+                return true;
+            }
+        }
+
+        let mut depth = self
+            .ranges
+            .values()
+            .map(|source_location| inline_callers(&self.source_locations, source_location).len())
+            .max()
+            .unwrap_or(0);
+        loop {
+            depth = depth.saturating_sub(1);
+            self.truncate_inlinees(depth);
+            if self.serialized_size() <= max_size {
+                return true;
+            }
+            if depth == 0 {
+                break;
+            }
+        }
+
+        for (_, source_location) in self.ranges.iter_mut() {
+            if *source_location != NO_SOURCE_LOCATION && source_location.raw.file_idx != u32::MAX {
+                source_location.raw.file_idx = u32::MAX;
+                source_location.raw.line = 0;
+                source_location.column = 0;
+                self.stats.dropped_line_info += 1;
+            }
+        }
+        self.compact();
+
+        self.serialized_size() <= max_size
+    }
+
+    /// Merges ranges that resolve to the same location as the range before them.
+    ///
+    /// Consecutive line records often resolve to the same function, file, line and inline chain,
+    /// for example if the line program starts a new row for each statement on a line. Such ranges
+    /// are merged into a single range if they also have the same provenance. The number of merged
+    /// ranges is reported in [`Stats::coalesced_ranges`].
+    ///
+    /// Redundant ranges are always left out when serializing, so this only needs to be called to
+    /// inspect the statistics. Call this after all data has been processed.
+    pub fn coalesce_ranges(&mut self) {
+        let redundant = self.redundant_ranges();
+        for address in &redundant {
+            self.ranges.remove(address);
+            self.range_provenance.remove(address);
+            self.range_flags.remove(address);
+        }
+        self.stats.coalesced_ranges += redundant.len() as u64;
+    }
+
+    /// Returns the addresses of ranges that resolve to the same location with the same provenance
+    /// as the range before them, see [`coalesce_ranges`](Self::coalesce_ranges).
+    ///
+    /// Ranges that start the function body or a basic block are never redundant, since the
+    /// [flags](raw::SECTION_RANGE_FLAGS) mark their start address. Neither are ranges with
+    /// [candidates](Self::set_keep_candidates), which refer to the range by its start address.
+    /// Like collapsed line records, a coalesced range keeps the statement flag of its first range.
+    fn redundant_ranges(&self) -> Vec<u64> {
+        let starts = raw::RANGE_FLAG_PROLOGUE_END | raw::RANGE_FLAG_BASIC_BLOCK;
+        let mut redundant = Vec::new();
+        let mut previous = None;
+        for (&address, source_location) in &self.ranges {
+            let flags = self.range_flags.get(&address).copied().unwrap_or(0);
+            let current = (source_location, self.range_provenance.get(&address));
+            if previous == Some(current)
+                && flags & starts == 0
+                && !self.candidates.contains_key(&address)
+            {
+                redundant.push(address);
+            }
+            previous = Some(current);
+        }
+        redundant
+    }
+
+    /// Drops the inline frames nested more than `max_depth` levels deep, so that their addresses
+    /// resolve to the innermost remaining caller.
+    fn truncate_inlinees(&mut self, max_depth: usize) {
+        for source_location in self.ranges.values_mut() {
+            let callers = inline_callers(&self.source_locations, source_location);
+            if callers.len() > max_depth {
+                let dropped = callers.len() - max_depth;
+                let caller = callers[dropped - 1].clone();
+                *source_location = caller;
+                self.stats.dropped_inline_frames += dropped as u64;
+            }
+        }
+        self.compact();
+    }
+
+    /// Removes redundant ranges as well as unreferenced source locations, functions, files and
+    /// strings.
+    fn compact(&mut self) {
+        self.coalesce_ranges();
+
+        let mut files = IndexSet::new();
+        let mut functions = IndexSet::new();
+        let (old_files, old_functions) = (&self.files, &self.functions);
+        let mut remap = |raw: &mut native::SourceLocation| {
+            if let Some(file) = old_files.get_index(raw.file_idx as usize) {
+                raw.file_idx = files.insert_full(file.clone()).0 as u32;
+            }
+            if let Some(function) = old_functions.get_index(raw.function_idx as usize) {
+                raw.function_idx = functions.insert_full(function.clone()).0 as u32;
+            }
+        };
+
+        // callers are inserted before their inlinees
+        let mut source_locations = IndexSet::new();
+        let candidates = self.candidates.values_mut().flat_map(|candidates| {
+            candidates
+                .iter_mut()
+                .map(|(source_location, _)| source_location)
+        });
+        for source_location in self.ranges.values_mut().chain(candidates) {
+            let callers: Vec<_> = inline_callers(&self.source_locations, source_location)
+                .into_iter()
+                .cloned()
+                .collect();
+            let mut inlined_into_idx = u32::MAX;
+            for mut caller in callers.into_iter().rev() {
+                remap(&mut caller.raw);
+                caller.raw.inlined_into_idx = inlined_into_idx;
+                inlined_into_idx = source_locations.insert_full(caller).0 as u32;
+            }
+            remap(&mut source_location.raw);
+            source_location.raw.inlined_into_idx = inlined_into_idx;
+        }
+        self.source_locations = source_locations;
+
+        // declared files are kept along with their functions
+        let functions: Vec<FunctionEntry> = functions
+            .into_iter()
+            .map(|mut function: FunctionEntry| {
+                let decl_file = self.files.get_index(function.decl_file_idx as usize);
+                function.decl_file_idx = match decl_file {
+                    Some(file) => files.insert_full(file.clone()).0 as u32,
+                    None => u32::MAX,
+                };
+                function
+            })
+            .collect();
+
+        let budget = self.strings.budget();
+        let old_strings = std::mem::replace(&mut self.strings, StringTable::with_budget(budget));
+        let strings = &mut self.strings;
+        let mut remap = |offset: u32| strings.insert(&old_strings.get(offset));
+
+        self.files = files
+            .into_iter()
+            .map(|file| native::File {
+                comp_dir_offset: remap(file.comp_dir_offset),
+                directory_offset: remap(file.directory_offset),
+                path_name_offset: remap(file.path_name_offset),
+            })
+            .collect();
+        self.functions = functions
+            .into_iter()
+            .map(|function| FunctionEntry {
+                raw: native::Function {
+                    name_offset: remap(function.raw.name_offset),
+                    comp_dir_offset: remap(function.raw.comp_dir_offset),
+                    ..function.raw
+                },
+                linkage_name_offset: remap(function.linkage_name_offset),
+                ..function
+            })
+            .collect();
+        for segment in &mut self.segments {
+            segment.name_offset = remap(segment.name_offset);
+        }
+
+        if let Some(error) = old_strings.take_error() {
+            self.strings.set_error(error);
+        }
+    }
+
+    /// Returns a copy of the data to serialize, laid out in a canonical order.
+    ///
+    /// Files, functions and the source locations of inline callers are ordered by their first
+    /// use when walking the ranges in ascending address order, outermost caller first. Unused
+    /// files and functions follow, sorted by their contents. Strings are ordered by their first
+    /// use in files and functions. This makes the serialized SymCache independent of the order
+    /// in which its data was processed. Redundant ranges, unused source locations and strings
+    /// are dropped.
+    ///
+    /// Use [`into_canonical`](Self::into_canonical) to avoid copying the ranges.
+    fn canonicalize(&self) -> Self {
+        let (mut canonical, order) = self.canonical_tables();
+        let redundant: HashSet<u64> = self.redundant_ranges().into_iter().collect();
+        canonical.ranges = self
+            .ranges
+            .iter()
+            .filter(|(addr, _)| !redundant.contains(addr))
+            .map(|(&addr, source_location)| (addr, order.source_location(source_location)))
+            .collect();
+
+        let ranges = &canonical.ranges;
+        let kept = |addr: &u64| ranges.contains_key(addr);
+        canonical.range_provenance = self
+            .range_provenance
+            .iter()
+            .filter(|(addr, _)| kept(addr))
+            .map(|(&addr, &provenance)| (addr, provenance))
+            .collect();
+        canonical.range_flags = self
+            .range_flags
+            .iter()
+            .filter(|(addr, _)| kept(addr))
+            .map(|(&addr, &flags)| (addr, flags))
+            .collect();
+        canonical
+    }
+
+    /// Lays out the data to serialize in a canonical order, see
+    /// [`canonicalize`](Self::canonicalize).
+    ///
+    /// The ranges and their metadata are moved and updated in place instead of being copied.
+    fn into_canonical(mut self) -> Self {
+        let (mut canonical, order) = self.canonical_tables();
+        for addr in self.redundant_ranges() {
+            self.ranges.remove(&addr);
+            self.range_provenance.remove(&addr);
+            self.range_flags.remove(&addr);
+        }
+        for source_location in self.ranges.values_mut() {
+            *source_location = order.source_location(source_location);
+        }
+
+        canonical.ranges = std::mem::take(&mut self.ranges);
+        canonical.range_provenance = std::mem::take(&mut self.range_provenance);
+        canonical.range_flags = std::mem::take(&mut self.range_flags);
+
+        if let Some(error) = self.strings.take_error() {
+            canonical.strings.set_error(error);
+        }
+        canonical
+    }
+
+    /// Lays out all data but the ranges in a canonical order, see
+    /// [`canonicalize`](Self::canonicalize).
+    ///
+    /// Candidates are laid out along with the tables, as their source locations are stored with
+    /// the source locations of inline callers.
+    ///
+    /// Returns the new tables along with the order they were laid out in, which remaps the
+    /// source locations of ranges.
+    fn canonical_tables(&self) -> (Self, CanonicalOrder) {
+        let mut files = IndexSet::new();
+        let mut functions = IndexSet::new();
+        let mut callers = IndexSet::new();
+
+        // candidates are only kept along with their range
+        let candidates: Vec<_> = self
+            .candidates
+            .iter()
+            .filter(|(addr, _)| self.ranges.contains_key(addr))
+            .flat_map(|(&addr, candidates)| candidates.iter().map(move |c| (addr, c)))
+            .collect();
+
+        let mut chain = Vec::new();
+        let candidate_locations = candidates
+            .iter()
+            .map(|(_, (source_location, _))| source_location);
+        for source_location in self.ranges.values().chain(candidate_locations) {
+            let mut caller_idx = source_location.raw.inlined_into_idx;
+            while let Some(caller) = self.source_locations.get_index(caller_idx as usize) {
+                if callers.contains(&caller_idx) || chain.contains(&caller_idx) {
+                    break;
+                }
+                chain.push(caller_idx);
+                caller_idx = caller.raw.inlined_into_idx;
+            }
+
+            let callers_outermost_first = chain.drain(..).rev().map(|idx| {
+                callers.insert(idx);
+                &self.source_locations[idx as usize].raw
+            });
+            for raw in callers_outermost_first.chain(std::iter::once(&source_location.raw)) {
+                if (raw.file_idx as usize) < self.files.len() {
+                    files.insert(raw.file_idx);
+                }
+                if (raw.function_idx as usize) < self.functions.len() {
+                    functions.insert(raw.function_idx);
+                }
+            }
+        }
+
+        // every string is read once to sort by it, even if it has been spilled
+        let string = |offset| self.strings.get(offset);
+        let mut unused_files: Vec<_> = (0..self.files.len() as u32)
+            .filter(|idx| !files.contains(idx))
+            .collect();
+        unused_files.sort_by_cached_key(|&idx| {
+            let file = &self.files[idx as usize];
+            (
+                string(file.comp_dir_offset),
+                string(file.directory_offset),
+                string(file.path_name_offset),
+            )
+        });
+        files.extend(unused_files);
+        let mut unused_functions: Vec<_> = (0..self.functions.len() as u32)
+            .filter(|idx| !functions.contains(idx))
+            .collect();
+        unused_functions.sort_by_cached_key(|&idx| {
+            let function = &self.functions[idx as usize];
+            (
+                string(function.raw.name_offset),
+                string(function.raw.comp_dir_offset),
+                string(function.linkage_name_offset),
+                (function.entry_pc_high, function.raw.entry_pc),
+                function.raw.lang,
+                function.decl_line,
+            )
+        });
+        functions.extend(unused_functions);
+
+        let mut canonical = Self {
+            debug_id: self.debug_id,
+            arch: self.arch,
+            section_alignment: self.section_alignment,
+            image_base: self.image_base,
+            name_index: self.name_index,
+            call_edges: self.call_edges,
+            keep_candidates: self.keep_candidates,
+            strings: StringTable::with_budget(self.strings.budget()),
+            last_addr: self.last_addr,
+            ..Self::default()
+        };
+
+        let order = CanonicalOrder {
+            files,
+            functions,
+            callers,
+        };
+
+        let strings = &mut canonical.strings;
+        let mut remap = |offset: u32| strings.insert(&string(offset));
+        canonical.files = order
+            .files
+            .iter()
+            .map(|&idx| {
+                let file = &self.files[idx as usize];
+                native::File {
+                    comp_dir_offset: remap(file.comp_dir_offset),
+                    directory_offset: remap(file.directory_offset),
+                    path_name_offset: remap(file.path_name_offset),
+                }
+            })
+            .collect();
+        canonical.functions = order
+            .functions
+            .iter()
+            .map(|&idx| {
+                let function = &self.functions[idx as usize];
+                FunctionEntry {
+                    raw: native::Function {
+                        name_offset: remap(function.raw.name_offset),
+                        comp_dir_offset: remap(function.raw.comp_dir_offset),
+                        ..function.raw
+                    },
+                    linkage_name_offset: remap(function.linkage_name_offset),
+                    decl_file_idx: CanonicalOrder::index_of(&order.files, function.decl_file_idx),
+                    ..function.clone()
+                }
+            })
+            .collect();
+        canonical.segments = self
+            .segments
+            .iter()
+            .map(|segment| native::Segment {
+                name_offset: remap(segment.name_offset),
+                ..*segment
+            })
+            .collect();
+        canonical.source_locations = order
+            .callers
+            .iter()
+            .map(|&idx| order.source_location(&self.source_locations[idx as usize]))
+            .collect();
+
+        // the source locations of candidates follow the callers, as they are only referenced by
+        // their index
+        for (addr, (source_location, provenance)) in candidates {
+            let source_location = order.source_location(source_location);
+            canonical.source_locations.insert(source_location.clone());
+            let candidates = canonical.candidates.entry(addr).or_default();
+            candidates.push((source_location, *provenance));
+        }
+
+        (canonical, order)
+    }
+
+    /// Computes an upper bound of the size of the SymCache as it is written by
+    /// [`serialize`](Self::serialize).
+    ///
+    /// This is exact unless there are redundant ranges, unused source locations or strings, which
+    /// are dropped by [`canonicalize`](Self::canonicalize).
+    fn serialized_size(&self) -> u64 {
+        let num_ranges = self.serialized_ranges().count();
+        // candidates are added to the source locations when laying out the data
+        let num_candidates = self
+            .candidates
+            .values()
+            .flatten()
+            .filter(|(source_location, _)| !self.source_locations.contains(source_location))
+            .count();
+        let num_source_locations = self.source_locations.len() + num_candidates + num_ranges;
+
+        // the last section is not padded
+        let table_start = self.section_table_start(num_source_locations, num_ranges);
+        let sections = self.section_table(table_start, num_source_locations, num_ranges);
+        match sections.last() {
+            Some(section) => section.offset.get() + section.len.get(),
+            None => table_start as u64,
+        }
+    }
+
+    /// Returns the ranges as they are serialized, in ascending order.
+    ///
+    /// In case we have a definite end address, this includes a trailing sentinel range at
+    /// [`last_addr`](Self::last_addr) that has no source location.
+    fn serialized_ranges(&self) -> impl Iterator<Item = (u64, &SourceLocation)> + '_ {
+        // TODO: to be extra safe, we might check that `last_addr` is indeed larger than
+        // the largest range at some point. An already defined range at the last addr is a bug.
+        let sentinel = self
+            .last_addr
+            .filter(|last_addr| !self.ranges.contains_key(last_addr));
+        let split = sentinel.unwrap_or(u64::MAX);
+
+        let before = self.ranges.range(..split);
+        let after = self.ranges.range(split..);
+        before
+            .map(|(addr, source_location)| (*addr, source_location))
+            .chain(sentinel.map(|addr| (addr, &NO_SOURCE_LOCATION)))
+            .chain(after.map(|(addr, source_location)| (*addr, source_location)))
+    }
+
+    /// Computes the offset of the section table, which follows the header, the core sections and
+    /// the string data, each padded to the section alignment.
+    fn section_table_start(&self, num_source_locations: usize, num_ranges: usize) -> usize {
+        let alignment = self.section_alignment.max(raw::DEFAULT_SECTION_ALIGNMENT) as usize;
+        let section = |len: usize| len + raw::align_to(len, alignment);
+
+        section(std::mem::size_of::<raw::Header>())
+            + section(self.files.len() * std::mem::size_of::<raw::File>())
+            + section(self.functions.len() * std::mem::size_of::<raw::Function>())
+            + section(num_source_locations * std::mem::size_of::<raw::SourceLocation>())
+            + section(num_ranges * std::mem::size_of::<raw::Range>())
+            + section(self.strings.len())
+    }
+
+    /// Lays out the extension sections, which follow the section table at `table_start`.
+    fn section_table(
+        &self,
+        table_start: usize,
+        num_source_locations: usize,
+        num_ranges: usize,
+    ) -> Vec<raw::Section> {
+        let alignment = self.section_alignment.max(raw::DEFAULT_SECTION_ALIGNMENT) as usize;
+        let u32_size = std::mem::size_of::<u32>();
+
+        let mut sections = vec![(raw::SECTION_PROVENANCE, 1, num_ranges)];
+        if self.has_range_flags() {
+            sections.push((raw::SECTION_RANGE_FLAGS, 1, num_ranges));
+        }
+        if self.has_columns() {
+            sections.push((raw::SECTION_COLUMNS, u32_size, num_source_locations));
+        }
+        if self.has_linkage_names() {
+            sections.push((raw::SECTION_LINKAGE_NAMES, u32_size, self.functions.len()));
+        }
+        if self.has_high_ranges() {
+            sections.push((raw::SECTION_RANGES_HIGH, u32_size, num_ranges));
+        }
+        if self.has_high_entry_pcs() {
+            sections.push((raw::SECTION_ENTRY_PCS_HIGH, u32_size, self.functions.len()));
+        }
+        if self.image_base != 0 {
+            sections.push((raw::SECTION_IMAGE_BASE, std::mem::size_of::<u64>(), 1));
+        }
+        if self.name_index {
+            sections.push((raw::SECTION_NAME_INDEX, u32_size, self.functions.len()));
+        }
+        if self.call_edges {
+            let edge_size = std::mem::size_of::<raw::CallEdge>();
+            let count = self.collect_call_edges().len() * edge_size / u32_size;
+            sections.push((raw::SECTION_CALL_EDGES, u32_size, count));
+        }
+        if self.has_declarations() {
+            let count = self.functions.len() * 2;
+            sections.push((raw::SECTION_DECLARATIONS, u32_size, count));
+        }
+        if !self.segments.is_empty() {
+            let u64_size = std::mem::size_of::<u64>();
+            let count = self.segments.len() * std::mem::size_of::<raw::Segment>() / u64_size;
+            sections.push((raw::SECTION_SEGMENTS, u64_size, count));
+        }
+        let num_candidates = self.serialized_candidates().len();
+        if num_candidates > 0 {
+            let count = num_candidates * std::mem::size_of::<raw::Candidate>() / u32_size;
+            sections.push((raw::SECTION_CANDIDATES, u32_size, count));
+        }
+
+        let mut offset = table_start + sections.len() * std::mem::size_of::<raw::Section>();
+        sections
+            .into_iter()
+            .map(|(kind, element_size, count)| {
+                offset += raw::align_to(offset, alignment);
+                let section = raw::Section {
+                    kind: kind.into(),
+                    element_size: (element_size as u32).into(),
+                    offset: (offset as u64).into(),
+                    len: ((element_size * count) as u64).into(),
+                };
+                offset += element_size * count;
+                section
+            })
+            .collect()
+    }
+
+    /// Returns whether any source location has a column, see [`raw::SECTION_COLUMNS`].
+    fn has_columns(&self) -> bool {
+        let ranges = self
+            .serialized_ranges()
+            .map(|(_, source_location)| source_location);
+        let mut source_locations = self.source_locations.iter().chain(ranges);
+        source_locations.any(|source_location| source_location.column != 0)
+    }
+
+    /// Returns whether any serialized range has flags, see [`raw::SECTION_RANGE_FLAGS`].
+    fn has_range_flags(&self) -> bool {
+        let mut ranges = self.serialized_ranges();
+        ranges.any(|(addr, _)| self.range_flags.contains_key(&addr))
+    }
+
+    /// Returns whether any function has a linkage name, see [`raw::SECTION_LINKAGE_NAMES`].
+    fn has_linkage_names(&self) -> bool {
+        let mut functions = self.functions.iter();
+        functions.any(|function| function.linkage_name_offset != u32::MAX)
+    }
+
+    /// Returns whether any range starts beyond 32 bits, see [`raw::SECTION_RANGES_HIGH`].
+    fn has_high_ranges(&self) -> bool {
+        let last_addr = self.ranges.keys().next_back().max(self.last_addr.as_ref());
+        matches!(last_addr, Some(&addr) if addr > u64::from(u32::MAX))
+    }
+
+    /// Returns whether any function starts beyond 32 bits, see [`raw::SECTION_ENTRY_PCS_HIGH`].
+    fn has_high_entry_pcs(&self) -> bool {
+        let mut functions = self.functions.iter();
+        functions.any(|function| function.entry_pc_high != 0)
+    }
+
+    /// Returns whether any function has a declaration, see [`raw::SECTION_DECLARATIONS`].
+    fn has_declarations(&self) -> bool {
+        let mut functions = self.functions.iter();
+        functions.any(|function| function.decl_file_idx != u32::MAX || function.decl_line != 0)
+    }
+
+    /// Collects the distinct calls from every caller to its inlinees, see
+    /// [`raw::SECTION_CALL_EDGES`].
+    fn collect_call_edges(&self) -> BTreeSet<native::CallEdge> {
+        let ranges = self
+            .serialized_ranges()
+            .map(|(_, source_location)| source_location);
+        self.source_locations
+            .iter()
+            .chain(ranges)
+            .filter_map(|callee| {
+                // the caller is positioned at the call site of the inlinee
+                let caller = self
+                    .source_locations
+                    .get_index(callee.raw.inlined_into_idx as usize)?;
+                Some(native::CallEdge {
+                    caller_idx: caller.raw.function_idx,
+                    callee_idx: callee.raw.function_idx,
+                    file_idx: caller.raw.file_idx,
+                    line: caller.raw.line,
+                })
+            })
+            .collect()
+    }
+
+    /// Collects the candidates of all serialized ranges, see [`raw::SECTION_CANDIDATES`].
+    ///
+    /// Their source locations must already be laid out, as they are by
+    /// [`canonicalize`](Self::canonicalize).
+    fn serialized_candidates(&self) -> Vec<raw::Candidate> {
+        if self.candidates.is_empty() {
+            return Vec::new();
+        }
+
+        let mut serialized = Vec::new();
+        for (range_idx, (addr, _)) in self.serialized_ranges().enumerate() {
+            for (source_location, provenance) in self.candidates.get(&addr).into_iter().flatten() {
+                let source_location_idx = self.source_locations.get_index_of(source_location);
+                serialized.push(raw::Candidate {
+                    range_idx: (range_idx as u32).into(),
+                    source_location_idx: source_location_idx
+                        .map_or(u32::MAX, |idx| idx as u32)
+                        .into(),
+                    provenance: u32::from(*provenance).into(),
+                });
+            }
+        }
+        serialized
+    }
+
+    // Methods for serializing to a [`Write`] below:
+    // Feel free to move these to a separate file.
+
+    /// Serialize the converted data.
+    ///
+    /// This writes the SymCache binary format into the given [`Write`], and returns the number of
+    /// bytes written.
+    pub fn serialize<W: Write>(self, writer: &mut W) -> std::io::Result<u64> {
+        let canonical = self.into_canonical();
+        if let Some(error) = canonical.strings.take_error() {
+            return Err(error);
+        }
+        canonical.write_to(writer)
+    }
+
+    /// Serialize the converted data without consuming the converter.
+    ///
+    /// This streams the SymCache binary format into the given [`Write`], without buffering the
+    /// output, and returns the number of bytes written. The output is reproducible: the same data
+    /// always serializes to the same bytes, regardless of the order in which it was processed.
+    /// The writer does not need to support seeking, so it can for example be a socket or a
+    /// compressing encoder. Since the data is written in many small chunks, unbuffered writers
+    /// should be wrapped in a [`BufWriter`](std::io::BufWriter).
+    ///
+    /// This lays out a copy of the data, while [`serialize`](Self::serialize) reorders it in
+    /// place.
+    pub fn serialize_to<W: Write>(&self, writer: W) -> std::io::Result<u64> {
+        let canonical = self.canonicalize();
+        if let Some(error) = self.strings.take_error() {
+            return Err(error);
+        }
+        canonical.write_to(writer)
+    }
+
+    /// Writes the SymCache binary format in the current order of the data.
+    fn write_to<W: Write>(&self, writer: W) -> std::io::Result<u64> {
+        let section_alignment = self.section_alignment.max(raw::DEFAULT_SECTION_ALIGNMENT);
+        let mut writer = WriteWrapper::new(writer, section_alignment as usize);
+
+        let num_files = self.files.len() as u32;
+        let num_functions = self.functions.len() as u32;
+        let num_ranges = self.serialized_ranges().count() as u32;
+        let num_source_locations = self.source_locations.len() as u32 + num_ranges;
+        let string_bytes = self.strings.len() as u32;
+
+        let table_start =
+            self.section_table_start(num_source_locations as usize, num_ranges as usize);
+        let sections = self.section_table(
+            table_start,
+            num_source_locations as usize,
+            num_ranges as usize,
+        );
+        let flags = raw::FLAG_CHECKSUM;
+        let name_index = self.sorted_name_index();
+
+        // the sections are written twice, first to compute the checksum for the header, which
+        // covers everything after the aligned header
+        let header_size = std::mem::size_of::<raw::Header>();
+        let mut checksum = WriteWrapper::new(ChecksumWriter::default(), section_alignment as usize);
+        checksum.position = header_size + raw::align_to(header_size, checksum.alignment);
+        self.write_sections(&mut checksum, &sections, &name_index)?;
+        let checksum = checksum.writer.0.finalize();
+
+        let header = raw::Header {
+            magic: raw::SYMCACHE_MAGIC.into(),
+            version: raw::SYMCACHE_VERSION.into(),
+
+            debug_id: self.debug_id.into(),
+            arch: (self.arch as u32).into(),
+
+            num_files: num_files.into(),
+            num_functions: num_functions.into(),
+            num_source_locations: num_source_locations.into(),
+            num_ranges: num_ranges.into(),
+            string_bytes: string_bytes.into(),
+            section_alignment: section_alignment.into(),
+            flags: flags.into(),
+            checksum: checksum.into(),
+            num_sections: (sections.len() as u32).into(),
+        };
+
+        writer.write(&[header])?;
+        writer.align()?;
+
+        self.write_sections(&mut writer, &sections, &name_index)?;
+        writer.writer.flush()?;
+        Ok(writer.position as u64)
+    }
+
+    /// Returns the indexes of all functions sorted by name, see [`raw::SECTION_NAME_INDEX`].
+    ///
+    /// This is empty unless the name index is enabled.
+    fn sorted_name_index(&self) -> Vec<raw::U32> {
+        if !self.name_index {
+            return Vec::new();
+        }
+
+        let mut indices: Vec<u32> = (0..self.functions.len() as u32).collect();
+        indices.sort_by_cached_key(|&idx| {
+            self.strings
+                .get(self.functions[idx as usize].raw.name_offset)
+        });
+        indices.into_iter().map(raw::U32::new).collect()
+    }
+
+    /// Writes all data following the header, including the section table and the extension
+    /// `sections` it describes.
+    fn write_sections<W: Write>(
+        &self,
+        writer: &mut WriteWrapper<W>,
+        sections: &[raw::Section],
+        name_index: &[raw::U32],
+    ) -> std::io::Result<()> {
+        for f in &self.files {
+            writer.write(&[f.to_raw()])?;
+        }
+        writer.align()?;
+
+        for f in &self.functions {
+            writer.write(&[f.raw.to_raw()])?;
+        }
+        writer.align()?;
+
+        for s in &self.source_locations {
+            writer.write(&[s.raw.to_raw()])?;
+        }
+        for (_, s) in self.serialized_ranges() {
+            writer.write(&[s.raw.to_raw()])?;
+        }
+        writer.align()?;
+
+        for (r, _) in self.serialized_ranges() {
+            writer.write(&[raw::Range((r as u32).into())])?;
+        }
+        writer.align()?;
+
+        self.strings.write_to(&mut writer.writer)?;
+        writer.position += self.strings.len();
+        writer.align()?;
+
+        writer.write(sections)?;
+        for section in sections {
+            writer.align()?;
+            debug_assert_eq!(writer.position as u64, section.offset.get());
+            self.write_extension_section(writer, section.kind.get(), name_index)?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes the contents of an extension section of the given kind.
+    fn write_extension_section<W: Write>(
+        &self,
+        writer: &mut WriteWrapper<W>,
+        kind: u32,
+        name_index: &[raw::U32],
+    ) -> std::io::Result<()> {
+        match kind {
+            raw::SECTION_PROVENANCE => {
+                for (addr, _) in self.serialized_ranges() {
+                    let provenance = self.range_provenance.get(&addr);
+                    writer.write(&[provenance.copied().unwrap_or(raw::PROVENANCE_UNKNOWN)])?;
+                }
+            }
+            raw::SECTION_RANGE_FLAGS => {
+                for (addr, _) in self.serialized_ranges() {
+                    let flags = self.range_flags.get(&addr);
+                    writer.write(&[flags.copied().unwrap_or(0)])?;
+                }
+            }
+            raw::SECTION_COLUMNS => {
+                let ranges = self.serialized_ranges().map(|(_, s)| s);
+                for s in self.source_locations.iter().chain(ranges) {
+                    writer.write(&[raw::U32::new(s.column)])?;
+                }
+            }
+            raw::SECTION_LINKAGE_NAMES => {
+                for f in &self.functions {
+                    writer.write(&[raw::U32::new(f.linkage_name_offset)])?;
+                }
+            }
+            raw::SECTION_RANGES_HIGH => {
+                for (r, _) in self.serialized_ranges() {
+                    writer.write(&[raw::U32::new((r >> 32) as u32)])?;
+                }
+            }
+            raw::SECTION_IMAGE_BASE => {
+                writer.write(&[raw::U64::new(self.image_base)])?;
+            }
+            raw::SECTION_ENTRY_PCS_HIGH => {
+                for f in &self.functions {
+                    writer.write(&[raw::U32::new(f.entry_pc_high)])?;
+                }
+            }
+            raw::SECTION_NAME_INDEX => {
+                writer.write(name_index)?;
+            }
+            raw::SECTION_CALL_EDGES => {
+                for edge in self.collect_call_edges() {
+                    writer.write(&[edge.to_raw()])?;
+                }
+            }
+            raw::SECTION_CANDIDATES => {
+                writer.write(&self.serialized_candidates())?;
+            }
+            raw::SECTION_SEGMENTS => {
+                for segment in &self.segments {
+                    writer.write(&[segment.to_raw()])?;
+                }
+            }
+            raw::SECTION_DECLARATIONS => {
+                for f in &self.functions {
+                    writer.write(&[raw::Declaration {
+                        file_idx: f.decl_file_idx.into(),
+                        line: f.decl_line.into(),
+                    }])?;
+                }
+            }
+            _ => unreachable!("unknown section kind {}", kind),
+        }
+
+        Ok(())
+    }
+}
+
+/// The in-memory counterparts of the structs in [`raw`], which hold native integers.
+///
+/// The converter works with these, and only converts them to the little-endian [`raw`] structs
+/// when serializing.
+mod native {
+    use super::raw;
+
+    /// See [`raw::Function`].
+    #[derive(Debug, Clone, Hash, PartialEq, Eq)]
+    pub struct Function {
+        pub name_offset: u32,
+        pub comp_dir_offset: u32,
+        pub entry_pc: u32,
+        pub lang: u32,
+    }
+
+    impl Function {
+        pub fn to_raw(&self) -> raw::Function {
+            raw::Function {
+                name_offset: self.name_offset.into(),
+                comp_dir_offset: self.comp_dir_offset.into(),
+                entry_pc: self.entry_pc.into(),
+                lang: self.lang.into(),
+            }
+        }
+    }
+
+    /// See [`raw::File`].
+    #[derive(Debug, Clone, Hash, PartialEq, Eq)]
+    pub struct File {
+        pub comp_dir_offset: u32,
+        pub directory_offset: u32,
+        pub path_name_offset: u32,
+    }
+
+    impl File {
+        pub fn to_raw(&self) -> raw::File {
+            raw::File {
+                comp_dir_offset: self.comp_dir_offset.into(),
+                directory_offset: self.directory_offset.into(),
+                path_name_offset: self.path_name_offset.into(),
+            }
+        }
+    }
+
+    /// See [`raw::SourceLocation`].
+    #[derive(Debug, Clone, Hash, PartialEq, Eq)]
+    pub struct SourceLocation {
+        pub file_idx: u32,
+        pub line: u32,
+        pub function_idx: u32,
+        pub inlined_into_idx: u32,
+    }
+
+    impl SourceLocation {
+        pub fn to_raw(&self) -> raw::SourceLocation {
+            raw::SourceLocation {
+                file_idx: self.file_idx.into(),
+                line: self.line.into(),
+                function_idx: self.function_idx.into(),
+                inlined_into_idx: self.inlined_into_idx.into(),
+            }
+        }
+    }
+
+    /// See [`raw::CallEdge`].
+    ///
+    /// The fields are ordered such that edges sort by caller first.
+    #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+    pub struct CallEdge {
+        pub caller_idx: u32,
+        pub callee_idx: u32,
+        pub file_idx: u32,
+        pub line: u32,
+    }
+
+    impl CallEdge {
+        pub fn to_raw(&self) -> raw::CallEdge {
+            raw::CallEdge {
+                caller_idx: self.caller_idx.into(),
+                callee_idx: self.callee_idx.into(),
+                file_idx: self.file_idx.into(),
+                line: self.line.into(),
+            }
+        }
+    }
+
+    /// See [`raw::Segment`].
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct Segment {
+        pub start: u64,
+        pub size: u64,
+        pub name_offset: u32,
+    }
+
+    impl Segment {
+        pub fn to_raw(&self) -> raw::Segment {
+            raw::Segment {
+                start: self.start.into(),
+                size: self.size.into(),
+                name_offset: u64::from(self.name_offset).into(),
+            }
+        }
+    }
+
+    /// The sentinel that says that no source location is present, see
+    /// [`raw::SourceLocation::is_none`].
+    pub const NO_SOURCE_LOCATION: SourceLocation = SourceLocation {
+        file_idx: u32::MAX,
+        line: u32::MAX,
+        function_idx: u32::MAX,
+        inlined_into_idx: u32::MAX,
+    };
+}
+
+/// A [`native::SourceLocation`] along with its column, see [`raw::SECTION_COLUMNS`].
+///
+/// The column is part of the identity of a source location, but is serialized in a separate
+/// section.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+struct SourceLocation {
+    raw: native::SourceLocation,
+    /// The column number, `0` if unknown.
+    column: u32,
+}
+
+/// A [`native::Function`] along with data that is used to identify it or serialized in separate
+/// sections.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+struct FunctionEntry {
+    raw: native::Function,
+    /// The index of the source file if functions are identified by
+    /// [`FunctionIdentity::NameAndFile`], and `u32::MAX` otherwise.
+    discriminator: u32,
+    /// The string offset of the linkage name, see [`raw::SECTION_LINKAGE_NAMES`].
+    linkage_name_offset: u32,
+    /// The upper 32 bits of the entry pc, see [`raw::SECTION_ENTRY_PCS_HIGH`].
+    entry_pc_high: u32,
+    /// The index of the file the function is declared in, see [`raw::SECTION_DECLARATIONS`].
+    decl_file_idx: u32,
+    /// The line the function is declared at, `0` if unknown.
+    decl_line: u32,
+}
+
+/// The order of the files, functions and inline callers of a converter in its canonical layout,
+/// see [`canonicalize`](SymCacheConverter::canonicalize).
+///
+/// Each set holds the previous indexes in their new order.
+struct CanonicalOrder {
+    files: IndexSet<u32>,
+    functions: IndexSet<u32>,
+    callers: IndexSet<u32>,
+}
+
+impl CanonicalOrder {
+    /// Returns the new index of the entry at `idx`, or `u32::MAX` if it was dropped.
+    fn index_of(set: &IndexSet<u32>, idx: u32) -> u32 {
+        match set.get_index_of(&idx) {
+            Some(new_idx) => new_idx as u32,
+            None => u32::MAX,
+        }
+    }
+
+    /// Updates the indexes of a source location to the canonical layout.
+    fn source_location(&self, source_location: &SourceLocation) -> SourceLocation {
+        let raw = &source_location.raw;
+        SourceLocation {
+            raw: native::SourceLocation {
+                file_idx: Self::index_of(&self.files, raw.file_idx),
+                line: raw.line,
+                function_idx: Self::index_of(&self.functions, raw.function_idx),
+                inlined_into_idx: Self::index_of(&self.callers, raw.inlined_into_idx),
+            },
+            column: source_location.column,
+        }
+    }
+}
+
+/// The data converted from a single compilation unit, see
+/// [`process_dwarf`](SymCacheConverter::process_dwarf).
+///
+/// Unlike the [`SymCacheConverter`] that produced it, this can be sent to another thread.
+#[cfg(feature = "parallel")]
+struct UnitTables {
+    strings: StringTable,
+    files: IndexSet<native::File>,
+    functions: IndexSet<FunctionEntry>,
+    source_locations: IndexSet<SourceLocation>,
+    ranges: BTreeMap<u64, SourceLocation>,
+    range_provenance: HashMap<u64, u8>,
+    range_flags: HashMap<u64, u8>,
+    candidates: BTreeMap<u64, Vec<(SourceLocation, u8)>>,
+    stats: Stats,
+    last_addr: Option<u64>,
+}
+
+#[cfg(feature = "parallel")]
+impl From<SymCacheConverter> for UnitTables {
+    fn from(converter: SymCacheConverter) -> Self {
+        Self {
+            strings: converter.strings,
+            files: converter.files,
+            functions: converter.functions,
+            source_locations: converter.source_locations,
+            ranges: converter.ranges,
+            range_provenance: converter.range_provenance,
+            range_flags: converter.range_flags,
+            candidates: converter.candidates,
+            stats: converter.stats,
+            last_addr: converter.last_addr,
+        }
+    }
+}
+
+#[cfg(feature = "parallel")]
+impl From<UnitTables> for SymCacheConverter {
+    fn from(tables: UnitTables) -> Self {
+        Self {
+            strings: tables.strings,
+            files: tables.files,
+            functions: tables.functions,
+            source_locations: tables.source_locations,
+            ranges: tables.ranges,
+            range_provenance: tables.range_provenance,
+            range_flags: tables.range_flags,
+            candidates: tables.candidates,
+            stats: tables.stats,
+            last_addr: tables.last_addr,
+            ..Self::default()
+        }
+    }
+}
+
+/// Returns whether the cancel flag is set or the deadline has passed.
+fn is_cancelled(cancel_flag: Option<&AtomicBool>, deadline: Option<Instant>) -> bool {
+    let cancelled = cancel_flag.map_or(false, |flag| flag.load(Ordering::Relaxed));
+    let expired = deadline.map_or(false, |deadline| Instant::now() >= deadline);
+    cancelled || expired
+}
+
+/// Returns the [flags](raw::SECTION_RANGE_FLAGS) of the range that starts with the given line.
+fn range_flags(line: &symbolic_debuginfo::LineInfo<'_>) -> u8 {
+    let mut flags = 0;
+    if line.prologue_end {
+        flags |= raw::RANGE_FLAG_PROLOGUE_END;
+    }
+    if !line.is_stmt {
+        flags |= raw::RANGE_FLAG_NOT_STMT;
+    }
+    if line.basic_block {
+        flags |= raw::RANGE_FLAG_BASIC_BLOCK;
+    }
+    flags
+}
+
+/// Inserts the strings of a transformed file and returns the index of the file.
+fn insert_file(
+    strings: &mut StringTable,
+    files: &mut IndexSet<native::File>,
+    file: &transform::File<'_>,
+) -> u32 {
+    let path_name_offset = strings.insert(&file.name);
+    let directory_offset = file
+        .directory
+        .as_ref()
+        .map_or(u32::MAX, |d| strings.insert(d));
+    let comp_dir_offset = file
+        .comp_dir
+        .as_ref()
+        .map_or(u32::MAX, |cd| strings.insert(cd));
+
+    let (file_idx, _) = files.insert_full(native::File {
+        path_name_offset,
+        directory_offset,
+        comp_dir_offset,
+    });
+    file_idx as u32
+}
+
+/// Returns the inline callers of a source location, innermost first.
+///
+/// The walk is bounded by the number of source locations in case of cyclic inline chains.
+fn inline_callers<'a>(
+    source_locations: &'a IndexSet<SourceLocation>,
+    source_location: &'a SourceLocation,
+) -> Vec<&'a SourceLocation> {
+    let mut callers = Vec::new();
+    let mut caller_idx = source_location.raw.inlined_into_idx;
+    while let Some(caller) = source_locations.get_index(caller_idx as usize) {
+        if callers.len() >= source_locations.len() {
+            break;
+        }
+        callers.push(caller);
+        caller_idx = caller.raw.inlined_into_idx;
+    }
+    callers
+}
+
+/// The sentinel that says that no source location is present, see
+/// [`raw::SourceLocation::is_none`].
+const NO_SOURCE_LOCATION: SourceLocation = SourceLocation {
+    raw: native::NO_SOURCE_LOCATION,
+    column: 0,
+};
+
+/// A [`Write`] that only computes the checksum of the written data, see [`raw::FLAG_CHECKSUM`].
+#[derive(Default)]
+struct ChecksumWriter(crc32fast::Hasher);
+
+impl Write for ChecksumWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+struct WriteWrapper<W> {
+    writer: W,
+    position: usize,
+    alignment: usize,
+}
+
+impl<W: Write> WriteWrapper<W> {
+    fn new(writer: W, alignment: usize) -> Self {
+        Self {
+            writer,
+            position: 0,
+            alignment,
+        }
+    }
+
+    fn write<T: AsBytes>(&mut self, data: &[T]) -> std::io::Result<usize> {
+        let buf = data.as_bytes();
+        self.writer.write_all(buf)?;
+        self.position += buf.len();
+        Ok(buf.len())
     }
 
     fn align(&mut self) -> std::io::Result<usize> {
-        let buf = &[0u8; 7];
-        let len = raw::align_to_eight(self.position);
-        self.write(&buf[0..len])
+        let len = raw::align_to(self.position, self.alignment);
+        std::io::copy(&mut std::io::repeat(0).take(len as u64), &mut self.writer)?;
+        self.position += len;
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use symbolic_common::Name;
+
+    use super::*;
+
+    use crate::new::testutils::{
+        function, inlined_functions, line, serialize, symbol, symbol_cache, DwarfBuilder,
+        TestFunction, TestUnit,
+    };
+    use crate::new::{endian, swap_endianness, Error};
+
+    #[test]
+    #[cfg(feature = "demangle")]
+    fn test_demangling() {
+        fn convert(keep_linkage_names: bool) -> Vec<u8> {
+            let mut function = inlined_functions();
+            function.name = Name::new("_ZN3foo4mainEv", NameMangling::Mangled, Language::Cpp);
+
+            let mut converter = SymCacheConverter::new();
+            converter.set_demangling(DemangleOptions::name_only());
+            converter.set_keep_linkage_names(keep_linkage_names);
+            converter.process_symbolic_function(&function);
+            converter.process_symbolic_symbol(&symbol("_ZN3foo5otherEv", 0x2000, 0x10));
+            serialize(converter)
+        }
+
+        fn names<'a>(cache: &SymCache<'a>, addr: u64) -> Vec<(Option<&'a str>, Option<&'a str>)> {
+            cache
+                .lookup(addr)
+                .map(|sl| {
+                    let function = sl.function().unwrap();
+                    (function.name(), function.linkage_name())
+                })
+                .collect()
+        }
+
+        let buf = convert(true);
+        let cache = SymCache::parse(&buf).unwrap();
+        assert!(!cache.linkage_names.is_empty());
+        assert_eq!(
+            names(&cache, 0x100c),
+            [
+                (Some("helper"), None),
+                (Some("foo::main"), Some("_ZN3foo4mainEv"))
+            ]
+        );
+        assert_eq!(
+            names(&cache, 0x2000),
+            [(Some("foo::other"), Some("_ZN3foo5otherEv"))]
+        );
+
+        let mut foreign = buf.clone();
+        endian::swap_in_place(&mut foreign, false).unwrap();
+        let swapped = swap_endianness(&foreign).unwrap();
+        assert_eq!(&swapped[..], &buf[..]);
+
+        let buf = convert(false);
+        let cache = SymCache::parse(&buf).unwrap();
+        assert!(cache.linkage_names.is_empty());
+        assert_eq!(names(&cache, 0x2000), [(Some("foo::other"), None)]);
+    }
+
+    #[test]
+    fn test_transform_strings() {
+        let mut converter = SymCacheConverter::new();
+        converter.transform_strings(|s| match s {
+            "helper" => Some("inlined_helper".into()),
+            "/build/src" => Some("/src".into()),
+            _ => None,
+        });
+        converter.transform_strings(|s| s.strip_suffix(".h").map(|s| format!("{}.hpp", s)));
+        converter.process_symbolic_function(&inlined_functions());
+        let buf = serialize(converter);
+
+        let cache = SymCache::parse(&buf).unwrap();
+        let frames: Vec<_> = cache
+            .lookup(0x100c)
+            .map(|sl| {
+                (
+                    sl.function().unwrap().name(),
+                    sl.file().unwrap().full_path(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            frames,
+            [
+                (Some("inlined_helper"), "/src/helper.hpp".into()),
+                (Some("main"), "/src/main.c".into())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_path_rewrites() {
+        let mut function = inlined_functions();
+        function.lines[0].file.dir = b"/home/runner/work/proj/src";
+        function.lines[1].file.dir = b"/home/runner/workspace/src";
+        function.inlinees[0].lines[0].file.dir = b"C:\\agent\\include";
+
+        let mut converter = SymCacheConverter::new();
+        converter.set_normalize_path_separators(true);
+        converter.remap_path_prefix("/home/runner/work", "/work");
+        converter.remap_path_prefix("/home/runner/work/proj", "/proj");
+        converter.strip_path_prefix("C:/agent");
+        converter.process_symbolic_function(&function);
+        let buf = serialize(converter);
+
+        let cache = SymCache::parse(&buf).unwrap();
+        let paths = |addr| {
+            cache
+                .lookup(addr)
+                .map(|sl| sl.file().unwrap().full_path())
+                .collect::<Vec<_>>()
+        };
+        // the last matching prefix wins
+        assert_eq!(paths(0x1000), ["/proj/src/main.c"]);
+        // prefixes only match entire path components, and stripped paths become relative
+        assert_eq!(
+            paths(0x100c),
+            ["include/helper.h", "/home/runner/workspace/src/main.c"]
+        );
+    }
+
+    #[test]
+    fn test_high_addresses() {
+        let mut converter = SymCacheConverter::new();
+        for (name, address) in [
+            ("low", 0x1000),
+            ("high", 0x1_0000_1000),
+            ("kernel", 0xffff_ff80_0000_1000),
+        ] {
+            converter.process_symbolic_symbol(&symbol(name, address, 0x10));
+        }
+        let buf = serialize(converter);
+        let cache = SymCache::parse(&buf).unwrap();
+        cache.validate().unwrap();
+
+        let name = |addr| {
+            let sl = cache.lookup(addr).next()?;
+            sl.function()?.name()
+        };
+        assert_eq!(name(0x1008), Some("low"));
+        assert_eq!(name(0x1_0000_0008), None);
+        assert_eq!(name(0x1_0000_1008), Some("high"));
+        assert_eq!(name(0x1_0000_1010), None);
+        assert_eq!(name(0xffff_ff80_0000_100f), Some("kernel"));
+
+        let function = cache
+            .lookup(0x1_0000_1008)
+            .next()
+            .unwrap()
+            .function()
+            .unwrap();
+        assert_eq!(function.entry_pc(), 0x1_0000_1000);
+        let ranges: Vec<_> = cache
+            .ranges()
+            .map(|(range, _)| range.start..range.end)
+            .collect();
+        assert_eq!(
+            ranges,
+            [
+                0x1000..0x1010,
+                0x1_0000_1000..0x1_0000_1010,
+                0xffff_ff80_0000_1000..0xffff_ff80_0000_1010,
+            ]
+        );
+        let sizes: Vec<_> = cache.function_sizes().iter().map(|f| f.size).collect();
+        assert_eq!(sizes, [0x10, 0x10, 0x10]);
+
+        let mut foreign = buf.clone();
+        endian::swap_in_place(&mut foreign, false).unwrap();
+        let swapped = swap_endianness(&foreign).unwrap();
+        assert_eq!(&swapped[..], &buf[..]);
+
+        // caches with 32-bit addresses only do not need the extension sections
+        let cache = SymCache::parse(&buf).unwrap();
+        assert!(!cache.ranges_high.is_empty());
+        let buf = symbol_cache();
+        let cache = SymCache::parse(&buf).unwrap();
+        assert!(cache.ranges_high.is_empty());
+        assert!(cache.entry_pcs_high.is_empty());
+        assert_eq!(cache.address_space_end(), 1 << 32);
+    }
+
+    #[test]
+    fn test_image_base() {
+        let mut converter = SymCacheConverter::new();
+        converter.set_image_base(0x40_0000);
+        let mut function = inlined_functions();
+        function.address += 0x40_0000;
+        for line in &mut function.lines {
+            line.address += 0x40_0000;
+        }
+        for line in &mut function.inlinees[0].lines {
+            line.address += 0x40_0000;
+        }
+        function.inlinees[0].address += 0x40_0000;
+        converter.process_symbolic_function(&function);
+        for (name, address) in [("below", 0x1000), ("above", 0x40_2000)] {
+            converter.process_symbolic_symbol(&symbol(name, address, 0x10));
+        }
+        let buf = serialize(converter);
+
+        let cache = SymCache::parse(&buf).unwrap();
+        assert_eq!(cache.image_base(), 0x40_0000);
+        let frames: Vec<_> = cache
+            .lookup(0x100c)
+            .map(|sl| (sl.function().unwrap().name().unwrap(), sl.line()))
+            .collect();
+        assert_eq!(frames, [("helper", 5), ("main", 2)]);
+        let sl = cache.lookup(0x2008).next().unwrap();
+        assert_eq!(sl.function().unwrap().name(), Some("above"));
+        assert_eq!(sl.function().unwrap().entry_pc(), 0x2000);
+        assert!(cache.lookup(0x40_100c).next().is_none());
+
+        let names: Vec<_> = cache.functions().filter_map(|f| f.name()).collect();
+        assert!(!names.contains(&"below"));
+
+        let mut foreign = buf.clone();
+        endian::swap_in_place(&mut foreign, false).unwrap();
+        let swapped = swap_endianness(&foreign).unwrap();
+        assert_eq!(SymCache::parse(&swapped).unwrap().image_base(), 0x40_0000);
+
+        assert_eq!(SymCache::parse(&symbol_cache()).unwrap().image_base(), 0);
+    }
+
+    #[test]
+    fn test_merge() {
+        let symbols = |symbols: &[(&'static str, u64, u64)]| {
+            let mut converter = SymCacheConverter::new();
+            for &(name, address, size) in symbols {
+                converter.process_symbolic_symbol(&symbol(name, address, size));
+            }
+            converter
+        };
+
+        let mut converter = symbols(&[("main", 0x1000, 0x10), ("big", 0x4000, 0x100)]);
+        let mut other = symbols(&[("extra", 0x2000, 0x8), ("tail", 0x3ff0, 0x20)]);
+        other.process_symbolic_function(&inlined_functions());
+        let errors = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        converter.set_error_sink({
+            let errors = errors.clone();
+            move |error| errors.borrow_mut().push(error)
+        });
+        converter.merge(other);
+        assert_eq!(converter.stats().conflicting_lines, 1);
+        let errors = errors.take();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].kind, ConversionErrorKind::ConflictingLines);
+        assert_eq!(errors[0].address, Some(0x1000));
+        assert_eq!(errors[0].function.as_deref(), Some("main"));
+
+        let buf = serialize(converter);
+        let cache = SymCache::parse(&buf).unwrap();
+        cache.validate().unwrap();
+
+        let lookup = |addr| {
+            cache
+                .lookup(addr)
+                .map(|sl| (sl.function().unwrap().name().unwrap(), sl.line()))
+                .collect::<Vec<_>>()
+        };
+        // the line information of the merged function replaces the bare symbol
+        assert_eq!(lookup(0x1004), [("main", 1)]);
+        assert_eq!(lookup(0x100c), [("helper", 5), ("main", 2)]);
+        assert_eq!(lookup(0x1010), []);
+        assert_eq!(lookup(0x2004), [("extra", 0)]);
+        assert_eq!(lookup(0x200c), []);
+        assert_eq!(lookup(0x3ff8), [("tail", 0)]);
+        // the end of a merged symbol does not cut the existing one short
+        assert_eq!(lookup(0x4000), [("big", 0)]);
+        assert_eq!(lookup(0x4050), [("big", 0)]);
+        assert_eq!(lookup(0x4100), []);
+    }
+
+    #[test]
+    fn test_process_symcache() {
+        let mut function = inlined_functions();
+        function.lines[1].column = 7;
+        let mut converter = SymCacheConverter::new();
+        converter.set_arch(Arch::Amd64);
+        converter.process_symbolic_function(&function);
+        let functions = serialize(converter);
+
+        let mut converter = SymCacheConverter::new();
+        converter.process_symbolic_symbol(&symbol("high", 0x1_0000_2000, 0x10));
+        let symbols = serialize(converter);
+
+        let mut converter = SymCacheConverter::new();
+        for buf in [&functions, &symbols, &functions] {
+            converter
+                .process_symcache(&SymCache::parse(buf).unwrap())
+                .unwrap();
+        }
+        assert_eq!(converter.stats().conflicting_lines, 0);
+        let merged = serialize(converter);
+
+        let cache = SymCache::parse(&merged).unwrap();
+        cache.validate().unwrap();
+        assert_eq!(cache.arch(), Arch::Amd64);
+        assert_eq!(cache.functions().count(), 3);
+        assert_eq!(cache.files().count(), 2);
+
+        let lookup = |addr| {
+            cache
+                .lookup(addr)
+                .map(|sl| {
+                    let name = sl.function().unwrap().name().unwrap();
+                    (name, sl.line(), sl.column())
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(lookup(0x1004), [("main", 1, 0)]);
+        assert_eq!(lookup(0x100c), [("helper", 5, 0), ("main", 2, 7)]);
+        assert_eq!(lookup(0x1010), []);
+        assert_eq!(lookup(0x1_0000_2008), [("high", 0, 0)]);
+        assert_eq!(lookup(0x1_0000_2010), []);
+    }
+
+    #[test]
+    fn test_columns() {
+        let mut function = inlined_functions();
+        function.lines[0].column = 3;
+        function.lines[1].column = 7;
+        function.inlinees[0].lines[0].column = 9;
+
+        let mut converter = SymCacheConverter::new();
+        converter.process_symbolic_function(&function);
+        let buf = serialize(converter);
+
+        let cache = SymCache::parse(&buf).unwrap();
+        assert!(!cache.columns.is_empty());
+        let columns: Vec<_> = cache.lookup(0x1000).map(|sl| sl.column()).collect();
+        assert_eq!(columns, [3]);
+        let columns: Vec<_> = cache.lookup(0x100c).map(|sl| sl.column()).collect();
+        assert_eq!(columns, [9, 7]);
+
+        // the column section is missing its last byte
+        let truncated = &buf[..buf.len() - 1];
+        assert!(matches!(
+            SymCache::parse(truncated),
+            Err(Error::BadFormatLength)
+        ));
+
+        let mut foreign = buf.clone();
+        endian::swap_in_place(&mut foreign, false).unwrap();
+        let swapped = swap_endianness(&foreign).unwrap();
+        assert_eq!(&swapped[..], &buf[..]);
+
+        // the column section is only written if there are any columns
+        let mut converter = SymCacheConverter::new();
+        converter.process_symbolic_function(&inlined_functions());
+        let buf = serialize(converter);
+
+        let cache = SymCache::parse(&buf).unwrap();
+        assert!(cache.columns.is_empty());
+        assert_eq!(cache.lookup(0x1000).next().unwrap().column(), 0);
+    }
+
+    #[test]
+    fn test_conflicting_lines() {
+        let function = |name, lines| function(0x1000, 0x10, name, lines);
+        let line = line(0x1000, 0x10, "", "main.c", 1);
+
+        let convert = |functions: &[Function<'_>]| {
+            let errors = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+            let mut converter = SymCacheConverter::new();
+            converter.set_error_sink({
+                let errors = errors.clone();
+                move |error| errors.borrow_mut().push(error)
+            });
+            for function in functions {
+                converter.process_symbolic_function(function);
+            }
+            let conflicts = converter.stats().conflicting_lines;
+            let buf = serialize(converter);
+            let cache = SymCache::parse(&buf).unwrap();
+            let sl = cache.lookup(0x1008).next().unwrap();
+            let name = sl.function().unwrap().name().map(String::from);
+            let errors = errors.take();
+            (name, conflicts, errors)
+        };
+
+        // folded functions: the first one wins
+        let (name, conflicts, errors) = convert(&[
+            function("a", vec![line.clone()]),
+            function("b", vec![line.clone()]),
+        ]);
+        assert_eq!(name.as_deref(), Some("a"));
+        assert_eq!(conflicts, 1);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].kind, ConversionErrorKind::ConflictingLines);
+        assert_eq!(errors[0].severity, Severity::Warning);
+        assert_eq!(errors[0].address, Some(0x1000));
+        assert_eq!(errors[0].function.as_deref(), Some("b"));
+
+        // records with line information are preferred
+        let (name, conflicts, errors) =
+            convert(&[function("a", vec![]), function("b", vec![line])]);
+        assert_eq!(name.as_deref(), Some("b"));
+        assert_eq!(conflicts, 1);
+        assert_eq!(errors[0].address, Some(0x1000));
+        assert_eq!(errors[0].function.as_deref(), Some("b"));
+    }
+
+    #[test]
+    fn test_overflowing_lines() {
+        let line = |address, number| line(address, 4, "", "main.c", number);
+        let function = function(
+            0x1000,
+            0x10,
+            "main",
+            vec![
+                line(0x1000, 1),
+                line(0x1004, u32::MAX as u64),
+                line(0x1008, u64::MAX),
+            ],
+        );
+
+        let mut converter = SymCacheConverter::new();
+        converter.process_symbolic_function(&function);
+        assert_eq!(converter.stats().overflowing_lines, 2);
+
+        let buf = serialize(converter);
+        let cache = SymCache::parse(&buf).unwrap();
+
+        let lines: Vec<_> = [0x1000, 0x1004, 0x1008]
+            .iter()
+            .map(|&addr| cache.lookup(addr).next().unwrap().line())
+            .collect();
+        assert_eq!(lines, [1, 0, 0]);
+    }
+
+    #[test]
+    fn test_function_identity() {
+        let function = |address, file, inlinees, inline| Function {
+            compilation_dir: b"/build",
+            inlinees,
+            inline,
+            ..function(
+                address,
+                0x10,
+                if inline { "helper" } else { "caller" },
+                vec![line(address, 0x10, "src", file, 1)],
+            )
+        };
+        let functions = [
+            function(
+                0x1000,
+                "a.c",
+                vec![function(0x1000, "a.c", vec![], true)],
+                false,
+            ),
+            function(
+                0x2000,
+                "b.c",
+                vec![function(0x2000, "b.c", vec![], true)],
+                false,
+            ),
+        ];
+
+        let num_functions = |identity| {
+            let mut converter = SymCacheConverter::new();
+            converter.set_function_identity(identity);
+            for function in &functions {
+                converter.process_symbolic_function(function);
+            }
+            let buf = serialize(converter);
+            SymCache::parse(&buf).unwrap().functions.len()
+        };
+
+        // the two `caller`s differ by their entry address, the inlined `helper`s only by file
+        assert_eq!(num_functions(FunctionIdentity::Name), 3);
+        assert_eq!(num_functions(FunctionIdentity::NameAndFile), 4);
+    }
+
+    #[test]
+    fn test_limit_size() {
+        let convert = |max_size| {
+            let mut converter = SymCacheConverter::new();
+            converter.process_symbolic_function(&inlined_functions());
+            let fits = converter.limit_size(max_size);
+            let stats = converter.stats().clone();
+            let buf = serialize(converter);
+            (fits, stats, buf)
+        };
+
+        let (fits, stats, full) = convert(u64::MAX);
+        assert!(fits);
+        assert_eq!(stats.dropped_inline_frames, 0);
+        assert_eq!(stats.dropped_line_info, 0);
+
+        let (fits, stats, buf) = convert(full.len() as u64 - 1);
+        assert!(fits);
+        assert!(buf.len() < full.len());
+        assert_eq!(stats.dropped_inline_frames, 1);
+        assert_eq!(stats.dropped_line_info, 0);
+        let cache = SymCache::parse(&buf).unwrap();
+        let frames: Vec<_> = cache.lookup(0x100c).map(|sl| sl.line()).collect();
+        assert_eq!(frames, [2]);
+
+        let (fits, stats, buf) = convert(0);
+        assert!(!fits);
+        assert_eq!(stats.dropped_inline_frames, 1);
+        assert_eq!(stats.dropped_line_info, 2);
+        let cache = SymCache::parse(&buf).unwrap();
+        let sl = cache.lookup(0x100c).next().unwrap();
+        assert_eq!(sl.function().unwrap().name(), Some("main"));
+        assert_eq!(sl.line(), 0);
+        assert!(sl.file().is_none());
+        assert_eq!(cache.lookup(0x1010).count(), 0);
+    }
+
+    #[test]
+    fn test_limit_size_inline_depth() {
+        // every level needs a line record at the address of the nested call
+        let mut function = inlined_functions();
+        let mut line = function.lines[1].clone();
+        line.address = 0x100c;
+        line.line = 3;
+        function.lines.push(line);
+        let helper = &mut function.inlinees[0];
+        let mut line = helper.lines[0].clone();
+        line.address = 0x100c;
+        line.line = 6;
+        helper.lines.push(line);
+        let mut inner = helper.clone();
+        inner.address = 0x100c;
+        inner.size = 0x4;
+        inner.name = Name::from("inner");
+        inner.lines = vec![inner.lines[1].clone()];
+        inner.lines[0].line = 7;
+        helper.inlinees.push(inner);
+
+        let convert = |max_size| {
+            let mut converter = SymCacheConverter::new();
+            converter.process_symbolic_function(&function);
+            let fits = converter.limit_size(max_size);
+            let stats = converter.stats().clone();
+            let buf = serialize(converter);
+            (fits, stats, buf)
+        };
+
+        let (_, _, full) = convert(u64::MAX);
+        let cache = SymCache::parse(&full).unwrap();
+        let frames: Vec<_> = cache.lookup(0x100c).map(|sl| sl.line()).collect();
+        assert_eq!(frames, [7, 6, 3]);
+
+        // only the innermost level is dropped
+        let (fits, stats, buf) = convert(full.len() as u64 - 1);
+        assert!(fits);
+        assert_eq!(stats.dropped_inline_frames, 1);
+        assert_eq!(stats.dropped_line_info, 0);
+        let cache = SymCache::parse(&buf).unwrap();
+        let frames: Vec<_> = cache.lookup(0x100c).map(|sl| sl.line()).collect();
+        assert_eq!(frames, [6, 3]);
+        let frames: Vec<_> = cache.lookup(0x1008).map(|sl| sl.line()).collect();
+        assert_eq!(frames, [5, 2]);
+        cache.validate().unwrap();
+    }
+
+    #[test]
+    fn test_serialize_to() {
+        let mut function = inlined_functions();
+        function.lines[0].column = 3;
+
+        let mut converter = SymCacheConverter::new();
+        converter.process_symbolic_function(&function);
+        converter.process_symbolic_symbol(&symbol("other", 0x2000, 0x10));
+
+        // streaming does not consume the converter or require a seekable writer
+        let mut streamed = Vec::new();
+        let written = converter.serialize_to(&mut streamed).unwrap();
+        assert_eq!(written, streamed.len() as u64);
+        assert_eq!(converter.serialize_to(std::io::sink()).unwrap(), written);
+
+        let buf = serialize(converter);
+        assert_eq!(streamed, buf);
+
+        let cache = SymCache::parse(&buf).unwrap();
+        let function = cache.lookup(0x2008).next().unwrap().function();
+        assert_eq!(function.and_then(|f| f.name()), Some("other"));
+        assert_eq!(cache.lookup(0x2010).count(), 0);
+    }
+
+    #[test]
+    fn test_reproducible() {
+        let functions = || {
+            let mut converter = SymCacheConverter::new();
+            converter.process_symbolic_function(&inlined_functions());
+            converter
+        };
+        let symbols = || {
+            let mut converter = SymCacheConverter::new();
+            for (name, address) in [("other", 0x2000), ("unused", 0x1000)] {
+                converter.process_symbolic_symbol(&symbol(name, address, 0x10));
+            }
+            converter
+        };
+
+        let mut converter = functions();
+        converter.merge(symbols());
+        let buf = serialize(converter);
+
+        // processing the same data in a different order results in identical bytes
+        let mut converter = symbols();
+        converter.merge(functions());
+        let reversed = serialize(converter);
+        assert_eq!(buf, reversed);
+
+        let cache = SymCache::parse(&buf).unwrap();
+        let names: Vec<_> = cache.functions().map(|f| f.name()).collect();
+        assert_eq!(
+            names,
+            [Some("main"), Some("helper"), Some("other"), Some("unused")]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "spill")]
+    fn test_memory_budget() {
+        let convert = |budget: Option<usize>| {
+            let mut converter = SymCacheConverter::new();
+            if let Some(budget) = budget {
+                converter.set_memory_budget(budget);
+            }
+            // strings are added again after they have been spilled
+            for _ in 0..2 {
+                converter.process_symbolic_function(&inlined_functions());
+            }
+            serialize(converter)
+        };
+
+        let buf = convert(None);
+        assert_eq!(convert(Some(0)), buf);
+        assert_eq!(convert(Some(16)), buf);
+
+        let cache = SymCache::parse(&buf).unwrap();
+        assert_eq!(cache.functions().count(), 2);
+    }
+
+    #[test]
+    fn test_function_filter() {
+        let mut converter = SymCacheConverter::new();
+        converter.set_function_filter(|function| function.name != "helper");
+        converter.process_symbolic_function(&inlined_functions());
+        assert_eq!(converter.stats().filtered_functions, 1);
+        let buf = serialize(converter);
+
+        // the filtered inlinee resolves to its caller
+        let cache = SymCache::parse(&buf).unwrap();
+        let frames: Vec<_> = cache
+            .lookup(0x100c)
+            .map(|sl| (sl.function().and_then(|f| f.name()), sl.line()))
+            .collect();
+        assert_eq!(frames, [(Some("main"), 2)]);
+
+        let mut converter = SymCacheConverter::new();
+        converter.set_function_filter(|function| function.name != "main");
+        converter.process_symbolic_symbol(&symbol("before", 0x800, 0));
+        converter.process_symbolic_function(&inlined_functions());
+        converter.process_symbolic_symbol(&symbol("main", 0x1000, 0x10));
+        assert_eq!(converter.stats().filtered_functions, 1);
+        let buf = serialize(converter);
+
+        // the filtered function neither resolves to the preceding symbol nor its own symbol
+        let cache = SymCache::parse(&buf).unwrap();
+        assert_eq!(cache.lookup(0x900).count(), 1);
+        assert_eq!(cache.lookup(0x1004).count(), 0);
+        assert_eq!(cache.lookup(0x100c).count(), 0);
+    }
+
+    #[test]
+    fn test_file_filter() {
+        let mut converter = SymCacheConverter::new();
+        converter.set_file_filter(|file| file.name != "helper.h");
+        converter.process_symbolic_function(&inlined_functions());
+        assert_eq!(converter.stats().filtered_lines, 1);
+        let buf = serialize(converter);
+
+        let cache = SymCache::parse(&buf).unwrap();
+        assert_eq!(cache.files().count(), 1);
+        let frames: Vec<_> = cache
+            .lookup(0x100c)
+            .map(|sl| (sl.function().and_then(|f| f.name()), sl.line()))
+            .collect();
+        assert_eq!(frames, [(Some("helper"), 0), (Some("main"), 2)]);
+    }
+
+    #[test]
+    fn test_synthetic_functions() {
+        let mut converter = SymCacheConverter::new();
+        let ranges = [
+            AddrRange {
+                start: 0x2020,
+                end: 0x2030,
+            },
+            AddrRange {
+                start: 0x2000,
+                end: 0x2010,
+            },
+        ];
+        converter.add_synthetic_function("jit_stub", &ranges);
+        let range = AddrRange {
+            start: 0x2004,
+            end: 0x2008,
+        };
+        assert!(converter.add_source_location(range, "jit.js", 7));
+        let range = AddrRange {
+            start: 0x2010,
+            end: 0x2018,
+        };
+        assert!(!converter.add_source_location(range, "jit.js", 8));
+        let buf = serialize(converter);
+
+        let cache = SymCache::parse(&buf).unwrap();
+        let frame = |addr| {
+            cache.lookup(addr).next().map(|sl| {
+                let function = sl.function().unwrap();
+                let file = sl.file().map(|file| file.full_path());
+                (function.name(), function.entry_pc(), file, sl.line())
+            })
+        };
+        assert_eq!(frame(0x2000), Some((Some("jit_stub"), 0x2000, None, 0)));
+        assert_eq!(
+            frame(0x2004),
+            Some((Some("jit_stub"), 0x2000, Some("jit.js".into()), 7))
+        );
+        assert_eq!(frame(0x2008), Some((Some("jit_stub"), 0x2000, None, 0)));
+        assert_eq!(frame(0x2014), None);
+        assert_eq!(frame(0x2024), Some((Some("jit_stub"), 0x2000, None, 0)));
+        assert_eq!(frame(0x2030), None);
+        assert_eq!(cache.functions().count(), 1);
+    }
+
+    #[test]
+    fn test_perf_map() {
+        let mut converter = SymCacheConverter::new();
+        converter
+            .process_perf_map(b"2000 10 LazyCompile:~main app.js:1\n2020 8 stub\n")
+            .unwrap();
+        assert!(converter.process_perf_map(b"2000 main\n").is_err());
+        let buf = serialize(converter);
+
+        let cache = SymCache::parse(&buf).unwrap();
+        let name = |addr| {
+            let sl = cache.lookup(addr).next()?;
+            sl.function().and_then(|function| function.name())
+        };
+        assert_eq!(name(0x2008), Some("LazyCompile:~main app.js:1"));
+        assert_eq!(name(0x2010), None);
+        assert_eq!(name(0x2024), Some("stub"));
+        assert_eq!(name(0x2028), None);
+    }
+
+    #[test]
+    fn test_coalesce_ranges() {
+        let line = |address, number| line(address, 4, "/build/src", "main.c", number);
+        let function = function(
+            0x1000,
+            0x10,
+            "main",
+            vec![
+                line(0x1000, 1),
+                line(0x1004, 1),
+                line(0x1008, 1),
+                line(0x100c, 2),
+            ],
+        );
+
+        let mut converter = SymCacheConverter::new();
+        converter.process_symbolic_function(&function);
+        let mut uncoalesced = Vec::new();
+        converter.serialize_to(&mut uncoalesced).unwrap();
+        converter.coalesce_ranges();
+        assert_eq!(converter.stats().coalesced_ranges, 2);
+        let buf = serialize(converter);
+
+        // redundant ranges are left out in any case
+        assert_eq!(buf, uncoalesced);
+        let cache = SymCache::parse(&buf).unwrap();
+        let ranges: Vec<_> = cache.ranges().map(|(range, _)| range.start).collect();
+        assert_eq!(ranges, [0x1000, 0x100c]);
+        let lines: Vec<_> = (0x1000..0x1010)
+            .step_by(4)
+            .map(|addr| cache.lookup(addr).next().unwrap().line())
+            .collect();
+        assert_eq!(lines, [1, 1, 1, 2]);
+    }
+
+    #[test]
+    fn test_max_inline_depth() {
+        fn nested(depth: u64, max: u64) -> Function<'static> {
+            let lines = vec![line(0x1000, 0x10, "/build/src", "main.c", depth + 1)];
+            Function {
+                inlinees: if depth < max {
+                    vec![nested(depth + 1, max)]
+                } else {
+                    vec![]
+                },
+                inline: depth > 0,
+                ..function(0x1000, 0x10, format!("depth{}", depth), lines)
+            }
+        }
+
+        let mut converter = SymCacheConverter::new();
+        converter.set_max_inline_depth(3);
+        converter.process_symbolic_function(&nested(0, 10));
+        assert_eq!(converter.stats().skipped_inlinees, 1);
+        let buf = serialize(converter);
+        let cache = SymCache::parse(&buf).unwrap();
+        let lines: Vec<_> = cache.lookup(0x1000).map(|sl| sl.line()).collect();
+        assert_eq!(lines, [4, 3, 2, 1]);
+
+        let mut converter = SymCacheConverter::new();
+        converter.process_symbolic_function(&nested(0, 1000));
+        assert_eq!(converter.stats().skipped_inlinees, 1);
+        let buf = serialize(converter);
+        let cache = SymCache::parse(&buf).unwrap();
+        assert_eq!(cache.lookup(0x1000).count(), DEFAULT_MAX_INLINE_DEPTH + 1);
+    }
+
+    /// Looks up `addr` and returns the name, full path and line of every frame.
+    fn dwarf_frames(cache: &SymCache<'_>, addr: u64) -> Vec<(String, String, u32)> {
+        cache
+            .lookup(addr)
+            .map(|sl| {
+                let name = sl.function().and_then(|f| f.name()).unwrap_or_default();
+                let path = sl.file().map(|f| f.full_path()).unwrap_or_default();
+                (name.to_owned(), path, sl.line())
+            })
+            .collect()
+    }
+
+    fn frame(name: &str, path: &str, line: u32) -> (String, String, u32) {
+        (name.to_owned(), path.to_owned(), line)
+    }
+
+    #[test]
+    fn test_dwarf_inlinees() {
+        let leaf = TestFunction {
+            name: "leaf",
+            ranges: vec![(0x100c, 0x4)],
+            call_site: Some(("/src/helper.h", 21)),
+            ..Default::default()
+        };
+        let helper = TestFunction {
+            name: "helper",
+            ranges: vec![(0x1008, 0x8)],
+            call_site: Some(("/src/main.c", 11)),
+            inlinees: vec![leaf],
+        };
+        let main = TestFunction {
+            name: "main",
+            ranges: vec![(0x1000, 0x20)],
+            inlinees: vec![helper],
+            ..Default::default()
+        };
+        let buf = DwarfBuilder::new()
+            .unit(TestUnit {
+                name: "/src/main.c",
+                comp_dir: "/src",
+                functions: vec![main],
+                lines: vec![
+                    (0x1000, "/src/main.c", 10),
+                    (0x1008, "/src/helper.h", 20),
+                    (0x100c, "/src/leaf.h", 30),
+                    (0x1010, "/src/main.c", 12),
+                ],
+            })
+            .convert();
+
+        let cache = SymCache::parse(&buf).unwrap();
+        cache.validate().unwrap();
+
+        assert_eq!(
+            dwarf_frames(&cache, 0x1004),
+            [frame("main", "/src/main.c", 10)]
+        );
+        assert_eq!(
+            dwarf_frames(&cache, 0x1008),
+            [
+                frame("helper", "/src/helper.h", 20),
+                frame("main", "/src/main.c", 11),
+            ]
+        );
+        assert_eq!(
+            dwarf_frames(&cache, 0x100e),
+            [
+                frame("leaf", "/src/leaf.h", 30),
+                frame("helper", "/src/helper.h", 21),
+                frame("main", "/src/main.c", 11),
+            ]
+        );
+        assert_eq!(
+            dwarf_frames(&cache, 0x1014),
+            [frame("main", "/src/main.c", 12)]
+        );
+        assert!(dwarf_frames(&cache, 0x1020).is_empty());
+    }
+
+    #[test]
+    fn test_dwarf_units_and_ranges() {
+        let buf = DwarfBuilder::new()
+            .unit(TestUnit {
+                name: "/src/a.c",
+                comp_dir: "/src",
+                functions: vec![TestFunction {
+                    name: "a",
+                    ranges: vec![(0x1000, 0x10)],
+                    ..Default::default()
+                }],
+                lines: vec![(0x1000, "/src/a.c", 1)],
+            })
+            .unit(TestUnit {
+                name: "/src/b.c",
+                comp_dir: "/src",
+                functions: vec![TestFunction {
+                    name: "b",
+                    ranges: vec![(0x2000, 0x10), (0x3000, 0x10)],
+                    ..Default::default()
+                }],
+                lines: vec![(0x2000, "/src/b.c", 5), (0x3000, "/src/b.c", 7)],
+            })
+            .convert();
+
+        let cache = SymCache::parse(&buf).unwrap();
+        cache.validate().unwrap();
+
+        assert_eq!(cache.functions().count(), 2);
+        assert_eq!(dwarf_frames(&cache, 0x1008), [frame("a", "/src/a.c", 1)]);
+        assert_eq!(dwarf_frames(&cache, 0x2008), [frame("b", "/src/b.c", 5)]);
+        assert_eq!(dwarf_frames(&cache, 0x3008), [frame("b", "/src/b.c", 7)]);
+        assert!(dwarf_frames(&cache, 0x1010).is_empty());
+    }
+
+    #[test]
+    #[cfg(all(feature = "parallel", feature = "spill"))]
+    fn test_dwarf_units_memory_budget() {
+        use symbolic_debuginfo::dwarf::DwarfDebugSession;
+        use symbolic_debuginfo::{ObjectKind, SymbolMap};
+        let unit = |name, address| TestUnit {
+            name,
+            comp_dir: "/src",
+            functions: vec![TestFunction {
+                name,
+                ranges: vec![(address, 0x10)],
+                ..Default::default()
+            }],
+            lines: vec![(address, name, 1)],
+        };
+        let dwarf = DwarfBuilder::new()
+            .unit(unit("/src/a.c", 0x1000))
+            .unit(unit("/src/b.c", 0x2000))
+            .build();
+        let session =
+            DwarfDebugSession::parse(&dwarf, SymbolMap::new(), 0, ObjectKind::Debug).unwrap();
+
+        // every unit spills its strings, which are read back when merging the units
+        let convert = |budget: Option<usize>| {
+            let mut converter = SymCacheConverter::new();
+            if let Some(budget) = budget {
+                converter.set_memory_budget(budget);
+            }
+            converter.process_dwarf(&session, None).unwrap();
+            serialize(converter)
+        };
+
+        let buf = convert(None);
+        assert_eq!(convert(Some(0)), buf);
+
+        let cache = SymCache::parse(&buf).unwrap();
+        assert_eq!(cache.functions().count(), 2);
     }
 }
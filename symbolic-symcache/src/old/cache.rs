@@ -1,11 +1,12 @@
 #![allow(deprecated)]
 
+use std::collections::BTreeSet;
 use std::fmt;
 
 use symbolic_common::{Arch, AsSelf, DebugId, Language, Name, NameMangling};
 
 use crate::format;
-use crate::SymCacheError;
+use crate::{SymCacheError, SymCacheErrorKind};
 
 /// A platform independent symbolication cache.
 ///
@@ -140,7 +141,7 @@ impl<'a> SymCache<'a> {
             // We are currently looking at an inline function. Since we're scanning linearly, ensure
             // that we're also including its parent. This might be from a completely different
             // inlining branch, so honor the existing `last_id` value as it might be lower.
-            if let Some(parent_id) = current_fn.parent(current_id) {
+            if let Some(parent_id) = parent_id(current_fn, current_id)? {
                 last_id = parent_id.min(last_id);
             }
 
@@ -168,6 +169,27 @@ impl<'a> SymCache<'a> {
         })
     }
 
+    /// Returns every address at which the result of [`lookup`](Self::lookup) may change, in
+    /// ascending order.
+    ///
+    /// These are the start and end addresses of all functions and the addresses of all line
+    /// records. Functions of unknown size do not have an end address.
+    pub(crate) fn lookup_boundaries(&self) -> Result<BTreeSet<u64>, SymCacheError> {
+        let mut boundaries = BTreeSet::new();
+        for record in self.function_records()? {
+            let mut address = record.addr_start();
+            boundaries.insert(address);
+            if record.addr_end() != u64::MAX {
+                boundaries.insert(record.addr_end());
+            }
+            for line_record in record.line_records.read(self.data)? {
+                address += u64::from(line_record.addr_off);
+                boundaries.insert(address);
+            }
+        }
+        Ok(boundaries)
+    }
+
     /// Resolves the raw list of `FuncRecords` from the funcs segment.
     fn function_records(&self) -> Result<&'a [format::FuncRecord], SymCacheError> {
         self.header.functions.read(self.data)
@@ -357,11 +379,16 @@ impl<'a, 'c> Iterator for Lookup<'a, 'c> {
 
     fn next(&mut self) -> Option<Self::Item> {
         let (addr, id, fun) = self.current?;
+        let parent_id = match parent_id(fun, id) {
+            Ok(parent_id) => parent_id,
+            Err(error) => {
+                self.current = None;
+                return Some(Err(error));
+            }
+        };
         let line_result = self.cache.build_line_info(fun, addr, None);
 
-        self.current = fun
-            .parent(id)
-            .map(|parent_id| (addr, parent_id, &self.funcs[parent_id]));
+        self.current = parent_id.map(|parent_id| (addr, parent_id, &self.funcs[parent_id]));
 
         if let Ok(ref line_info) = line_result {
             self.inner = Some((
@@ -393,6 +420,25 @@ impl fmt::Debug for Lookup<'_, '_> {
     }
 }
 
+/// Resolves the index of the parent of the function at `id`, if it was inlined.
+///
+/// Parents are always stored before their inlinees, so the parent must have a lower index. This
+/// rejects parent offsets of corrupt SymCaches, which would point outside of the function segment
+/// or create cycles.
+fn parent_id(record: &format::FuncRecord, id: usize) -> Result<Option<usize>, SymCacheError> {
+    if record.parent_offset == !0 {
+        return Ok(None);
+    }
+
+    match id.checked_sub(usize::from(record.parent_offset)) {
+        Some(parent_id) if parent_id < id => Ok(Some(parent_id)),
+        _ => Err(SymCacheError::new(
+            SymCacheErrorKind::BadCacheFile,
+            "invalid inline parent offset",
+        )),
+    }
+}
+
 /// Information on a matched source line.
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct LineInfo<'a> {
@@ -456,15 +502,12 @@ impl<'a> LineInfo<'a> {
 
     /// The joined path and file name relative to the compilation directory.
     pub fn path(&self) -> String {
-        let joined = symbolic_common::join_path(self.base_dir, self.filename);
-        symbolic_common::clean_path(&joined).into_owned()
+        symbolic_common::join_file_path("", self.base_dir, self.filename)
     }
 
     /// The fully joined absolute path including the compilation directory.
     pub fn abs_path(&self) -> String {
-        let joined_path = symbolic_common::join_path(self.base_dir, self.filename);
-        let joined = symbolic_common::join_path(self.comp_dir, &joined_path);
-        symbolic_common::clean_path(&joined).into_owned()
+        symbolic_common::join_file_path(self.comp_dir, self.base_dir, self.filename)
     }
 
     /// The line number within the file.
@@ -66,6 +66,18 @@ pub enum SymCacheErrorKind {
 
     /// Generic error when writing a symcache, most likely IO.
     WriteFailed,
+
+    /// A debug file does not belong to the object file it was supplied for.
+    MismatchedDebugFile,
+
+    /// A module with the same debug identifier was already written.
+    DuplicateModule,
+
+    /// The conversion was cancelled or exceeded its deadline.
+    Cancelled,
+
+    /// An archive does not contain an object for the requested architecture.
+    MissingArchitecture,
 }
 
 impl fmt::Display for SymCacheErrorKind {
@@ -83,6 +95,10 @@ impl fmt::Display for SymCacheErrorKind {
             Self::ValueTooLarge(kind) => write!(f, "{} too large for symcache file format", kind),
             Self::TooManyValues(kind) => write!(f, "too many {}s for symcache", kind),
             Self::WriteFailed => write!(f, "failed to write symcache"),
+            Self::MismatchedDebugFile => write!(f, "debug file does not match the object file"),
+            Self::DuplicateModule => write!(f, "duplicate module in symcache"),
+            Self::Cancelled => write!(f, "symcache conversion was cancelled"),
+            Self::MissingArchitecture => write!(f, "no object found for the architecture"),
         }
     }
 }
@@ -249,7 +249,7 @@ impl FuncRecord {
         if self.parent_offset == !0 {
             None
         } else {
-            Some(func_id - (self.parent_offset as usize))
+            func_id.checked_sub(self.parent_offset as usize)
         }
     }
 }
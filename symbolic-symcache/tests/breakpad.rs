@@ -140,3 +140,44 @@ PUBLIC d80 0 public_record"#;
         .collect();
     assert_eq!(lookup_result[0].symbol(), "public_record");
 }
+
+#[test]
+fn test_inlinees() {
+    let data = b"MODULE Linux x86_64 492E2DD23CC306CA9C494EEF1533A3810 crash
+FILE 0 main.c
+FILE 1 helper.h
+INLINE_ORIGIN 0 helper
+FUNC 1000 20 0 main
+INLINE 0 7 0 0 1008 10
+1000 8 3 0
+1008 10 20 1
+1018 8 4 0
+";
+    let breakpad = BreakpadObject::parse(data).unwrap();
+
+    let mut buffer = Vec::new();
+    SymCacheWriter::write_object(&breakpad, Cursor::new(&mut buffer)).unwrap();
+    let symcache = SymCache::parse(&buffer).unwrap();
+
+    let frames: Vec<_> = symcache
+        .lookup(0x100c)
+        .unwrap()
+        .filter_map(Result::ok)
+        .map(|frame| (frame.symbol().to_owned(), frame.path(), frame.line()))
+        .collect();
+    assert_eq!(
+        frames,
+        [
+            ("helper".to_owned(), "helper.h".to_owned(), 20),
+            ("main".to_owned(), "main.c".to_owned(), 7),
+        ]
+    );
+
+    let frames: Vec<_> = symcache
+        .lookup(0x1018)
+        .unwrap()
+        .filter_map(Result::ok)
+        .map(|frame| frame.line())
+        .collect();
+    assert_eq!(frames, [4]);
+}
@@ -1,7 +1,10 @@
 use std::fmt;
+use std::io::Cursor;
+use std::sync::Arc;
 
 use symbolic_common::ByteView;
-use symbolic_symcache::SymCache;
+use symbolic_debuginfo::Object;
+use symbolic_symcache::{NewSymCache, OwnedSymCache, SourceLocationIter, SymCache, SymCacheWriter};
 use symbolic_testutils::fixture;
 
 type Error = Box<dyn std::error::Error>;
@@ -88,3 +91,86 @@ fn test_lookup() -> Result<(), Error> {
 
     Ok(())
 }
+
+#[test]
+fn test_lookup_many() -> Result<(), Error> {
+    let buffer = ByteView::open(fixture("macos/crash.dSYM/Contents/Resources/DWARF/crash"))?;
+    let object = Object::parse(&buffer)?;
+    let buffer = SymCacheWriter::write_object(&object, Cursor::new(Vec::new()))?.into_inner();
+    let symcache = NewSymCache::parse(&buffer)?;
+
+    // unsorted, with duplicates and addresses outside of the cache
+    let mut addrs: Vec<u64> = (0..0x4000).rev().step_by(3).collect();
+    addrs.extend_from_slice(&[0x1d00, 0x1d00, 0, u64::from(u32::MAX) + 1, u64::MAX]);
+
+    fn frames(iter: SourceLocationIter<'_, '_>) -> Vec<(Option<String>, u32)> {
+        iter.map(|sl| {
+            (
+                sl.function().and_then(|f| f.name().map(String::from)),
+                sl.line(),
+            )
+        })
+        .collect()
+    }
+    let results = symcache.lookup_many(&addrs);
+    assert_eq!(results.len(), addrs.len());
+    for (&addr, result) in addrs.iter().zip(results) {
+        assert_eq!(frames(result), frames(symcache.lookup(addr)), "{:#x}", addr);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_shared_between_threads() -> Result<(), Error> {
+    let buffer = ByteView::open(fixture("macos/crash.dSYM/Contents/Resources/DWARF/crash"))?;
+    let object = Object::parse(&buffer)?;
+    let buffer = SymCacheWriter::write_object(&object, Cursor::new(Vec::new()))?.into_inner();
+
+    let shared = Arc::new(OwnedSymCache::parse(ByteView::from_vec(buffer))?);
+
+    let expected = shared.get().lookup(0x1d00).count();
+    assert!(expected > 0);
+
+    let threads: Vec<_> = (0..4)
+        .map(|_| {
+            let shared = Arc::clone(&shared);
+            std::thread::spawn(move || shared.get().lookup(0x1d00).count())
+        })
+        .collect();
+    for thread in threads {
+        assert_eq!(thread.join().unwrap(), expected);
+    }
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn test_serialize_json() -> Result<(), Error> {
+    let buffer = ByteView::open(fixture("macos/crash.dSYM/Contents/Resources/DWARF/crash"))?;
+    let object = Object::parse(&buffer)?;
+    let buffer = SymCacheWriter::write_object(&object, Cursor::new(Vec::new()))?.into_inner();
+    let symcache = OwnedSymCache::parse(ByteView::from_vec(buffer))?;
+    let symcache = symcache.get();
+
+    let json = serde_json::to_value(symcache)?;
+    assert_eq!(json["debug_id"], symcache.debug_id().to_string());
+    assert_eq!(json["arch"], "x86_64");
+    assert_eq!(
+        json["functions"].as_array().unwrap().len(),
+        symcache.functions().count()
+    );
+    assert_eq!(
+        json["files"].as_array().unwrap().len(),
+        symcache.files().count()
+    );
+
+    let ranges = json["ranges"].as_array().unwrap();
+    assert_eq!(ranges.len(), symcache.ranges().count());
+    let frames = ranges[0]["frames"].as_array().unwrap();
+    assert!(!frames.is_empty());
+    assert_eq!(frames.last().unwrap()["inlined"], false);
+
+    Ok(())
+}
@@ -1,5 +1,8 @@
-use symbolic_common::ByteView;
-use symbolic_symcache::SymCache;
+use std::io::Cursor;
+
+use symbolic_common::{ByteView, Name};
+use symbolic_debuginfo::{FileInfo, Function, LineInfo as DebugLineInfo};
+use symbolic_symcache::{SymCache, SymCacheError, SymCacheErrorKind, SymCacheWriter};
 use symbolic_testutils::fixture;
 
 use similar_asserts::assert_eq;
@@ -25,3 +28,189 @@ fn test_v1() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+#[test]
+#[allow(deprecated)]
+fn test_upgrade_legacy() -> Result<(), Box<dyn std::error::Error>> {
+    for path in [
+        "symcache/compat/v1.symc",
+        "symcache/current/linux.symc",
+        "symcache/current/macos.symc",
+    ] {
+        let buffer = ByteView::open(fixture(path))?;
+        let legacy = SymCache::parse(&buffer)?;
+        assert!(!legacy.is_latest());
+
+        let mut writer = SymCacheWriter::new(Cursor::new(Vec::new()))?;
+        writer.process_symcache_v7(&buffer)?;
+        let upgraded = writer.finish()?.into_inner();
+        let upgraded = SymCache::parse(&upgraded)?;
+        assert!(upgraded.is_latest());
+        assert_eq!(upgraded.debug_id(), legacy.debug_id());
+        assert_eq!(upgraded.arch(), legacy.arch());
+
+        let frames = |symcache: &SymCache<'_>, addr| -> Result<_, SymCacheError> {
+            let frames: Vec<_> = symcache.lookup(addr)?.collect()?;
+            let function_address = frames.last().map(|frame| frame.function_address());
+            let frames: Vec<_> = frames
+                .iter()
+                .map(|frame| {
+                    let symbol = frame.symbol().to_owned();
+                    let file = (frame.base_dir().to_owned(), frame.filename().to_owned());
+                    (symbol, file, frame.line())
+                })
+                .collect();
+            Ok((frames, function_address))
+        };
+
+        for function in legacy.functions() {
+            let function = function?;
+            let mut addrs = vec![function.address()];
+            for line in function.lines() {
+                addrs.push(function.address() + line?.address());
+            }
+            for addr in addrs {
+                assert_eq!(frames(&upgraded, addr)?, frames(&legacy, addr)?);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+#[allow(deprecated)]
+fn test_upgrade_legacy_corrupt() -> Result<(), Box<dyn std::error::Error>> {
+    let buffer = ByteView::open(fixture("symcache/compat/v1.symc"))?;
+    let symcache = SymCache::parse(&buffer)?;
+    let inlinee = symcache
+        .functions()
+        .find_map(|function| function.ok().filter(|f| f.parent_id().is_some()))
+        .expect("no inlined functions in symcache");
+    let parent_offset = inlinee.id() - inlinee.parent_id().unwrap();
+
+    // V1 offsets are relative to the end of the header. The parent offset is stored after the
+    // address, length, line records and compilation directory of the function record.
+    let functions_offset = symbolic_symcache::format::Header::parse(&buffer)?
+        .functions
+        .offset;
+    let offset = std::mem::size_of::<symbolic_symcache::format::HeaderV1>()
+        + functions_offset as usize
+        + inlinee.id() * std::mem::size_of::<symbolic_symcache::format::FuncRecord>()
+        + 19;
+    assert_eq!(
+        u16::from_le_bytes([buffer[offset], buffer[offset + 1]]) as usize,
+        parent_offset
+    );
+
+    // a parent pointing to the function itself or before the first function
+    for corrupt_offset in [0, inlinee.id() as u16 + 1] {
+        let mut data = buffer.to_vec();
+        data[offset..offset + 2].copy_from_slice(&corrupt_offset.to_le_bytes());
+
+        let symcache = SymCache::parse(&data)?;
+        let error = symcache
+            .lookup(inlinee.address())
+            .and_then(|lookup| lookup.collect::<Vec<_>>())
+            .unwrap_err();
+        assert_eq!(error.kind(), SymCacheErrorKind::BadCacheFile);
+
+        let mut writer = SymCacheWriter::new(Cursor::new(Vec::new()))?;
+        let error = writer.process_symcache_v7(&data).unwrap_err();
+        assert_eq!(error.kind(), SymCacheErrorKind::BadCacheFile);
+    }
+
+    Ok(())
+}
+
+#[test]
+#[allow(deprecated)]
+fn test_lookup_frame_addresses() -> Result<(), Box<dyn std::error::Error>> {
+    let line = |address, name, line, column| DebugLineInfo {
+        address,
+        size: None,
+        file: FileInfo {
+            name,
+            dir: b"/build",
+        },
+        line,
+        column,
+        prologue_end: false,
+        is_stmt: true,
+        basic_block: false,
+    };
+    let helper = Function {
+        address: 0x1008,
+        size: 0x10,
+        name: Name::from("helper"),
+        compilation_dir: b"",
+        lines: vec![
+            line(0x1008, b"helper.h", 5, 3),
+            line(0x100c, b"helper.h", 5, 7),
+            line(0x1010, b"helper.h", 6, 3),
+        ],
+        inlinees: vec![],
+        inline: true,
+        decl_file: None,
+        decl_line: 0,
+    };
+    let main = Function {
+        address: 0x1000,
+        size: 0x20,
+        name: Name::from("main"),
+        compilation_dir: b"",
+        lines: vec![
+            line(0x1000, b"main.c", 1, 0),
+            line(0x1008, b"main.c", 2, 0),
+            line(0x100c, b"main.c", 2, 0),
+            line(0x1010, b"main.c", 2, 0),
+            line(0x1018, b"main.c", 3, 0),
+        ],
+        inlinees: vec![helper],
+        inline: false,
+        decl_file: None,
+        decl_line: 0,
+    };
+
+    let mut writer = SymCacheWriter::new(Cursor::new(Vec::new()))?;
+    writer.add_function(main)?;
+    let buffer = writer.finish()?.into_inner();
+    let symcache = SymCache::parse(&buffer)?;
+
+    let frames = |addr| -> Result<_, SymCacheError> {
+        let frames: Vec<_> = symcache.lookup(addr)?.collect()?;
+        Ok(frames
+            .iter()
+            .map(|frame| {
+                let symbol = frame.symbol().to_owned();
+                (
+                    symbol,
+                    frame.line(),
+                    frame.function_address(),
+                    frame.line_address(),
+                )
+            })
+            .collect::<Vec<_>>())
+    };
+
+    let frame = |symbol: &str, line, function_address, line_address| {
+        (symbol.to_owned(), line, function_address, line_address)
+    };
+    assert_eq!(
+        frames(0x100e)?,
+        [
+            frame("helper", 5, 0x1008, 0x1008),
+            frame("main", 2, 0x1000, 0x1008),
+        ]
+    );
+    assert_eq!(
+        frames(0x1014)?,
+        [
+            frame("helper", 6, 0x1008, 0x1010),
+            frame("main", 2, 0x1000, 0x1008),
+        ]
+    );
+    assert_eq!(frames(0x101a)?, [frame("main", 3, 0x1000, 0x1018)]);
+
+    Ok(())
+}
@@ -1,11 +1,22 @@
+use std::cell::RefCell;
 use std::fmt;
-use std::io::Cursor;
-
-use symbolic_common::{ByteView, SelfCell};
-use symbolic_debuginfo::macho::BcSymbolMap;
-use symbolic_debuginfo::Object;
+use std::io::{self, Cursor};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+use symbolic_common::{Arch, ByteView, SelfCell};
+use symbolic_debuginfo::dwarf::{Dwarf, DwarfErrorKind};
+use symbolic_debuginfo::elf::ElfObject;
+use symbolic_debuginfo::macho::{BcSymbolMap, MachObject};
+use symbolic_debuginfo::pe::PeObject;
+use symbolic_debuginfo::{Archive, Object};
 use symbolic_symcache::transform::{self, Transformer};
-use symbolic_symcache::{SymCache, SymCacheWriter};
+use symbolic_symcache::{
+    ConversionErrorKind, MultiSymCache, MultiSymCacheWriter, NewSymCache, Severity, SymCache,
+    SymCacheErrorKind, SymCacheWriter,
+};
 use symbolic_testutils::fixture;
 
 type Error = Box<dyn std::error::Error>;
@@ -56,17 +67,17 @@ fn test_write_header_linux() -> Result<(), Error> {
     let symcache = SymCache::parse(&buffer)?;
     insta::assert_debug_snapshot!(symcache, @r###"
     SymCache {
-        version: 7,
+        version: 8,
         debug_id: DebugId {
             uuid: "c0bcc3f1-9827-fe65-3058-404b2831d9e6",
             appendix: 0,
         },
         arch: Amd64,
         files: 55,
-        functions: 697,
-        source_locations: 8236,
-        ranges: 6762,
-        string_bytes: 52180,
+        functions: 702,
+        source_locations: 8381,
+        ranges: 6907,
+        string_bytes: 53385,
     }
     "###);
 
@@ -96,7 +107,7 @@ fn test_write_header_macos() -> Result<(), Error> {
     let symcache = SymCache::parse(&buffer)?;
     insta::assert_debug_snapshot!(symcache, @r###"
     SymCache {
-        version: 7,
+        version: 8,
         debug_id: DebugId {
             uuid: "67e9247c-814e-392b-a027-dbde6748fcbf",
             appendix: 0,
@@ -104,8 +115,8 @@ fn test_write_header_macos() -> Result<(), Error> {
         arch: Amd64,
         files: 36,
         functions: 639,
-        source_locations: 6033,
-        ranges: 4591,
+        source_locations: 7253,
+        ranges: 5811,
         string_bytes: 42829,
     }
     "###);
@@ -126,6 +137,213 @@ fn test_write_functions_macos() -> Result<(), Error> {
     Ok(())
 }
 
+#[test]
+fn test_validate() -> Result<(), Error> {
+    for path in [
+        "linux/crash.debug",
+        "macos/crash.dSYM/Contents/Resources/DWARF/crash",
+        "windows/crash.pdb",
+    ] {
+        let buffer = ByteView::open(fixture(path))?;
+        let object = Object::parse(&buffer)?;
+
+        let mut buffer = Vec::new();
+        SymCacheWriter::write_object(&object, Cursor::new(&mut buffer))?;
+        let symcache = NewSymCache::parse_verified(&buffer)?;
+        symcache.validate()?;
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_write_merged() -> Result<(), Error> {
+    let buffer = ByteView::open(fixture("linux/crash.debug"))?;
+    let object = Object::parse(&buffer)?;
+
+    let mut buffer = Vec::new();
+    SymCacheWriter::write_object(&object, Cursor::new(&mut buffer))?;
+    let symcache = NewSymCache::parse(&buffer)?;
+
+    let mut merged = Vec::new();
+    SymCacheWriter::write_merged([&symcache, &symcache], Cursor::new(&mut merged))?;
+    let merged = NewSymCache::parse(&merged)?;
+    merged.validate()?;
+
+    let ranges = |symcache: &NewSymCache<'_>| {
+        symcache
+            .ranges()
+            .map(|(range, sl)| {
+                let name = sl.function().and_then(|f| f.name()).map(String::from);
+                (range, name, sl.line())
+            })
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(ranges(&merged), ranges(&symcache));
+    assert_eq!(merged.functions().count(), symcache.functions().count());
+    assert_eq!(merged.files().count(), symcache.files().count());
+
+    Ok(())
+}
+
+#[test]
+fn test_write_dwarf_units() -> Result<(), Error> {
+    for name in [
+        "linux/crash.debug",
+        "macos/crash.dSYM/Contents/Resources/DWARF/crash",
+    ] {
+        let buffer = ByteView::open(fixture(name))?;
+        let object = Object::parse(&buffer)?;
+
+        let mut expected = Vec::new();
+        SymCacheWriter::write_object(&object, Cursor::new(&mut expected))?;
+        let expected = NewSymCache::parse(&expected)?;
+
+        // with the `parallel` feature, compilation units are converted separately and merged
+        let mut writer = SymCacheWriter::new(Cursor::new(Vec::new()))?;
+        writer.process_any_object(&object)?;
+        let buffer = writer.finish()?.into_inner();
+        let symcache = NewSymCache::parse(&buffer)?;
+        symcache.validate()?;
+
+        let diff = expected.diff(&symcache);
+        assert!(diff.is_empty(), "{}: {:?}", name, diff);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_write_serialize_to() -> Result<(), Error> {
+    let buffer = ByteView::open(fixture("linux/crash.debug"))?;
+    let object = Object::parse(&buffer)?;
+
+    let mut writer = SymCacheWriter::new(Cursor::new(Vec::new()))?;
+    writer.process_object(&object)?;
+
+    let mut streamed = Vec::new();
+    let written = writer.serialize_to(&mut streamed)?;
+    assert_eq!(written, streamed.len() as u64);
+
+    let buffer = writer.finish()?.into_inner();
+    assert_eq!(streamed, buffer);
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "spill")]
+fn test_write_memory_budget() -> Result<(), Error> {
+    let buffer = ByteView::open(fixture("linux/crash.debug"))?;
+    let object = Object::parse(&buffer)?;
+
+    let mut expected = Vec::new();
+    SymCacheWriter::write_object(&object, Cursor::new(&mut expected))?;
+
+    let mut writer = SymCacheWriter::new(Cursor::new(Vec::new()))?;
+    writer.set_memory_budget(1024);
+    writer.process_object(&object)?;
+    let buffer = writer.finish()?.into_inner();
+    assert_eq!(buffer, expected);
+
+    Ok(())
+}
+
+#[test]
+fn test_write_error_sink() -> Result<(), Error> {
+    let buffer = ByteView::open(fixture("linux/crash.debug"))?;
+    let debug_info = ElfObject::parse(&buffer)?.section("debug_info").unwrap();
+
+    // corrupt a debugging information entry within the first compilation unit
+    let mut data = buffer.to_vec();
+    data[debug_info.offset as usize + 194] ^= 0xff;
+    let object = Object::parse(&data)?;
+
+    let errors = Rc::new(RefCell::new(Vec::new()));
+    let mut writer = SymCacheWriter::new(Cursor::new(Vec::new()))?;
+    writer.set_error_sink({
+        let errors = Rc::clone(&errors);
+        move |error| errors.borrow_mut().push(error)
+    });
+
+    let error = writer.process_any_object(&object).unwrap_err();
+    assert_eq!(error.kind(), SymCacheErrorKind::BadDebugFile);
+
+    let errors = errors.borrow();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].unit_name.as_deref(), Some("../linux/main.cpp"));
+    assert_eq!(
+        errors[0].kind,
+        ConversionErrorKind::Dwarf(DwarfErrorKind::CorruptedData)
+    );
+    assert_eq!(errors[0].severity, Severity::Fatal);
+
+    Ok(())
+}
+
+#[test]
+fn test_write_broken_line_sequence() -> Result<(), Error> {
+    let buffer = ByteView::open(fixture("linux/crash.debug"))?;
+    let debug_line = ElfObject::parse(&buffer)?.section("debug_line").unwrap();
+
+    // corrupt an instruction in the line program of the first compilation unit
+    let mut data = buffer.to_vec();
+    data[debug_line.offset as usize + 1757] ^= 0xff;
+    let object = Object::parse(&data)?;
+
+    // without an error sink, the line program is truncated silently
+    let mut writer = SymCacheWriter::new(Cursor::new(Vec::new()))?;
+    writer.process_any_object(&object)?;
+    let expected = writer.finish()?.into_inner();
+
+    let errors = Rc::new(RefCell::new(Vec::new()));
+    let mut writer = SymCacheWriter::new(Cursor::new(Vec::new()))?;
+    writer.set_error_sink({
+        let errors = Rc::clone(&errors);
+        move |error| errors.borrow_mut().push(error)
+    });
+    writer.process_any_object(&object)?;
+    let buffer = writer.finish()?.into_inner();
+    assert_eq!(buffer, expected);
+
+    // the fixture also contains conflicting line records, which are reported separately
+    let errors = errors.borrow();
+    let errors: Vec<_> = errors
+        .iter()
+        .filter(|error| error.kind != ConversionErrorKind::ConflictingLines)
+        .collect();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].unit_name.as_deref(), Some("../linux/main.cpp"));
+    assert_eq!(
+        errors[0].kind,
+        ConversionErrorKind::Dwarf(DwarfErrorKind::InvalidLineSequence)
+    );
+    assert_eq!(errors[0].severity, Severity::Warning);
+
+    Ok(())
+}
+
+#[test]
+fn test_write_unit_stats() -> Result<(), Error> {
+    let buffer = ByteView::open(fixture("linux/crash.debug"))?;
+    let object = Object::parse(&buffer)?;
+
+    let mut writer = SymCacheWriter::new(Cursor::new(Vec::new()))?;
+    writer.process_any_object(&object)?;
+    let stats = writer.stats();
+
+    assert!(!stats.units.is_empty());
+    assert!(stats.units.windows(2).all(|w| w[0].index < w[1].index));
+    let functions: u64 = stats.units.iter().map(|unit| unit.functions).sum();
+    assert_eq!(functions, stats.processed_functions);
+    for unit in &stats.units {
+        let inlinees: u64 = unit.inline_depths.iter().sum();
+        assert_eq!(inlinees, unit.inlinees);
+    }
+
+    Ok(())
+}
+
 #[test]
 fn test_write_large_symbol_names() -> Result<(), Error> {
     let buffer = ByteView::open(fixture("regression/large_symbol.sym"))?;
@@ -218,6 +436,367 @@ fn test_trailing_marker() -> Result<(), Error> {
     Ok(())
 }
 
+#[test]
+fn test_section_alignment() -> Result<(), Error> {
+    let buffer = ByteView::open(fixture("macos/crash.dSYM/Contents/Resources/DWARF/crash"))?;
+    let object = Object::parse(&buffer)?;
+
+    let mut default_buffer = Vec::new();
+    SymCacheWriter::write_object(&object, Cursor::new(&mut default_buffer))?;
+
+    let mut paged_buffer = Vec::new();
+    let mut writer = SymCacheWriter::new(Cursor::new(&mut paged_buffer))?;
+    writer.set_section_alignment(4096);
+    writer.process_object(&object)?;
+    writer.finish()?;
+
+    assert!(paged_buffer.len() > default_buffer.len());
+
+    let default_cache = SymCache::parse(&default_buffer)?;
+    let paged_cache = SymCache::parse(&paged_buffer)?;
+    for addr in [0x1bc0, 0x1d00, 0x2000] {
+        let default_lines = default_cache.lookup(addr)?.collect::<Vec<_>>()?;
+        let paged_lines = paged_cache.lookup(addr)?.collect::<Vec<_>>()?;
+        assert!(!default_lines.is_empty());
+        assert_eq!(default_lines, paged_lines);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_write_incrementally() -> Result<(), Error> {
+    let mut buffer = Vec::new();
+    let mut writer = SymCacheWriter::new(Cursor::new(&mut buffer))?;
+
+    for path in ["linux/crash", "linux/crash.debug"] {
+        let object_buffer = ByteView::open(fixture(path))?;
+        let object = Object::parse(&object_buffer)?;
+        writer.process_object(&object)?;
+    }
+    writer.finish()?;
+
+    let symcache = SymCache::parse(&buffer)?;
+    let lines = symcache.lookup(0x1a3d)?.collect::<Vec<_>>()?;
+    assert!(lines.iter().any(|line| line.line() > 0));
+
+    Ok(())
+}
+
+#[test]
+fn test_write_multi() -> Result<(), Error> {
+    let mut writer = MultiSymCacheWriter::new(Cursor::new(Vec::new()))?;
+    let mut expected = Vec::new();
+
+    // the stripped binary and its debug file are combined into one module
+    let mut module = SymCacheWriter::new(io::sink())?;
+    let mut single = SymCacheWriter::new(Cursor::new(Vec::new()))?;
+    for path in ["linux/crash", "linux/crash.debug"] {
+        let buffer = ByteView::open(fixture(path))?;
+        let object = Object::parse(&buffer)?;
+        module.process_object(&object)?;
+        single.process_object(&object)?;
+    }
+    writer.append(module)?;
+    expected.push(single.finish()?.into_inner());
+
+    let buffer = ByteView::open(fixture("macos/crash.dSYM/Contents/Resources/DWARF/crash"))?;
+    let object = Object::parse(&buffer)?;
+    writer.append_object(&object)?;
+    let mut single = Vec::new();
+    SymCacheWriter::write_object(&object, Cursor::new(&mut single))?;
+    expected.push(single);
+
+    let error = writer.append_object(&object).unwrap_err();
+    assert_eq!(error.kind(), SymCacheErrorKind::DuplicateModule);
+
+    let buffer = writer.finish()?.into_inner();
+    let multi = MultiSymCache::parse(&buffer)?;
+    assert_eq!(multi.len(), 2);
+
+    for expected in expected {
+        let symcache = NewSymCache::parse(&expected)?;
+        let module = multi.get(symcache.debug_id()).unwrap();
+        assert_eq!(module.arch(), symcache.arch());
+        assert_eq!(module.data(), &expected[..]);
+        module.symcache()?.verify()?;
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_limit_size() -> Result<(), Error> {
+    let buffer = ByteView::open(fixture("macos/crash.dSYM/Contents/Resources/DWARF/crash"))?;
+    let object = Object::parse(&buffer)?;
+
+    let mut full_buffer = Vec::new();
+    SymCacheWriter::write_object(&object, Cursor::new(&mut full_buffer))?;
+
+    let max_size = full_buffer.len() as u64 / 2;
+    let mut buffer = Vec::new();
+    let mut writer = SymCacheWriter::new(Cursor::new(&mut buffer))?;
+    writer.process_object(&object)?;
+    assert!(writer.limit_size(max_size));
+    assert!(writer.stats().dropped_inline_frames > 0);
+    writer.finish()?;
+
+    assert!(buffer.len() as u64 <= max_size);
+    let symcache = SymCache::parse(&buffer)?;
+    let lines = symcache.lookup(0x1d00)?.collect::<Vec<_>>()?;
+    assert_eq!(lines.len(), 1);
+
+    Ok(())
+}
+
+#[test]
+fn test_cancel() -> Result<(), Error> {
+    let buffer = ByteView::open(fixture("linux/crash.debug"))?;
+    let object = Object::parse(&buffer)?;
+
+    let mut buffer = Vec::new();
+    let mut writer = SymCacheWriter::new(Cursor::new(&mut buffer))?;
+    writer.set_deadline(Instant::now());
+    let error = writer.process_object(&object).unwrap_err();
+    assert_eq!(error.kind(), SymCacheErrorKind::Cancelled);
+    assert_eq!(writer.stats().processed_functions, 0);
+
+    let mut buffer = Vec::new();
+    let mut writer = SymCacheWriter::new(Cursor::new(&mut buffer))?;
+    let flag = Arc::new(AtomicBool::new(false));
+    writer.set_cancel_flag(flag.clone());
+    writer.process_object(&object)?;
+    let processed_functions = writer.stats().processed_functions;
+    assert!(processed_functions > 0);
+
+    flag.store(true, Ordering::Relaxed);
+    let error = writer.process_object(&object).unwrap_err();
+    assert_eq!(error.kind(), SymCacheErrorKind::Cancelled);
+    assert_eq!(writer.stats().processed_functions, processed_functions);
+
+    // the partial result can still be written
+    writer.finish()?;
+    SymCache::parse(&buffer)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_process_any_object() -> Result<(), Error> {
+    for path in [
+        "linux/crash",
+        "linux/crash.debug",
+        "windows/crash.pdb",
+        "windows/crash.sym",
+    ] {
+        let buffer = ByteView::open(fixture(path))?;
+        let object = Object::parse(&buffer)?;
+
+        let mut expected = Vec::new();
+        SymCacheWriter::write_object(&object, Cursor::new(&mut expected))?;
+
+        let mut buffer = Vec::new();
+        let mut writer = SymCacheWriter::new(Cursor::new(&mut buffer))?;
+        writer.process_any_object(&object)?;
+        writer.finish()?;
+
+        assert_eq!(buffer, expected, "{}", path);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_process_any_object_gopclntab() -> Result<(), Error> {
+    let buffer = ByteView::open(fixture("linux/gopclntab/gen/main"))?;
+    let object = Object::parse(&buffer)?;
+    assert!(!object.has_debug_info());
+
+    let mut buffer = Vec::new();
+    let mut writer = SymCacheWriter::new(Cursor::new(&mut buffer))?;
+    writer.process_any_object(&object)?;
+    writer.finish()?;
+
+    let symcache = SymCache::parse(&buffer)?;
+    let lookup = |addr| -> Result<_, Error> {
+        let symbols = symcache.lookup(addr)?.collect::<Vec<_>>()?;
+        Ok(symbols
+            .iter()
+            .map(|s| (s.function_name().to_string(), s.path(), s.line()))
+            .collect::<Vec<_>>())
+    };
+
+    let main = |line| vec![("main.main".to_owned(), "/src/main.go".to_owned(), line)];
+    assert_eq!(lookup(0x1040)?, main(5));
+    assert_eq!(lookup(0x1043)?, main(6));
+
+    let compute = |line| vec![("main.compute".to_owned(), "/src/main.go".to_owned(), line)];
+    assert_eq!(lookup(0x1140)?, compute(1));
+    assert_eq!(lookup(0x1144)?, compute(2));
+
+    Ok(())
+}
+
+#[test]
+fn test_write_archive() -> Result<(), Error> {
+    let buffer = ByteView::open(fixture(
+        "macos/Example.framework.dSYM/Contents/Resources/DWARF/Example",
+    ))?;
+    let archive = Archive::parse(&buffer)?;
+    assert!(archive.is_multi());
+
+    let symcaches = SymCacheWriter::write_archive(&archive, |_| Cursor::new(Vec::new()))?;
+    assert_eq!(symcaches.len(), archive.object_count());
+
+    for (arch, cursor) in symcaches {
+        let symcache = SymCache::parse(cursor.get_ref())?;
+        assert_eq!(symcache.arch(), arch);
+
+        let single = SymCacheWriter::write_archive_object(&archive, arch, Cursor::new(Vec::new()))?;
+        assert_eq!(single.get_ref(), cursor.get_ref());
+    }
+
+    let error =
+        SymCacheWriter::write_archive_object(&archive, Arch::Wasm32, Cursor::new(Vec::new()))
+            .unwrap_err();
+    assert_eq!(error.kind(), SymCacheErrorKind::MissingArchitecture);
+
+    Ok(())
+}
+
+#[test]
+fn test_process_macho() -> Result<(), Error> {
+    let buffer = ByteView::open(fixture("macos/crash.dSYM/Contents/Resources/DWARF/crash"))?;
+    let object = MachObject::parse(&buffer)?;
+
+    let write = |macho: bool| -> Result<Vec<u8>, Error> {
+        let mut buffer = Vec::new();
+        let mut writer = SymCacheWriter::new(Cursor::new(&mut buffer))?;
+        if macho {
+            writer.process_macho(&object)?;
+        } else {
+            writer.process_object(&object)?;
+        }
+        writer.finish()?;
+        Ok(buffer)
+    };
+
+    // images with a single code segment do not record it
+    let buffer = write(true)?;
+    assert_eq!(buffer, write(false)?);
+    let symcache = NewSymCache::parse(&buffer)?;
+    assert!(symcache.segments().is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn test_process_split_dwarf() -> Result<(), Error> {
+    let buffer = ByteView::open(fixture("linux/split_dwarf/gen/split"))?;
+    let object = ElfObject::parse(&buffer)?;
+    let dwp_buffer = ByteView::open(fixture("linux/split_dwarf/gen/split.dwp"))?;
+    let dwp = ElfObject::parse(&dwp_buffer)?;
+
+    let mut buffer = Vec::new();
+    let mut writer = SymCacheWriter::new(Cursor::new(&mut buffer))?;
+    writer.process_split_dwarf(&object, &[dwp])?;
+    writer.finish()?;
+
+    let symcache = SymCache::parse(&buffer)?;
+    let lines = symcache.lookup(0x1140)?.collect::<Vec<_>>()?;
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0].function_name().as_str(), "helper");
+    assert_eq!(lines[0].line(), 1);
+    assert_eq!(lines[1].function_name().as_str(), "compute");
+    assert_eq!(lines[1].line(), 4);
+    assert!(lines[1].filename().ends_with("main.c"));
+
+    Ok(())
+}
+
+#[test]
+fn test_process_dwarf_with_sup() -> Result<(), Error> {
+    let buffer = ByteView::open(fixture("linux/dwz/gen/main"))?;
+    let object = ElfObject::parse(&buffer)?;
+    let sup_buffer = ByteView::open(fixture("linux/dwz/gen/main.sup"))?;
+    let sup = ElfObject::parse(&sup_buffer)?;
+
+    let mut buffer = Vec::new();
+    let mut writer = SymCacheWriter::new(Cursor::new(&mut buffer))?;
+    writer.process_dwarf_with_sup(&object, &sup)?;
+    writer.finish()?;
+
+    let symcache = SymCache::parse(&buffer)?;
+    let lines = symcache.lookup(0x1000_2000)?.collect::<Vec<_>>()?;
+    assert_eq!(lines.len(), 1);
+    assert_eq!(lines[0].function_name().as_str(), "sup_function");
+
+    Ok(())
+}
+
+#[test]
+fn test_process_wasm() -> Result<(), Error> {
+    let data = ByteView::open(fixture("wasm/simple.wasm"))?;
+
+    let mut buffer = Vec::new();
+    let mut writer = SymCacheWriter::new(Cursor::new(&mut buffer))?;
+    writer.process_wasm(&data)?;
+    writer.finish()?;
+
+    let symcache = SymCache::parse(&buffer)?;
+    assert_eq!(symcache.arch(), Arch::Wasm32);
+    // `internal_func` starts at 0x2 in the code section, which starts at 0x89 in the file
+    let lines = symcache.lookup(0x8b)?.collect::<Vec<_>>()?;
+    assert_eq!(lines.len(), 1);
+    assert_eq!(lines[0].function_name().as_str(), "internal_func");
+    assert_eq!(lines[0].filename(), "lib.rs");
+
+    Ok(())
+}
+
+#[test]
+fn test_write_pdb() -> Result<(), Error> {
+    let buffer = ByteView::open(fixture("windows/crash.pdb"))?;
+    let object = Object::parse(&buffer)?;
+
+    let mut buffer = Vec::new();
+    SymCacheWriter::write_object(&object, Cursor::new(&mut buffer))?;
+
+    let symcache = SymCache::parse(&buffer)?;
+    let lines = symcache.lookup(0x2959)?.collect::<Vec<_>>()?;
+    let line = lines.last().unwrap();
+    assert_eq!(line.function_name().as_str(), "main");
+    assert_eq!(line.line(), 34);
+    assert!(line.filename().ends_with("main.cpp"));
+
+    Ok(())
+}
+
+#[test]
+fn test_pe_with_pdb() -> Result<(), Error> {
+    let pe_buffer = ByteView::open(fixture("windows/crash.exe"))?;
+    let pe = PeObject::parse(&pe_buffer)?;
+    let pdb_buffer = ByteView::open(fixture("windows/crash.pdb"))?;
+
+    let mut buffer = Vec::new();
+    let mut writer = SymCacheWriter::new(Cursor::new(&mut buffer))?;
+    writer.process_pe_with_pdb(&pe, &pdb_buffer)?;
+    writer.finish()?;
+
+    let symcache = SymCache::parse(&buffer)?;
+    assert_eq!(symcache.debug_id(), pe.debug_id());
+    assert_eq!(symcache.arch(), pe.arch());
+
+    let other_pdb_buffer = ByteView::open(fixture("windows/CrashWithException.pdb"))?;
+    let mut writer = SymCacheWriter::new(Cursor::new(Vec::new()))?;
+    let error = writer
+        .process_pe_with_pdb(&pe, &other_pdb_buffer)
+        .unwrap_err();
+    assert_eq!(error.kind(), SymCacheErrorKind::MismatchedDebugFile);
+
+    Ok(())
+}
+
 // FIXME: This is a huge pain, can't this be simpler somehow?
 struct OwnedBcSymbolMap(SelfCell<ByteView<'static>, BcSymbolMap<'static>>);
 
@@ -268,3 +847,34 @@ fn test_transformer_symbolmap() -> Result<(), Error> {
 
     Ok(())
 }
+
+#[test]
+fn test_apply_bcsymbolmap() -> Result<(), Error> {
+    let buffer = ByteView::open(
+        "../symbolic-debuginfo/tests/fixtures/2d10c42f-591d-3265-b147-78ba0868073f.dwarf-hidden",
+    )?;
+    let object = Object::parse(&buffer)?;
+
+    let mut buffer = Vec::new();
+    let mut writer = SymCacheWriter::new(Cursor::new(&mut buffer))?;
+
+    let map_buffer = ByteView::open(
+        "../symbolic-debuginfo/tests/fixtures/c8374b6d-6e96-34d8-ae38-efaa5fec424f.bcsymbolmap",
+    )?;
+    writer.apply_bcsymbolmap(&BcSymbolMap::parse(&map_buffer)?);
+    drop(map_buffer);
+
+    writer.process_object(&object)?;
+    writer.finish()?;
+    let cache = SymCache::parse(&buffer)?;
+
+    let sl = cache.lookup(0x5a74)?.next().unwrap()?;
+
+    assert_eq!(sl.function_name(), "-[SentryMessage initWithFormatted:]");
+    assert_eq!(
+        sl.abs_path(),
+        "/Users/philipphofmann/git-repos/sentry-cocoa/Sources/Sentry/SentryMessage.m"
+    );
+
+    Ok(())
+}
@@ -0,0 +1,93 @@
+#!/bin/bash
+
+# This script was used to generate the fixtures in the "gen" directory, used to test resolving
+# references into supplementary files via `ElfObject::debug_session_with_sup`.
+#
+# `dwz` only creates a supplementary file when several inputs share debugging information, so the
+# DWARF sections are written by hand instead, mimicking what `dwz -m` emits:
+#
+# - `main` contains a compilation unit with a function named via `DW_FORM_GNU_strp_alt` and an
+#   inlined function whose `DW_AT_abstract_origin` uses `DW_FORM_GNU_ref_alt`.
+# - `main.sup` contains a partial unit with the referenced function and the referenced strings.
+#   Unlike the C compilation unit in `main`, the partial unit is declared as C++.
+
+# Pre-requisites:
+#
+# - gcc
+# - objcopy (GNU Binary Utilities)
+# - python3
+
+OUTPUT=gen
+
+# 0. Clean and remake output directory, switch to it
+rm -rf $OUTPUT
+mkdir -p $OUTPUT
+cd $OUTPUT
+
+# 1. write the raw DWARF sections of both files.
+python3 - << EOF_PYTHON
+import struct
+
+def unit(entries):
+    # DWARF 4 unit header: unit_length, version, debug_abbrev_offset, address_size
+    body = struct.pack("<HIB", 4, 0, 8) + entries
+    return struct.pack("<I", len(body)) + body
+
+def write(name, data):
+    with open(name, "wb") as f:
+        f.write(data)
+
+# DW_FORM_GNU_ref_alt (0x1f20) and DW_FORM_GNU_strp_alt (0x1f21) as ULEB128
+REF_ALT = b"\xa0\x3e"
+STRP_ALT = b"\xa1\x3e"
+
+write("sup_abbrev.bin", bytes([
+    1, 0x3c, 1, 0x13, 0x0b, 0, 0,     # DW_TAG_partial_unit, children: DW_AT_language (data1)
+    2, 0x2e, 0, 0x03, 0x0e, 0, 0,     # DW_TAG_subprogram: DW_AT_name (strp)
+    0,
+]))
+write("sup_str.bin", b"sup_inlined\0sup_function\0")
+# The partial unit DIE (DW_LANG_C_plus_plus_14) is at offset 11, the subprogram at offset 13.
+write("sup_info.bin", unit(bytes([1, 0x21, 2]) + struct.pack("<I", 0) + bytes([0])))
+
+write("main_abbrev.bin", bytes([
+    # DW_TAG_compile_unit, children: DW_AT_name (string), DW_AT_language (data1),
+    # DW_AT_low_pc (addr), DW_AT_high_pc (data4)
+    1, 0x11, 1, 0x03, 0x08, 0x13, 0x0b, 0x11, 0x01, 0x12, 0x06, 0, 0,
+    # DW_TAG_subprogram, children: DW_AT_name (string), DW_AT_low_pc, DW_AT_high_pc
+    2, 0x2e, 1, 0x03, 0x08, 0x11, 0x01, 0x12, 0x06, 0, 0,
+])
+    # DW_TAG_inlined_subroutine: DW_AT_abstract_origin (GNU_ref_alt), DW_AT_low_pc, DW_AT_high_pc
+    + bytes([3, 0x1d, 0, 0x31]) + REF_ALT + bytes([0x11, 0x01, 0x12, 0x06, 0, 0])
+    # DW_TAG_subprogram: DW_AT_name (GNU_strp_alt), DW_AT_low_pc, DW_AT_high_pc
+    + bytes([4, 0x2e, 0, 0x03]) + STRP_ALT + bytes([0x11, 0x01, 0x12, 0x06, 0, 0])
+    + bytes([0]))
+write("main_info.bin", unit(
+    bytes([1]) + b"main.c\0" + bytes([0x0c]) + struct.pack("<QI", 0x10001000, 0x1010)
+    + bytes([2]) + b"outer\0" + struct.pack("<QI", 0x10001000, 0x20)
+    + bytes([3]) + struct.pack("<IQI", 13, 0x10001000, 0x10)
+    + bytes([0])
+    + bytes([4]) + struct.pack("<IQI", 12, 0x10002000, 0x10)
+    + bytes([0])
+))
+EOF_PYTHON
+
+# 2. compile a minimal program and attach the sections to the main and supplementary files.
+gcc -x c -Os -o base - << EOF_C
+int main() {
+    return 0;
+}
+EOF_C
+
+objcopy \
+    --add-section .debug_abbrev=main_abbrev.bin \
+    --add-section .debug_info=main_info.bin \
+    base main
+
+objcopy --strip-all \
+    --add-section .debug_abbrev=sup_abbrev.bin \
+    --add-section .debug_info=sup_info.bin \
+    --add-section .debug_str=sup_str.bin \
+    base main.sup
+
+rm base *.bin
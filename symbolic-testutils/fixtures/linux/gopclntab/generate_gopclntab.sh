@@ -0,0 +1,109 @@
+#!/bin/bash
+
+# This script was used to generate the fixtures in the "gen" directory, used to test the fallback
+# to the Go pc/line table via `SymCacheWriter::process_any_object`.
+#
+# Without a Go toolchain, the pc/line table is written by hand instead, mimicking what the Go 1.18
+# linker emits for a binary built with `-ldflags=-s -w`:
+#
+# - `main` is compiled without debug information and stripped of its symbol table.
+# - Its `.gopclntab` section declares `compute` as `main.compute` and `main` as `main.main`. Both
+#   are located in `/src/main.go`, and each maps the first half of its code to the line of its
+#   declaration and the second half to the line after it.
+
+# Pre-requisites:
+#
+# - gcc
+# - nm, objcopy, strip (GNU Binary Utilities)
+# - python3
+
+OUTPUT=gen
+
+# 0. Clean and remake output directory, switch to it
+rm -rf $OUTPUT
+mkdir -p $OUTPUT
+cd $OUTPUT
+
+# 1. compile the example without debug information.
+gcc -O2 -o main.unstripped ../main.c
+
+# 2. write the pc/line table for the functions of the symbol table.
+nm -S main.unstripped | python3 -c '
+import struct, sys
+
+symbols = {}
+for line in sys.stdin:
+    parts = line.split()
+    if len(parts) == 4:
+        symbols[parts[3]] = (int(parts[0], 16), int(parts[1], 16))
+
+def uvarint(value):
+    out = bytearray()
+    while value >= 0x80:
+        out.append(value & 0x7f | 0x80)
+        value >>= 7
+    out.append(value)
+    return bytes(out)
+
+def zigzag(value):
+    return (value << 1) ^ (value >> 63)
+
+def pc_values(entries):
+    # pairs of value and code size, starting from the initial value of -1
+    out, prev = bytearray(), -1
+    for value, size in entries:
+        out += uvarint(zigzag(value - prev)) + uvarint(size)
+        prev = value
+    return bytes(out + b"\0")
+
+functions = [("main.compute", "compute", 1), ("main.main", "main", 5)]
+functions.sort(key=lambda f: symbols[f[1]][0])
+text_start = symbols[functions[0][1]][0]
+
+funcname_tab = b"".join(name.encode() + b"\0" for name, _, _ in functions)
+cu_tab = struct.pack("<I", 0)
+file_tab = b"/src/main.go\0"
+# a leading padding byte, since offset `0` denotes a missing table
+pc_tab = bytearray(b"\0")
+records = []
+name_offset = 0
+for name, symbol, line in functions:
+    address, size = symbols[symbol]
+    half = size // 2
+    pcfile = len(pc_tab)
+    pc_tab += pc_values([(0, size)])
+    pcln = len(pc_tab)
+    pc_tab += pc_values([(line, half), (line + 1, size - half)])
+    records.append((address - text_start, name_offset, pcfile, pcln))
+    name_offset += len(name) + 1
+
+header_size = 8 + 8 * 8
+funcname_offset = header_size
+cu_offset = funcname_offset + len(funcname_tab)
+file_offset = cu_offset + len(cu_tab)
+pc_offset = file_offset + len(file_tab)
+func_offset = pc_offset + len(pc_tab)
+
+data = struct.pack("<IBBBB", 0xfffffff0, 0, 0, 1, 8)
+data += struct.pack("<8Q", len(functions), 1, text_start, funcname_offset, cu_offset,
+                    file_offset, pc_offset, func_offset)
+data += funcname_tab + cu_tab + file_tab + pc_tab
+
+# function table: pairs of entry offset and record offset, then the end of the last function
+table_size = 8 * len(functions) + 4
+last_address, last_size = symbols[functions[-1][1]]
+func_tab = b""
+for index, (entryoff, _, _, _) in enumerate(records):
+    func_tab += struct.pack("<II", entryoff, table_size + 36 * index)
+func_tab += struct.pack("<I", last_address + last_size - text_start)
+# entryoff, nameoff, args, deferreturn, pcsp, pcfile, pcln, npcdata, cu_offset
+for entryoff, nameoff, pcfile, pcln in records:
+    func_tab += struct.pack("<9I", entryoff, nameoff, 0, 0, 0, pcfile, pcln, 0, 0)
+
+open("gopclntab", "wb").write(data + func_tab)
+'
+
+# 3. strip the symbol table and embed the pc/line table.
+strip --strip-all -o main.stripped main.unstripped
+objcopy --add-section .gopclntab=gopclntab main.stripped main
+rm main.unstripped main.stripped gopclntab
@@ -0,0 +1,7 @@
+__attribute__((noinline)) int compute(int x) {
+    return x * 3 + 1;
+}
+
+int main(int argc, char **argv) {
+    return compute(argc);
+}
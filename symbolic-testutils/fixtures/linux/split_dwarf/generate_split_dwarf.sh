@@ -0,0 +1,23 @@
+#!/bin/bash
+
+# This script was used to generate the fixtures in the "gen" directory, used to test split DWARF
+# support via `ElfObject::debug_session_with_split_dwarf`.
+
+# Pre-requisites:
+#
+# - gcc
+# - llvm-dwp (LLVM)
+
+OUTPUT=gen
+
+# 0. Clean and remake output directory, switch to it
+rm -rf $OUTPUT
+mkdir -p $OUTPUT
+cd $OUTPUT
+
+# 1. compile the example with split DWARF. This leaves skeleton units in `split` and writes the
+# full debug information to `split-main.dwo`. The compilation directory is remapped to `/build`.
+gcc -g -gsplit-dwarf -O2 -fdebug-prefix-map="$(pwd)"=/build -o split ../main.c
+
+# 2. package the .dwo file into a .dwp file.
+llvm-dwp -o split.dwp split-main.dwo
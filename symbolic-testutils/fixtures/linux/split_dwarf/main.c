@@ -0,0 +1,10 @@
+static inline int helper(int x) { return x * 3 + 1; }
+
+__attribute__((noinline)) int compute(int x) {
+    int y = helper(x);
+    return y + helper(y);
+}
+
+int main(int argc, char **argv) {
+    return compute(argc);
+}
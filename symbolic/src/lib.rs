@@ -57,6 +57,7 @@
 //! - **`common-serde`**
 //! - **`debuginfo-serde`**
 //! - **`minidump-serde`**
+//! - **`symcache-serde`**
 //! - **`unreal-serde`**
 //!
 //! ## Minimal Rust Version